@@ -1,18 +1,22 @@
 use dioxus::prelude::*;
+use common::domain::directory_listing::DirectoryEntry;
 use common::domain::errors::ApplicationError;
+use common::domain::sync_selection::SyncSelection;
 use common::ports::i18n_driven_port::I18nDrivenPort;
+use crate::domain::user_actions::{UserAction, UserActionSender};
 use crate::ports::driven::ui_driven_port::UIDrivenPort;
-use crate::ui::views::{Login, Home, ErrorView, ConnectingView};
+use crate::ui::views::{Login, Home, ErrorView, ConnectingView, SyncSelectionView};
 
 #[derive(Clone)]
 pub struct DioxusAdapter<I18nPort: I18nDrivenPort> {
     current_element: Signal<Element>,
-    i18n: I18nPort
+    i18n: I18nPort,
+    actions: UserActionSender,
 }
 
 impl<I18nPort: I18nDrivenPort> DioxusAdapter<I18nPort> {
-    pub fn new(initial: Signal<Element>, i18n: I18nPort) -> Self {
-        Self { current_element: initial, i18n }
+    pub fn new(initial: Signal<Element>, i18n: I18nPort, actions: UserActionSender) -> Self {
+        Self { current_element: initial, i18n, actions }
     }
 
     // pub fn current_element(&self) -> Element {
@@ -32,11 +36,43 @@ impl<I18n: I18nDrivenPort + Clone + 'static> UIDrivenPort for DioxusAdapter<I18n
     }
 
     fn show_home_view(&mut self) {
-        self.current_element.set(rsx! { Home {} });
+        let i18n = self.i18n.clone();
+        let actions = self.actions.clone();
+        let choose_actions = self.actions.clone();
+        self.current_element.set(rsx! {
+            Home {
+                i18n,
+                on_choose_folders: move |_| {
+                    let _ = choose_actions.send(UserAction::ChooseFolders);
+                },
+                on_logout: move |_| {
+                    let _ = actions.send(UserAction::Logout);
+                }
+            }
+        });
     }
 
     fn show_loading_view(&mut self) {
         let i18n = self.i18n.clone();
         self.current_element.set(rsx! { ConnectingView { i18n } });
     }
+
+    fn show_sync_selection_view(&mut self, folders: Vec<DirectoryEntry>, selection: SyncSelection) {
+        let i18n = self.i18n.clone();
+        let save_actions = self.actions.clone();
+        let cancel_actions = self.actions.clone();
+        self.current_element.set(rsx! {
+            SyncSelectionView {
+                i18n,
+                folders,
+                selection,
+                on_save: move |selection| {
+                    let _ = save_actions.send(UserAction::SaveSyncSelection(selection));
+                },
+                on_cancel: move |_| {
+                    let _ = cancel_actions.send(UserAction::CancelSyncSelection);
+                }
+            }
+        });
+    }
 }
\ No newline at end of file
@@ -1,26 +1,31 @@
 use std::future::Future;
 use futures_util::StreamExt;
+use tonic::service::interceptor::InterceptedService;
 use tonic::transport::Channel;
 use std::time::Duration;
 use tonic::Request;
 use common::{
-    domain::text_keys::TextKeys::ConnectionErrorMessage,
+    adapters::grpc_channel::connect_channel,
+    adapters::session_secret::SessionSecretInterceptor,
     domain::errors::*,
-    application_error,
-    domain::defaults::{CONNECTION_TIMEOUT_SECONDS, DEFAULT_SERVER_URL}
+    domain::defaults::CONNECTION_TIMEOUT_SECONDS
 
 };
-use common::kdrive::Empty;
+use common::domain::directory_listing::DirectoryListing;
+use common::domain::sync_conflict::ConflictResolution;
+use common::domain::sync_selection::SyncSelection;
+use common::kdrive::{Empty, ListDirectoryRequest, ResolveConflictRequest};
 use common::kdrive::kdrive_service_client::KdriveServiceClient;
 use crate::{
     domain::events::ServerEventStream,
+    domain::settings::ClientSettings,
     ports::driven::server_driven_port::ServerDrivenPort
 };
 
 
 #[derive(Clone)]
 pub struct GrpcServerAdapter {
-    client: KdriveServiceClient<Channel>,
+    client: KdriveServiceClient<InterceptedService<Channel, SessionSecretInterceptor>>,
 }
 
 impl PartialEq for GrpcServerAdapter {
@@ -29,22 +34,18 @@ impl PartialEq for GrpcServerAdapter {
     }
 }
 impl GrpcServerAdapter {
-    async fn connect_with_url(url: &'static str) -> Result<Self, ApplicationError> {
-        let channel = Channel::from_static(url)
-            .connect_timeout(Duration::from_secs(CONNECTION_TIMEOUT_SECONDS))
-            .connect()
-            .await
-            .map_err(|e| {
-                application_error!(ConnectionErrorMessage, e.to_string())
-            })?;
+    async fn connect_with_url(url: &str, session_secret: SessionSecretInterceptor) -> Result<Self, ApplicationError> {
+        let channel = connect_channel(url, Duration::from_secs(CONNECTION_TIMEOUT_SECONDS)).await?;
 
         Ok(Self {
-            client: KdriveServiceClient::new(channel),
+            client: KdriveServiceClient::with_interceptor(channel, session_secret),
         })
     }
 
-    pub async fn connect() -> Result<Self, ApplicationError> {
-        Self::connect_with_url(DEFAULT_SERVER_URL).await
+    pub async fn connect(settings: &ClientSettings) -> Result<Self, ApplicationError> {
+        // The service writes a new secret on every start, so it is read again on every connect
+        let session_secret = SessionSecretInterceptor::from_file(&settings.session_secret_path)?;
+        Self::connect_with_url(&settings.server_url, session_secret).await
     }
 }
 
@@ -57,7 +58,9 @@ impl ServerDrivenPort for GrpcServerAdapter {
                 .await
                 .map_err(|status| ApplicationError::from(status))?;
 
-            Ok(response.into_inner().is_authenticated)
+            // The service refreshes an expired access token on its own, the user stays logged in meanwhile
+            let status = response.into_inner();
+            Ok(status.is_authenticated || status.has_tokens)
         }
     }
 
@@ -85,6 +88,18 @@ impl ServerDrivenPort for GrpcServerAdapter {
         }
     }
 
+    fn logout(&self) -> impl Future<Output=Result<(), ApplicationError>> + Send {
+        let mut client = self.client.clone();
+        async move {
+            client
+                .logout(Empty {})
+                .await
+                .map_err(|status| ApplicationError::from(status))?;
+
+            Ok(())
+        }
+    }
+
     fn subscribe_events(&self)
         -> impl Future<Output=Result<ServerEventStream, ApplicationError>> + Send
     {
@@ -103,22 +118,94 @@ impl ServerDrivenPort for GrpcServerAdapter {
             Ok(Box::pin(stream) as ServerEventStream)
         }
     }
+
+    fn resolve_conflict(&self, file_id: u64, path: String, resolution: ConflictResolution)
+        -> impl Future<Output=Result<(), ApplicationError>> + Send
+    {
+        let mut client = self.client.clone();
+        let request = ResolveConflictRequest {
+            file_id,
+            path,
+            resolution: common::kdrive::ConflictResolution::from(resolution).into(),
+        };
+        async move {
+            client
+                .resolve_conflict(request)
+                .await
+                .map_err(ApplicationError::from)?;
+
+            Ok(())
+        }
+    }
+
+    fn list_directory(&self, path: String, depth: u32)
+        -> impl Future<Output=Result<DirectoryListing, ApplicationError>> + Send
+    {
+        let mut client = self.client.clone();
+        async move {
+            let mut listing = DirectoryListing { path: path.clone(), entries: Vec::new(), next_page_token: None };
+            let mut page_token = String::new();
+            loop {
+                let page: DirectoryListing = client
+                    .list_directory(ListDirectoryRequest { path: path.clone(), depth, page_size: 0, page_token })
+                    .await
+                    .map_err(ApplicationError::from)?
+                    .into_inner()
+                    .into();
+                listing.entries.extend(page.entries);
+                match page.next_page_token {
+                    Some(next) => page_token = next,
+                    None => return Ok(listing),
+                }
+            }
+        }
+    }
+
+    fn get_sync_selection(&self) -> impl Future<Output=Result<SyncSelection, ApplicationError>> + Send {
+        let mut client = self.client.clone();
+        async move {
+            let response = client
+                .get_sync_selection(Empty {})
+                .await
+                .map_err(ApplicationError::from)?;
+
+            Ok(response.into_inner().into())
+        }
+    }
+
+    fn set_sync_selection(&self, selection: SyncSelection) -> impl Future<Output=Result<(), ApplicationError>> + Send {
+        let mut client = self.client.clone();
+        async move {
+            client
+                .set_sync_selection(common::kdrive::SyncSelection::from(selection))
+                .await
+                .map_err(ApplicationError::from)?;
+
+            Ok(())
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use common::domain::text_keys::TextKeys::InvalidServerUrl;
     use tokio::time::Duration;
     use tokio_stream::StreamExt;
     use crate::adapters::test_helpers::fake_kdrive_service::start_test_server;
     use common::kdrive::server_event::Event as ServerEventKind;
+    use common::domain::text_keys::TextKeys::CouldNotReadSessionSecret;
+
+    fn test_session_secret() -> SessionSecretInterceptor {
+        SessionSecretInterceptor::new("test-session-secret").unwrap()
+    }
 
     #[tokio::test]
     async fn connect_fails_within_reasonable_time_when_target_is_unreachable() {
         let unreachable_url = "http://192.0.2.1:50051";
 
         let start = std::time::Instant::now();
-        let result = GrpcServerAdapter::connect_with_url(unreachable_url).await;
+        let result = GrpcServerAdapter::connect_with_url(unreachable_url, test_session_secret()).await;
 
         let elapsed = start.elapsed();
 
@@ -137,7 +224,7 @@ mod tests {
 
         // And a gRPC server adapter connected to it
         let adapter =
-            GrpcServerAdapter::connect_with_url(Box::leak(server_url.into_boxed_str()))
+            GrpcServerAdapter::connect_with_url(&server_url, test_session_secret())
                 .await
                 .unwrap();
 
@@ -152,4 +239,85 @@ mod tests {
             Some(ServerEventKind::AuthFlowCompleted(_))
         ));
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn client_stays_logged_in_while_the_access_token_is_refreshed() {
+        // Given a server with tokens whose access token has expired
+        let (server_url, _handle) = start_test_server().await;
+        let adapter = GrpcServerAdapter::connect_with_url(&server_url, test_session_secret()).await.unwrap();
+
+        // When the client asks whether the user is logged in
+        let result = adapter.is_authenticated().await;
+
+        // Then the user does not have to log in again
+        assert!(result.unwrap());
+    }
+
+    #[tokio::test]
+    async fn client_resolves_a_conflict_through_the_server() {
+        let (server_url, _handle) = start_test_server().await;
+        let adapter = GrpcServerAdapter::connect_with_url(&server_url, test_session_secret()).await.unwrap();
+
+        let result = adapter.resolve_conflict(4, "notes.txt".to_string(), ConflictResolution::KeepBoth).await;
+
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn client_reads_every_page_of_a_listing() {
+        // Given a server that lists a folder in two pages
+        let (server_url, _handle) = start_test_server().await;
+        let adapter = GrpcServerAdapter::connect_with_url(&server_url, test_session_secret()).await.unwrap();
+
+        // When the client lists it
+        let listing = adapter.list_directory("Documents".to_string(), 1).await.unwrap();
+
+        // Then the entries of both pages are there
+        assert_eq!(listing.path, "Documents");
+        assert_eq!(listing.entries.len(), 2);
+        assert_eq!(listing.next_page_token, None);
+    }
+
+    #[tokio::test]
+    async fn connect_fails_for_an_invalid_server_url() {
+        let result = GrpcServerAdapter::connect_with_url("not a url", test_session_secret()).await;
+
+        assert_eq!(result.err().map(|error| error.text_key), Some(InvalidServerUrl));
+    }
+
+    #[tokio::test]
+    async fn connect_fails_when_the_session_secret_cannot_be_read() {
+        // Given settings pointing to a session secret the service did not write
+        let settings = ClientSettings {
+            server_url: "http://127.0.0.1:50051".to_string(),
+            session_secret_path: std::env::temp_dir().join("kdrive_desktop_secret_that_does_not_exist"),
+        };
+
+        // When the adapter connects
+        let result = GrpcServerAdapter::connect(&settings).await;
+
+        // Then the missing secret is reported
+        assert_eq!(result.err().map(|error| error.text_key), Some(CouldNotReadSessionSecret));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn client_receives_events_through_a_unix_socket() {
+        use crate::adapters::test_helpers::fake_kdrive_service::start_test_unix_server;
+
+        // Given a gRPC server listening on a Unix domain socket
+        let socket_path = std::env::temp_dir().join(format!("kdrive_desktop_{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&socket_path);
+        let (server_url, _handle) = start_test_unix_server(&socket_path).await;
+
+        // When the adapter connects through the socket and subscribes to server events
+        let adapter = GrpcServerAdapter::connect_with_url(&server_url, test_session_secret()).await.unwrap();
+        let mut events = adapter.subscribe_events().await.unwrap();
+
+        // Then the events of the server arrive
+        let event = events.next().await.unwrap().unwrap();
+        assert!(matches!(event.event, Some(ServerEventKind::AuthFlowCompleted(_))));
+
+        let _ = std::fs::remove_file(&socket_path);
+    }
+}
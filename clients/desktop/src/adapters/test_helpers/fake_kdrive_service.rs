@@ -32,8 +32,10 @@ impl KdriveService for FakeKdriveService {
         &self,
         _request: Request<Empty>,
     ) -> Result<Response<common::kdrive::AuthStatus>, Status> {
+        // The access token expired and waits for the service to refresh it
         Ok(Response::new(common::kdrive::AuthStatus {
             is_authenticated: false,
+            has_tokens: true,
         }))
     }
 
@@ -51,6 +53,20 @@ impl KdriveService for FakeKdriveService {
         Ok(Response::new(Empty {}))
     }
 
+    async fn cancel_auth_flow(
+        &self,
+        _request: Request<Empty>,
+    ) -> Result<Response<Empty>, Status> {
+        Ok(Response::new(Empty {}))
+    }
+
+    async fn logout(
+        &self,
+        _request: Request<Empty>,
+    ) -> Result<Response<Empty>, Status> {
+        Ok(Response::new(Empty {}))
+    }
+
     type SubscribeEventsStream = EventStream;
 
     async fn subscribe_events(
@@ -66,6 +82,51 @@ impl KdriveService for FakeKdriveService {
         let stream = tokio_stream::iter(vec![Ok(event)]);
         Ok(Response::new(Box::pin(stream)))
     }
+
+    async fn list_directory(
+        &self,
+        request: Request<common::kdrive::ListDirectoryRequest>,
+    ) -> Result<Response<common::kdrive::ListDirectoryResponse>, Status> {
+        let request = request.into_inner();
+        // Every page holds one entry, the second page is the last
+        let (name, next_page_token) = match request.page_token.as_str() {
+            "" => ("first", "1"),
+            _ => ("second", ""),
+        };
+        Ok(Response::new(common::kdrive::ListDirectoryResponse {
+            path: request.path,
+            entries: vec![common::kdrive::DirectoryEntry { name: name.to_string(), ..Default::default() }],
+            next_page_token: next_page_token.to_string(),
+        }))
+    }
+
+    async fn search_files(
+        &self,
+        _request: Request<common::kdrive::SearchFilesRequest>,
+    ) -> Result<Response<common::kdrive::SearchFilesResponse>, Status> {
+        Ok(Response::new(common::kdrive::SearchFilesResponse::default()))
+    }
+
+    async fn resolve_conflict(
+        &self,
+        _request: Request<common::kdrive::ResolveConflictRequest>,
+    ) -> Result<Response<Empty>, Status> {
+        Ok(Response::new(Empty {}))
+    }
+
+    async fn get_sync_selection(
+        &self,
+        _request: Request<Empty>,
+    ) -> Result<Response<common::kdrive::SyncSelection>, Status> {
+        Ok(Response::new(common::kdrive::SyncSelection::default()))
+    }
+
+    async fn set_sync_selection(
+        &self,
+        _request: Request<common::kdrive::SyncSelection>,
+    ) -> Result<Response<Empty>, Status> {
+        Ok(Response::new(Empty {}))
+    }
 }
 
 #[allow(dead_code)]
@@ -85,4 +146,24 @@ pub async fn start_test_server() -> (String, JoinHandle<()>) {
     });
 
     (url, handle)
-}
\ No newline at end of file
+}
+
+/// Serves the fake service on a Unix domain socket, the returned URL is the one clients connect with
+#[cfg(unix)]
+#[allow(dead_code)]
+pub async fn start_test_unix_server(socket_path: &std::path::Path) -> (String, JoinHandle<()>) {
+    let listener = tokio::net::UnixListener::bind(socket_path).unwrap();
+    let url = format!("unix://{}", socket_path.display());
+
+    let handle = tokio::spawn(async move {
+        Server::builder()
+            .add_service(
+                KdriveServiceServer::new(FakeKdriveService::default()),
+            )
+            .serve_with_incoming(tokio_stream::wrappers::UnixListenerStream::new(listener))
+            .await
+            .unwrap();
+    });
+
+    (url, handle)
+}
@@ -1,3 +1,5 @@
 pub mod test_helpers;
 pub mod events;
-pub mod ui_core;
\ No newline at end of file
+pub mod ui_core;
+pub mod user_actions;
+pub mod settings;
\ No newline at end of file
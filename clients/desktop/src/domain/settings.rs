@@ -0,0 +1,147 @@
+use std::path::PathBuf;
+use std::path::Path;
+use common::domain::defaults::{default_session_secret_path, default_socket_path, session_secret_path_next_to, DEFAULT_SERVER_URL, UNIX_SOCKET_URL_PREFIX};
+
+pub const SERVER_URL_ENV_VAR: &str = "KDRIVE_SERVER_URL";
+pub const SESSION_SECRET_FILE_ENV_VAR: &str = "KDRIVE_SESSION_SECRET_FILE";
+const SERVER_URL_FLAG: &str = "--server-url";
+const SOCKET_FLAG: &str = "--socket";
+const SESSION_SECRET_FILE_FLAG: &str = "--session-secret-file";
+
+/// Where the client finds its service, so several instances or test services can run side by side.
+/// Besides "http://host:port" the URL can point to a Unix domain socket with "unix:///path/to/socket".
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientSettings {
+    pub server_url: String,
+    /// The file the service writes its session secret to, it has to be sent with every request
+    pub session_secret_path: PathBuf,
+}
+
+impl ClientSettings {
+    pub fn from_environment() -> Self {
+        Self::parse(
+            std::env::args().skip(1),
+            std::env::var(SERVER_URL_ENV_VAR).ok(),
+            std::env::var(SESSION_SECRET_FILE_ENV_VAR).ok(),
+        )
+    }
+
+    /// The command line wins over the environment variable, arguments meant for others are ignored
+    pub fn parse<I>(args: I, env_server_url: Option<String>, env_session_secret_path: Option<String>) -> Self
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut server_url = env_server_url.filter(|url| !url.is_empty());
+        let mut session_secret_path = env_session_secret_path.filter(|path| !path.is_empty()).map(PathBuf::from);
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            if arg == SOCKET_FLAG {
+                server_url = Some(default_socket_url());
+            } else if arg == SERVER_URL_FLAG {
+                server_url = args.next().or(server_url);
+            } else if arg == SESSION_SECRET_FILE_FLAG {
+                session_secret_path = args.next().map(PathBuf::from).or(session_secret_path);
+            } else if let Some(path) = arg.strip_prefix(SESSION_SECRET_FILE_FLAG).and_then(|rest| rest.strip_prefix('=')) {
+                session_secret_path = Some(PathBuf::from(path));
+            } else if let Some(url) = arg.strip_prefix(SERVER_URL_FLAG).and_then(|rest| rest.strip_prefix('=')) {
+                server_url = Some(url.to_string());
+            }
+        }
+
+        let server_url = server_url.unwrap_or_else(|| DEFAULT_SERVER_URL.to_string());
+        // Like the service, the secret of a service on another socket is looked for next to that socket
+        let session_secret_path = session_secret_path
+            .or_else(|| server_url.strip_prefix(UNIX_SOCKET_URL_PREFIX).map(|socket| session_secret_path_next_to(Path::new(socket))))
+            .unwrap_or_else(default_session_secret_path);
+        Self {
+            server_url,
+            session_secret_path,
+        }
+    }
+}
+
+fn default_socket_url() -> String {
+    format!("{}{}", UNIX_SOCKET_URL_PREFIX, default_socket_path().display())
+}
+
+impl Default for ClientSettings {
+    fn default() -> Self {
+        Self::parse(Vec::new(), None, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn uses_the_default_server_url_without_settings() {
+        let settings = ClientSettings::parse(args(&[]), None, None);
+
+        assert_eq!(settings.server_url, DEFAULT_SERVER_URL);
+        assert_eq!(settings.session_secret_path, default_session_secret_path());
+    }
+
+    #[test]
+    fn uses_the_server_url_from_the_environment() {
+        let settings = ClientSettings::parse(args(&[]), Some("http://127.0.0.1:50100".to_string()), None);
+
+        assert_eq!(settings.server_url, "http://127.0.0.1:50100");
+    }
+
+    #[test]
+    fn command_line_wins_over_the_environment() {
+        let settings = ClientSettings::parse(
+            args(&["--server-url", "http://127.0.0.1:50200"]),
+            Some("http://127.0.0.1:50100".to_string()),
+            None,
+        );
+
+        assert_eq!(settings.server_url, "http://127.0.0.1:50200");
+    }
+
+    #[test]
+    fn accepts_the_server_url_flag_with_equals_sign_and_ignores_other_arguments() {
+        let settings = ClientSettings::parse(args(&["--other", "--server-url=http://[::1]:50300"]), None, None);
+
+        assert_eq!(settings.server_url, "http://[::1]:50300");
+    }
+
+    #[test]
+    fn socket_flag_uses_the_default_socket_of_the_service() {
+        let settings = ClientSettings::parse(args(&["--socket"]), Some("http://127.0.0.1:50100".to_string()), None);
+
+        assert_eq!(settings.server_url, format!("unix://{}", default_socket_path().display()));
+    }
+
+    #[test]
+    fn session_secret_file_comes_from_the_command_line_or_the_environment() {
+        let from_env = ClientSettings::parse(args(&[]), None, Some("/tmp/env_secret".to_string()));
+        let from_args = ClientSettings::parse(
+            args(&["--session-secret-file", "/tmp/arg_secret"]),
+            None,
+            Some("/tmp/env_secret".to_string()),
+        );
+
+        assert_eq!(from_env.session_secret_path, PathBuf::from("/tmp/env_secret"));
+        assert_eq!(from_args.session_secret_path, PathBuf::from("/tmp/arg_secret"));
+    }
+
+    #[test]
+    fn session_secret_file_is_looked_for_next_to_the_socket() {
+        let next_to_socket = ClientSettings::parse(args(&["--server-url", "unix:///tmp/other/kdrive.sock"]), None, None);
+        let explicit = ClientSettings::parse(
+            args(&["--server-url", "unix:///tmp/other/kdrive.sock", "--session-secret-file", "/tmp/arg_secret"]),
+            None,
+            None,
+        );
+
+        assert_eq!(next_to_socket.session_secret_path, PathBuf::from("/tmp/other/session_secret"));
+        assert_eq!(explicit.session_secret_path, PathBuf::from("/tmp/arg_secret"));
+    }
+}
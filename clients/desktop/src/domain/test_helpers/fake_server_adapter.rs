@@ -1,7 +1,11 @@
 use futures_util::stream;
 use std::future;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
+use common::domain::directory_listing::{DirectoryEntry, DirectoryListing};
 use common::domain::errors::ApplicationError;
+use common::domain::sync_conflict::ConflictResolution;
+use common::domain::sync_selection::SyncSelection;
 use common::kdrive::ServerEvent;
 use common::kdrive::server_event::Event;
 use crate::domain::events::ServerEventStream;
@@ -10,47 +14,74 @@ use crate::ports::driven::server_driven_port::ServerDrivenPort;
 pub const TEST_URL_RESPONSE: &str = "http://localhost:8080/test-url-response";
 
 #[allow(dead_code)]
-#[derive(PartialEq, Clone)]
+#[derive(Clone)]
 pub struct FakeServerAdapter {
     authenticated: bool,
     error: Option<ApplicationError>,
     delay: Option<Duration>,
     event: Option<ServerEvent>,
+    logout_called: Arc<Mutex<bool>>,
+    resolved_conflicts: Arc<Mutex<Vec<(u64, String, ConflictResolution)>>>,
+    entries: Vec<DirectoryEntry>,
+    sync_selection: Arc<Mutex<SyncSelection>>,
 }
 
 #[allow(dead_code)]
 impl FakeServerAdapter {
 
     pub fn new(authenticated: bool) -> Self {
-        FakeServerAdapter { authenticated, error: None, delay: None, event: None }
+        FakeServerAdapter { authenticated, error: None, delay: None, event: None, logout_called: Arc::new(Mutex::new(false)), resolved_conflicts: Arc::new(Mutex::new(Vec::new())), entries: Vec::new(), sync_selection: Arc::new(Mutex::new(SyncSelection::default())) }
     }
 
     pub fn slow(delay: Duration) -> Self {
-        FakeServerAdapter { authenticated: false, error: None, delay: Some(delay), event: None }
+        FakeServerAdapter { delay: Some(delay), ..FakeServerAdapter::new(false) }
     }
 
     pub fn with_event(event: Event) -> Self {
         FakeServerAdapter {
-            authenticated: false,
-            error: None,
-            delay: None,
-            event: Some(ServerEvent { event: Some(event) })
+            event: Some(ServerEvent { event: Some(event) }),
+            ..FakeServerAdapter::new(false)
         }
     }
 
     pub fn with_server_event(event: ServerEvent) -> Self {
         FakeServerAdapter {
-            authenticated: false,
-            error: None,
-            delay: None,
             event: Some(event),
+            ..FakeServerAdapter::new(false)
         }
     }
 
+    /// The entries of the root of the drive
+    pub fn with_entries(mut self, entries: Vec<DirectoryEntry>) -> Self {
+        self.entries = entries;
+        self
+    }
+
+    pub fn with_sync_selection(self, selection: SyncSelection) -> Self {
+        *self.sync_selection.lock().unwrap() = selection;
+        self
+    }
+
     pub fn set_error(&mut self, error: ApplicationError) {
         self.error = Some(error);
     }
 
+    pub fn set_authenticated(&mut self, authenticated: bool) {
+        self.authenticated = authenticated;
+    }
+
+    pub fn logout_was_called(&self) -> bool {
+        *self.logout_called.lock().unwrap()
+    }
+
+    pub fn sync_selection(&self) -> SyncSelection {
+        self.sync_selection.lock().unwrap().clone()
+    }
+
+    pub fn resolved_conflicts(&self) -> Vec<(u64, String, ConflictResolution)> {
+        self.resolved_conflicts.lock().unwrap().clone()
+    }
+
 }
 
 impl ServerDrivenPort for FakeServerAdapter {
@@ -79,6 +110,11 @@ impl ServerDrivenPort for FakeServerAdapter {
         Ok(())
     }
 
+    async fn logout(&self) -> Result<(), ApplicationError> {
+        *self.logout_called.lock().unwrap() = true;
+        Ok(())
+    }
+
     fn subscribe_events(&self) -> impl Future<Output=Result<ServerEventStream, ApplicationError>> + Send {
         let events: Vec<Result<ServerEvent, ApplicationError>> = match &self.event {
             Some(e) => vec![Ok(e.clone())],
@@ -89,4 +125,28 @@ impl ServerDrivenPort for FakeServerAdapter {
             Box::pin(stream::iter(events)) as ServerEventStream
         ))
     }
-}
\ No newline at end of file
+
+    async fn resolve_conflict(&self, file_id: u64, path: String, resolution: ConflictResolution) -> Result<(), ApplicationError> {
+        if let Some(error) = &self.error {
+            return Err(error.clone());
+        }
+        self.resolved_conflicts.lock().unwrap().push((file_id, path, resolution));
+        Ok(())
+    }
+
+    async fn list_directory(&self, path: String, _depth: u32) -> Result<DirectoryListing, ApplicationError> {
+        if let Some(error) = &self.error {
+            return Err(error.clone());
+        }
+        Ok(DirectoryListing { path, entries: self.entries.clone(), next_page_token: None })
+    }
+
+    async fn get_sync_selection(&self) -> Result<SyncSelection, ApplicationError> {
+        Ok(self.sync_selection())
+    }
+
+    async fn set_sync_selection(&self, selection: SyncSelection) -> Result<(), ApplicationError> {
+        *self.sync_selection.lock().unwrap() = selection;
+        Ok(())
+    }
+}
@@ -1,5 +1,7 @@
 use std::sync::{Arc, Mutex};
+use common::domain::directory_listing::DirectoryEntry;
 use common::domain::errors::ApplicationError;
+use common::domain::sync_selection::SyncSelection;
 use crate::ports::driven::ui_driven_port::UIDrivenPort;
 
 #[allow(dead_code)]
@@ -10,6 +12,7 @@ pub struct FakeUIAdapter {
     home_view_shown: Arc<Mutex<bool>>,
     loading_view_shown: Arc<Mutex<bool>>,
     login_url: Arc<Mutex<Option<String>>>,
+    sync_selection_view: Arc<Mutex<Option<(Vec<DirectoryEntry>, SyncSelection)>>>,
 }
 
 #[allow(dead_code)]
@@ -21,6 +24,7 @@ impl FakeUIAdapter {
             home_view_shown: Arc::new(Mutex::new(false)),
             loading_view_shown: Arc::new(Mutex::new(false)),
             login_url: Arc::new(Mutex::new(None)),
+            sync_selection_view: Arc::new(Mutex::new(None)),
         }
     }
 
@@ -41,6 +45,10 @@ impl FakeUIAdapter {
     }
 
     pub fn login_url_shown(&self) -> Option<String> { self.login_url.lock().unwrap().clone() }
+
+    pub fn sync_selection_view_shown(&self) -> Option<(Vec<DirectoryEntry>, SyncSelection)> {
+        self.sync_selection_view.lock().unwrap().clone()
+    }
 }
 
 impl UIDrivenPort for FakeUIAdapter {
@@ -60,4 +68,8 @@ impl UIDrivenPort for FakeUIAdapter {
     fn show_loading_view(&mut self) {
         *self.loading_view_shown.lock().unwrap() = true;
     }
+
+    fn show_sync_selection_view(&mut self, folders: Vec<DirectoryEntry>, selection: SyncSelection) {
+        *self.sync_selection_view.lock().unwrap() = Some((folders, selection));
+    }
 }
\ No newline at end of file
@@ -1,7 +1,9 @@
 use std::time::Duration;
 use common::application_error;
-use common::domain::defaults::CONNECTION_TIMEOUT_SECONDS;
+use common::domain::defaults::{CONNECTION_TIMEOUT_SECONDS, SYNC_SELECTION_DEPTH};
+use common::domain::directory_listing::{DirectoryEntry, EntryKind};
 use common::domain::text_keys::TextKeys::ConnectionErrorMessage;
+use crate::domain::user_actions::{UserAction, UserActionReceiver};
 use crate::ports::driven::server_driven_port::ServerDrivenPort;
 use crate::ports::driven::ui_driven_port::UIDrivenPort;
 use common::kdrive::server_event::Event;
@@ -15,6 +17,7 @@ where
     server: Server,
     ui: UI,
     timeout: Duration,
+    user_actions: Option<UserActionReceiver>,
 }
 
 impl<Server, UI> UICore<Server, UI>
@@ -27,7 +30,12 @@ where
     }
 
     pub fn with_timeout(server: Server, ui: UI, timeout: Duration) -> Self {
-        Self { server, ui, timeout }
+        Self { server, ui, timeout, user_actions: None }
+    }
+
+    pub fn with_user_actions(mut self, user_actions: UserActionReceiver) -> Self {
+        self.user_actions = Some(user_actions);
+        self
     }
 
     pub async fn run(&mut self) {
@@ -42,15 +50,42 @@ where
 
         self.start_up_view_logic().await;
 
-        // Listen to events
-        while let Some(Ok(server_event)) = events.next().await {
-            if let Some(event) = server_event.event {
-                self.handle_events(event);
+        // Listen to user actions and events
+        loop {
+            tokio::select! {
+                biased;
+                Some(action) = next_user_action(&mut self.user_actions) => {
+                    self.handle_user_action(action).await;
+                }
+                server_event = events.next() => match server_event {
+                    Some(Ok(server_event)) => {
+                        if let Some(event) = server_event.event {
+                            self.handle_events(event).await;
+                        }
+                    }
+                    _ => break,
+                }
+            }
+        }
+    }
+
+    async fn handle_user_action(&mut self, action: UserAction) {
+        match action {
+            UserAction::Logout => {
+                if let Err(error) = self.server.logout().await {
+                    self.ui.show_error_view(error);
+                }
             }
+            UserAction::ChooseFolders => self.sync_selection_view().await,
+            UserAction::SaveSyncSelection(selection) => match self.server.set_sync_selection(selection).await {
+                Ok(()) => self.ui.show_home_view(),
+                Err(error) => self.ui.show_error_view(error),
+            },
+            UserAction::CancelSyncSelection => self.ui.show_home_view(),
         }
     }
 
-    fn handle_events(&mut self, event: Event) {
+    async fn handle_events(&mut self, event: Event) {
         match event {
             Event::AuthFlowCompleted(_) => {
                 self.ui.show_home_view();
@@ -58,6 +93,24 @@ where
             Event::Error(err) => {
                 self.ui.show_error_view(err.into());
             }
+            Event::TokensRefreshed(_) => {
+                // Nothing changes for the user, the service keeps working
+            }
+            Event::LoggedOut(_) => {
+                self.auth_flow().await;
+            }
+            Event::CrawlProgress(_) => {
+                // The home view does not show the progress of the initial crawl yet
+            }
+            Event::SyncCompleted(_) | Event::RemoteChangesDetected(_) => {
+                // The service keeps both sides in line on its own
+            }
+            Event::ConflictDetected(_) => {
+                // The home view does not list conflicts yet, they wait until resolved through the server port
+            }
+            Event::DownloadProgress(_) => {
+                // The home view does not show transfers yet
+            }
         }
     }
 
@@ -78,6 +131,15 @@ where
         }
     }
 
+    async fn sync_selection_view(&mut self) {
+        let listing = self.server.list_directory(String::new(), SYNC_SELECTION_DEPTH).await;
+        let selection = self.server.get_sync_selection().await;
+        match (listing, selection) {
+            (Ok(listing), Ok(selection)) => self.ui.show_sync_selection_view(folders(listing.entries), selection),
+            (Err(error), _) | (_, Err(error)) => self.ui.show_error_view(error),
+        }
+    }
+
     async fn auth_flow(&mut self) {
         match self.server.start_initial_auth_flow().await {
             Ok(url) => {
@@ -95,6 +157,22 @@ where
     }
 }
 
+/// Leaves out the files, only folders can be selected
+fn folders(entries: Vec<DirectoryEntry>) -> Vec<DirectoryEntry> {
+    entries
+        .into_iter()
+        .filter(|entry| entry.kind == EntryKind::Directory)
+        .map(|entry| DirectoryEntry { children: folders(entry.children), ..entry })
+        .collect()
+}
+
+async fn next_user_action(user_actions: &mut Option<UserActionReceiver>) -> Option<UserAction> {
+    match user_actions {
+        Some(user_actions) => user_actions.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::time::Duration;
@@ -105,7 +183,20 @@ mod tests {
     use common::domain::defaults::CONNECTION_TIMEOUT_SECONDS;
     use common::domain::text_keys::TextKeys::ConnectionErrorMessage;
     use common::kdrive::server_event::Event;
-    use common::kdrive::AuthFlowCompleted;
+    use common::kdrive::{AuthFlowCompleted, LoggedOut};
+    use common::domain::directory_listing::SyncStatus;
+    use common::domain::sync_selection::SyncSelection;
+    use crate::domain::user_actions::UserAction;
+
+    fn entry(id: u64, name: &str, kind: EntryKind, children: Vec<DirectoryEntry>) -> DirectoryEntry {
+        DirectoryEntry { id, name: name.to_string(), kind, size: None, modified_at: 0, sync_status: SyncStatus::Synced, children }
+    }
+
+    async fn run_with_action(server: FakeServerAdapter, ui: FakeUIAdapter, action: UserAction) {
+        let (actions, receiver) = tokio::sync::mpsc::unbounded_channel();
+        actions.send(action).unwrap();
+        UICore::new(server, ui).with_user_actions(receiver).run().await;
+    }
 
     #[tokio::test]
     async fn shows_error_view_when_server_returns_error() {
@@ -221,4 +312,70 @@ mod tests {
 
         assert!(ui.error_view_was_shown());
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn shows_login_view_when_logged_out_event_received() {
+        // Given an authenticated user
+        let mut server = FakeServerAdapter::with_event(Event::LoggedOut(LoggedOut {}));
+        server.set_authenticated(true);
+        let ui = FakeUIAdapter::new();
+        let mut core = UICore::new(server, ui.clone());
+
+        // When the server reports that the user logged out
+        core.run().await;
+
+        // Then the login view is shown again
+        assert!(ui.login_view_was_shown());
+    }
+
+    #[tokio::test]
+    async fn logs_out_when_logout_action_received() {
+        // Given an authenticated user who clicked logout
+        let server = FakeServerAdapter::new(true);
+        let ui = FakeUIAdapter::new();
+        let (actions, receiver) = tokio::sync::mpsc::unbounded_channel();
+        actions.send(UserAction::Logout).unwrap();
+        let mut core = UICore::new(server.clone(), ui.clone()).with_user_actions(receiver);
+
+        // When
+        core.run().await;
+
+        // Then the server is asked to log out
+        assert!(server.logout_was_called());
+    }
+
+    #[tokio::test]
+    async fn shows_the_folders_of_the_drive_when_choosing_folders() {
+        // Given a drive with a folder holding a file and a folder, and one folder left out
+        let server = FakeServerAdapter::new(true)
+            .with_entries(vec![
+                entry(2, "Projects", EntryKind::Directory, vec![
+                    entry(3, "Current", EntryKind::Directory, vec![]),
+                    entry(4, "plan.txt", EntryKind::File, vec![]),
+                ]),
+                entry(5, "notes.txt", EntryKind::File, vec![]),
+            ])
+            .with_sync_selection(SyncSelection::default().with_rule(3, false));
+        let ui = FakeUIAdapter::new();
+
+        // When the user chooses folders
+        run_with_action(server, ui.clone(), UserAction::ChooseFolders).await;
+
+        // Then only the folders are shown, with the current selection
+        let (folders, selection) = ui.sync_selection_view_shown().unwrap();
+        assert_eq!(folders, [entry(2, "Projects", EntryKind::Directory, vec![entry(3, "Current", EntryKind::Directory, vec![])])]);
+        assert_eq!(selection.rule(3), Some(false));
+    }
+
+    #[tokio::test]
+    async fn saves_the_selection_and_goes_back_home() {
+        let server = FakeServerAdapter::new(false);
+        let ui = FakeUIAdapter::new();
+        let selection = SyncSelection::default().with_rule(2, false);
+
+        run_with_action(server.clone(), ui.clone(), UserAction::SaveSyncSelection(selection.clone())).await;
+
+        assert_eq!(server.sync_selection(), selection);
+        assert!(ui.home_view_was_shown());
+    }
+}
@@ -0,0 +1,13 @@
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+use common::domain::sync_selection::SyncSelection;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UserAction {
+    Logout,
+    ChooseFolders,
+    SaveSyncSelection(SyncSelection),
+    CancelSyncSelection,
+}
+
+pub type UserActionSender = UnboundedSender<UserAction>;
+pub type UserActionReceiver = UnboundedReceiver<UserAction>;
\ No newline at end of file
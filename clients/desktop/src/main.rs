@@ -1,3 +1,5 @@
+use std::cell::RefCell;
+use std::rc::Rc;
 use dioxus::prelude::*;
 use dioxus::desktop::use_window;
 use dioxus::desktop::tao::window::Icon;
@@ -13,6 +15,7 @@ use common::domain::text_keys::TextKeys::FailedToLoadWindowsIcon;
 use common::domain::text_keys::TextKeys::FailedToLoadLinuxIcon;
 use common::domain::text_keys::TextKeys::WindowTitle;
 use crate::adapters::dioxus_adapter::DioxusAdapter;
+use crate::domain::settings::ClientSettings;
 use crate::domain::ui_core::UICore;
 
 use crate::ui::views::ConnectingView;
@@ -45,16 +48,28 @@ fn App() -> Element {
         window.set_window_icon(Some(load_icon(i18n_for_window.clone())));
     });
 
+    let (actions, user_actions) = use_hook(|| {
+        let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+        (sender, Rc::new(RefCell::new(Some(receiver))))
+    });
+
     let dioxus_adapter =
-        use_hook(|| DioxusAdapter::new(element_signal, i18n.clone()));
+        use_hook(|| DioxusAdapter::new(element_signal, i18n.clone(), actions));
+
+    let settings = use_hook(ClientSettings::from_environment);
 
     // Start UICore
     use_future(move || {
         let adapter_for_core = dioxus_adapter.clone();
+        let settings = settings.clone();
+        let user_actions = user_actions.borrow_mut().take();
         async move {
-            if let Ok(grpc_adapter) = GrpcServerAdapter::connect().await {
+            if let Ok(grpc_adapter) = GrpcServerAdapter::connect(&settings).await {
                 let mut core =
                     UICore::new(grpc_adapter, adapter_for_core);
+                if let Some(user_actions) = user_actions {
+                    core = core.with_user_actions(user_actions);
+                }
                 core.run().await;
             }
         }
@@ -1,9 +1,19 @@
+use common::domain::directory_listing::DirectoryListing;
 use common::domain::errors::ApplicationError;
+use common::domain::sync_conflict::ConflictResolution;
+use common::domain::sync_selection::SyncSelection;
 use crate::domain::events::ServerEventStream;
 
 pub trait ServerDrivenPort: Send + Sync + Clone + 'static {
+    /// True while the service has tokens for the user, also when the access token expired and waits for a refresh
     fn is_authenticated(&self) -> impl Future<Output = Result<bool, ApplicationError>> + Send;
     fn start_initial_auth_flow(&self) -> impl Future<Output = Result<String, ApplicationError>> + Send;
     fn continue_initial_auth_flow(&self) -> impl Future<Output = Result<(), ApplicationError>> + Send;
+    fn logout(&self) -> impl Future<Output = Result<(), ApplicationError>> + Send;
     fn subscribe_events(&self) -> impl Future<Output = Result<ServerEventStream, ApplicationError>> + Send;
+    fn resolve_conflict(&self, file_id: u64, path: String, resolution: ConflictResolution) -> impl Future<Output = Result<(), ApplicationError>> + Send;
+    /// Every page of the listing, `depth` as for the ListDirectory RPC
+    fn list_directory(&self, path: String, depth: u32) -> impl Future<Output = Result<DirectoryListing, ApplicationError>> + Send;
+    fn get_sync_selection(&self) -> impl Future<Output = Result<SyncSelection, ApplicationError>> + Send;
+    fn set_sync_selection(&self, selection: SyncSelection) -> impl Future<Output = Result<(), ApplicationError>> + Send;
 }
\ No newline at end of file
@@ -1,8 +1,12 @@
+use common::domain::directory_listing::DirectoryEntry;
 use common::domain::errors::ApplicationError;
+use common::domain::sync_selection::SyncSelection;
 
 pub trait UIDrivenPort {
     fn show_login_view(&mut self, url: String);
     fn show_error_view(&mut self, error: ApplicationError);
     fn show_home_view(&mut self);
     fn show_loading_view(&mut self);
+    /// The folders of the drive, with the folders inside them, to check the ones to sync
+    fn show_sync_selection_view(&mut self, folders: Vec<DirectoryEntry>, selection: SyncSelection);
 }
@@ -0,0 +1,56 @@
+use common::domain::directory_listing::DirectoryEntry;
+use common::domain::sync_selection::SyncSelection;
+use dioxus::prelude::*;
+
+/// One level of folders with a checkbox each, the folders inside them are listed below their parent
+#[component]
+pub fn FolderTree(folders: Vec<DirectoryEntry>, parent_included: bool, selection: Signal<SyncSelection>) -> Element {
+    rsx! {
+        ul {
+            class: "pl-4",
+            for folder in folders {
+                FolderRow { key: "{folder.id}", folder, parent_included, selection }
+            }
+        }
+    }
+}
+
+#[component]
+fn FolderRow(folder: DirectoryEntry, parent_included: bool, selection: Signal<SyncSelection>) -> Element {
+    let mut selection = selection;
+    let included = selection.read().rule(folder.id).unwrap_or(parent_included);
+    let name = folder.name.clone();
+    let children = folder.children.clone();
+
+    rsx! {
+        li {
+            label {
+                class: "flex items-center gap-2 py-1 cursor-pointer",
+                input {
+                    r#type: "checkbox",
+                    class: "accent-blue-600",
+                    checked: included,
+                    onchange: move |_| toggle(&mut selection.write(), &folder, parent_included, !included),
+                }
+                span { "{name}" }
+            }
+            if !children.is_empty() {
+                FolderTree { folders: children, parent_included: included, selection }
+            }
+        }
+    }
+}
+
+/// Checking or unchecking a folder decides for everything below it, a rule that says what the parent says is dropped
+fn toggle(selection: &mut SyncSelection, folder: &DirectoryEntry, parent_included: bool, included: bool) {
+    let mut pending: Vec<&DirectoryEntry> = folder.children.iter().collect();
+    while let Some(child) = pending.pop() {
+        selection.rules.remove(&child.id);
+        pending.extend(child.children.iter());
+    }
+    if included == parent_included {
+        selection.rules.remove(&folder.id);
+    } else {
+        selection.rules.insert(folder.id, included);
+    }
+}
@@ -6,3 +6,5 @@ mod hero;
 pub use hero::Hero;
 mod title_banner;
 pub use title_banner::TitleBanner;
+mod folder_tree;
+pub use folder_tree::FolderTree;
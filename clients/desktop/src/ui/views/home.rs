@@ -1,11 +1,34 @@
 use crate::ui::components::Hero;
+use common::domain::text_keys::TextKeys::{ChooseFoldersBtn, LogoutBtn};
+use common::ports::i18n_driven_port::I18nDrivenPort;
 use dioxus::prelude::*;
 
 /// The Home page component that will be rendered when the current route is `[Route::Home]`
 #[component]
-pub fn Home() -> Element {
+pub fn Home<I18nPort: I18nDrivenPort + 'static>(
+    i18n: I18nPort,
+    on_choose_folders: EventHandler<MouseEvent>,
+    on_logout: EventHandler<MouseEvent>,
+) -> Element {
     rsx! {
         Hero {}
 
+        div {
+            class: "flex justify-center gap-4 p-8",
+
+            button {
+                class: "px-8 py-2 bg-blue-600 hover:bg-blue-500 text-white font-bold text-lg rounded-xl
+                        transition-all duration-200 transform active:scale-95 shadow-lg shadow-blue-900/20 cursor-pointer",
+                onclick: move |event| on_choose_folders.call(event),
+                "{i18n.t(ChooseFoldersBtn)}"
+            }
+
+            button {
+                class: "px-8 py-2 bg-slate-800 hover:bg-slate-700 text-white font-bold text-lg rounded-xl
+                        transition-all duration-200 transform active:scale-95 border border-slate-700 cursor-pointer",
+                onclick: move |event| on_logout.call(event),
+                "{i18n.t(LogoutBtn)}"
+            }
+        }
     }
 }
@@ -19,3 +19,6 @@ pub use connecting_view::ConnectingView;
 
 mod error_view;
 pub use error_view::ErrorView;
+
+mod sync_selection_view;
+pub use sync_selection_view::SyncSelectionView;
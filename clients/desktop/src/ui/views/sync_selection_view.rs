@@ -0,0 +1,57 @@
+use common::domain::directory_listing::DirectoryEntry;
+use common::domain::sync_selection::SyncSelection;
+use common::domain::text_keys::TextKeys::{CancelBtn, SaveBtn, SyncSelectionTitle};
+use common::ports::i18n_driven_port::I18nDrivenPort;
+use dioxus::prelude::*;
+use crate::ui::components::{FolderTree, TitleBanner};
+
+#[component]
+pub fn SyncSelectionView<I18nPort: I18nDrivenPort + 'static>(
+    i18n: I18nPort,
+    folders: Vec<DirectoryEntry>,
+    selection: SyncSelection,
+    on_save: EventHandler<SyncSelection>,
+    on_cancel: EventHandler<MouseEvent>,
+) -> Element {
+    let selection = use_signal(|| selection);
+
+    rsx! {
+        div {
+            class: "min-h-screen flex flex-col items-center bg-[#0f1116] p-8 text-white",
+
+            div {
+                class: "pt-[5vh] flex flex-col items-center gap-y-6 w-full max-w-xl",
+
+                TitleBanner { i18n: i18n.clone() },
+
+                h2 {
+                    class: "text-xl",
+                    {i18n.t(SyncSelectionTitle)}
+                }
+
+                div {
+                    class: "w-full max-h-[50vh] overflow-y-auto bg-slate-900/80 p-4 rounded-lg border border-slate-800",
+                    FolderTree { folders, parent_included: true, selection }
+                }
+
+                div {
+                    class: "flex gap-4",
+
+                    button {
+                        class: "px-8 py-2 bg-slate-800 hover:bg-slate-700 text-white font-bold text-lg rounded-xl
+                                transition-all duration-200 transform active:scale-95 border border-slate-700 cursor-pointer",
+                        onclick: move |event| on_cancel.call(event),
+                        "{i18n.t(CancelBtn)}"
+                    }
+
+                    button {
+                        class: "px-8 py-2 bg-blue-600 hover:bg-blue-500 text-white font-bold text-lg rounded-xl
+                                transition-all duration-200 transform active:scale-95 shadow-lg shadow-blue-900/20 cursor-pointer",
+                        onclick: move |_| on_save.call(selection()),
+                        "{i18n.t(SaveBtn)}"
+                    }
+                }
+            }
+        }
+    }
+}
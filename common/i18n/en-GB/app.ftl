@@ -38,3 +38,59 @@ LoadingMessage = Loading
 NotAuthenticated = The application has not been authenticated yet
 KeyringNotAvailable = Trying to use the Keyring on your OS while it is not available. This is a bug.
 NotImplemented = This functionality has not been implemented yet
+CouldNotDeleteTokenFile = Could not delete token file: { $reason }
+CouldNotDeleteTokensFromKeyring = Could not delete tokens from keyring: { $reason }
+TokenRevocationFailed = Token revocation failed: { $reason }
+LogoutBtn = Log out
+InvalidOAuthState = The login response did not match the login request. Please start logging in again.
+AuthFlowTimedOut = Logging in took too long. Please try again.
+AuthFlowCancelled = Logging in was cancelled.
+CallbackPortInUse = The port for the login callback is already in use by another application
+CouldNotReadConfigFile = Could not read the configuration file
+CouldNotParseConfigFile = The configuration file is not valid
+InvalidLogLevel = Invalid log level, use error, warn, info, debug or trace
+InvalidListenAddress = Invalid listen address
+NoHomeFolderFound = No home folder found
+UnknownCommandLineArgument = Unknown command line argument
+MissingCommandLineValue = Missing value for command line argument
+InvalidServerUrl = Invalid service URL
+UnixSocketsNotSupported = Unix domain sockets are not supported on this system
+CouldNotBindSocket = Could not open the service socket
+SocketInUse = Another service is already listening on the socket
+UnauthenticatedClient = The service refused the request because the session secret is missing or wrong
+CouldNotWriteSessionSecret = Could not write the session secret of the service
+CouldNotReadSessionSecret = Could not read the session secret of the service, is the service running?
+ApiNotAuthenticated = kDrive refused the request because you are not logged in
+ApiAccessDenied = You do not have access to this file or drive on kDrive
+ApiNotFound = The file or folder does not exist on kDrive
+ApiConflict = A file or folder with this name already exists on kDrive
+ApiRateLimited = kDrive received too many requests, try again later
+ApiServerError = kDrive could not handle the request
+ApiRequestFailed = Could not reach kDrive
+ApiUnexpectedResponse = kDrive sent an unexpected response
+CouldNotOpenMetadataStore = Could not open the file metadata database
+MetadataStoreTooNew = The file metadata database was created by a newer version of the service
+MetadataStoreQueryFailed = Could not read or update the file metadata database
+InvalidPageToken = The page token is not valid
+DirectoryNotFound = The folder does not exist
+NotADirectory = The path is a file, not a folder
+NoDriveAvailable = No kDrive is available for this account
+SearchIndexNotReady = The drive has not been read yet, search is available once the first files are stored
+LocalFileNotFound = There is no file or folder at this path in the sync folder
+LocalFileExists = There already is a file or folder at this path in the sync folder
+LocalFileError = Could not read or change a file in the sync folder
+InvalidLocalPath = This path does not point into the sync folder
+LocalWatcherFailed = Could not watch the sync folder for changes
+SyncNotReady = Syncing starts once the whole drive is indexed
+InvalidConflictPolicy = Invalid conflict policy, use keep_both, prefer_local, prefer_remote or ask
+InvalidConflictResolution = Choose how the conflict is resolved
+ConflictNotFound = There is no conflict for this file
+DownloadCorrupted = The downloaded file does not match the file on kDrive, it will be downloaded again
+SyncStopped = The sync was stopped because the user logged out
+SyncRootOfAnotherDrive = The sync folder holds the files of another kDrive, empty it or choose another folder
+LocalFileChanged = The file changed in the sync folder during the sync, the next sync takes the change in
+SyncPlanInconsistent = The stored state of the sync does not add up, the sync could not be planned
+ChooseFoldersBtn = Choose folders
+SyncSelectionTitle = Folders to sync on this computer
+SaveBtn = Save
+CancelBtn = Cancel
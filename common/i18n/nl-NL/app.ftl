@@ -37,4 +37,60 @@ TransportError = Transport fout: { $reason }
 LoadingMessage = Laden
 NotAuthenticated = De applicatie is nog niet geauthenticeerd.
 KeyringNotAvailable = Geprobeerd om de Keyring op uw besturingssysteem te gebruiken terwijl het niet beschikbaar is. Dit is een bug.
-NotImplemented = Deze functionaliteit is nog niet geimplementeerd.
\ No newline at end of file
+NotImplemented = Deze functionaliteit is nog niet geimplementeerd.
+CouldNotDeleteTokenFile = Kon het tokenbestand niet verwijderen: { $reason }
+CouldNotDeleteTokensFromKeyring = Kon de tokens niet uit de keyring verwijderen: { $reason }
+TokenRevocationFailed = Intrekken van het token is mislukt: { $reason }
+LogoutBtn = Uitloggen
+InvalidOAuthState = Het inlogantwoord hoort niet bij het inlogverzoek. Start het inloggen opnieuw.
+AuthFlowTimedOut = Het inloggen duurde te lang. Probeer het opnieuw.
+AuthFlowCancelled = Het inloggen is geannuleerd.
+CallbackPortInUse = De poort voor het inlogantwoord is al in gebruik door een andere applicatie
+CouldNotReadConfigFile = Kon het configuratiebestand niet lezen
+CouldNotParseConfigFile = Het configuratiebestand is niet geldig
+InvalidLogLevel = Ongeldig logniveau, gebruik error, warn, info, debug of trace
+InvalidListenAddress = Ongeldig luisteradres
+NoHomeFolderFound = Geen thuismap gevonden
+UnknownCommandLineArgument = Onbekend opdrachtregelargument
+MissingCommandLineValue = Ontbrekende waarde voor opdrachtregelargument
+InvalidServerUrl = Ongeldige service-URL
+UnixSocketsNotSupported = Unix domain sockets worden niet ondersteund op dit systeem
+CouldNotBindSocket = Kon de socket van de dienst niet openen
+SocketInUse = Er luistert al een andere dienst op de socket
+UnauthenticatedClient = De dienst heeft het verzoek geweigerd omdat het sessiegeheim ontbreekt of onjuist is
+CouldNotWriteSessionSecret = Kon het sessiegeheim van de dienst niet schrijven
+CouldNotReadSessionSecret = Kon het sessiegeheim van de dienst niet lezen, draait de dienst?
+ApiNotAuthenticated = kDrive heeft het verzoek geweigerd omdat je niet bent ingelogd
+ApiAccessDenied = Je hebt geen toegang tot dit bestand of deze drive op kDrive
+ApiNotFound = Het bestand of de map bestaat niet op kDrive
+ApiConflict = Er bestaat al een bestand of map met deze naam op kDrive
+ApiRateLimited = kDrive heeft te veel verzoeken ontvangen, probeer het later opnieuw
+ApiServerError = kDrive kon het verzoek niet afhandelen
+ApiRequestFailed = Kon kDrive niet bereiken
+ApiUnexpectedResponse = kDrive stuurde een onverwacht antwoord
+CouldNotOpenMetadataStore = Kon de database met bestandsgegevens niet openen
+MetadataStoreTooNew = De database met bestandsgegevens is gemaakt door een nieuwere versie van de service
+MetadataStoreQueryFailed = Kon de database met bestandsgegevens niet lezen of bijwerken
+InvalidPageToken = Het paginatoken is ongeldig
+DirectoryNotFound = De map bestaat niet
+NotADirectory = Het pad is een bestand, geen map
+NoDriveAvailable = Er is geen kDrive beschikbaar voor dit account
+SearchIndexNotReady = De drive is nog niet ingelezen, zoeken kan zodra de eerste bestanden zijn opgeslagen
+LocalFileNotFound = Er is geen bestand of map op dit pad in de synchronisatiemap
+LocalFileExists = Er staat al een bestand of map op dit pad in de synchronisatiemap
+LocalFileError = Kon een bestand in de synchronisatiemap niet lezen of wijzigen
+InvalidLocalPath = Dit pad wijst niet naar een plek in de synchronisatiemap
+LocalWatcherFailed = Kon de synchronisatiemap niet op wijzigingen controleren
+SyncNotReady = Synchroniseren begint zodra de hele drive is geïndexeerd
+InvalidConflictPolicy = Ongeldig conflictbeleid, gebruik keep_both, prefer_local, prefer_remote of ask
+InvalidConflictResolution = Kies hoe het conflict wordt opgelost
+ConflictNotFound = Er is geen conflict voor dit bestand
+DownloadCorrupted = Het gedownloade bestand komt niet overeen met het bestand op kDrive, het wordt opnieuw gedownload
+SyncStopped = Het synchroniseren is gestopt omdat de gebruiker is uitgelogd
+SyncRootOfAnotherDrive = De synchronisatiemap bevat de bestanden van een andere kDrive, maak hem leeg of kies een andere map
+LocalFileChanged = Het bestand is tijdens het synchroniseren gewijzigd in de synchronisatiemap, de volgende synchronisatie neemt de wijziging mee
+SyncPlanInconsistent = De opgeslagen staat van de synchronisatie klopt niet, de synchronisatie kon niet worden gepland
+ChooseFoldersBtn = Mappen kiezen
+SyncSelectionTitle = Mappen om op deze computer te synchroniseren
+SaveBtn = Opslaan
+CancelBtn = Annuleren
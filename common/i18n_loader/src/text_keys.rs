@@ -55,4 +55,60 @@ pub enum TextKeys {
     NotAuthenticated,
     KeyringNotAvailable,
     NotImplemented,
+    CouldNotDeleteTokenFile,
+    CouldNotDeleteTokensFromKeyring,
+    TokenRevocationFailed,
+    LogoutBtn,
+    InvalidOAuthState,
+    AuthFlowTimedOut,
+    AuthFlowCancelled,
+    CallbackPortInUse,
+    CouldNotReadConfigFile,
+    CouldNotParseConfigFile,
+    InvalidLogLevel,
+    InvalidListenAddress,
+    NoHomeFolderFound,
+    UnknownCommandLineArgument,
+    MissingCommandLineValue,
+    InvalidServerUrl,
+    UnixSocketsNotSupported,
+    CouldNotBindSocket,
+    SocketInUse,
+    UnauthenticatedClient,
+    CouldNotWriteSessionSecret,
+    CouldNotReadSessionSecret,
+    ApiNotAuthenticated,
+    ApiAccessDenied,
+    ApiNotFound,
+    ApiConflict,
+    ApiRateLimited,
+    ApiServerError,
+    ApiRequestFailed,
+    ApiUnexpectedResponse,
+    CouldNotOpenMetadataStore,
+    MetadataStoreTooNew,
+    MetadataStoreQueryFailed,
+    InvalidPageToken,
+    DirectoryNotFound,
+    NotADirectory,
+    NoDriveAvailable,
+    SearchIndexNotReady,
+    LocalFileNotFound,
+    LocalFileExists,
+    LocalFileError,
+    InvalidLocalPath,
+    LocalWatcherFailed,
+    SyncNotReady,
+    InvalidConflictPolicy,
+    InvalidConflictResolution,
+    ConflictNotFound,
+    DownloadCorrupted,
+    SyncStopped,
+    SyncRootOfAnotherDrive,
+    LocalFileChanged,
+    SyncPlanInconsistent,
+    ChooseFoldersBtn,
+    SyncSelectionTitle,
+    SaveBtn,
+    CancelBtn,
 }
\ No newline at end of file
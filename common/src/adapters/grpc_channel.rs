@@ -0,0 +1,82 @@
+use std::time::Duration;
+use tonic::transport::{Channel, Endpoint};
+use crate::application_error;
+use crate::domain::defaults::UNIX_SOCKET_URL_PREFIX;
+use crate::domain::errors::ApplicationError;
+use crate::domain::text_keys::TextKeys::{ConnectionErrorMessage, InvalidServerUrl};
+
+/// Connects to the service on "http://host:port" or on a Unix domain socket with "unix:///path/to/socket"
+pub async fn connect_channel(url: &str, connect_timeout: Duration) -> Result<Channel, ApplicationError> {
+    match url.strip_prefix(UNIX_SOCKET_URL_PREFIX) {
+        Some(path) => connect_unix_socket(path, connect_timeout).await,
+        None => Channel::from_shared(url.to_string())
+            .map_err(|_| application_error!(InvalidServerUrl, url))?
+            .connect_timeout(connect_timeout)
+            .connect()
+            .await
+            .map_err(|e| application_error!(ConnectionErrorMessage, e.to_string())),
+    }
+}
+
+#[cfg(unix)]
+async fn connect_unix_socket(path: &str, connect_timeout: Duration) -> Result<Channel, ApplicationError> {
+    use hyper_util::rt::TokioIo;
+    use tokio::net::UnixStream;
+    use tonic::transport::Uri;
+    use tower::service_fn;
+
+    if path.is_empty() {
+        return Err(application_error!(InvalidServerUrl, UNIX_SOCKET_URL_PREFIX));
+    }
+    let path = path.to_string();
+
+    // The URI is only used for the HTTP/2 authority, the connector ignores it
+    Endpoint::from_static("http://localhost")
+        .connect_timeout(connect_timeout)
+        .connect_with_connector(service_fn(move |_: Uri| {
+            let path = path.clone();
+            async move { Ok::<_, std::io::Error>(TokioIo::new(UnixStream::connect(path).await?)) }
+        }))
+        .await
+        .map_err(|e| application_error!(ConnectionErrorMessage, e.to_string()))
+}
+
+#[cfg(not(unix))]
+async fn connect_unix_socket(_path: &str, _connect_timeout: Duration) -> Result<Channel, ApplicationError> {
+    use crate::domain::text_keys::TextKeys::UnixSocketsNotSupported;
+
+    Err(application_error!(UnixSocketsNotSupported))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(2);
+
+    #[tokio::test]
+    async fn invalid_url_returns_an_error() {
+        let err = connect_channel("not a url", TIMEOUT).await.expect_err("Expected InvalidServerUrl error");
+
+        assert_eq!(err.text_key, InvalidServerUrl);
+    }
+
+    #[tokio::test]
+    async fn unix_url_without_path_returns_an_error() {
+        let err = connect_channel("unix://", TIMEOUT).await.expect_err("Expected InvalidServerUrl error");
+
+        assert_eq!(err.text_key, InvalidServerUrl);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn missing_socket_returns_a_connection_error() {
+        let path = std::env::temp_dir().join(format!("kdrive_rs_missing_{}.sock", std::process::id()));
+
+        let err = connect_channel(&format!("unix://{}", path.display()), TIMEOUT)
+            .await
+            .expect_err("Expected ConnectionErrorMessage error");
+
+        assert_eq!(err.text_key, ConnectionErrorMessage);
+    }
+}
@@ -1 +1,2 @@
-pub mod i18n_embedded_adapter;
\ No newline at end of file
+pub mod i18n_embedded_adapter;pub mod grpc_channel;
+pub mod session_secret;
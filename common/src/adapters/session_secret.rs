@@ -0,0 +1,79 @@
+use std::fs;
+use std::path::Path;
+use tonic::metadata::{Ascii, MetadataValue};
+use tonic::service::Interceptor;
+use tonic::{Request, Status};
+use crate::application_error;
+use crate::domain::defaults::{SESSION_SECRET_METADATA_KEY, SESSION_SECRET_SCHEME};
+use crate::domain::errors::ApplicationError;
+use crate::domain::text_keys::TextKeys::CouldNotReadSessionSecret;
+
+/// Attaches the session secret of the service to every request of a client
+#[derive(Debug, Clone)]
+pub struct SessionSecretInterceptor {
+    authorization: MetadataValue<Ascii>,
+}
+
+impl SessionSecretInterceptor {
+    pub fn new(secret: &str) -> Result<Self, ApplicationError> {
+        let authorization = format!("{SESSION_SECRET_SCHEME}{secret}")
+            .parse()
+            .map_err(|_| application_error!(CouldNotReadSessionSecret))?;
+
+        Ok(Self { authorization })
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, ApplicationError> {
+        Self::new(&read_session_secret(path)?)
+    }
+}
+
+impl Interceptor for SessionSecretInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        request
+            .metadata_mut()
+            .insert(SESSION_SECRET_METADATA_KEY, self.authorization.clone());
+        Ok(request)
+    }
+}
+
+pub fn read_session_secret(path: &Path) -> Result<String, ApplicationError> {
+    let secret = fs::read_to_string(path)
+        .map_err(|e| application_error!(CouldNotReadSessionSecret, format!("{}: {}", path.display(), e)))?;
+
+    Ok(secret.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interceptor_attaches_the_secret_as_bearer_token() {
+        let mut interceptor = SessionSecretInterceptor::new("0123abcd").unwrap();
+
+        let request = interceptor.call(Request::new(())).unwrap();
+
+        assert_eq!(request.metadata().get(SESSION_SECRET_METADATA_KEY).unwrap(), "Bearer 0123abcd");
+    }
+
+    #[test]
+    fn secret_is_read_without_surrounding_whitespace() {
+        let path = std::env::temp_dir().join(format!("kdrive_rs_session_secret_{}", std::process::id()));
+        fs::write(&path, "0123abcd\n").unwrap();
+
+        let secret = read_session_secret(&path);
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(secret.unwrap(), "0123abcd");
+    }
+
+    #[test]
+    fn missing_secret_file_returns_an_error() {
+        let path = std::env::temp_dir().join("kdrive_rs_session_secret_that_does_not_exist");
+
+        let err = read_session_secret(&path).expect_err("Expected CouldNotReadSessionSecret error");
+
+        assert_eq!(err.text_key, CouldNotReadSessionSecret);
+    }
+}
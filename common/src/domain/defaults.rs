@@ -11,4 +11,44 @@ pub fn default_server_addr() -> std::net::SocketAddr {
 pub const DOMAIN: &str = "app";
 pub const DEFAULT_LANGUAGE : Language = Language::EnGb;
 pub const CONNECTION_TIMEOUT_SECONDS: u64 = 2;
+pub const DEFAULT_LISTING_PAGE_SIZE: usize = 500;
+pub const DEFAULT_SEARCH_LIMIT: usize = 100;
+pub const MAX_SEARCH_LIMIT: usize = 1000;
+/// How many levels of folders the desktop client shows to choose what is synced
+pub const SYNC_SELECTION_DEPTH: u32 = 3;
 pub const APPLICATION_ERROR_DETAIL_FIELD_NAME: &str = "error-detail";
+pub const UNIX_SOCKET_URL_PREFIX: &str = "unix://";
+pub const SOCKET_FOLDER_NAME: &str = "kdrive_rs";
+pub const SOCKET_FILE_NAME: &str = "kdrive.sock";
+pub const SESSION_SECRET_FILE_NAME: &str = "session_secret";
+/// The socket and session secret live in the per-user runtime folder, a folder of the user in the temp folder is
+/// the fallback on systems without one
+pub fn default_runtime_folder() -> std::path::PathBuf {
+    dirs::runtime_dir()
+        .map(|runtime_dir| runtime_dir.join(SOCKET_FOLDER_NAME))
+        .unwrap_or_else(user_temp_folder)
+}
+/// The temp folder is shared by every user on Unix, the user id keeps users out of each other's folder
+#[cfg(unix)]
+fn user_temp_folder() -> std::path::PathBuf {
+    // SAFETY: geteuid has no preconditions and cannot fail
+    let user_id = unsafe { libc::geteuid() };
+    std::env::temp_dir().join(format!("{SOCKET_FOLDER_NAME}_{user_id}"))
+}
+#[cfg(not(unix))]
+fn user_temp_folder() -> std::path::PathBuf {
+    std::env::temp_dir().join(SOCKET_FOLDER_NAME)
+}
+pub fn default_socket_path() -> std::path::PathBuf {
+    default_runtime_folder().join(SOCKET_FILE_NAME)
+}
+pub fn default_session_secret_path() -> std::path::PathBuf {
+    default_runtime_folder().join(SESSION_SECRET_FILE_NAME)
+}
+/// The session secret of a service on another socket lives next to that socket, so services side by side keep
+/// their own secret
+pub fn session_secret_path_next_to(socket_path: &std::path::Path) -> std::path::PathBuf {
+    socket_path.with_file_name(SESSION_SECRET_FILE_NAME)
+}
+pub const SESSION_SECRET_METADATA_KEY: &str = "authorization";
+pub const SESSION_SECRET_SCHEME: &str = "Bearer ";
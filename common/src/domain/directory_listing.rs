@@ -1,4 +1,236 @@
 use serde::{Deserialize, Serialize};
+use crate::application_error;
+use crate::domain::defaults::DEFAULT_LISTING_PAGE_SIZE;
+use crate::domain::errors::ApplicationError;
+use crate::domain::text_keys::TextKeys::InvalidPageToken;
+use crate::kdrive;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EntryKind {
+    File,
+    Directory,
+}
+
+/// How far an entry is in being synced, `Unknown` when the service has no record of it yet
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SyncStatus {
+    Unknown,
+    PendingDownload,
+    PendingUpload,
+    Synced,
+    Conflict,
+    /// Left out of the sync by an ignore pattern
+    Ignored,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct DirectoryEntry {
+    /// The kDrive file id
+    pub id: u64,
+    pub name: String,
+    pub kind: EntryKind,
+    /// Directories have no size
+    pub size: Option<u64>,
+    /// Seconds since the Unix epoch
+    pub modified_at: i64,
+    pub sync_status: SyncStatus,
+    /// Filled for directories as long as the requested depth allows it
+    pub children: Vec<DirectoryEntry>,
+}
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
-pub struct DirectoryListing {}
\ No newline at end of file
+pub struct DirectoryListing {
+    pub path: String,
+    pub entries: Vec<DirectoryEntry>,
+    /// Asks for the entries after this page, `None` on the last page
+    pub next_page_token: Option<String>,
+}
+
+/// Which of the top level entries of a listing to return, the token of a page is the offset of its first entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListingPage {
+    pub offset: usize,
+    pub size: usize,
+}
+
+impl ListingPage {
+    /// An empty token asks for the first page, size 0 for the default size
+    pub fn parse(page_token: &str, page_size: u32) -> Result<Self, ApplicationError> {
+        let offset = if page_token.is_empty() {
+            0
+        } else {
+            page_token.parse().map_err(|_| application_error!(InvalidPageToken, page_token))?
+        };
+        let size = match page_size {
+            0 => DEFAULT_LISTING_PAGE_SIZE,
+            size => size as usize,
+        };
+        Ok(ListingPage { offset, size })
+    }
+
+    /// The token that asks for the page after this one
+    pub fn next_token(&self) -> String {
+        self.offset.saturating_add(self.size).to_string()
+    }
+}
+
+impl Default for ListingPage {
+    fn default() -> Self {
+        ListingPage { offset: 0, size: DEFAULT_LISTING_PAGE_SIZE }
+    }
+}
+
+impl From<EntryKind> for kdrive::EntryKind {
+    fn from(kind: EntryKind) -> Self {
+        match kind {
+            EntryKind::File => kdrive::EntryKind::File,
+            EntryKind::Directory => kdrive::EntryKind::Directory,
+        }
+    }
+}
+
+impl From<kdrive::EntryKind> for EntryKind {
+    fn from(kind: kdrive::EntryKind) -> Self {
+        match kind {
+            kdrive::EntryKind::File => EntryKind::File,
+            kdrive::EntryKind::Directory => EntryKind::Directory,
+        }
+    }
+}
+
+impl From<SyncStatus> for kdrive::SyncStatus {
+    fn from(status: SyncStatus) -> Self {
+        match status {
+            SyncStatus::Unknown => kdrive::SyncStatus::Unknown,
+            SyncStatus::PendingDownload => kdrive::SyncStatus::PendingDownload,
+            SyncStatus::PendingUpload => kdrive::SyncStatus::PendingUpload,
+            SyncStatus::Synced => kdrive::SyncStatus::Synced,
+            SyncStatus::Conflict => kdrive::SyncStatus::Conflict,
+            SyncStatus::Ignored => kdrive::SyncStatus::Ignored,
+        }
+    }
+}
+
+impl From<kdrive::SyncStatus> for SyncStatus {
+    fn from(status: kdrive::SyncStatus) -> Self {
+        match status {
+            kdrive::SyncStatus::Unknown => SyncStatus::Unknown,
+            kdrive::SyncStatus::PendingDownload => SyncStatus::PendingDownload,
+            kdrive::SyncStatus::PendingUpload => SyncStatus::PendingUpload,
+            kdrive::SyncStatus::Synced => SyncStatus::Synced,
+            kdrive::SyncStatus::Conflict => SyncStatus::Conflict,
+            kdrive::SyncStatus::Ignored => SyncStatus::Ignored,
+        }
+    }
+}
+
+impl From<DirectoryEntry> for kdrive::DirectoryEntry {
+    fn from(entry: DirectoryEntry) -> Self {
+        kdrive::DirectoryEntry {
+            id: entry.id,
+            name: entry.name,
+            kind: kdrive::EntryKind::from(entry.kind).into(),
+            size: entry.size,
+            modified_at: entry.modified_at,
+            sync_status: kdrive::SyncStatus::from(entry.sync_status).into(),
+            children: entry.children.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<kdrive::DirectoryEntry> for DirectoryEntry {
+    fn from(entry: kdrive::DirectoryEntry) -> Self {
+        DirectoryEntry {
+            id: entry.id,
+            kind: entry.kind().into(),
+            sync_status: entry.sync_status().into(),
+            name: entry.name,
+            size: entry.size,
+            modified_at: entry.modified_at,
+            children: entry.children.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<DirectoryListing> for kdrive::ListDirectoryResponse {
+    fn from(listing: DirectoryListing) -> Self {
+        kdrive::ListDirectoryResponse {
+            path: listing.path,
+            entries: listing.entries.into_iter().map(Into::into).collect(),
+            next_page_token: listing.next_page_token.unwrap_or_default(),
+        }
+    }
+}
+
+impl From<kdrive::ListDirectoryResponse> for DirectoryListing {
+    fn from(response: kdrive::ListDirectoryResponse) -> Self {
+        DirectoryListing {
+            path: response.path,
+            entries: response.entries.into_iter().map(Into::into).collect(),
+            next_page_token: Some(response.next_page_token).filter(|token| !token.is_empty()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listing(count: u64) -> DirectoryListing {
+        let entries = (1..=count)
+            .map(|id| DirectoryEntry {
+                id,
+                name: format!("file {id}"),
+                kind: EntryKind::File,
+                size: Some(id),
+                modified_at: 0,
+                sync_status: SyncStatus::Synced,
+                children: Vec::new(),
+            })
+            .collect();
+        DirectoryListing { path: "/".to_string(), entries, next_page_token: None }
+    }
+
+    #[test]
+    fn empty_token_asks_for_the_first_page() {
+        let page = ListingPage::parse("", 2).unwrap();
+
+        assert_eq!(page, ListingPage { offset: 0, size: 2 });
+        assert_eq!(page.next_token(), "2");
+    }
+
+    #[test]
+    fn token_is_the_offset_of_the_page() {
+        let page = ListingPage::parse("4", 2).unwrap();
+
+        assert_eq!(page, ListingPage { offset: 4, size: 2 });
+        assert_eq!(page.next_token(), "6");
+    }
+
+    #[test]
+    fn page_size_zero_uses_the_default() {
+        let page = ListingPage::parse("", 0).unwrap();
+
+        assert_eq!(page, ListingPage::default());
+    }
+
+    #[test]
+    fn invalid_page_token_is_refused() {
+        let result = ListingPage::parse("not a number", 2);
+
+        assert_eq!(result.unwrap_err().text_key, InvalidPageToken);
+    }
+
+    #[test]
+    fn listing_survives_the_round_trip_through_grpc() {
+        let mut original = listing(2);
+        original.entries[0].kind = EntryKind::Directory;
+        original.entries[0].size = None;
+        original.entries[0].children = listing(1).entries;
+        original.next_page_token = Some("2".to_string());
+
+        let response: kdrive::ListDirectoryResponse = original.clone().into();
+
+        assert_eq!(DirectoryListing::from(response), original);
+    }
+}
@@ -3,7 +3,7 @@ use std::string::ParseError;
 use serde::{Deserialize, Serialize};
 use i18n_loader::TextKeys::{ParserError, TransportError};
 use crate::domain::text_keys::TextKeys;
-use tonic::Status;
+use tonic::{Code, Status};
 use tokio::sync::oneshot::error::RecvError;
 use tonic::metadata::MetadataValue;
 use crate::domain::defaults::APPLICATION_ERROR_DETAIL_FIELD_NAME;
@@ -101,7 +101,12 @@ impl From<tonic::transport::Error> for ApplicationError {
 impl From<ApplicationError> for Status {
     fn from(err: ApplicationError) -> Self {
         let message = err.text_key.to_string();
-        let mut status = Status::invalid_argument(message);
+        // Clients can tell a refused session secret apart from errors of the request itself
+        let code = match err.text_key {
+            TextKeys::UnauthenticatedClient => Code::Unauthenticated,
+            _ => Code::InvalidArgument,
+        };
+        let mut status = Status::new(code, message);
 
         if let Some(detail) = err.detail {
             if let Ok(value) = detail.parse::<MetadataValue<_>>() {
@@ -212,6 +217,19 @@ mod tests {
         assert_eq!(decoded.text_key, original.text_key);
     }
 
+    #[test]
+    fn unauthenticated_client_error_has_the_unauthenticated_status_code() {
+        let original = ApplicationError {
+            text_key: TextKeys::UnauthenticatedClient,
+            detail: None,
+        };
+
+        let status: Status = original.clone().into();
+
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+        assert_eq!(ApplicationError::from(status), original);
+    }
+
     #[test]
     fn application_error_grpc_round_trip_preserves_detail_if_valid() {
         let original = ApplicationError {
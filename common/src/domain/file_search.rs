@@ -0,0 +1,81 @@
+use serde::{Deserialize, Serialize};
+use crate::domain::defaults::{DEFAULT_SEARCH_LIMIT, MAX_SEARCH_LIMIT};
+use crate::domain::directory_listing::DirectoryEntry;
+use crate::kdrive;
+
+/// Filters for a search through the stored metadata, a file has to match all of them
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileSearch {
+    /// Part of the name, case insensitive
+    pub name: Option<String>,
+    /// Without the dot, only files match
+    pub extension: Option<String>,
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    /// Seconds since the Unix epoch
+    pub modified_after: Option<i64>,
+    pub modified_before: Option<i64>,
+    pub limit: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchResult {
+    /// From the root of the drive, starting with a slash
+    pub path: String,
+    pub entry: DirectoryEntry,
+}
+
+fn non_empty(value: String) -> Option<String> {
+    Some(value).filter(|value| !value.is_empty())
+}
+
+impl From<kdrive::SearchFilesRequest> for FileSearch {
+    fn from(request: kdrive::SearchFilesRequest) -> Self {
+        let limit = match request.limit as usize {
+            0 => DEFAULT_SEARCH_LIMIT,
+            limit => limit.min(MAX_SEARCH_LIMIT),
+        };
+        FileSearch {
+            name: non_empty(request.name),
+            extension: non_empty(request.extension.trim_start_matches('.').to_string()),
+            min_size: request.min_size,
+            max_size: request.max_size,
+            modified_after: request.modified_after,
+            modified_before: request.modified_before,
+            limit,
+        }
+    }
+}
+
+impl From<SearchResult> for kdrive::SearchResult {
+    fn from(result: SearchResult) -> Self {
+        kdrive::SearchResult {
+            path: result.path,
+            entry: Some(result.entry.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_request_fields_do_not_filter() {
+        let search = FileSearch::from(kdrive::SearchFilesRequest::default());
+
+        assert_eq!(search, FileSearch { limit: DEFAULT_SEARCH_LIMIT, ..FileSearch::default() });
+    }
+
+    #[test]
+    fn extension_loses_its_dot_and_limit_is_capped() {
+        let search = FileSearch::from(kdrive::SearchFilesRequest {
+            extension: ".pdf".to_string(),
+            limit: u32::MAX,
+            ..Default::default()
+        });
+
+        assert_eq!(search.extension.as_deref(), Some("pdf"));
+        assert_eq!(search.limit, MAX_SEARCH_LIMIT);
+    }
+}
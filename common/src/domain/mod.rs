@@ -2,4 +2,7 @@ pub mod defaults;
 pub mod errors;
 pub mod language;
 pub mod text_keys;
-pub mod directory_listing;
\ No newline at end of file
+pub mod directory_listing;
+pub mod file_search;
+pub mod sync_conflict;
+pub mod sync_selection;
\ No newline at end of file
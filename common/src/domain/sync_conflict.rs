@@ -0,0 +1,119 @@
+use serde::{Deserialize, Serialize};
+use crate::application_error;
+use crate::domain::directory_listing::EntryKind;
+use crate::domain::errors::ApplicationError;
+use crate::domain::text_keys::TextKeys::InvalidConflictResolution;
+use crate::kdrive;
+
+/// How a file that changed on both sides is settled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConflictResolution {
+    /// The local version is kept under a "conflicted copy" name next to the version of kDrive
+    KeepBoth,
+    KeepLocal,
+    KeepRemote,
+}
+
+/// One side of a conflict as the sync found it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileVersion {
+    pub kind: EntryKind,
+    /// Directories have no size
+    pub size: Option<u64>,
+    /// Seconds since the Unix epoch
+    pub modified_at: i64,
+    /// Only kDrive reports a hash
+    pub hash: Option<String>,
+}
+
+/// A file that changed on both sides since the last sync
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncConflict {
+    /// The kDrive file id
+    pub file_id: u64,
+    /// Relative to the sync root
+    pub path: String,
+    pub local: FileVersion,
+    pub remote: FileVersion,
+    /// How the conflict policy settled it, none while it waits for the user
+    pub resolution: Option<ConflictResolution>,
+}
+
+impl From<ConflictResolution> for kdrive::ConflictResolution {
+    fn from(resolution: ConflictResolution) -> Self {
+        match resolution {
+            ConflictResolution::KeepBoth => kdrive::ConflictResolution::KeepBoth,
+            ConflictResolution::KeepLocal => kdrive::ConflictResolution::KeepLocal,
+            ConflictResolution::KeepRemote => kdrive::ConflictResolution::KeepRemote,
+        }
+    }
+}
+
+impl TryFrom<kdrive::ConflictResolution> for ConflictResolution {
+    type Error = ApplicationError;
+
+    fn try_from(resolution: kdrive::ConflictResolution) -> Result<Self, Self::Error> {
+        match resolution {
+            kdrive::ConflictResolution::Unspecified => Err(application_error!(InvalidConflictResolution)),
+            kdrive::ConflictResolution::KeepBoth => Ok(ConflictResolution::KeepBoth),
+            kdrive::ConflictResolution::KeepLocal => Ok(ConflictResolution::KeepLocal),
+            kdrive::ConflictResolution::KeepRemote => Ok(ConflictResolution::KeepRemote),
+        }
+    }
+}
+
+impl From<FileVersion> for kdrive::FileVersion {
+    fn from(version: FileVersion) -> Self {
+        kdrive::FileVersion {
+            kind: kdrive::EntryKind::from(version.kind).into(),
+            size: version.size,
+            modified_at: version.modified_at,
+            hash: version.hash.unwrap_or_default(),
+        }
+    }
+}
+
+impl From<SyncConflict> for kdrive::ConflictDetected {
+    fn from(conflict: SyncConflict) -> Self {
+        kdrive::ConflictDetected {
+            file_id: conflict.file_id,
+            path: conflict.path,
+            local: Some(conflict.local.into()),
+            remote: Some(conflict.remote.into()),
+            resolution: conflict.resolution
+                .map_or(kdrive::ConflictResolution::Unspecified, kdrive::ConflictResolution::from)
+                .into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_conflict_waiting_for_the_user_has_no_resolution() {
+        let version = FileVersion { kind: EntryKind::File, size: Some(5), modified_at: 1_700_000_000, hash: None };
+        let conflict = SyncConflict {
+            file_id: 4,
+            path: "notes.txt".to_string(),
+            local: version.clone(),
+            remote: FileVersion { hash: Some("abc".to_string()), ..version },
+            resolution: None,
+        };
+
+        let event = kdrive::ConflictDetected::from(conflict);
+
+        assert_eq!(event.resolution(), kdrive::ConflictResolution::Unspecified);
+        assert_eq!(event.local.unwrap().hash, "");
+        assert_eq!(event.remote.unwrap().hash, "abc");
+    }
+
+    #[test]
+    fn a_resolution_has_to_be_chosen() {
+        let err = ConflictResolution::try_from(kdrive::ConflictResolution::Unspecified).unwrap_err();
+
+        assert_eq!(err.text_key, InvalidConflictResolution);
+        assert_eq!(ConflictResolution::try_from(kdrive::ConflictResolution::KeepLocal).unwrap(), ConflictResolution::KeepLocal);
+    }
+}
@@ -0,0 +1,72 @@
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use crate::kdrive;
+
+/// Which folders of the drive are synced. A rule decides for a folder and everything below it until a deeper
+/// rule decides otherwise, so a folder can be synced inside one that is not. Without rules everything is synced.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncSelection {
+    /// Whether the folder with this kDrive file id is included
+    pub rules: BTreeMap<u64, bool>,
+}
+
+impl SyncSelection {
+    pub fn with_rule(mut self, folder_id: u64, included: bool) -> Self {
+        self.rules.insert(folder_id, included);
+        self
+    }
+
+    pub fn rule(&self, folder_id: u64) -> Option<bool> {
+        self.rules.get(&folder_id).copied()
+    }
+
+    /// Whether a file is synced, given its own id followed by the ids of its parents up to the root
+    pub fn includes(&self, ids: impl IntoIterator<Item = u64>) -> bool {
+        ids.into_iter().find_map(|id| self.rule(id)).unwrap_or(true)
+    }
+}
+
+impl From<kdrive::SyncSelection> for SyncSelection {
+    fn from(selection: kdrive::SyncSelection) -> Self {
+        SyncSelection {
+            rules: selection.rules.into_iter().map(|rule| (rule.folder_id, rule.included)).collect(),
+        }
+    }
+}
+
+impl From<SyncSelection> for kdrive::SyncSelection {
+    fn from(selection: SyncSelection) -> Self {
+        kdrive::SyncSelection {
+            rules: selection.rules
+                .into_iter()
+                .map(|(folder_id, included)| kdrive::SelectionRule { folder_id, included })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_nearest_rule_decides() {
+        // Given Projects (2) left out with Projects/Current (3) synced again
+        let selection = SyncSelection::default().with_rule(2, false).with_rule(3, true);
+
+        // Then
+        assert!(selection.includes([4, 3, 2, 1]));
+        assert!(!selection.includes([5, 2, 1]));
+        assert!(selection.includes([6, 1]));
+    }
+
+    #[test]
+    fn rules_survive_the_round_trip() {
+        let selection = SyncSelection::default().with_rule(2, false).with_rule(3, true);
+
+        let message = kdrive::SyncSelection::from(selection.clone());
+
+        assert_eq!(message.rules.len(), 2);
+        assert_eq!(SyncSelection::from(message), selection);
+    }
+}
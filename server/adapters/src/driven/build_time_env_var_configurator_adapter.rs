@@ -1,9 +1,17 @@
 // adapters/src/driven/build_time_env_var_configurator_adapter.rs
-use engine::domain::configuration::Configuration;
+use std::path::PathBuf;
+use std::time::Duration;
+use dirs::{config_dir, home_dir};
+use engine::domain::configuration::{ConflictPolicy, Configuration, LogLevel};
 use engine::domain::default_values::configurator_defaults::*;
+use engine::domain::default_values::general_defaults::APPLICATION_NAME;
 use engine::ports::driven::configurator_driven_port::ConfiguratorPort;
-use oauth2::{AuthUrl, ClientId, RedirectUrl, TokenUrl};
+use oauth2::{AuthUrl, ClientId, RedirectUrl, RevocationUrl, TokenUrl};
+use oauth2::url::Url;
+use common::application_error;
+use common::domain::defaults::default_session_secret_path;
 use common::domain::errors::ApplicationError;
+use common::domain::text_keys::TextKeys::{InvalidListenAddress, NoConfigFolderFound, NoHomeFolderFound, ParserError};
 
 pub struct BuildTimeEnvVarConfiguratorPort;
 
@@ -21,16 +29,135 @@ impl ConfiguratorPort for BuildTimeEnvVarConfiguratorPort {
         let redirect_url = option_env!("REDIRECT_URL")
             .unwrap_or(DEFAULT_REDIRECT_URL)
             .to_string();
+        let revocation_url = option_env!("REVOCATION_URL")
+            .unwrap_or(DEFAULT_REVOCATION_URL)
+            .to_string();
+        let api_url = option_env!("API_URL")
+            .unwrap_or(DEFAULT_API_URL);
+        let fallback_redirect_ports = match option_env!("REDIRECT_FALLBACK_PORTS") {
+            Some(ports) => parse_ports(ports)?,
+            None => DEFAULT_REDIRECT_FALLBACK_PORTS.to_vec(),
+        };
+        let api_connect_timeout = match option_env!("API_CONNECT_TIMEOUT_SECONDS") {
+            Some(seconds) => seconds
+                .parse::<u64>()
+                .map_err(|e| application_error!(ParserError, e.to_string()))?,
+            None => DEFAULT_API_CONNECT_TIMEOUT_SECONDS,
+        };
+        let api_read_timeout = match option_env!("API_READ_TIMEOUT_SECONDS") {
+            Some(seconds) => seconds
+                .parse::<u64>()
+                .map_err(|e| application_error!(ParserError, e.to_string()))?,
+            None => DEFAULT_API_READ_TIMEOUT_SECONDS,
+        };
+        let auth_flow_timeout = match option_env!("AUTH_FLOW_TIMEOUT_SECONDS") {
+            Some(seconds) => seconds
+                .parse::<u64>()
+                .map_err(|e| application_error!(ParserError, e.to_string()))?,
+            None => DEFAULT_AUTH_FLOW_TIMEOUT_SECONDS,
+        };
+        let grpc_listen_address = option_env!("GRPC_LISTEN_ADDRESS")
+            .unwrap_or(DEFAULT_GRPC_LISTEN_ADDRESS);
+        let grpc_socket_path = option_env!("GRPC_SOCKET_PATH")
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from);
+        let session_secret_path = option_env!("SESSION_SECRET_PATH")
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(default_session_secret_path);
+        let sync_root = match option_env!("SYNC_ROOT").filter(|path| !path.is_empty()) {
+            Some(sync_root) => PathBuf::from(sync_root),
+            None => default_sync_root()?,
+        };
+        let data_dir = match option_env!("DATA_DIR").filter(|path| !path.is_empty()) {
+            Some(data_dir) => PathBuf::from(data_dir),
+            None => default_data_dir()?,
+        };
+        let log_level = option_env!("LOG_LEVEL")
+            .map(str::parse::<LogLevel>)
+            .transpose()?
+            .unwrap_or(DEFAULT_LOG_LEVEL);
+        let conflict_policy = option_env!("CONFLICT_POLICY")
+            .map(str::parse::<ConflictPolicy>)
+            .transpose()?
+            .unwrap_or(DEFAULT_CONFLICT_POLICY);
+        let ignore_patterns = option_env!("IGNORE_PATTERNS")
+            .filter(|patterns| !patterns.is_empty())
+            .map(parse_patterns)
+            .unwrap_or_else(|| DEFAULT_IGNORE_PATTERNS.iter().map(|pattern| pattern.to_string()).collect());
+        let upload_chunk_size = match option_env!("UPLOAD_CHUNK_SIZE_BYTES") {
+            Some(bytes) => bytes
+                .parse::<u64>()
+                .map_err(|e| application_error!(ParserError, e.to_string()))?,
+            None => DEFAULT_UPLOAD_CHUNK_SIZE,
+        };
+        let upload_parallelism = match option_env!("UPLOAD_PARALLELISM") {
+            Some(count) => count
+                .parse::<usize>()
+                .map_err(|e| application_error!(ParserError, e.to_string()))?,
+            None => DEFAULT_UPLOAD_PARALLELISM,
+        };
 
         Ok(Configuration {
             auth_url: AuthUrl::new(auth_url)?,
             token_url: TokenUrl::new(token_url)?,
             client_id: ClientId::new(client_id),
             redirect_url: RedirectUrl::new(redirect_url)?,
+            fallback_redirect_ports,
+            revocation_url: RevocationUrl::new(revocation_url)?,
+            api_url: Url::parse(api_url)?,
+            api_connect_timeout: Duration::from_secs(api_connect_timeout),
+            api_read_timeout: Duration::from_secs(api_read_timeout),
+            auth_flow_timeout: Duration::from_secs(auth_flow_timeout),
+            grpc_listen_addr: parse_listen_address(grpc_listen_address)?,
+            grpc_socket_path,
+            session_secret_path,
+            sync_root,
+            data_dir,
+            log_level,
+            conflict_policy,
+            ignore_patterns,
+            upload_chunk_size,
+            upload_parallelism,
         })
     }
 }
 
+pub(crate) fn default_sync_root() -> Result<PathBuf, ApplicationError> {
+    let home = home_dir().ok_or(application_error!(NoHomeFolderFound))?;
+    Ok(home.join(DEFAULT_SYNC_ROOT_FOLDER_NAME))
+}
+
+pub(crate) fn default_data_dir() -> Result<PathBuf, ApplicationError> {
+    let config = config_dir().ok_or(application_error!(NoConfigFolderFound))?;
+    Ok(config.join(APPLICATION_NAME))
+}
+
+pub(crate) fn parse_listen_address(address: &str) -> Result<std::net::SocketAddr, ApplicationError> {
+    address
+        .parse()
+        .map_err(|_| application_error!(InvalidListenAddress, address))
+}
+
+pub(crate) fn parse_ports(ports: &str) -> Result<Vec<u16>, ApplicationError> {
+    ports
+        .split(',')
+        .map(str::trim)
+        .filter(|port| !port.is_empty())
+        .map(|port| port.parse::<u16>().map_err(|e| application_error!(ParserError, e.to_string())))
+        .collect()
+}
+
+/// Comma separated, so a pattern cannot hold a comma of its own
+pub(crate) fn parse_patterns(patterns: &str) -> Vec<String> {
+    patterns
+        .split(',')
+        .map(str::trim)
+        .filter(|pattern| !pattern.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -66,4 +193,91 @@ mod tests {
 
         assert_eq!(config.redirect_url.as_str(), DEFAULT_REDIRECT_URL);
     }
+
+    #[test]
+    fn revocation_url_from_env() {
+        let port = BuildTimeEnvVarConfiguratorPort;
+        let config = port.load().unwrap();
+
+        assert_eq!(config.revocation_url.as_str(), DEFAULT_REVOCATION_URL);
+    }
+
+    #[test]
+    fn fallback_redirect_ports_from_env() {
+        let port = BuildTimeEnvVarConfiguratorPort;
+        let config = port.load().unwrap();
+
+        assert_eq!(config.fallback_redirect_ports, DEFAULT_REDIRECT_FALLBACK_PORTS);
+    }
+
+    #[test]
+    fn fallback_redirect_ports_are_comma_separated() {
+        assert_eq!(parse_ports("13629, 13630").unwrap(), vec![13629, 13630]);
+        assert!(parse_ports("").unwrap().is_empty());
+        assert_eq!(parse_ports("not-a-port").unwrap_err().text_key, ParserError);
+    }
+
+    #[test]
+    fn grpc_listen_addr_from_env() {
+        let port = BuildTimeEnvVarConfiguratorPort;
+        let config = port.load().unwrap();
+
+        assert_eq!(config.grpc_listen_addr.to_string(), DEFAULT_GRPC_LISTEN_ADDRESS);
+    }
+
+    #[test]
+    fn grpc_socket_is_not_used_by_default() {
+        let port = BuildTimeEnvVarConfiguratorPort;
+        let config = port.load().unwrap();
+
+        assert_eq!(config.grpc_socket_path, None);
+    }
+
+    #[test]
+    fn session_secret_defaults_to_the_runtime_folder() {
+        let port = BuildTimeEnvVarConfiguratorPort;
+        let config = port.load().unwrap();
+
+        assert_eq!(config.session_secret_path, default_session_secret_path());
+    }
+
+    #[test]
+    fn sync_root_defaults_to_a_folder_in_the_home_folder() {
+        let port = BuildTimeEnvVarConfiguratorPort;
+        let config = port.load().unwrap();
+
+        assert!(config.sync_root.ends_with(DEFAULT_SYNC_ROOT_FOLDER_NAME));
+    }
+
+    #[test]
+    fn data_dir_defaults_to_the_application_folder_in_the_config_folder() {
+        let port = BuildTimeEnvVarConfiguratorPort;
+        let config = port.load().unwrap();
+
+        assert!(config.data_dir.ends_with(APPLICATION_NAME));
+    }
+
+    #[test]
+    fn invalid_listen_address_returns_an_error() {
+        let err = parse_listen_address("not-an-address").expect_err("Expected InvalidListenAddress error");
+
+        assert_eq!(err.text_key, InvalidListenAddress);
+    }
+
+    #[test]
+    fn auth_flow_timeout_from_env() {
+        let port = BuildTimeEnvVarConfiguratorPort;
+        let config = port.load().unwrap();
+
+        assert_eq!(config.auth_flow_timeout, Duration::from_secs(DEFAULT_AUTH_FLOW_TIMEOUT_SECONDS));
+    }
+
+    #[test]
+    fn api_timeouts_from_env() {
+        let port = BuildTimeEnvVarConfiguratorPort;
+        let config = port.load().unwrap();
+
+        assert_eq!(config.api_connect_timeout, Duration::from_secs(DEFAULT_API_CONNECT_TIMEOUT_SECONDS));
+        assert_eq!(config.api_read_timeout, Duration::from_secs(DEFAULT_API_READ_TIMEOUT_SECONDS));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,756 @@
+use std::time::Duration;
+use async_trait::async_trait;
+use futures_util::TryStreamExt;
+use oauth2::url::Url;
+use reqwest::header::{CONTENT_TYPE, RANGE};
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use tokio::io::AsyncReadExt;
+use tokio_util::io::StreamReader;
+use common::application_error;
+use common::domain::errors::ApplicationError;
+use common::domain::text_keys::TextKeys;
+use common::domain::text_keys::TextKeys::{ApiAccessDenied, ApiConflict, ApiNotAuthenticated, ApiNotFound, ApiRateLimited, ApiRequestFailed, ApiServerError, ApiUnexpectedResponse};
+use engine::domain::configuration::Configuration;
+use engine::domain::default_values::configurator_defaults::{DEFAULT_API_CONNECT_TIMEOUT_SECONDS, DEFAULT_API_READ_TIMEOUT_SECONDS};
+use engine::domain::default_values::general_defaults::API_LIST_PAGE_SIZE;
+use engine::domain::remote_file::{ChangePage, Drive, DriveId, FileId, FileKind, FilePage, RemoteChange, RemoteFile};
+use engine::domain::upload_session::UploadTarget;
+use engine::ports::driven::kdrive_api_driven_port::{KDriveApiDrivenPort, RemoteFileReader};
+use engine::ports::driven::token_store_driven_port::TokenStoreDrivenPort;
+
+/// Talks to the kDrive REST API with the access token that is in the token store at the time of the request,
+/// so refreshed tokens are picked up without telling the adapter.
+pub struct KDriveApiAdapter<Store>
+where
+    Store: TokenStoreDrivenPort,
+{
+    client: Client,
+    api_url: Url,
+    token_store: Store,
+}
+
+impl<Store> KDriveApiAdapter<Store>
+where
+    Store: TokenStoreDrivenPort,
+{
+    pub fn new(mut api_url: Url, token_store: Store) -> Self {
+        // The paths of the requests are joined onto the URL, without a slash at the end its last segment would go
+        if !api_url.path().ends_with('/') {
+            api_url.set_path(&format!("{}/", api_url.path()));
+        }
+        Self {
+            client: http_client(
+                Duration::from_secs(DEFAULT_API_CONNECT_TIMEOUT_SECONDS),
+                Duration::from_secs(DEFAULT_API_READ_TIMEOUT_SECONDS),
+            ),
+            api_url,
+            token_store,
+        }
+    }
+
+    pub fn new_from_config(config: &Configuration, token_store: Store) -> Self {
+        Self::new(config.api_url.clone(), token_store)
+            .with_timeouts(config.api_connect_timeout, config.api_read_timeout)
+    }
+
+    /// A request fails when connecting takes longer than `connect_timeout`, or when the API stays silent for
+    /// longer than `read_timeout` in the middle of it
+    pub fn with_timeouts(mut self, connect_timeout: Duration, read_timeout: Duration) -> Self {
+        self.client = http_client(connect_timeout, read_timeout);
+        self
+    }
+
+    fn request(&self, method: Method, path: &str) -> Result<RequestBuilder, ApplicationError> {
+        let access_token = self.token_store
+            .load()?
+            .map(|tokens| tokens.access_token)
+            .ok_or(application_error!(ApiNotAuthenticated))?;
+        let url = self.api_url.join(path)?;
+
+        Ok(self.client.request(method, url).bearer_auth(access_token))
+    }
+
+    async fn send(&self, request: RequestBuilder) -> Result<Response, ApplicationError> {
+        let response = request
+            .send()
+            .await
+            .map_err(|e| application_error!(ApiRequestFailed, e.to_string()))?;
+
+        if response.status().is_success() {
+            return Ok(response);
+        }
+
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        Err(map_api_error(status, &body))
+    }
+
+    async fn send_for<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<ApiResponse<T>, ApplicationError> {
+        let body = self.send(request)
+            .await?
+            .bytes()
+            .await
+            .map_err(|e| application_error!(ApiRequestFailed, e.to_string()))?;
+
+        serde_json::from_slice(&body)
+            .map_err(|e| application_error!(ApiUnexpectedResponse, e.to_string()))
+    }
+}
+
+#[async_trait]
+impl<Store> KDriveApiDrivenPort for KDriveApiAdapter<Store>
+where
+    Store: TokenStoreDrivenPort + Send + Sync,
+{
+    async fn list_drives(&self) -> Result<Vec<Drive>, ApplicationError> {
+        let request = self.request(Method::GET, "2/drive")?;
+        let response: ApiResponse<Vec<ApiDrive>> = self.send_for(request).await?;
+
+        Ok(response.data.into_iter().map(Drive::from).collect())
+    }
+
+    async fn list_files(&self, drive_id: DriveId, directory_id: FileId, cursor: Option<&str>) -> Result<FilePage, ApplicationError> {
+        let mut request = self
+            .request(Method::GET, &format!("3/drive/{drive_id}/files/{directory_id}/files"))?
+            .query(&[("limit", API_LIST_PAGE_SIZE.to_string())]);
+        if let Some(cursor) = cursor {
+            request = request.query(&[("cursor", cursor)]);
+        }
+        let response: ApiResponse<Vec<ApiFile>> = self.send_for(request).await?;
+
+        Ok(FilePage {
+            files: response.data.into_iter().map(RemoteFile::from).collect(),
+            cursor: response.cursor,
+            has_more: response.has_more,
+        })
+    }
+
+    async fn list_changes(&self, drive_id: DriveId, cursor: Option<&str>) -> Result<ChangePage, ApplicationError> {
+        let mut request = self
+            .request(Method::GET, &format!("3/drive/{drive_id}/files/activities"))?
+            .query(&[("limit", API_LIST_PAGE_SIZE.to_string())]);
+        if let Some(cursor) = cursor {
+            request = request.query(&[("cursor", cursor)]);
+        }
+        let response: ApiResponse<Vec<ApiActivity>> = self.send_for(request).await?;
+        let cursor = response.cursor
+            .ok_or_else(|| application_error!(ApiUnexpectedResponse, "activities without a cursor"))?;
+
+        Ok(ChangePage {
+            changes: response.data.into_iter().filter_map(ApiActivity::into_change).collect(),
+            cursor,
+            has_more: response.has_more,
+        })
+    }
+
+    async fn get_file(&self, drive_id: DriveId, file_id: FileId) -> Result<RemoteFile, ApplicationError> {
+        let request = self.request(Method::GET, &format!("3/drive/{drive_id}/files/{file_id}"))?;
+        let response: ApiResponse<ApiFile> = self.send_for(request).await?;
+
+        Ok(response.data.into())
+    }
+
+    async fn download_file_from(&self, drive_id: DriveId, file_id: FileId, offset: u64) -> Result<RemoteFileReader, ApplicationError> {
+        let mut request = self.request(Method::GET, &format!("2/drive/{drive_id}/files/{file_id}/download"))?;
+        if offset > 0 {
+            request = request.header(RANGE, format!("bytes={offset}-"));
+        }
+        let response = self.send(request).await?;
+        // Without a partial response the range was ignored and the content starts at the beginning
+        let skip = if response.status() == StatusCode::PARTIAL_CONTENT { 0 } else { offset };
+        let mut content = StreamReader::new(Box::pin(response.bytes_stream().map_err(std::io::Error::other)));
+        if skip > 0 {
+            tokio::io::copy(&mut (&mut content).take(skip), &mut tokio::io::sink())
+                .await
+                .map_err(|e| application_error!(ApiRequestFailed, e.to_string()))?;
+        }
+
+        Ok(Box::new(content))
+    }
+
+    async fn upload_file(&self, drive_id: DriveId, directory_id: FileId, name: &str, content: Vec<u8>) -> Result<RemoteFile, ApplicationError> {
+        let request = self
+            .request(Method::POST, &format!("3/drive/{drive_id}/upload"))?
+            .query(&[
+                ("directory_id", directory_id.to_string()),
+                ("file_name", name.to_string()),
+                ("total_size", content.len().to_string()),
+                ("conflict", "error".to_string()),
+            ])
+            .header(CONTENT_TYPE, "application/octet-stream")
+            .body(content);
+        let response: ApiResponse<ApiFile> = self.send_for(request).await?;
+
+        Ok(response.data.into())
+    }
+
+    async fn update_file(&self, drive_id: DriveId, file_id: FileId, content: Vec<u8>) -> Result<RemoteFile, ApplicationError> {
+        let request = self
+            .request(Method::POST, &format!("3/drive/{drive_id}/upload"))?
+            .query(&[
+                ("file_id", file_id.to_string()),
+                ("total_size", content.len().to_string()),
+                ("conflict", "version".to_string()),
+            ])
+            .header(CONTENT_TYPE, "application/octet-stream")
+            .body(content);
+        let response: ApiResponse<ApiFile> = self.send_for(request).await?;
+
+        Ok(response.data.into())
+    }
+
+    async fn start_upload_session(&self, drive_id: DriveId, target: &UploadTarget, total_size: u64, total_chunks: u64) -> Result<String, ApplicationError> {
+        let body = match target {
+            UploadTarget::NewFile { directory_id, name } => serde_json::json!({
+                "directory_id": directory_id,
+                "file_name": name,
+                "total_size": total_size,
+                "total_chunks": total_chunks,
+                "conflict": "error",
+            }),
+            UploadTarget::NewVersion { file_id } => serde_json::json!({
+                "file_id": file_id,
+                "total_size": total_size,
+                "total_chunks": total_chunks,
+                "conflict": "version",
+            }),
+        };
+        let request = self
+            .request(Method::POST, &format!("3/drive/{drive_id}/upload/session/start"))?
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.to_string());
+        let response: ApiResponse<ApiUploadSession> = self.send_for(request).await?;
+
+        Ok(response.data.token)
+    }
+
+    async fn upload_chunk(&self, drive_id: DriveId, token: &str, chunk_number: u64, content: Vec<u8>, hash: &str) -> Result<(), ApplicationError> {
+        let request = self
+            .request(Method::POST, &format!("3/drive/{drive_id}/upload/session/{token}/chunk"))?
+            .query(&[
+                ("chunk_number", chunk_number.to_string()),
+                ("chunk_size", content.len().to_string()),
+                ("chunk_hash", hash.to_string()),
+            ])
+            .header(CONTENT_TYPE, "application/octet-stream")
+            .body(content);
+        self.send(request).await?;
+        Ok(())
+    }
+
+    async fn finish_upload_session(&self, drive_id: DriveId, token: &str) -> Result<RemoteFile, ApplicationError> {
+        let request = self.request(Method::POST, &format!("3/drive/{drive_id}/upload/session/{token}/finish"))?;
+        let response: ApiResponse<ApiFinishedUpload> = self.send_for(request).await?;
+
+        Ok(response.data.file.into())
+    }
+
+    async fn cancel_upload_session(&self, drive_id: DriveId, token: &str) -> Result<(), ApplicationError> {
+        let request = self.request(Method::DELETE, &format!("2/drive/{drive_id}/upload/session/{token}"))?;
+        self.send(request).await?;
+        Ok(())
+    }
+
+    async fn create_directory(&self, drive_id: DriveId, parent_id: FileId, name: &str) -> Result<RemoteFile, ApplicationError> {
+        let body = serde_json::json!({ "name": name }).to_string();
+        let request = self
+            .request(Method::POST, &format!("3/drive/{drive_id}/files/{parent_id}/directory"))?
+            .header(CONTENT_TYPE, "application/json")
+            .body(body);
+        let response: ApiResponse<ApiFile> = self.send_for(request).await?;
+
+        Ok(response.data.into())
+    }
+
+    async fn move_file(&self, drive_id: DriveId, file_id: FileId, destination_directory_id: FileId) -> Result<(), ApplicationError> {
+        let request = self.request(Method::POST, &format!("3/drive/{drive_id}/files/{file_id}/move/{destination_directory_id}"))?;
+        self.send(request).await?;
+        Ok(())
+    }
+
+    async fn rename_file(&self, drive_id: DriveId, file_id: FileId, name: &str) -> Result<(), ApplicationError> {
+        let body = serde_json::json!({ "name": name }).to_string();
+        let request = self
+            .request(Method::POST, &format!("2/drive/{drive_id}/files/{file_id}/rename"))?
+            .header(CONTENT_TYPE, "application/json")
+            .body(body);
+        self.send(request).await?;
+        Ok(())
+    }
+
+    async fn delete_file(&self, drive_id: DriveId, file_id: FileId) -> Result<(), ApplicationError> {
+        let request = self.request(Method::DELETE, &format!("2/drive/{drive_id}/files/{file_id}"))?;
+        self.send(request).await?;
+        Ok(())
+    }
+
+    async fn restore_file(&self, drive_id: DriveId, file_id: FileId) -> Result<(), ApplicationError> {
+        let request = self.request(Method::POST, &format!("2/drive/{drive_id}/trash/{file_id}/restore"))?;
+        self.send(request).await?;
+        Ok(())
+    }
+}
+
+/// The HTTP status decides the key, the error code and description of kDrive become the detail
+pub(crate) fn map_api_error(status: StatusCode, body: &str) -> ApplicationError {
+    let error = serde_json::from_str::<ApiErrorResponse>(body)
+        .ok()
+        .map(|response| response.error);
+
+    let text_key = match status {
+        StatusCode::UNAUTHORIZED => ApiNotAuthenticated,
+        StatusCode::FORBIDDEN => ApiAccessDenied,
+        StatusCode::NOT_FOUND => ApiNotFound,
+        StatusCode::CONFLICT => ApiConflict,
+        StatusCode::TOO_MANY_REQUESTS => ApiRateLimited,
+        status if status.is_server_error() => ApiServerError,
+        _ => error
+            .as_ref()
+            .and_then(|error| text_key_for_error_code(&error.code))
+            .unwrap_or(ApiRequestFailed),
+    };
+
+    let detail = match error {
+        Some(ApiError { code, description: Some(description) }) => format!("{code}: {description}"),
+        Some(ApiError { code, description: None }) => code,
+        None => status.to_string(),
+    };
+
+    application_error!(text_key, detail)
+}
+
+/// Panics like `Client::new` when the TLS backend cannot be set up
+fn http_client(connect_timeout: Duration, read_timeout: Duration) -> Client {
+    Client::builder()
+        .connect_timeout(connect_timeout)
+        .read_timeout(read_timeout)
+        .build()
+        .expect("the TLS backend could not be set up")
+}
+
+/// kDrive also reports some errors with a 400 status, the code tells what went wrong
+fn text_key_for_error_code(code: &str) -> Option<TextKeys> {
+    match code {
+        "not_authorized" | "invalid_token" => Some(ApiNotAuthenticated),
+        "forbidden" | "access_denied" => Some(ApiAccessDenied),
+        "object_not_found" | "file_not_found" | "drive_not_found" => Some(ApiNotFound),
+        "conflict_error" | "destination_already_exists" => Some(ApiConflict),
+        "too_many_requests" => Some(ApiRateLimited),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiResponse<T> {
+    data: T,
+    #[serde(default)]
+    cursor: Option<String>,
+    #[serde(default)]
+    has_more: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiErrorResponse {
+    error: ApiError,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiError {
+    code: String,
+    #[serde(default)]
+    description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiDrive {
+    id: DriveId,
+    name: String,
+}
+
+impl From<ApiDrive> for Drive {
+    fn from(drive: ApiDrive) -> Self {
+        Drive { id: drive.id, name: drive.name }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiFile {
+    id: FileId,
+    parent_id: FileId,
+    name: String,
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    size: Option<u64>,
+    #[serde(default)]
+    last_modified_at: i64,
+    #[serde(default)]
+    hash: Option<String>,
+}
+
+impl From<ApiFile> for RemoteFile {
+    fn from(file: ApiFile) -> Self {
+        let kind = match file.kind.as_str() {
+            "dir" => FileKind::Directory,
+            _ => FileKind::File,
+        };
+
+        RemoteFile {
+            id: file.id,
+            parent_id: file.parent_id,
+            name: file.name,
+            kind,
+            size: file.size,
+            last_modified_at: file.last_modified_at,
+            hash: file.hash,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiUploadSession {
+    token: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiFinishedUpload {
+    file: ApiFile,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiActivity {
+    action: String,
+    file_id: FileId,
+    #[serde(default)]
+    file: Option<ApiFile>,
+}
+
+impl ApiActivity {
+    /// Actions that do not change the tree, like comments or shares, are left out
+    fn into_change(self) -> Option<RemoteChange> {
+        let file = self.file.map(RemoteFile::from);
+        match self.action.as_str() {
+            "file_create" | "file_restore" => file.map(RemoteChange::Created),
+            "file_update" => file.map(RemoteChange::Updated),
+            "file_move" | "file_rename" => file.map(RemoteChange::Moved),
+            "file_trash" | "file_delete" => Some(RemoteChange::Deleted(self.file_id)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use axum::body::Bytes;
+    use axum::extract::{Path, Query};
+    use axum::http::HeaderMap;
+    use axum::response::IntoResponse;
+    use axum::routing::{get, post};
+    use axum::{Json, Router};
+    use engine::domain::test_helpers::fake_token_store_adapter::{FakeTokenStoreFileAdapter, TEST_FILE_ACCESS_TOKEN};
+    use serde_json::{json, Value};
+    use super::*;
+
+    const DRIVE_ID: DriveId = 100;
+
+    type Received = Arc<Mutex<Vec<String>>>;
+
+    async fn start_api(router: Router) -> Url {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, router).await.unwrap();
+        });
+        Url::parse(&format!("http://{addr}")).unwrap()
+    }
+
+    fn adapter(api_url: Url) -> KDriveApiAdapter<FakeTokenStoreFileAdapter> {
+        KDriveApiAdapter::new(api_url, FakeTokenStoreFileAdapter::with_tokens())
+    }
+
+    fn file_json(id: FileId, name: &str, kind: &str) -> Value {
+        json!({ "id": id, "parent_id": 1, "name": name, "type": kind, "size": 3, "last_modified_at": 1_700_000_000 })
+    }
+
+    fn authorization(headers: &HeaderMap) -> String {
+        headers
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    #[tokio::test]
+    async fn requests_carry_the_access_token_from_the_token_store() {
+        // Given a kDrive API that records the authorization header
+        let received: Received = Arc::default();
+        let recorded = received.clone();
+        let api_url = start_api(Router::new().route("/2/drive", get(move |headers: HeaderMap| async move {
+            recorded.lock().unwrap().push(authorization(&headers));
+            Json(json!({ "result": "success", "data": [{ "id": DRIVE_ID, "name": "My kDrive" }] }))
+        }))).await;
+
+        // When the drives are listed
+        let drives = adapter(api_url).list_drives().await.unwrap();
+
+        // Then the drives are returned and the access token was sent
+        assert_eq!(drives, vec![Drive { id: DRIVE_ID, name: "My kDrive".to_string() }]);
+        assert_eq!(received.lock().unwrap().as_slice(), [format!("Bearer {TEST_FILE_ACCESS_TOKEN}")]);
+    }
+
+    #[tokio::test]
+    async fn requests_keep_the_path_of_the_api_url() {
+        // Given a kDrive API behind a proxy path
+        let api_url = start_api(Router::new().route("/kdrive/2/drive", get(|| async {
+            Json(json!({ "result": "success", "data": [{ "id": DRIVE_ID, "name": "My kDrive" }] }))
+        }))).await;
+
+        // When the drives are listed through an API URL without a slash at the end
+        let drives = adapter(api_url.join("kdrive").unwrap()).list_drives().await.unwrap();
+
+        // Then the request went to the path below the proxy path
+        assert_eq!(drives.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn request_without_tokens_is_not_sent() {
+        let adapter = KDriveApiAdapter::new(Url::parse("http://127.0.0.1:9").unwrap(), FakeTokenStoreFileAdapter::empty());
+
+        let err = adapter.list_drives().await.expect_err("Expected ApiNotAuthenticated error");
+
+        assert_eq!(err.text_key, ApiNotAuthenticated);
+    }
+
+    #[tokio::test]
+    async fn request_to_an_api_that_stays_silent_fails() {
+        // Given a kDrive API that never answers
+        let api_url = start_api(Router::new().route("/2/drive", get(|| std::future::pending::<Json<Value>>()))).await;
+        let adapter = adapter(api_url).with_timeouts(Duration::from_secs(1), Duration::from_millis(100));
+
+        // When the drives are listed
+        let err = adapter.list_drives().await.expect_err("Expected ApiRequestFailed error");
+
+        // Then the request gives up instead of waiting forever
+        assert_eq!(err.text_key, ApiRequestFailed);
+    }
+
+    #[tokio::test]
+    async fn files_are_listed_page_by_page() {
+        // Given a directory with two pages of files
+        let api_url = start_api(Router::new().route(
+            "/3/drive/{drive_id}/files/{directory_id}/files",
+            get(|Path((_, directory_id)): Path<(DriveId, FileId)>, Query(query): Query<HashMap<String, String>>| async move {
+                let page = match query.get("cursor").map(String::as_str) {
+                    None => json!({ "result": "success", "data": [file_json(2, "Documents", "dir")], "cursor": "next", "has_more": true }),
+                    Some(_) => json!({ "result": "success", "data": [file_json(3, "notes.txt", "file")], "has_more": false }),
+                };
+                assert_eq!(directory_id, 1);
+                Json(page)
+            }),
+        )).await;
+        let adapter = adapter(api_url);
+
+        // When both pages are listed
+        let first = adapter.list_files(DRIVE_ID, 1, None).await.unwrap();
+        let second = adapter.list_files(DRIVE_ID, 1, first.cursor.as_deref()).await.unwrap();
+
+        // Then the files and the cursor are mapped
+        assert!(first.has_more);
+        assert_eq!(first.cursor.as_deref(), Some("next"));
+        assert!(first.files[0].is_directory());
+        assert_eq!(first.files[0].name, "Documents");
+        assert!(!second.has_more);
+        assert_eq!(second.files[0].kind, FileKind::File);
+        assert_eq!(second.files[0].size, Some(3));
+    }
+
+    #[tokio::test]
+    async fn activities_become_changes_after_the_cursor() {
+        // Given a drive with activities of every kind after the cursor
+        let api_url = start_api(Router::new().route(
+            "/3/drive/{drive_id}/files/activities",
+            get(|Query(query): Query<HashMap<String, String>>| async move {
+                assert_eq!(query.get("cursor").map(String::as_str), Some("start"));
+                Json(json!({
+                    "result": "success",
+                    "data": [
+                        { "action": "file_create", "file_id": 2, "file": file_json(2, "Documents", "dir") },
+                        { "action": "file_rename", "file_id": 3, "file": file_json(3, "notes.txt", "file") },
+                        { "action": "comment_create", "file_id": 3 },
+                        { "action": "file_trash", "file_id": 4 },
+                    ],
+                    "cursor": "next",
+                    "has_more": false,
+                }))
+            }),
+        )).await;
+
+        // When the changes since the cursor are listed
+        let page = adapter(api_url).list_changes(DRIVE_ID, Some("start")).await.unwrap();
+
+        // Then the activities that change the tree are mapped in order
+        assert_eq!(page.cursor, "next");
+        assert_eq!(page.changes.len(), 3);
+        assert!(matches!(&page.changes[0], RemoteChange::Created(file) if file.is_directory()));
+        assert!(matches!(&page.changes[1], RemoteChange::Moved(file) if file.name == "notes.txt"));
+        assert_eq!(page.changes[2], RemoteChange::Deleted(4));
+    }
+
+    #[tokio::test]
+    async fn uploaded_content_is_sent_and_downloaded_content_returned() {
+        // Given a kDrive API that keeps the last upload
+        let stored: Arc<Mutex<Vec<u8>>> = Arc::default();
+        let (upload_store, download_store) = (stored.clone(), stored.clone());
+        let api_url = start_api(Router::new()
+            .route("/3/drive/{drive_id}/upload", post(move |Query(query): Query<HashMap<String, String>>, body: Bytes| async move {
+                *upload_store.lock().unwrap() = body.to_vec();
+                Json(json!({ "result": "success", "data": file_json(4, &query["file_name"], "file") }))
+            }))
+            .route("/2/drive/{drive_id}/files/{file_id}/download", get(move || async move {
+                download_store.lock().unwrap().clone()
+            }))).await;
+        let adapter = adapter(api_url);
+
+        // When a file is uploaded and downloaded again
+        let file = adapter.upload_file(DRIVE_ID, 1, "hello.txt", b"hey".to_vec()).await.unwrap();
+        let content = adapter.download_file(DRIVE_ID, file.id).await.unwrap();
+
+        // Then the content made the round trip
+        assert_eq!(file.name, "hello.txt");
+        assert_eq!(content, b"hey");
+    }
+
+    #[tokio::test]
+    async fn downloads_continue_from_the_requested_byte() {
+        // Given a kDrive API where file 4 honours ranges and file 5 always sends everything
+        let api_url = start_api(Router::new().route("/2/drive/{drive_id}/files/{file_id}/download", get(
+            |Path((_, file_id)): Path<(DriveId, FileId)>, headers: HeaderMap| async move {
+                let range = headers.get("range").and_then(|value| value.to_str().ok()).map(str::to_string);
+                match (file_id, range) {
+                    (4, Some(range)) => {
+                        let offset: usize = range.trim_start_matches("bytes=").trim_end_matches('-').parse().unwrap();
+                        (StatusCode::PARTIAL_CONTENT, b"hello world"[offset..].to_vec()).into_response()
+                    }
+                    _ => b"hello world".to_vec().into_response(),
+                }
+            },
+        ))).await;
+        let adapter = adapter(api_url);
+
+        // When both are downloaded from byte 6
+        let mut ranged = String::new();
+        adapter.download_file_from(DRIVE_ID, 4, 6).await.unwrap().read_to_string(&mut ranged).await.unwrap();
+        let mut whole = String::new();
+        adapter.download_file_from(DRIVE_ID, 5, 6).await.unwrap().read_to_string(&mut whole).await.unwrap();
+
+        // Then both return only the rest of the content
+        assert_eq!(ranged, "world");
+        assert_eq!(whole, "world");
+    }
+
+    #[tokio::test]
+    async fn chunks_are_sent_to_their_upload_session() {
+        // Given a kDrive API that records the chunks of a session
+        let received: Received = Arc::default();
+        let recorded = received.clone();
+        let api_url = start_api(Router::new()
+            .route("/3/drive/{drive_id}/upload/session/start", post(|body: Bytes| async move {
+                let body: Value = serde_json::from_slice(&body).unwrap();
+                assert_eq!((body["file_id"].as_u64(), body["total_chunks"].as_u64(), body["conflict"].as_str()), (Some(4), Some(2), Some("version")));
+                Json(json!({ "result": "success", "data": { "token": "session-token" } }))
+            }))
+            .route("/3/drive/{drive_id}/upload/session/{token}/chunk", post(
+                move |Path((_, token)): Path<(DriveId, String)>, Query(query): Query<HashMap<String, String>>, body: Bytes| async move {
+                    recorded.lock().unwrap().push(format!("{token} {} {} {}", query["chunk_number"], query["chunk_size"], body.len()));
+                    Json(json!({ "result": "success", "data": { "received_bytes": body.len() } }))
+                },
+            ))
+            .route("/3/drive/{drive_id}/upload/session/{token}/finish", post(|| async {
+                Json(json!({ "result": "success", "data": { "token": "session-token", "file": file_json(4, "video.mp4", "file") } }))
+            }))).await;
+        let adapter = adapter(api_url);
+
+        // When a new version is uploaded in two chunks
+        let token = adapter.start_upload_session(DRIVE_ID, &UploadTarget::NewVersion { file_id: 4 }, 5, 2).await.unwrap();
+        adapter.upload_chunk(DRIVE_ID, &token, 1, b"hey".to_vec(), "xxh3:1").await.unwrap();
+        adapter.upload_chunk(DRIVE_ID, &token, 2, b"yo".to_vec(), "xxh3:2").await.unwrap();
+        let file = adapter.finish_upload_session(DRIVE_ID, &token).await.unwrap();
+
+        // Then every chunk went to the session and the finished file is returned
+        assert_eq!(received.lock().unwrap().as_slice(), ["session-token 1 3 3", "session-token 2 2 2"]);
+        assert_eq!(file.name, "video.mp4");
+    }
+
+    #[tokio::test]
+    async fn file_operations_use_the_expected_endpoints() {
+        // Given a kDrive API that records the requests
+        let received: Received = Arc::default();
+        let record = |received: &Received, name: &'static str| {
+            let received = received.clone();
+            move || async move {
+                received.lock().unwrap().push(name.to_string());
+                Json(json!({ "result": "success", "data": true }))
+            }
+        };
+        let api_url = start_api(Router::new()
+            .route("/3/drive/{drive_id}/files/{file_id}/move/{destination_id}", post(record(&received, "move")))
+            .route("/2/drive/{drive_id}/files/{file_id}/rename", post(record(&received, "rename")))
+            .route("/2/drive/{drive_id}/files/{file_id}", axum::routing::delete(record(&received, "delete")))
+            .route("/2/drive/{drive_id}/trash/{file_id}/restore", post(record(&received, "restore")))).await;
+        let adapter = adapter(api_url);
+
+        // When a file is moved, renamed, deleted and restored
+        adapter.move_file(DRIVE_ID, 4, 2).await.unwrap();
+        adapter.rename_file(DRIVE_ID, 4, "renamed.txt").await.unwrap();
+        adapter.delete_file(DRIVE_ID, 4).await.unwrap();
+        adapter.restore_file(DRIVE_ID, 4).await.unwrap();
+
+        // Then every operation reached its endpoint
+        assert_eq!(received.lock().unwrap().as_slice(), ["move", "rename", "delete", "restore"]);
+    }
+
+    #[tokio::test]
+    async fn api_error_body_becomes_the_error_detail() {
+        // Given a kDrive API that does not know the file
+        let api_url = start_api(Router::new().route("/3/drive/{drive_id}/files/{file_id}", get(|| async {
+            (StatusCode::NOT_FOUND, Json(json!({
+                "result": "error",
+                "error": { "code": "object_not_found", "description": "File not found" }
+            }))).into_response()
+        }))).await;
+
+        // When the file is requested
+        let err = adapter(api_url).get_file(DRIVE_ID, 42).await.expect_err("Expected ApiNotFound error");
+
+        // Then the error is mapped to its key with the description of kDrive
+        assert_eq!(err.text_key, ApiNotFound);
+        assert_eq!(err.detail.as_deref(), Some("object_not_found: File not found"));
+    }
+
+    #[tokio::test]
+    async fn unexpected_response_body_returns_an_error() {
+        let api_url = start_api(Router::new().route("/2/drive", get(|| async { "not json" }))).await;
+
+        let err = adapter(api_url).list_drives().await.expect_err("Expected ApiUnexpectedResponse error");
+
+        assert_eq!(err.text_key, ApiUnexpectedResponse);
+    }
+
+    #[test]
+    fn http_status_and_error_code_map_to_text_keys() {
+        let error_body = |code: &str| json!({ "result": "error", "error": { "code": code } }).to_string();
+
+        assert_eq!(map_api_error(StatusCode::UNAUTHORIZED, "").text_key, ApiNotAuthenticated);
+        assert_eq!(map_api_error(StatusCode::FORBIDDEN, "").text_key, ApiAccessDenied);
+        assert_eq!(map_api_error(StatusCode::CONFLICT, "").text_key, ApiConflict);
+        assert_eq!(map_api_error(StatusCode::TOO_MANY_REQUESTS, "").text_key, ApiRateLimited);
+        assert_eq!(map_api_error(StatusCode::BAD_GATEWAY, "").text_key, ApiServerError);
+        assert_eq!(map_api_error(StatusCode::BAD_REQUEST, &error_body("destination_already_exists")).text_key, ApiConflict);
+        assert_eq!(map_api_error(StatusCode::BAD_REQUEST, &error_body("something_else")).text_key, ApiRequestFailed);
+        assert_eq!(map_api_error(StatusCode::BAD_REQUEST, "").detail.as_deref(), Some("400 Bad Request"));
+    }
+}
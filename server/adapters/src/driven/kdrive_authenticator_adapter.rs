@@ -1,5 +1,8 @@
 use std::collections::HashMap;
+use std::io;
+use std::net::{SocketAddr, TcpListener};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use async_trait::async_trait;
 use axum::extract::Query;
 use axum::http::StatusCode;
@@ -7,20 +10,29 @@ use axum::response::{Html, IntoResponse};
 use axum::Router;
 use axum::routing::get;
 use oauth2::basic::{BasicClient, BasicErrorResponse, BasicRevocationErrorResponse, BasicTokenIntrospectionResponse, BasicTokenResponse};
-use oauth2::{AccessToken, AuthUrl, Client, ClientId, CsrfToken, EndpointNotSet, EndpointSet, PkceCodeChallenge, PkceCodeVerifier, RedirectUrl, RefreshToken, Scope, StandardRevocableToken, TokenResponse, TokenUrl};
+use oauth2::{AccessToken, AuthUrl, Client, ClientId, CsrfToken, EndpointNotSet, EndpointSet, PkceCodeChallenge, PkceCodeVerifier, RedirectUrl, RefreshToken, RevocationUrl, Scope, StandardRevocableToken, TokenResponse, TokenUrl};
 use tokio::sync::oneshot;
 use tokio::task::JoinHandle;
-use tokio::time::Instant;
 use common::application_error;
 use common::domain::errors::ApplicationError;
-use common::domain::text_keys::TextKeys::{FlowNotStarted, MissingAuthorizationCode, NoAccessTokenReceived, NoRefreshTokenReceived, OAuthReturnedError, TokenRequestFailed};
-use engine::domain::callback_endpoint::{CallbackEndpoint, ParseRedirectUrl};
+use common::domain::text_keys::TextKeys::{CallbackPortInUse, FlowNotStarted, InvalidOAuthState, MissingAuthorizationCode, NoAccessTokenReceived, NoRefreshTokenReceived, OAuthReturnedError, TokenRequestFailed, TokenRevocationFailed};
+use engine::domain::callback_endpoint::{redirect_url_candidates, ParseRedirectUrl};
 use engine::domain::configuration::Configuration;
+use engine::domain::default_values::general_defaults::DEFAULT_ACCESS_TOKEN_LIFETIME_SECONDS;
 use engine::domain::tokens::Tokens;
-use engine::ports::driven::authenticator_driven_port::AuthenticatorDrivenPort;
+use engine::ports::driven::authenticator_driven_port::{AuthenticatorDrivenPort, PendingAuthorizationCode, PendingTokenRefresh};
+use engine::ports::driven::clock_driven_port::ClockDrivenPort;
+use crate::driven::system_clock_adapter::SystemClockAdapter;
 
-pub struct KDriveAuthenticator {
+/// The callback route hands the authorization code over to the waiting auth flow only once
+type SharedCodeSender = Arc<Mutex<Option<oneshot::Sender<Result<String, ApplicationError>>>>>;
+
+pub struct KDriveAuthenticator<Clock = SystemClockAdapter>
+where
+    Clock: ClockDrivenPort,
+{
     redirect_url: RedirectUrl,
+    fallback_redirect_ports: Vec<u16>,
     client: Client< BasicErrorResponse,
         BasicTokenResponse,
         BasicTokenIntrospectionResponse,
@@ -29,25 +41,35 @@ pub struct KDriveAuthenticator {
         EndpointSet,
         EndpointNotSet,
         EndpointNotSet,
-        EndpointNotSet,
+        EndpointSet,
         EndpointSet,
     >,
+    clock: Arc<Clock>,
     pkce_verifier: Option<PkceCodeVerifier>,
     csrf_token: Option<CsrfToken>,
 
-    server_handle: Option<JoinHandle<()>>,
+    server_handles: Vec<JoinHandle<()>>,
     code_rx: Option<oneshot::Receiver<Result<String,ApplicationError>>>,
     access_token: Option<AccessToken>,
     refresh_token: Option<RefreshToken>,
-    access_token_expiry: Option<Instant>,
+    access_token_expires_at: Option<i64>,
     pub auth_url: AuthUrl,
     pub token_url: TokenUrl,
     pub client_id: ClientId,
 }
 #[async_trait]
-impl AuthenticatorDrivenPort for KDriveAuthenticator {
+impl<Clock> AuthenticatorDrivenPort for KDriveAuthenticator<Clock>
+where
+    Clock: ClockDrivenPort + Send + Sync + 'static,
+{
 
     async fn start_initial_auth_flow(&mut self) -> Result<String, ApplicationError> {
+        // Starting again replaces a flow that is still waiting, this frees the callback port
+        self.cancel_auth_flow().await;
+
+        let (redirect_url, path, listeners) = self.bind_callback_listeners()?;
+        self.client = self.client.clone().set_redirect_uri(redirect_url);
+
         let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
         let (auth_url, csrf_token) = self.client
             .authorize_url(CsrfToken::new_random)
@@ -60,50 +82,50 @@ impl AuthenticatorDrivenPort for KDriveAuthenticator {
             .url();
 
         self.pkce_verifier = Some(pkce_verifier);
+        let expected_state = csrf_token.secret().clone();
         self.csrf_token = Some(csrf_token);
 
-        let callback_endpoint = self.redirect_url.parse()?;
         let (code_tx, code_rx) = oneshot::channel::<Result<String, ApplicationError>>();
         //let (error_tx, error_rx) = tokio::sync::mpsc::channel::<AuthFlowError>(1);
         let shared_sender = Arc::new(Mutex::new(Some(code_tx)));
 
-        let router = self.create_router(&callback_endpoint.path, shared_sender.clone());
-        let server_handle = self.start_callback_server(callback_endpoint, router).await;
+        let router = Self::create_router(&path, expected_state, shared_sender.clone());
+        for listener in listeners {
+            let server_handle = Self::start_callback_server(listener, router.clone())?;
+            self.server_handles.push(server_handle);
+        }
 
-        // Sla op in de struct
-        self.server_handle = Some(server_handle);
         self.code_rx = Some(code_rx);
         Ok(auth_url.to_string())
     }
 
-    async fn continue_initial_auth_flow(&mut self) -> Result<(), ApplicationError> {
-        let pkce_verifier = match self.pkce_verifier.take() {
-            Some(v) => v,
-            None => return Err(application_error!(FlowNotStarted)),
-        };
-
+    fn pending_authorization_code(&mut self) -> Result<PendingAuthorizationCode, ApplicationError> {
         let receiver = match self.code_rx.take() {
             Some(rx) => rx,
             None => return Err(application_error!(FlowNotStarted)),
         };
 
-        // Wait for the auth code to arrive
-        let code = match receiver.await {
-            Ok(Ok(code)) => code,
-            Ok(Err(e)) => return Err(e),
-            Err(e) => return Err(e.into()),
+        Ok(Box::pin(async move {
+            match receiver.await {
+                Ok(result) => result,
+                Err(e) => Err(e.into()),
+            }
+        }))
+    }
+
+    async fn exchange_authorization_code(&mut self, code: &str) -> Result<(), ApplicationError> {
+        let pkce_verifier = match self.pkce_verifier.take() {
+            Some(v) => v,
+            None => return Err(application_error!(FlowNotStarted)),
         };
 
-        // Close the callback server
-        if let Some(handle) = self.server_handle.take() {
-            handle.abort();
-        }
+        self.stop_callback_server().await;
 
         // Exchange code for tokens
         let http_client = reqwest::Client::new();
         let token_result = self
             .client
-            .exchange_code(oauth2::AuthorizationCode::new(code.clone()))
+            .exchange_code(oauth2::AuthorizationCode::new(code.to_string()))
             .set_pkce_verifier(pkce_verifier)
             .request_async(&http_client)
             .await
@@ -111,15 +133,18 @@ impl AuthenticatorDrivenPort for KDriveAuthenticator {
                 application_error!(TokenRequestFailed, e.to_string()) )?;
 
         // Access token should be requested by calling get_access_token()
-        self.access_token = Some(token_result.access_token().clone());
-
-        if let Some(refresh) = token_result.refresh_token() {
-            self.refresh_token = Some(refresh.clone());
-        }
+        self.store_token_response(&token_result);
 
         Ok(())
     }
 
+    async fn cancel_auth_flow(&mut self) {
+        self.pkce_verifier = None;
+        self.csrf_token = None;
+        self.code_rx = None;
+        self.stop_callback_server().await;
+    }
+
     async fn get_tokens(&self) -> Result<Tokens, ApplicationError> {
         let access_token = self.access_token
             .as_ref()
@@ -133,9 +158,8 @@ impl AuthenticatorDrivenPort for KDriveAuthenticator {
             .secret()
             .clone();
 
-        let expires_at = self.access_token_expiry
-            .map(|instant| instant.elapsed().as_secs() as i64)
-            .unwrap_or(0);
+        let expires_at = self.access_token_expires_at
+            .ok_or(application_error!(NoAccessTokenReceived))?;
 
         Ok(Tokens {
             access_token,
@@ -143,40 +167,104 @@ impl AuthenticatorDrivenPort for KDriveAuthenticator {
             expires_at,
         })
     }
+
+    fn pending_token_refresh(&self, refresh_token: &str) -> PendingTokenRefresh {
+        let client = self.client.clone();
+        let clock = self.clock.clone();
+        let refresh_token = RefreshToken::new(refresh_token.to_string());
+
+        Box::pin(async move {
+            let http_client = reqwest::Client::new();
+            let token_result = client
+                .exchange_refresh_token(&refresh_token)
+                .request_async(&http_client)
+                .await
+                .map_err(|e| application_error!(TokenRequestFailed, e.to_string()))?;
+
+            Ok(Tokens {
+                access_token: token_result.access_token().secret().clone(),
+                // Not every provider rotates the refresh token, keep the current one in that case
+                refresh_token: token_result.refresh_token().unwrap_or(&refresh_token).secret().clone(),
+                expires_at: token_expiry(clock.now(), token_result.expires_in()),
+            })
+        })
+    }
+
+    async fn revoke_tokens(&mut self, refresh_token: &str) -> Result<(), ApplicationError> {
+        let token = StandardRevocableToken::RefreshToken(RefreshToken::new(refresh_token.to_string()));
+        let http_client = reqwest::Client::new();
+        let result = match self.client.revoke_token(token) {
+            Ok(request) => request
+                .request_async(&http_client)
+                .await
+                .map_err(|e| application_error!(TokenRevocationFailed, e.to_string())),
+            Err(e) => Err(application_error!(TokenRevocationFailed, e.to_string())),
+        };
+
+        self.access_token = None;
+        self.refresh_token = None;
+        self.access_token_expires_at = None;
+        result
+    }
 }
 
-impl KDriveAuthenticator {
+impl KDriveAuthenticator<SystemClockAdapter> {
     pub fn new_from_config(config: &Configuration) -> Self{
-        KDriveAuthenticator::new(config.auth_url.clone(), config.token_url.clone(), config.client_id.clone(), config.redirect_url.clone())
+        KDriveAuthenticator::new(config.auth_url.clone(), config.token_url.clone(), config.client_id.clone(), config.redirect_url.clone(), config.revocation_url.clone())
+            .with_fallback_redirect_ports(config.fallback_redirect_ports.clone())
     }
     
-    pub fn new(auth_url: AuthUrl, token_url: TokenUrl, client_id: ClientId, redirect_url: RedirectUrl) -> Self {
+    pub fn new(auth_url: AuthUrl, token_url: TokenUrl, client_id: ClientId, redirect_url: RedirectUrl, revocation_url: RevocationUrl) -> Self {
+        KDriveAuthenticator::new_with_clock(auth_url, token_url, client_id, redirect_url, revocation_url, SystemClockAdapter)
+    }
+}
+
+impl<Clock> KDriveAuthenticator<Clock>
+where
+    Clock: ClockDrivenPort + Send + Sync + 'static,
+{
+    pub fn new_with_clock(auth_url: AuthUrl, token_url: TokenUrl, client_id: ClientId, redirect_url: RedirectUrl, revocation_url: RevocationUrl, clock: Clock) -> Self {
         let client = BasicClient::new(client_id.clone())
             .set_auth_uri(auth_url.clone())
             .set_token_uri(token_url.clone())
-            .set_redirect_uri(redirect_url.clone());
+            .set_redirect_uri(redirect_url.clone())
+            .set_revocation_url(revocation_url);
 
         Self {
-            auth_url, token_url, client_id, redirect_url, client,
-            pkce_verifier: None, csrf_token: None, server_handle: None, code_rx: None,
-            access_token: None, refresh_token: None, access_token_expiry: None
+            auth_url, token_url, client_id, redirect_url, client, clock: Arc::new(clock),
+            fallback_redirect_ports: Vec::new(),
+            pkce_verifier: None, csrf_token: None, server_handles: Vec::new(), code_rx: None,
+            access_token: None, refresh_token: None, access_token_expires_at: None
         }
     }
 
+    /// Ports to try, in order, when the port of the redirect URL is taken. Each must be registered with the OAuth client.
+    pub fn with_fallback_redirect_ports(mut self, ports: Vec<u16>) -> Self {
+        self.fallback_redirect_ports = ports;
+        self
+    }
+
     pub async fn get_access_token(&mut self) -> Result<AccessToken, ApplicationError> {
-        if let (Some(token), Some(expiry)) =
-            (&self.access_token, &self.access_token_expiry)
+        if let (Some(token), Some(expires_at)) = (&self.access_token, self.access_token_expires_at)
+            && self.clock.now() < expires_at
         {
-            if Instant::now() < *expiry {
-                return Ok(token.clone());
-            }
+            return Ok(token.clone());
         }
 
         let refresh_token = match &self.refresh_token {
-            Some(rt) => rt,
+            Some(rt) => rt.clone(),
             None => return Err(application_error!(FlowNotStarted)),
         };
 
+        self.exchange_refresh_token(&refresh_token).await?;
+
+        Ok(self
+            .access_token
+            .clone()
+            .expect("Net vernieuwd maar geen access token behouden"))
+    }
+
+    async fn exchange_refresh_token(&mut self, refresh_token: &RefreshToken) -> Result<(), ApplicationError> {
         let http_client = reqwest::Client::new();
         let token_result = self
             .client
@@ -186,24 +274,30 @@ impl KDriveAuthenticator {
             .map_err(|e|
                 application_error!(TokenRequestFailed, e.to_string()) )?;
 
+        // Not every provider rotates the refresh token, keep the current one in that case
+        self.refresh_token = Some(refresh_token.clone());
+        self.store_token_response(&token_result);
+        Ok(())
+    }
+
+    fn store_token_response(&mut self, token_result: &BasicTokenResponse) {
         self.access_token = Some(token_result.access_token().clone());
-        if let Some(rt) = token_result.refresh_token() {
-            self.refresh_token = Some(rt.clone());
+
+        if let Some(refresh) = token_result.refresh_token() {
+            self.refresh_token = Some(refresh.clone());
         }
-        self.access_token_expiry = token_result
-            .expires_in()
-            .map(|d| Instant::now() + d);
 
-        Ok(self
-            .access_token
-            .clone()
-            .expect("Net vernieuwd maar geen access token behouden"))
+        self.access_token_expires_at = Some(self.expires_at(token_result.expires_in()));
+    }
+
+    fn expires_at(&self, expires_in: Option<Duration>) -> i64 {
+        token_expiry(self.clock.now(), expires_in)
     }
 
     fn create_router(
-        &self,
         path: &str,
-        sender: Arc<Mutex<Option<oneshot::Sender<Result<String, ApplicationError>>>>>,
+        expected_state: String,
+        sender: SharedCodeSender,
     ) -> Router {
 
         Router::new().route(
@@ -211,13 +305,18 @@ impl KDriveAuthenticator {
             get(move |Query(params): Query<HashMap<String, String>>| {
                 async move {
                     let (status, html, result) =
-                        KDriveAuthenticator::handle_oauth_params(&params);
+                        Self::handle_oauth_params(&params, &expected_state);
+
+                    // A forged callback must not be able to end the flow, keep waiting for the real one
+                    if let Err(error) = &result && error.text_key == InvalidOAuthState {
+                        return (status, Html(html)).into_response();
+                    }
 
-                    if let Ok(mut guard) = sender.lock() {
-                        if let Some(sender) = guard.take() {
-                            // Error handling is done when the receiver awaits the result
-                            let _ = sender.send(result);
-                        }
+                    if let Ok(mut guard) = sender.lock()
+                        && let Some(sender) = guard.take()
+                    {
+                        // Error handling is done when the receiver awaits the result
+                        let _ = sender.send(result);
                     }
 
                     (status, Html(html)).into_response()
@@ -226,7 +325,15 @@ impl KDriveAuthenticator {
         )
     }
 
-    fn handle_oauth_params(params: &HashMap<String, String>) -> (StatusCode, &'static str, Result<String, ApplicationError>) {
+    fn handle_oauth_params(params: &HashMap<String, String>, expected_state: &str) -> (StatusCode, &'static str, Result<String, ApplicationError>) {
+        if params.get("state").map(String::as_str) != Some(expected_state) {
+            return (
+                StatusCode::FORBIDDEN,
+                include_str!("templates/invalid_oauth_state_error.html"),
+                Err(application_error!(InvalidOAuthState)),
+            );
+        }
+
         match (params.get("code"), params.get("error")) {
             (Some(code), _) => (
                 StatusCode::OK,
@@ -246,21 +353,281 @@ impl KDriveAuthenticator {
         }
     }
 
-    async fn start_callback_server(
-        &self,
-        callback_endpoint: CallbackEndpoint,
-        router: Router,
-    ) -> JoinHandle<()> {
+    async fn stop_callback_server(&mut self) {
+        for handle in self.server_handles.drain(..) {
+            handle.abort();
+            // Wait until the task is gone so the listener is closed and the port can be reused
+            let _ = handle.await;
+        }
+    }
 
-        tokio::spawn(async move {
-            if let Err(err) = axum_server::bind(callback_endpoint.addr)
-                .serve(router.into_make_service())
-                .await
-            {
-                eprintln!("Callback server ended: {:?}", err);
+    /// Binds the first redirect URL whose port is free, so a port in use is reported before the user opens the browser
+    fn bind_callback_listeners(&self) -> Result<(RedirectUrl, String, Vec<TcpListener>), ApplicationError> {
+        let mut failures = Vec::new();
+
+        for redirect_url in redirect_url_candidates(&self.redirect_url, &self.fallback_redirect_ports)? {
+            let callback_endpoint = redirect_url.parse()?;
+            match Self::bind_all(&callback_endpoint.addrs) {
+                Ok(listeners) => return Ok((redirect_url, callback_endpoint.path, listeners)),
+                Err(err) => failures.push(format!("{} ({})", redirect_url.as_str(), err)),
             }
-        })
+        }
 
+        Err(application_error!(CallbackPortInUse, failures.join(", ")))
     }
 
-}
\ No newline at end of file
+    fn bind_all(addrs: &[SocketAddr]) -> io::Result<Vec<TcpListener>> {
+        let mut listeners = Vec::new();
+        let mut unavailable = None;
+
+        for addr in addrs {
+            match TcpListener::bind(addr) {
+                Ok(listener) => listeners.push(listener),
+                // The browser could end up at the other application, so the whole port is unusable
+                Err(err) if err.kind() == io::ErrorKind::AddrInUse => return Err(err),
+                // Not every computer has IPv6 loopback, one of the addresses is enough
+                Err(err) => unavailable = Some(err),
+            }
+        }
+
+        match unavailable {
+            Some(err) if listeners.is_empty() => Err(err),
+            _ => Ok(listeners),
+        }
+    }
+
+    fn start_callback_server(listener: TcpListener, router: Router) -> Result<JoinHandle<()>, ApplicationError> {
+        let server = listener
+            .set_nonblocking(true)
+            .and_then(|()| axum_server::from_tcp(listener))
+            .map_err(|err| application_error!(CallbackPortInUse, err.to_string()))?;
+
+        Ok(tokio::spawn(async move {
+            // Serving only stops when the auth flow shuts the server down
+            let _ = server.serve(router.into_make_service()).await;
+        }))
+    }
+
+}
+
+fn token_expiry(now: i64, expires_in: Option<Duration>) -> i64 {
+    let lifetime = expires_in
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(DEFAULT_ACCESS_TOKEN_LIFETIME_SECONDS);
+
+    now + lifetime
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use engine::domain::default_values::configurator_defaults::*;
+    use engine::domain::default_values::general_defaults::DEFAULT_ACCESS_TOKEN_LIFETIME_SECONDS;
+    use engine::domain::test_helpers::fake_clock::{FakeClock, TEST_NOW};
+    use axum::body::Body;
+    use axum::http::Request;
+    use oauth2::{AuthUrl, ClientId, RedirectUrl, RevocationUrl, TokenUrl};
+    use tower::ServiceExt;
+    use super::*;
+
+    fn authenticator_at(now: i64) -> KDriveAuthenticator<FakeClock> {
+        authenticator_with_redirect_url(DEFAULT_REDIRECT_URL, now)
+    }
+
+    fn authenticator_with_redirect_url(redirect_url: &str, now: i64) -> KDriveAuthenticator<FakeClock> {
+        KDriveAuthenticator::new_with_clock(
+            AuthUrl::new(DEFAULT_AUTH_URL.to_string()).unwrap(),
+            TokenUrl::new(DEFAULT_TOKEN_URL.to_string()).unwrap(),
+            ClientId::new(DEFAULT_CLIENT_ID.to_string()),
+            RedirectUrl::new(redirect_url.to_string()).unwrap(),
+            RevocationUrl::new(DEFAULT_REVOCATION_URL.to_string()).unwrap(),
+            FakeClock::at(now),
+        )
+    }
+
+    fn free_local_port() -> u16 {
+        std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+    }
+
+    #[test]
+    fn expires_at_is_an_absolute_timestamp_based_on_expires_in() {
+        let authenticator = authenticator_at(TEST_NOW);
+
+        let expires_at = authenticator.expires_at(Some(Duration::from_secs(7200)));
+
+        assert_eq!(expires_at, TEST_NOW + 7200);
+    }
+
+    #[test]
+    fn expires_at_falls_back_to_the_default_lifetime_without_expires_in() {
+        let authenticator = authenticator_at(TEST_NOW);
+
+        let expires_at = authenticator.expires_at(None);
+
+        assert_eq!(expires_at, TEST_NOW + DEFAULT_ACCESS_TOKEN_LIFETIME_SECONDS);
+    }
+
+    #[tokio::test]
+    async fn get_tokens_fails_before_any_token_was_received() {
+        let authenticator = authenticator_at(TEST_NOW);
+
+        let result = authenticator.get_tokens().await;
+
+        let err = result.expect_err("Expected NoAccessTokenReceived error");
+        assert_eq!(err.text_key, NoAccessTokenReceived);
+    }
+
+    const TEST_STATE: &str = "test-csrf-state";
+
+    fn callback_params(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn callback_with_matching_state_returns_the_code() {
+        let params = callback_params(&[("code", "auth-code"), ("state", TEST_STATE)]);
+
+        let (status, _, result) = KDriveAuthenticator::<FakeClock>::handle_oauth_params(&params, TEST_STATE);
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(result.unwrap(), "auth-code");
+    }
+
+    #[test]
+    fn callback_with_forged_state_is_rejected() {
+        let params = callback_params(&[("code", "injected-code"), ("state", "forged-state")]);
+
+        let (status, html, result) = KDriveAuthenticator::<FakeClock>::handle_oauth_params(&params, TEST_STATE);
+
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(html, include_str!("templates/invalid_oauth_state_error.html"));
+        assert_eq!(result.expect_err("Expected InvalidOAuthState error").text_key, InvalidOAuthState);
+    }
+
+    #[test]
+    fn callback_without_state_is_rejected() {
+        let params = callback_params(&[("code", "injected-code")]);
+
+        let (status, _, result) = KDriveAuthenticator::<FakeClock>::handle_oauth_params(&params, TEST_STATE);
+
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(result.expect_err("Expected InvalidOAuthState error").text_key, InvalidOAuthState);
+    }
+
+    #[test]
+    fn oauth_error_with_forged_state_is_rejected() {
+        let params = callback_params(&[("error", "access_denied"), ("state", "forged-state")]);
+
+        let (_, _, result) = KDriveAuthenticator::<FakeClock>::handle_oauth_params(&params, TEST_STATE);
+
+        assert_eq!(result.expect_err("Expected InvalidOAuthState error").text_key, InvalidOAuthState);
+    }
+
+    #[tokio::test]
+    async fn forged_callback_does_not_end_the_auth_flow() {
+        // Given a callback router waiting for the code of the real login
+        let (code_tx, mut code_rx) = oneshot::channel::<Result<String, ApplicationError>>();
+        let sender = Arc::new(Mutex::new(Some(code_tx)));
+        let router = KDriveAuthenticator::<FakeClock>::create_router("/callback", TEST_STATE.to_string(), sender);
+
+        // When a local page injects a code with a forged state
+        let response = router.clone()
+            .oneshot(Request::get("/callback?code=injected-code&state=forged-state").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        // Then the callback is rejected and the flow keeps waiting
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert!(code_rx.try_recv().is_err());
+
+        // And the real callback still completes the flow
+        let response = router
+            .oneshot(Request::get(format!("/callback?code=real-code&state={TEST_STATE}")).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(code_rx.await.unwrap().unwrap(), "real-code");
+    }
+
+    #[tokio::test]
+    async fn cancelling_the_auth_flow_frees_the_callback_port() {
+        // Given an auth flow waiting for the callback
+        let port = free_local_port();
+        let mut authenticator = authenticator_with_redirect_url(&format!("http://127.0.0.1:{port}/kdrive/auth"), TEST_NOW);
+        authenticator.start_initial_auth_flow().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // When the flow is cancelled
+        authenticator.cancel_auth_flow().await;
+
+        // Then the callback server is gone and the flow can no longer be continued
+        assert!(std::net::TcpListener::bind(("127.0.0.1", port)).is_ok());
+        let err = authenticator.pending_authorization_code().err().expect("Expected FlowNotStarted error");
+        assert_eq!(err.text_key, FlowNotStarted);
+    }
+
+    #[tokio::test]
+    async fn the_auth_flow_can_be_restarted_on_the_same_port() {
+        // Given an auth flow waiting for the callback
+        let port = free_local_port();
+        let mut authenticator = authenticator_with_redirect_url(&format!("http://127.0.0.1:{port}/kdrive/auth"), TEST_NOW);
+        authenticator.start_initial_auth_flow().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // When the flow is started again
+        authenticator.start_initial_auth_flow().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // Then the new callback server listens on the port
+        assert!(std::net::TcpListener::bind(("127.0.0.1", port)).is_err());
+        assert!(authenticator.pending_authorization_code().is_ok());
+        authenticator.cancel_auth_flow().await;
+    }
+
+    #[tokio::test]
+    async fn starting_the_auth_flow_fails_when_the_callback_port_is_in_use() {
+        // Given another application listening on the callback port
+        let other_application = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = other_application.local_addr().unwrap().port();
+        let mut authenticator = authenticator_with_redirect_url(&format!("http://127.0.0.1:{port}/kdrive/auth"), TEST_NOW);
+
+        // When the auth flow is started
+        let result = authenticator.start_initial_auth_flow().await;
+
+        // Then a clear error is returned
+        let err = result.expect_err("Expected CallbackPortInUse error");
+        assert_eq!(err.text_key, CallbackPortInUse);
+    }
+
+    #[tokio::test]
+    async fn starting_the_auth_flow_falls_back_to_the_next_redirect_port() {
+        // Given another application listening on the callback port
+        let other_application = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = other_application.local_addr().unwrap().port();
+        let fallback_port = free_local_port();
+        let mut authenticator = authenticator_with_redirect_url(&format!("http://127.0.0.1:{port}/kdrive/auth"), TEST_NOW)
+            .with_fallback_redirect_ports(vec![fallback_port]);
+
+        // When the auth flow is started
+        let auth_url = authenticator.start_initial_auth_flow().await.unwrap();
+
+        // Then the callback server and the redirect URL use the fallback port
+        assert!(auth_url.contains(&format!("127.0.0.1%3A{fallback_port}")));
+        assert!(std::net::TcpListener::bind(("127.0.0.1", fallback_port)).is_err());
+        authenticator.cancel_auth_flow().await;
+    }
+
+    #[tokio::test]
+    async fn localhost_callback_listens_on_loopback() {
+        // Given a redirect URL using localhost
+        let port = free_local_port();
+        let mut authenticator = authenticator_with_redirect_url(&format!("http://localhost:{port}/kdrive/auth"), TEST_NOW);
+
+        // When the auth flow is started
+        authenticator.start_initial_auth_flow().await.unwrap();
+
+        // Then the callback server listens on the loopback address
+        assert!(std::net::TcpListener::bind(("127.0.0.1", port)).is_err());
+        authenticator.cancel_auth_flow().await;
+    }
+}
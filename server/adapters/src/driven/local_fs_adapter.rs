@@ -0,0 +1,433 @@
+use std::fs::File;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use async_trait::async_trait;
+use tokio::fs;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use common::application_error;
+use common::domain::errors::ApplicationError;
+use common::domain::text_keys::TextKeys::{InvalidLocalPath, LocalFileError, LocalFileExists, LocalFileNotFound, NotADirectory};
+use engine::domain::configuration::Configuration;
+use engine::domain::default_values::general_defaults::{LOCAL_TRASH_DIRECTORY_NAME, PARTIAL_FILE_SUFFIX};
+use engine::domain::local_file::LocalFile;
+use engine::domain::remote_file::FileKind;
+use engine::domain::sync::sync_path::{file_name, join_path, parent_path};
+use engine::ports::driven::local_fs_driven_port::{LocalFileReader, LocalFsDrivenPort};
+
+/// The sync root on disk. Its trash and files that are still being written are hidden, symbolic links and names
+/// that are not valid UTF-8 are left alone.
+pub struct LocalFsAdapter {
+    root: PathBuf,
+}
+
+impl LocalFsAdapter {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    pub fn new_from_config(config: &Configuration) -> Result<Self, ApplicationError> {
+        std::fs::create_dir_all(&config.sync_root)
+            .map_err(|e| application_error!(LocalFileError, "{:?}: {}", config.sync_root, e))?;
+        Ok(Self::new(config.sync_root.clone()))
+    }
+
+    /// Only plain names are allowed between the slashes, so a path can never point outside the root
+    fn resolve(&self, path: &str) -> Result<PathBuf, ApplicationError> {
+        if path.is_empty() {
+            return Ok(self.root.clone());
+        }
+        let mut resolved = self.root.clone();
+        for (depth, name) in path.split('/').enumerate() {
+            let trash = depth == 0 && name == LOCAL_TRASH_DIRECTORY_NAME;
+            if trash || name.is_empty() || name == "." || name == ".." || name.contains('\\') || is_hidden(name) {
+                return Err(application_error!(InvalidLocalPath, path));
+            }
+            resolved.push(name);
+        }
+        Ok(resolved)
+    }
+
+    async fn existing(&self, path: &str) -> Result<(PathBuf, std::fs::Metadata), ApplicationError> {
+        let resolved = self.resolve(path)?;
+        let metadata = fs::symlink_metadata(&resolved).await.map_err(|e| io_error(path, e))?;
+        Ok((resolved, metadata))
+    }
+
+    async fn check_directory(&self, path: &str) -> Result<(), ApplicationError> {
+        let (_, metadata) = self.existing(path).await?;
+        if !metadata.is_dir() {
+            return Err(application_error!(NotADirectory, path));
+        }
+        Ok(())
+    }
+
+    async fn check_free(&self, path: &str) -> Result<PathBuf, ApplicationError> {
+        let resolved = self.resolve(path)?;
+        match fs::symlink_metadata(&resolved).await {
+            Ok(_) => Err(application_error!(LocalFileExists, path)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(resolved),
+            Err(e) => Err(io_error(path, e)),
+        }
+    }
+
+    /// Hidden next to the file it becomes, so finishing it is a rename within the same directory
+    fn partial_path(&self, path: &str) -> Result<PathBuf, ApplicationError> {
+        self.resolve(path)?;
+        Ok(self.resolve(parent_path(path))?.join(format!(".{}{}", file_name(path), PARTIAL_FILE_SUFFIX)))
+    }
+
+    async fn write_partial(&self, partial: &Path, path: &str, content: &mut (dyn AsyncRead + Send + Unpin)) -> Result<u64, ApplicationError> {
+        let mut file = fs::File::create(partial).await.map_err(|e| io_error(path, e))?;
+        let size = tokio::io::copy(content, &mut file).await.map_err(|e| io_error(path, e))?;
+        file.flush().await.map_err(|e| io_error(path, e))?;
+        file.sync_all().await.map_err(|e| io_error(path, e))?;
+        Ok(size)
+    }
+
+    /// A name in the trash that is not taken yet, the time keeps names of the same file apart
+    async fn trash_path(&self, path: &str) -> Result<PathBuf, ApplicationError> {
+        let trash = self.root.join(LOCAL_TRASH_DIRECTORY_NAME);
+        fs::create_dir_all(&trash).await.map_err(|e| io_error(LOCAL_TRASH_DIRECTORY_NAME, e))?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+        let mut attempt = 0;
+        loop {
+            let candidate = trash.join(format!("{}-{}-{}", now, attempt, file_name(path)));
+            if fs::symlink_metadata(&candidate).await.is_err() {
+                return Ok(candidate);
+            }
+            attempt += 1;
+        }
+    }
+}
+
+/// Partial files are named `.<name>.part`, a file of the user that only ends in `.part` is still synced
+fn is_hidden(name: &str) -> bool {
+    name.starts_with('.') && name.ends_with(PARTIAL_FILE_SUFFIX)
+}
+
+fn io_error(path: &str, error: std::io::Error) -> ApplicationError {
+    match error.kind() {
+        ErrorKind::NotFound => application_error!(LocalFileNotFound, path),
+        ErrorKind::AlreadyExists => application_error!(LocalFileExists, path),
+        _ => application_error!(LocalFileError, "{}: {}", path, error),
+    }
+}
+
+fn to_seconds(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(after) => after.as_secs() as i64,
+        Err(before) => -(before.duration().as_secs() as i64),
+    }
+}
+
+fn from_seconds(seconds: i64) -> SystemTime {
+    if seconds >= 0 {
+        UNIX_EPOCH + Duration::from_secs(seconds as u64)
+    } else {
+        UNIX_EPOCH - Duration::from_secs(seconds.unsigned_abs())
+    }
+}
+
+/// Symbolic links and anything else that is not a plain file or directory is not synced
+fn local_file(path: String, metadata: &std::fs::Metadata) -> Option<LocalFile> {
+    let kind = if metadata.is_dir() {
+        FileKind::Directory
+    } else if metadata.is_file() {
+        FileKind::File
+    } else {
+        return None;
+    };
+    Some(LocalFile {
+        path,
+        kind,
+        size: metadata.is_file().then_some(metadata.len()),
+        modified_at: metadata.modified().map(to_seconds).unwrap_or_default(),
+    })
+}
+
+#[async_trait]
+impl LocalFsDrivenPort for LocalFsAdapter {
+    async fn read_dir(&self, path: &str) -> Result<Vec<LocalFile>, ApplicationError> {
+        self.check_directory(path).await?;
+        let mut entries = fs::read_dir(self.resolve(path)?).await.map_err(|e| io_error(path, e))?;
+        let mut files = Vec::new();
+        while let Some(entry) = entries.next_entry().await.map_err(|e| io_error(path, e))? {
+            let Ok(name) = entry.file_name().into_string() else {
+                continue;
+            };
+            if is_hidden(&name) || (path.is_empty() && name == LOCAL_TRASH_DIRECTORY_NAME) {
+                continue;
+            }
+            let metadata = entry.metadata().await.map_err(|e| io_error(path, e))?;
+            files.extend(local_file(join_path(path, &name), &metadata));
+        }
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(files)
+    }
+
+    async fn stat(&self, path: &str) -> Result<Option<LocalFile>, ApplicationError> {
+        match fs::symlink_metadata(self.resolve(path)?).await {
+            Ok(metadata) => Ok(local_file(path.to_string(), &metadata)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(io_error(path, e)),
+        }
+    }
+
+    async fn open_file(&self, path: &str) -> Result<LocalFileReader, ApplicationError> {
+        let file = fs::File::open(self.resolve(path)?).await.map_err(|e| io_error(path, e))?;
+        Ok(Box::new(file))
+    }
+
+    async fn write_file_from(&self, path: &str, content: &mut (dyn AsyncRead + Send + Unpin)) -> Result<u64, ApplicationError> {
+        let resolved = self.resolve(path)?;
+        self.check_directory(parent_path(path)).await?;
+        if fs::symlink_metadata(&resolved).await.is_ok_and(|metadata| metadata.is_dir()) {
+            return Err(application_error!(LocalFileExists, path));
+        }
+
+        let partial = self.partial_path(path)?;
+        let written = match self.write_partial(&partial, path, content).await {
+            Ok(size) => fs::rename(&partial, &resolved).await.map(|_| size).map_err(|e| io_error(path, e)),
+            Err(e) => Err(e),
+        };
+        if written.is_err() {
+            let _ = fs::remove_file(&partial).await;
+        }
+        written
+    }
+
+    async fn partial_size(&self, path: &str) -> Result<u64, ApplicationError> {
+        match fs::metadata(self.partial_path(path)?).await {
+            Ok(metadata) => Ok(metadata.len()),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(0),
+            Err(e) => Err(io_error(path, e)),
+        }
+    }
+
+    async fn append_partial(&self, path: &str, content: &mut (dyn AsyncRead + Send + Unpin)) -> Result<u64, ApplicationError> {
+        let partial = self.partial_path(path)?;
+        self.check_directory(parent_path(path)).await?;
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&partial)
+            .await
+            .map_err(|e| io_error(path, e))?;
+
+        // Every part is written before the next is read, so a failing reader loses nothing that arrived
+        let mut appended = 0;
+        let mut buffer = vec![0; 64 * 1024];
+        let result = loop {
+            match content.read(&mut buffer).await {
+                Ok(0) => break Ok(appended),
+                Ok(read) => {
+                    file.write_all(&buffer[..read]).await.map_err(|e| io_error(path, e))?;
+                    appended += read as u64;
+                }
+                Err(e) => break Err(io_error(path, e)),
+            }
+        };
+        file.flush().await.map_err(|e| io_error(path, e))?;
+        file.sync_all().await.map_err(|e| io_error(path, e))?;
+        result
+    }
+
+    async fn open_partial(&self, path: &str) -> Result<LocalFileReader, ApplicationError> {
+        let file = fs::File::open(self.partial_path(path)?).await.map_err(|e| io_error(path, e))?;
+        Ok(Box::new(file))
+    }
+
+    async fn finish_partial(&self, path: &str) -> Result<(), ApplicationError> {
+        let partial = self.partial_path(path)?;
+        let resolved = self.resolve(path)?;
+        if fs::symlink_metadata(&resolved).await.is_ok_and(|metadata| metadata.is_dir()) {
+            return Err(application_error!(LocalFileExists, path));
+        }
+        fs::rename(&partial, &resolved).await.map_err(|e| io_error(path, e))
+    }
+
+    async fn discard_partial(&self, path: &str) -> Result<(), ApplicationError> {
+        match fs::remove_file(self.partial_path(path)?).await {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(io_error(path, e)),
+            _ => Ok(()),
+        }
+    }
+
+    async fn create_directory(&self, path: &str) -> Result<(), ApplicationError> {
+        let resolved = self.resolve(path)?;
+        self.check_directory(parent_path(path)).await?;
+        fs::create_dir(resolved).await.map_err(|e| io_error(path, e))
+    }
+
+    async fn rename(&self, from: &str, to: &str) -> Result<(), ApplicationError> {
+        let (source, _) = self.existing(from).await?;
+        self.check_directory(parent_path(to)).await?;
+        let target = self.check_free(to).await?;
+        fs::rename(source, target).await.map_err(|e| io_error(from, e))
+    }
+
+    async fn move_to_trash(&self, path: &str) -> Result<(), ApplicationError> {
+        if path.is_empty() {
+            return Err(application_error!(InvalidLocalPath, path));
+        }
+        let (source, _) = self.existing(path).await?;
+        let target = self.trash_path(path).await?;
+        fs::rename(source, target).await.map_err(|e| io_error(path, e))
+    }
+
+    async fn set_modified_at(&self, path: &str, modified_at: i64) -> Result<(), ApplicationError> {
+        let (resolved, _) = self.existing(path).await?;
+        let path = path.to_string();
+        tokio::task::spawn_blocking(move || {
+            File::open(&resolved)
+                .and_then(|file| file.set_modified(from_seconds(modified_at)))
+                .map_err(|e| io_error(&path, e))
+        })
+        .await
+        .map_err(|e| application_error!(LocalFileError, e))?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TempRoot(PathBuf);
+
+    impl TempRoot {
+        fn new(name: &str) -> Self {
+            let root = std::env::temp_dir().join(format!("kdrive_rs_{}_{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&root);
+            std::fs::create_dir_all(&root).unwrap();
+            TempRoot(root)
+        }
+
+        fn adapter(&self) -> LocalFsAdapter {
+            LocalFsAdapter::new(self.0.clone())
+        }
+    }
+
+    impl Drop for TempRoot {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[tokio::test]
+    async fn written_files_are_listed_and_read_back() {
+        // Given an empty sync root
+        let root = TempRoot::new("local_fs_write");
+        let adapter = root.adapter();
+
+        // When a directory and files are written
+        adapter.create_directory("Documents").await.unwrap();
+        adapter.write_file("Documents/notes.txt", b"some notes").await.unwrap();
+        adapter.write_file("Documents/notes.txt", b"more notes").await.unwrap();
+        adapter.write_file("report.pdf", b"report").await.unwrap();
+
+        // Then they are listed sorted by path, without leftovers of the writes
+        let root_files = adapter.read_dir("").await.unwrap();
+        let paths: Vec<_> = root_files.iter().map(|file| file.path.as_str()).collect();
+        assert_eq!(paths, ["Documents", "report.pdf"]);
+        let documents = adapter.read_dir("Documents").await.unwrap();
+        assert_eq!(documents.len(), 1);
+        assert_eq!(documents[0].size, Some(10));
+        assert_eq!(adapter.read_file("Documents/notes.txt").await.unwrap(), b"more notes");
+    }
+
+    #[tokio::test]
+    async fn partial_files_stay_hidden_until_they_replace_the_file() {
+        // Given a file and a download of its new content in two parts
+        let root = TempRoot::new("local_fs_partial");
+        let adapter = root.adapter();
+        adapter.write_file("notes.txt", b"old").await.unwrap();
+        adapter.write_file("video.part", b"a file of the user").await.unwrap();
+        adapter.append_partial("notes.txt", &mut &b"new "[..]).await.unwrap();
+        adapter.append_partial("notes.txt", &mut &b"notes"[..]).await.unwrap();
+
+        // When the partial file is finished
+        let size = adapter.partial_size("notes.txt").await.unwrap();
+        let listed = adapter.read_dir("").await.unwrap().len();
+        adapter.finish_partial("notes.txt").await.unwrap();
+
+        // Then it was not listed and now replaces the old content
+        assert_eq!((size, listed), (9, 2));
+        assert_eq!(adapter.read_file("notes.txt").await.unwrap(), b"new notes");
+        assert_eq!(adapter.partial_size("notes.txt").await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn renames_never_replace_an_existing_path() {
+        // Given two files
+        let root = TempRoot::new("local_fs_rename");
+        let adapter = root.adapter();
+        adapter.write_file("a.txt", b"a").await.unwrap();
+        adapter.write_file("b.txt", b"b").await.unwrap();
+
+        // When one is renamed onto the other and then to a free name
+        let err = adapter.rename("a.txt", "b.txt").await.expect_err("Expected LocalFileExists error");
+        adapter.rename("a.txt", "c.txt").await.unwrap();
+
+        // Then only the second rename happened
+        assert_eq!(err.text_key, LocalFileExists);
+        assert_eq!(adapter.read_file("b.txt").await.unwrap(), b"b");
+        assert_eq!(adapter.read_file("c.txt").await.unwrap(), b"a");
+        assert_eq!(adapter.stat("a.txt").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn trashed_files_leave_the_sync_root_but_stay_on_disk() {
+        // Given a directory with a file
+        let root = TempRoot::new("local_fs_trash");
+        let adapter = root.adapter();
+        adapter.create_directory("Documents").await.unwrap();
+        adapter.write_file("Documents/notes.txt", b"some notes").await.unwrap();
+
+        // When the directory is moved to the trash
+        adapter.move_to_trash("Documents").await.unwrap();
+
+        // Then the sync root looks empty while the trash still holds the file
+        assert!(adapter.read_dir("").await.unwrap().is_empty());
+        let trash: Vec<_> = std::fs::read_dir(root.0.join(LOCAL_TRASH_DIRECTORY_NAME)).unwrap().collect();
+        assert_eq!(trash.len(), 1);
+        let trashed = trash[0].as_ref().unwrap().path().join("notes.txt");
+        assert_eq!(std::fs::read(trashed).unwrap(), b"some notes");
+    }
+
+    #[tokio::test]
+    async fn modification_time_can_be_set() {
+        let root = TempRoot::new("local_fs_mtime");
+        let adapter = root.adapter();
+        adapter.write_file("notes.txt", b"some notes").await.unwrap();
+
+        adapter.set_modified_at("notes.txt", 1_700_000_000).await.unwrap();
+
+        assert_eq!(adapter.stat("notes.txt").await.unwrap().unwrap().modified_at, 1_700_000_000);
+    }
+
+    #[tokio::test]
+    async fn paths_outside_the_sync_root_are_refused() {
+        let root = TempRoot::new("local_fs_outside");
+        let adapter = root.adapter();
+
+        for path in ["../notes.txt", "Documents/../../notes.txt", "/notes.txt", "./notes.txt", ".kdrive-trash/notes.txt"] {
+            let err = adapter.write_file(path, b"notes").await.expect_err("Expected InvalidLocalPath error");
+            assert_eq!(err.text_key, InvalidLocalPath, "{path}");
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn symbolic_links_are_not_listed() {
+        let root = TempRoot::new("local_fs_symlink");
+        let adapter = root.adapter();
+        adapter.write_file("notes.txt", b"some notes").await.unwrap();
+        std::os::unix::fs::symlink(root.0.join("notes.txt"), root.0.join("link.txt")).unwrap();
+
+        let files = adapter.read_dir("").await.unwrap();
+
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, "notes.txt");
+    }
+}
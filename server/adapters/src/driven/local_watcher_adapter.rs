@@ -0,0 +1,187 @@
+use std::path::{Path, PathBuf};
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use common::application_error;
+use common::domain::errors::ApplicationError;
+use common::domain::text_keys::TextKeys::LocalWatcherFailed;
+use engine::domain::configuration::Configuration;
+use engine::domain::default_values::general_defaults::{LOCAL_TRASH_DIRECTORY_NAME, PARTIAL_FILE_SUFFIX};
+use engine::domain::local_change::LocalFsEvent;
+use engine::ports::driven::local_watcher_driven_port::LocalWatcherDrivenPort;
+
+/// Watches the sync root with the file system notifications of the platform, inotify on Linux.
+/// The trash and files that are still being written by the sync itself are left out.
+pub struct LocalWatcherAdapter {
+    root: PathBuf,
+    watcher: Option<RecommendedWatcher>,
+}
+
+impl LocalWatcherAdapter {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root, watcher: None }
+    }
+
+    pub fn new_from_config(config: &Configuration) -> Self {
+        Self::new(config.sync_root.clone())
+    }
+}
+
+impl LocalWatcherDrivenPort for LocalWatcherAdapter {
+    fn watch(&mut self) -> Result<UnboundedReceiver<LocalFsEvent>, ApplicationError> {
+        // Notifications name the canonical path, a root reached through a symbolic link would match nothing
+        let root = self.root.canonicalize().map_err(|e| application_error!(LocalWatcherFailed, "{:?}: {}", self.root, e))?;
+        let (sender, receiver) = unbounded_channel();
+        let handler_root = root.clone();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            forward(&handler_root, event, &sender);
+        })
+        .map_err(|e| application_error!(LocalWatcherFailed, e))?;
+        watcher
+            .watch(&root, RecursiveMode::Recursive)
+            .map_err(|e| application_error!(LocalWatcherFailed, "{:?}: {}", root, e))?;
+        self.watcher = Some(watcher);
+        Ok(receiver)
+    }
+}
+
+fn forward(root: &Path, event: notify::Result<Event>, sender: &UnboundedSender<LocalFsEvent>) {
+    // An error means events may be missing, so a full scan is the only safe answer
+    let events = match event {
+        Ok(event) => local_events(root, event),
+        Err(_) => vec![LocalFsEvent::Overflowed],
+    };
+    for event in events {
+        // The receiver is gone once nobody listens anymore, there is nothing left to tell
+        let _ = sender.send(event);
+    }
+}
+
+fn local_events(root: &Path, event: Event) -> Vec<LocalFsEvent> {
+    if event.need_rescan() {
+        return vec![LocalFsEvent::Overflowed];
+    }
+    let tracker = event.tracker();
+    let paths: Vec<Option<String>> = event.paths.iter().map(|path| relative_path(root, path)).collect();
+    let Some(Some(path)) = paths.first().cloned() else {
+        return Vec::new();
+    };
+
+    let event = match event.kind {
+        EventKind::Create(_) | EventKind::Modify(ModifyKind::Data(_) | ModifyKind::Any | ModifyKind::Other) => {
+            LocalFsEvent::Changed { path }
+        }
+        EventKind::Remove(_) => LocalFsEvent::Removed { path },
+        EventKind::Modify(ModifyKind::Name(mode)) => match (mode, tracker) {
+            (RenameMode::From, Some(cookie)) => LocalFsEvent::MovedFrom { path, cookie },
+            (RenameMode::To, Some(cookie)) => LocalFsEvent::MovedTo { path, cookie },
+            // Both halves already came on their own with the same tracker
+            (RenameMode::Both, Some(_)) => return Vec::new(),
+            (RenameMode::Both, None) => match paths.get(1).cloned().flatten() {
+                Some(to) => LocalFsEvent::Moved { from: path, to },
+                None => LocalFsEvent::Removed { path },
+            },
+            (RenameMode::From, None) => LocalFsEvent::Removed { path },
+            // Without a tracker only the disk can tell which side of the rename this is
+            (_, _) if root.join(&path).exists() => LocalFsEvent::Changed { path },
+            (_, _) => LocalFsEvent::Removed { path },
+        },
+        // Reading a file or the sync setting its modification time does not change it
+        EventKind::Modify(ModifyKind::Metadata(_)) | EventKind::Access(_) | EventKind::Any | EventKind::Other => {
+            return Vec::new();
+        }
+    };
+    vec![event]
+}
+
+/// The path inside the sync root with `/` between the names, nothing for the root itself or paths that are not synced
+fn relative_path(root: &Path, path: &Path) -> Option<String> {
+    let names = path.strip_prefix(root).ok()?
+        .iter()
+        .map(|name| name.to_str())
+        .collect::<Option<Vec<_>>>()?;
+    let first = names.first()?;
+    if *first == LOCAL_TRASH_DIRECTORY_NAME || names.iter().any(|name| name.starts_with('.') && name.ends_with(PARTIAL_FILE_SUFFIX)) {
+        return None;
+    }
+    Some(names.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use tokio::time::timeout;
+    use super::*;
+
+    struct TempRoot(PathBuf);
+
+    impl TempRoot {
+        fn new(name: &str) -> Self {
+            let root = std::env::temp_dir().join(format!("kdrive_rs_{}_{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&root);
+            std::fs::create_dir_all(&root).unwrap();
+            TempRoot(root)
+        }
+    }
+
+    impl Drop for TempRoot {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    async fn next_events(receiver: &mut UnboundedReceiver<LocalFsEvent>) -> Vec<LocalFsEvent> {
+        let mut events = Vec::new();
+        while let Ok(Some(event)) = timeout(Duration::from_millis(300), receiver.recv()).await {
+            events.push(event);
+        }
+        events
+    }
+
+    #[test]
+    fn paths_are_relative_to_the_root_and_skip_what_is_not_synced() {
+        let root = Path::new("/sync");
+
+        assert_eq!(relative_path(root, Path::new("/sync/Documents/notes.txt")), Some("Documents/notes.txt".to_string()));
+        assert_eq!(relative_path(root, Path::new("/sync")), None);
+        assert_eq!(relative_path(root, Path::new("/elsewhere/notes.txt")), None);
+        assert_eq!(relative_path(root, Path::new("/sync/.kdrive-trash/notes.txt")), None);
+        assert_eq!(relative_path(root, Path::new("/sync/Documents/.notes.txt.part")), None);
+        assert_eq!(relative_path(root, Path::new("/sync/Documents/video.part")), Some("Documents/video.part".to_string()));
+    }
+
+    #[test]
+    fn lost_events_ask_for_a_rescan() {
+        let event = Event::new(EventKind::Other).set_flag(notify::event::Flag::Rescan);
+
+        assert_eq!(local_events(Path::new("/sync"), event), [LocalFsEvent::Overflowed]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn writes_and_renames_in_the_sync_root_are_reported() {
+        // Given a watched sync root with a file
+        let root = TempRoot::new("local_watcher");
+        std::fs::write(root.0.join("draft.txt"), b"draft").unwrap();
+        let mut adapter = LocalWatcherAdapter::new(root.0.clone());
+        let mut receiver = adapter.watch().unwrap();
+
+        // When a folder is created and the file is renamed into it
+        std::fs::create_dir(root.0.join("Documents")).unwrap();
+        std::fs::rename(root.0.join("draft.txt"), root.0.join("Documents/notes.txt")).unwrap();
+        let events = next_events(&mut receiver).await;
+
+        // Then the rename comes as two halves with the same cookie
+        assert_eq!(events[0], LocalFsEvent::Changed { path: "Documents".to_string() });
+        let halves: Vec<_> = events.iter()
+            .filter_map(|event| match event {
+                LocalFsEvent::MovedFrom { path, cookie } | LocalFsEvent::MovedTo { path, cookie } => Some((path.as_str(), *cookie)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(halves.len(), 2);
+        assert_eq!(halves[0].0, "draft.txt");
+        assert_eq!(halves[1].0, "Documents/notes.txt");
+        assert_eq!(halves[0].1, halves[1].1);
+    }
+}
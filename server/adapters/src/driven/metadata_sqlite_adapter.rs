@@ -1,19 +1,1145 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row, Transaction};
+use rusqlite::types::Value;
+use common::application_error;
+use common::domain::errors::ApplicationError;
+use common::domain::file_search::FileSearch;
+use common::domain::sync_selection::SyncSelection;
+use common::domain::text_keys::TextKeys::{CouldNotOpenMetadataStore, MetadataStoreQueryFailed, MetadataStoreTooNew};
+use engine::domain::configuration::Configuration;
+use engine::domain::default_values::general_defaults::METADATA_DATABASE_FILE_NAME;
+use engine::domain::metadata::{CrawlProgress, FileMetadata, SyncState};
+use engine::domain::remote_file::{DriveId, FileId, FileKind, RemoteFile};
+use engine::domain::upload_session::{UploadSession, UploadTarget};
 use engine::ports::driven::metadata_driven_port::MetadataDrivenPort;
 
-pub struct MetadataSqliteAdapter {}
+/// Schema changes in the order they are applied, the database remembers how many ran in `user_version`.
+/// Never edit a migration that has shipped, add a new one instead.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE files (
+        id INTEGER PRIMARY KEY,
+        parent_id INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        size INTEGER,
+        last_modified_at INTEGER NOT NULL,
+        hash TEXT,
+        sync_state TEXT NOT NULL
+    );
+    CREATE INDEX files_parent_id ON files (parent_id);
+    CREATE TABLE sync_info (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );",
+    "CREATE TABLE crawl_queue (
+        position INTEGER PRIMARY KEY AUTOINCREMENT,
+        directory_id INTEGER NOT NULL UNIQUE
+    );",
+    // The trigram tokenizer lets MATCH find any part of a name, the triggers keep the index in step with files
+    "CREATE VIRTUAL TABLE files_search USING fts5 (name, content = 'files', content_rowid = 'id', tokenize = 'trigram');
+    INSERT INTO files_search (files_search) VALUES ('rebuild');
+    CREATE TRIGGER files_search_insert AFTER INSERT ON files BEGIN
+        INSERT INTO files_search (rowid, name) VALUES (new.id, new.name);
+    END;
+    CREATE TRIGGER files_search_delete AFTER DELETE ON files BEGIN
+        INSERT INTO files_search (files_search, rowid, name) VALUES ('delete', old.id, old.name);
+    END;
+    CREATE TRIGGER files_search_update AFTER UPDATE OF name ON files BEGIN
+        INSERT INTO files_search (files_search, rowid, name) VALUES ('delete', old.id, old.name);
+        INSERT INTO files_search (rowid, name) VALUES (new.id, new.name);
+    END;",
+    // The state both sides had after the last sync, while files follows kDrive between syncs
+    "CREATE TABLE synced_files (
+        id INTEGER PRIMARY KEY,
+        parent_id INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        size INTEGER,
+        last_modified_at INTEGER NOT NULL,
+        hash TEXT
+    );
+    CREATE INDEX synced_files_parent_id ON synced_files (parent_id);
+    INSERT INTO synced_files SELECT id, parent_id, name, kind, size, last_modified_at, hash FROM files WHERE sync_state = 'synced';",
+    "CREATE TABLE sync_selection (
+        folder_id INTEGER PRIMARY KEY,
+        included INTEGER NOT NULL
+    );",
+    // A new version of a file has a file_id, a new file a directory_id and a name
+    "CREATE TABLE upload_sessions (
+        path TEXT PRIMARY KEY,
+        token TEXT NOT NULL,
+        file_id INTEGER,
+        directory_id INTEGER,
+        name TEXT,
+        total_size INTEGER NOT NULL,
+        chunk_size INTEGER NOT NULL,
+        modified_at INTEGER NOT NULL
+    );
+    CREATE TABLE uploaded_chunks (
+        path TEXT NOT NULL,
+        chunk_number INTEGER NOT NULL,
+        PRIMARY KEY (path, chunk_number)
+    );",
+    // The version is the hash kDrive reported for the file, or its modification time when it reported none
+    "CREATE TABLE partial_downloads (
+        path TEXT PRIMARY KEY,
+        version TEXT NOT NULL
+    );",
+];
+
+const FILE_COLUMNS: &str = "id, parent_id, name, kind, size, last_modified_at, hash, sync_state";
+const SYNCED_FILE_COLUMNS: &str = "id, parent_id, name, kind, size, last_modified_at, hash";
+const UPLOAD_SESSION_COLUMNS: &str = "path, token, file_id, directory_id, name, total_size, chunk_size, modified_at";
+const INDEXED_KEY: &str = "indexed";
+const REMOTE_CURSOR_KEY: &str = "remote_cursor";
+const SYNC_ROOT_DRIVE_KEY: &str = "sync_root_drive";
+
+pub struct MetadataSqliteAdapter {
+    connection: Mutex<Connection>,
+}
 
 impl MetadataSqliteAdapter {
-    pub fn new() -> Self {
-        Self {}
+    pub fn new_from_config(config: &Configuration) -> Result<Self, ApplicationError> {
+        Self::open(&config.data_dir.join(METADATA_DATABASE_FILE_NAME))
+    }
+
+    /// Opens the database at `path`, creating it and bringing its schema up to date when needed
+    pub fn open(path: &Path) -> Result<Self, ApplicationError> {
+        if let Some(folder) = path.parent() {
+            fs::create_dir_all(folder).map_err(|e| application_error!(CouldNotOpenMetadataStore, "{:?}: {}", path, e))?;
+        }
+        let connection = Connection::open(path).map_err(|e| application_error!(CouldNotOpenMetadataStore, "{:?}: {}", path, e))?;
+        connection
+            .pragma_update(None, "journal_mode", "WAL")
+            .map_err(|e| application_error!(CouldNotOpenMetadataStore, e))?;
+        Self::from_connection(connection)
+    }
+
+    pub fn open_in_memory() -> Result<Self, ApplicationError> {
+        let connection = Connection::open_in_memory().map_err(|e| application_error!(CouldNotOpenMetadataStore, e))?;
+        Self::from_connection(connection)
+    }
+
+    fn from_connection(mut connection: Connection) -> Result<Self, ApplicationError> {
+        migrate(&mut connection)?;
+        Ok(Self { connection: Mutex::new(connection) })
+    }
+
+    fn connection(&self) -> MutexGuard<'_, Connection> {
+        self.connection.lock().unwrap()
+    }
+}
+
+fn migrate(connection: &mut Connection) -> Result<(), ApplicationError> {
+    let version: usize = connection
+        .pragma_query_value(None, "user_version", |row| row.get(0))
+        .map_err(|e| application_error!(CouldNotOpenMetadataStore, e))?;
+    if version > MIGRATIONS.len() {
+        return Err(application_error!(MetadataStoreTooNew, "schema version {}, expected at most {}", version, MIGRATIONS.len()));
+    }
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let transaction = connection.transaction().map_err(|e| application_error!(CouldNotOpenMetadataStore, e))?;
+        transaction
+            .execute_batch(migration)
+            .and_then(|_| transaction.pragma_update(None, "user_version", index + 1))
+            .and_then(|_| transaction.commit())
+            .map_err(|e| application_error!(CouldNotOpenMetadataStore, "migration {}: {}", index + 1, e))?;
+    }
+    Ok(())
+}
+
+fn query_failed(error: rusqlite::Error) -> ApplicationError {
+    application_error!(MetadataStoreQueryFailed, error)
+}
+
+fn kind_to_sql(kind: FileKind) -> &'static str {
+    match kind {
+        FileKind::File => "file",
+        FileKind::Directory => "dir",
+    }
+}
+
+fn sync_state_to_sql(sync_state: SyncState) -> &'static str {
+    match sync_state {
+        SyncState::PendingDownload => "pending_download",
+        SyncState::PendingUpload => "pending_upload",
+        SyncState::Synced => "synced",
+        SyncState::Conflict => "conflict",
+    }
+}
+
+fn invalid_column(index: usize, value: &str) -> rusqlite::Error {
+    rusqlite::Error::InvalidColumnType(index, value.to_string(), rusqlite::types::Type::Text)
+}
+
+fn remote_file_from_row(row: &Row) -> rusqlite::Result<RemoteFile> {
+    let kind = match row.get_ref(3)?.as_str()? {
+        "file" => FileKind::File,
+        "dir" => FileKind::Directory,
+        other => return Err(invalid_column(3, other)),
+    };
+    Ok(RemoteFile {
+        id: row.get(0)?,
+        parent_id: row.get(1)?,
+        name: row.get(2)?,
+        kind,
+        size: row.get(4)?,
+        last_modified_at: row.get(5)?,
+        hash: row.get(6)?,
+    })
+}
+
+fn file_from_row(row: &Row) -> rusqlite::Result<FileMetadata> {
+    let sync_state = match row.get_ref(7)?.as_str()? {
+        "pending_download" => SyncState::PendingDownload,
+        "pending_upload" => SyncState::PendingUpload,
+        "synced" => SyncState::Synced,
+        "conflict" => SyncState::Conflict,
+        other => return Err(invalid_column(7, other)),
+    };
+    Ok(FileMetadata::new(remote_file_from_row(row)?, sync_state))
+}
+
+fn upload_session_from_row(row: &Row) -> rusqlite::Result<UploadSession> {
+    let target = match (row.get::<_, Option<FileId>>(2)?, row.get::<_, Option<FileId>>(3)?, row.get::<_, Option<String>>(4)?) {
+        (Some(file_id), _, _) => UploadTarget::NewVersion { file_id },
+        (None, Some(directory_id), Some(name)) => UploadTarget::NewFile { directory_id, name },
+        _ => return Err(invalid_column(2, "no upload target")),
+    };
+    Ok(UploadSession {
+        path: row.get(0)?,
+        token: row.get(1)?,
+        target,
+        total_size: row.get(5)?,
+        chunk_size: row.get(6)?,
+        modified_at: row.get(7)?,
+        uploaded_chunks: BTreeSet::new(),
+    })
+}
+
+fn insert_files(transaction: &Transaction, files: &[FileMetadata]) -> Result<(), ApplicationError> {
+    let mut statement = transaction
+        .prepare_cached(&format!(
+            // An upsert instead of INSERT OR REPLACE, a replace skips the delete trigger and corrupts the search index
+            "INSERT INTO files ({FILE_COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+            ON CONFLICT (id) DO UPDATE SET
+                parent_id = excluded.parent_id,
+                name = excluded.name,
+                kind = excluded.kind,
+                size = excluded.size,
+                last_modified_at = excluded.last_modified_at,
+                hash = excluded.hash,
+                sync_state = excluded.sync_state"
+        ))
+        .map_err(query_failed)?;
+    for file in files {
+        let remote = &file.remote;
+        statement
+            .execute(params![
+                remote.id,
+                remote.parent_id,
+                remote.name,
+                kind_to_sql(remote.kind),
+                remote.size,
+                remote.last_modified_at,
+                remote.hash,
+                sync_state_to_sql(file.sync_state),
+            ])
+            .map_err(query_failed)?;
+    }
+    Ok(())
+}
+
+/// Removes the row and every row whose parent chain leads to it
+fn delete_subtree(connection: &Connection, table: &str, id: FileId) -> Result<(), ApplicationError> {
+    connection
+        .execute(
+            &format!(
+                "WITH RECURSIVE subtree (id) AS (
+                    SELECT ?1
+                    UNION
+                    SELECT {table}.id FROM {table} JOIN subtree ON {table}.parent_id = subtree.id
+                )
+                DELETE FROM {table} WHERE id IN subtree"
+            ),
+            [id],
+        )
+        .map_err(query_failed)?;
+    Ok(())
+}
+
+/// Names shorter than a trigram cannot use the index and fall back to LIKE
+const MIN_INDEXED_NAME_LENGTH: usize = 3;
+
+fn escape_like(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+/// The WHERE clause and its parameters for a search, every filter that is set adds a condition
+fn search_conditions(search: &FileSearch) -> (String, Vec<Value>) {
+    let mut conditions = vec!["id != parent_id".to_string()];
+    let mut values = Vec::new();
+    let mut add = |condition: &str, value: Value| {
+        values.push(value);
+        conditions.push(condition.replace('?', &format!("?{}", values.len())));
+    };
+
+    if let Some(name) = &search.name {
+        if name.chars().count() >= MIN_INDEXED_NAME_LENGTH {
+            // A quoted phrase, so the characters of the name are never read as query syntax
+            add("id IN (SELECT rowid FROM files_search WHERE files_search MATCH ?)", Value::Text(format!("\"{}\"", name.replace('"', "\"\""))));
+        } else {
+            add("name LIKE ? ESCAPE '\\'", Value::Text(format!("%{}%", escape_like(name))));
+        }
+    }
+    if let Some(extension) = &search.extension {
+        add("kind = 'file' AND name LIKE ? ESCAPE '\\'", Value::Text(format!("%.{}", escape_like(extension))));
     }
+    if let Some(min_size) = search.min_size {
+        add("size >= ?", Value::Integer(min_size as i64));
+    }
+    if let Some(max_size) = search.max_size {
+        add("size <= ?", Value::Integer(max_size as i64));
+    }
+    if let Some(modified_after) = search.modified_after {
+        add("last_modified_at >= ?", Value::Integer(modified_after));
+    }
+    if let Some(modified_before) = search.modified_before {
+        add("last_modified_at <= ?", Value::Integer(modified_before));
+    }
+    (conditions.join(" AND "), values)
+}
+
+fn queue_directories<'a>(transaction: &Transaction, directory_ids: impl IntoIterator<Item = &'a FileId>) -> Result<(), ApplicationError> {
+    let mut statement = transaction
+        .prepare_cached("INSERT OR IGNORE INTO crawl_queue (directory_id) VALUES (?1)")
+        .map_err(query_failed)?;
+    for directory_id in directory_ids {
+        statement.execute([directory_id]).map_err(query_failed)?;
+    }
+    Ok(())
 }
 
 impl MetadataDrivenPort for MetadataSqliteAdapter {
-    fn has_metadata(&self) -> bool {
-        todo!()
+    fn has_metadata(&self) -> Result<bool, ApplicationError> {
+        self.connection()
+            .query_row("SELECT EXISTS (SELECT 1 FROM files)", [], |row| row.get(0))
+            .map_err(query_failed)
+    }
+
+    fn has_index(&self) -> Result<bool, ApplicationError> {
+        self.connection()
+            .query_row("SELECT EXISTS (SELECT 1 FROM sync_info WHERE key = ?1)", [INDEXED_KEY], |row| row.get(0))
+            .map_err(query_failed)
+    }
+
+    fn mark_indexed(&self) -> Result<(), ApplicationError> {
+        self.connection()
+            .execute("INSERT OR REPLACE INTO sync_info (key, value) VALUES (?1, 'true')", [INDEXED_KEY])
+            .map_err(query_failed)?;
+        Ok(())
+    }
+
+    fn save_files(&self, files: &[FileMetadata]) -> Result<(), ApplicationError> {
+        let mut connection = self.connection();
+        let transaction = connection.transaction().map_err(query_failed)?;
+        insert_files(&transaction, files)?;
+        transaction.commit().map_err(query_failed)
+    }
+
+    fn all_files(&self) -> Result<Vec<FileMetadata>, ApplicationError> {
+        let connection = self.connection();
+        let mut statement = connection
+            .prepare(&format!("SELECT {FILE_COLUMNS} FROM files"))
+            .map_err(query_failed)?;
+        statement
+            .query_map([], file_from_row)
+            .and_then(|rows| rows.collect())
+            .map_err(query_failed)
     }
 
-    fn has_index(&self) -> bool {
-        todo!()
+    fn get_file(&self, id: FileId) -> Result<Option<FileMetadata>, ApplicationError> {
+        self.connection()
+            .query_row(&format!("SELECT {FILE_COLUMNS} FROM files WHERE id = ?1"), [id], file_from_row)
+            .optional()
+            .map_err(query_failed)
+    }
+
+    fn get_children(&self, parent_id: FileId) -> Result<Vec<FileMetadata>, ApplicationError> {
+        let connection = self.connection();
+        let mut statement = connection
+            .prepare_cached(&format!(
+                "SELECT {FILE_COLUMNS} FROM files WHERE parent_id = ?1 AND id != ?1 ORDER BY name"
+            ))
+            .map_err(query_failed)?;
+        statement
+            .query_map([parent_id], file_from_row)
+            .and_then(|rows| rows.collect())
+            .map_err(query_failed)
+    }
+
+    fn get_children_page(&self, parent_id: FileId, offset: usize, limit: usize) -> Result<Vec<FileMetadata>, ApplicationError> {
+        let connection = self.connection();
+        let mut statement = connection
+            .prepare_cached(&format!(
+                "SELECT {FILE_COLUMNS} FROM files WHERE parent_id = ?1 AND id != ?1 ORDER BY name LIMIT ?2 OFFSET ?3"
+            ))
+            .map_err(query_failed)?;
+        statement
+            .query_map(params![parent_id, limit as i64, offset as i64], file_from_row)
+            .and_then(|rows| rows.collect())
+            .map_err(query_failed)
+    }
+
+    fn search_files(&self, search: &FileSearch) -> Result<Vec<FileMetadata>, ApplicationError> {
+        let (conditions, mut values) = search_conditions(search);
+        values.push(Value::Integer(search.limit as i64));
+        let connection = self.connection();
+        let mut statement = connection
+            .prepare(&format!(
+                "SELECT {FILE_COLUMNS} FROM files WHERE {conditions} ORDER BY name, id LIMIT ?{}",
+                values.len()
+            ))
+            .map_err(query_failed)?;
+        statement
+            .query_map(params_from_iter(values), file_from_row)
+            .and_then(|rows| rows.collect())
+            .map_err(query_failed)
+    }
+
+    fn set_sync_state(&self, id: FileId, sync_state: SyncState) -> Result<(), ApplicationError> {
+        self.connection()
+            .execute("UPDATE files SET sync_state = ?2 WHERE id = ?1", params![id, sync_state_to_sql(sync_state)])
+            .map_err(query_failed)?;
+        Ok(())
+    }
+
+    fn delete_file(&self, id: FileId) -> Result<(), ApplicationError> {
+        delete_subtree(&self.connection(), "files", id)
+    }
+
+    fn synced_files(&self) -> Result<Vec<RemoteFile>, ApplicationError> {
+        let connection = self.connection();
+        let mut statement = connection
+            .prepare(&format!("SELECT {SYNCED_FILE_COLUMNS} FROM synced_files"))
+            .map_err(query_failed)?;
+        statement
+            .query_map([], remote_file_from_row)
+            .and_then(|rows| rows.collect())
+            .map_err(query_failed)
+    }
+
+    fn save_synced_file(&self, file: &RemoteFile) -> Result<(), ApplicationError> {
+        let mut connection = self.connection();
+        let transaction = connection.transaction().map_err(query_failed)?;
+        insert_files(&transaction, &[FileMetadata::new(file.clone(), SyncState::Synced)])?;
+        transaction
+            .execute(
+                &format!("INSERT OR REPLACE INTO synced_files ({SYNCED_FILE_COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)"),
+                params![file.id, file.parent_id, file.name, kind_to_sql(file.kind), file.size, file.last_modified_at, file.hash],
+            )
+            .map_err(query_failed)?;
+        transaction.commit().map_err(query_failed)
+    }
+
+    fn forget_synced_file(&self, id: FileId) -> Result<(), ApplicationError> {
+        delete_subtree(&self.connection(), "synced_files", id)
+    }
+
+    fn remote_cursor(&self) -> Result<Option<String>, ApplicationError> {
+        self.connection()
+            .query_row("SELECT value FROM sync_info WHERE key = ?1", [REMOTE_CURSOR_KEY], |row| row.get(0))
+            .optional()
+            .map_err(query_failed)
+    }
+
+    fn apply_remote_changes(&self, saved: &[FileMetadata], deleted: &[FileId], cursor: &str) -> Result<(), ApplicationError> {
+        let mut connection = self.connection();
+        let transaction = connection.transaction().map_err(query_failed)?;
+        for id in deleted {
+            delete_subtree(&transaction, "files", *id)?;
+        }
+        insert_files(&transaction, saved)?;
+        transaction
+            .execute("INSERT OR REPLACE INTO sync_info (key, value) VALUES (?1, ?2)", params![REMOTE_CURSOR_KEY, cursor])
+            .map_err(query_failed)?;
+        transaction.commit().map_err(query_failed)
+    }
+
+    fn sync_selection(&self) -> Result<SyncSelection, ApplicationError> {
+        let connection = self.connection();
+        let mut statement = connection
+            .prepare("SELECT folder_id, included FROM sync_selection")
+            .map_err(query_failed)?;
+        let rules = statement
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .and_then(|rows| rows.collect())
+            .map_err(query_failed)?;
+        Ok(SyncSelection { rules })
+    }
+
+    fn save_sync_selection(&self, selection: &SyncSelection) -> Result<(), ApplicationError> {
+        let mut connection = self.connection();
+        let transaction = connection.transaction().map_err(query_failed)?;
+        transaction.execute("DELETE FROM sync_selection", []).map_err(query_failed)?;
+        for (folder_id, included) in &selection.rules {
+            transaction
+                .execute("INSERT INTO sync_selection (folder_id, included) VALUES (?1, ?2)", params![folder_id, included])
+                .map_err(query_failed)?;
+        }
+        transaction.commit().map_err(query_failed)
+    }
+
+    fn upload_session(&self, path: &str) -> Result<Option<UploadSession>, ApplicationError> {
+        let connection = self.connection();
+        let session = connection
+            .query_row(&format!("SELECT {UPLOAD_SESSION_COLUMNS} FROM upload_sessions WHERE path = ?1"), [path], upload_session_from_row)
+            .optional()
+            .map_err(query_failed)?;
+        let Some(mut session) = session else {
+            return Ok(None);
+        };
+
+        let mut statement = connection
+            .prepare_cached("SELECT chunk_number FROM uploaded_chunks WHERE path = ?1")
+            .map_err(query_failed)?;
+        session.uploaded_chunks = statement
+            .query_map([path], |row| row.get(0))
+            .and_then(|rows| rows.collect())
+            .map_err(query_failed)?;
+        Ok(Some(session))
+    }
+
+    fn save_upload_session(&self, session: &UploadSession) -> Result<(), ApplicationError> {
+        let (file_id, directory_id, name) = match &session.target {
+            UploadTarget::NewVersion { file_id } => (Some(*file_id), None, None),
+            UploadTarget::NewFile { directory_id, name } => (None, Some(*directory_id), Some(name.as_str())),
+        };
+        let mut connection = self.connection();
+        let transaction = connection.transaction().map_err(query_failed)?;
+        transaction
+            .execute("DELETE FROM uploaded_chunks WHERE path = ?1", [&session.path])
+            .map_err(query_failed)?;
+        transaction
+            .execute(
+                &format!("INSERT OR REPLACE INTO upload_sessions ({UPLOAD_SESSION_COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)"),
+                params![session.path, session.token, file_id, directory_id, name, session.total_size, session.chunk_size, session.modified_at],
+            )
+            .map_err(query_failed)?;
+        for chunk_number in &session.uploaded_chunks {
+            transaction
+                .execute("INSERT INTO uploaded_chunks (path, chunk_number) VALUES (?1, ?2)", params![session.path, chunk_number])
+                .map_err(query_failed)?;
+        }
+        transaction.commit().map_err(query_failed)
+    }
+
+    fn save_uploaded_chunk(&self, path: &str, chunk_number: u64) -> Result<(), ApplicationError> {
+        self.connection()
+            .execute(
+                "INSERT OR IGNORE INTO uploaded_chunks (path, chunk_number)
+                SELECT path, ?2 FROM upload_sessions WHERE path = ?1",
+                params![path, chunk_number],
+            )
+            .map_err(query_failed)?;
+        Ok(())
+    }
+
+    fn delete_upload_session(&self, path: &str) -> Result<(), ApplicationError> {
+        let mut connection = self.connection();
+        let transaction = connection.transaction().map_err(query_failed)?;
+        transaction.execute("DELETE FROM uploaded_chunks WHERE path = ?1", [path]).map_err(query_failed)?;
+        transaction.execute("DELETE FROM upload_sessions WHERE path = ?1", [path]).map_err(query_failed)?;
+        transaction.commit().map_err(query_failed)
+    }
+
+    fn partial_download(&self, path: &str) -> Result<Option<String>, ApplicationError> {
+        self.connection()
+            .query_row("SELECT version FROM partial_downloads WHERE path = ?1", [path], |row| row.get(0))
+            .optional()
+            .map_err(query_failed)
+    }
+
+    fn save_partial_download(&self, path: &str, version: &str) -> Result<(), ApplicationError> {
+        self.connection()
+            .execute("INSERT OR REPLACE INTO partial_downloads (path, version) VALUES (?1, ?2)", params![path, version])
+            .map_err(query_failed)?;
+        Ok(())
+    }
+
+    fn delete_partial_download(&self, path: &str) -> Result<(), ApplicationError> {
+        self.connection()
+            .execute("DELETE FROM partial_downloads WHERE path = ?1", [path])
+            .map_err(query_failed)?;
+        Ok(())
+    }
+
+    fn sync_root_drive(&self) -> Result<Option<DriveId>, ApplicationError> {
+        self.connection()
+            .query_row("SELECT CAST(value AS INTEGER) FROM sync_info WHERE key = ?1", [SYNC_ROOT_DRIVE_KEY], |row| row.get(0))
+            .optional()
+            .map_err(query_failed)
+    }
+
+    fn save_sync_root_drive(&self, drive_id: DriveId) -> Result<(), ApplicationError> {
+        self.connection()
+            .execute("INSERT OR REPLACE INTO sync_info (key, value) VALUES (?1, ?2)", params![SYNC_ROOT_DRIVE_KEY, drive_id.to_string()])
+            .map_err(query_failed)?;
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<(), ApplicationError> {
+        let mut connection = self.connection();
+        let transaction = connection.transaction().map_err(query_failed)?;
+        transaction
+            .execute_batch("DELETE FROM files; DELETE FROM synced_files; DELETE FROM crawl_queue; DELETE FROM sync_selection;
+                DELETE FROM upload_sessions; DELETE FROM uploaded_chunks; DELETE FROM partial_downloads;")
+            .map_err(query_failed)?;
+        transaction
+            .execute("DELETE FROM sync_info WHERE key != ?1", [SYNC_ROOT_DRIVE_KEY])
+            .map_err(query_failed)?;
+        transaction.commit().map_err(query_failed)
+    }
+
+    fn start_crawl(&self, root: &FileMetadata) -> Result<(), ApplicationError> {
+        let mut connection = self.connection();
+        let transaction = connection.transaction().map_err(query_failed)?;
+        insert_files(&transaction, std::slice::from_ref(root))?;
+        queue_directories(&transaction, [&root.remote.id])?;
+        transaction.commit().map_err(query_failed)
+    }
+
+    fn next_crawl_directory(&self) -> Result<Option<FileId>, ApplicationError> {
+        self.connection()
+            .query_row("SELECT directory_id FROM crawl_queue ORDER BY position LIMIT 1", [], |row| row.get(0))
+            .optional()
+            .map_err(query_failed)
+    }
+
+    fn save_crawled_directory(&self, directory_id: FileId, children: &[FileMetadata]) -> Result<(), ApplicationError> {
+        let mut connection = self.connection();
+        let transaction = connection.transaction().map_err(query_failed)?;
+        insert_files(&transaction, children)?;
+        transaction
+            .execute("DELETE FROM crawl_queue WHERE directory_id = ?1", [directory_id])
+            .map_err(query_failed)?;
+        queue_directories(&transaction, children.iter()
+            .filter(|child| child.remote.is_directory() && child.remote.id != directory_id)
+            .map(|child| &child.remote.id))?;
+        transaction.commit().map_err(query_failed)
+    }
+
+    fn crawl_progress(&self) -> Result<CrawlProgress, ApplicationError> {
+        self.connection()
+            .query_row(
+                "SELECT
+                    (SELECT COUNT(*) FROM files WHERE kind = 'dir'),
+                    (SELECT COUNT(*) FROM crawl_queue),
+                    (SELECT COUNT(*) FROM files WHERE kind = 'file'),
+                    (SELECT COALESCE(SUM(size), 0) FROM files WHERE kind = 'file')",
+                [],
+                |row| {
+                    let directories: u64 = row.get(0)?;
+                    let directories_pending: u64 = row.get(1)?;
+                    Ok(CrawlProgress {
+                        directories_visited: directories.saturating_sub(directories_pending),
+                        directories_pending,
+                        files_found: row.get(2)?,
+                        bytes_total: row.get(3)?,
+                    })
+                },
+            )
+            .map_err(query_failed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use engine::domain::default_values::general_defaults::ROOT_DIRECTORY_ID;
+    use super::*;
+
+    struct TempDatabase(PathBuf);
+
+    impl TempDatabase {
+        fn new(name: &str) -> Self {
+            TempDatabase(std::env::temp_dir().join(format!("kdrive_rs_{}_{}", name, std::process::id())))
+        }
+
+        fn path(&self) -> PathBuf {
+            self.0.join(METADATA_DATABASE_FILE_NAME)
+        }
     }
-}
\ No newline at end of file
+
+    impl Drop for TempDatabase {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn file(id: FileId, parent_id: FileId, name: &str, kind: FileKind) -> FileMetadata {
+        let remote = RemoteFile {
+            id,
+            parent_id,
+            name: name.to_string(),
+            kind,
+            size: (kind == FileKind::File).then_some(42),
+            last_modified_at: 1_700_000_000,
+            hash: (kind == FileKind::File).then(|| format!("hash-{id}")),
+        };
+        FileMetadata::new(remote, SyncState::PendingDownload)
+    }
+
+    fn store_with_tree() -> MetadataSqliteAdapter {
+        let store = MetadataSqliteAdapter::open_in_memory().unwrap();
+        store.save_files(&[
+            file(ROOT_DIRECTORY_ID, ROOT_DIRECTORY_ID, "", FileKind::Directory),
+            file(2, ROOT_DIRECTORY_ID, "Documents", FileKind::Directory),
+            file(3, 2, "report.pdf", FileKind::File),
+            file(4, ROOT_DIRECTORY_ID, "notes.txt", FileKind::File),
+        ]).unwrap();
+        store
+    }
+
+    #[test]
+    fn new_database_has_no_metadata_and_no_index() {
+        let store = MetadataSqliteAdapter::open_in_memory().unwrap();
+
+        assert!(!store.has_metadata().unwrap());
+        assert!(!store.has_index().unwrap());
+    }
+
+    #[test]
+    fn saved_file_is_read_back_unchanged() {
+        // Given an empty store
+        let store = MetadataSqliteAdapter::open_in_memory().unwrap();
+        let saved = file(3, 2, "report.pdf", FileKind::File);
+
+        // When a file is saved
+        store.save_file(&saved).unwrap();
+
+        // Then it is returned with every field intact
+        assert!(store.has_metadata().unwrap());
+        assert_eq!(store.get_file(3).unwrap(), Some(saved));
+        assert_eq!(store.get_file(99).unwrap(), None);
+    }
+
+    #[test]
+    fn saving_a_file_again_replaces_it() {
+        let store = store_with_tree();
+        let mut renamed = file(4, 2, "renamed.txt", FileKind::File);
+        renamed.sync_state = SyncState::Synced;
+
+        store.save_file(&renamed).unwrap();
+
+        assert_eq!(store.get_file(4).unwrap(), Some(renamed));
+        assert_eq!(store.get_children(ROOT_DIRECTORY_ID).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn children_are_sorted_by_name_and_exclude_the_root_itself() {
+        let store = store_with_tree();
+
+        let children = store.get_children(ROOT_DIRECTORY_ID).unwrap();
+
+        let names: Vec<_> = children.iter().map(|child| child.remote.name.as_str()).collect();
+        assert_eq!(names, ["Documents", "notes.txt"]);
+    }
+
+    #[test]
+    fn a_page_of_children_skips_the_ones_before_it() {
+        let store = store_with_tree();
+
+        let first = store.get_children_page(ROOT_DIRECTORY_ID, 0, 1).unwrap();
+        let second = store.get_children_page(ROOT_DIRECTORY_ID, 1, 1).unwrap();
+        let past_the_end = store.get_children_page(ROOT_DIRECTORY_ID, 2, 1).unwrap();
+
+        assert_eq!(first.iter().map(|child| child.remote.name.as_str()).collect::<Vec<_>>(), ["Documents"]);
+        assert_eq!(second.iter().map(|child| child.remote.name.as_str()).collect::<Vec<_>>(), ["notes.txt"]);
+        assert!(past_the_end.is_empty());
+    }
+
+    #[test]
+    fn sync_state_is_updated() {
+        let store = store_with_tree();
+
+        store.set_sync_state(3, SyncState::Conflict).unwrap();
+
+        assert_eq!(store.get_file(3).unwrap().unwrap().sync_state, SyncState::Conflict);
+    }
+
+    #[test]
+    fn deleting_a_directory_removes_everything_below_it() {
+        // Given a directory with a file inside
+        let store = store_with_tree();
+
+        // When the directory is deleted
+        store.delete_file(2).unwrap();
+
+        // Then the file is gone too, and the rest of the tree stays
+        assert_eq!(store.get_file(2).unwrap(), None);
+        assert_eq!(store.get_file(3).unwrap(), None);
+        assert!(store.get_file(4).unwrap().is_some());
+    }
+
+    #[test]
+    fn clear_forgets_files_and_index() {
+        let store = store_with_tree();
+        store.mark_indexed().unwrap();
+        store.save_sync_root_drive(100).unwrap();
+
+        store.clear().unwrap();
+
+        assert!(!store.has_metadata().unwrap());
+        assert!(!store.has_index().unwrap());
+        assert_eq!(store.sync_root_drive().unwrap(), Some(100));
+    }
+
+    #[test]
+    fn crawl_visits_directories_breadth_first() {
+        // Given a crawl that started at the root
+        let store = MetadataSqliteAdapter::open_in_memory().unwrap();
+        store.start_crawl(&file(ROOT_DIRECTORY_ID, ROOT_DIRECTORY_ID, "", FileKind::Directory)).unwrap();
+
+        // When the root and its first subdirectory are crawled
+        store.save_crawled_directory(ROOT_DIRECTORY_ID, &[
+            file(2, ROOT_DIRECTORY_ID, "Documents", FileKind::Directory),
+            file(3, ROOT_DIRECTORY_ID, "Pictures", FileKind::Directory),
+            file(4, ROOT_DIRECTORY_ID, "notes.txt", FileKind::File),
+        ]).unwrap();
+        let first = store.next_crawl_directory().unwrap();
+        store.save_crawled_directory(2, &[file(5, 2, "Archive", FileKind::Directory)]).unwrap();
+
+        // Then the sibling comes before the nested directory
+        assert_eq!(first, Some(2));
+        assert_eq!(store.next_crawl_directory().unwrap(), Some(3));
+        assert_eq!(store.crawl_progress().unwrap(), CrawlProgress {
+            directories_visited: 2,
+            directories_pending: 2,
+            files_found: 1,
+            bytes_total: 42,
+        });
+    }
+
+    #[test]
+    fn crawl_ends_when_the_queue_is_empty() {
+        let store = MetadataSqliteAdapter::open_in_memory().unwrap();
+        store.start_crawl(&file(ROOT_DIRECTORY_ID, ROOT_DIRECTORY_ID, "", FileKind::Directory)).unwrap();
+
+        store.save_crawled_directory(ROOT_DIRECTORY_ID, &[file(4, ROOT_DIRECTORY_ID, "notes.txt", FileKind::File)]).unwrap();
+
+        assert_eq!(store.next_crawl_directory().unwrap(), None);
+    }
+
+    #[test]
+    fn crawl_resumes_after_reopening_the_database() {
+        // Given a crawl that stopped with a directory left to visit
+        let database = TempDatabase::new("metadata_crawl_resume");
+        {
+            let store = MetadataSqliteAdapter::open(&database.path()).unwrap();
+            store.start_crawl(&file(ROOT_DIRECTORY_ID, ROOT_DIRECTORY_ID, "", FileKind::Directory)).unwrap();
+            store.save_crawled_directory(ROOT_DIRECTORY_ID, &[file(2, ROOT_DIRECTORY_ID, "Documents", FileKind::Directory)]).unwrap();
+        }
+
+        // When the database is opened again
+        let store = MetadataSqliteAdapter::open(&database.path()).unwrap();
+
+        // Then the crawl continues where it stopped
+        assert_eq!(store.next_crawl_directory().unwrap(), Some(2));
+        assert!(!store.has_index().unwrap());
+    }
+
+    #[test]
+    fn metadata_survives_reopening_the_database() {
+        // Given a database on disk with an indexed file
+        let database = TempDatabase::new("metadata_reopen");
+        {
+            let store = MetadataSqliteAdapter::open(&database.path()).unwrap();
+            store.save_file(&file(4, ROOT_DIRECTORY_ID, "notes.txt", FileKind::File)).unwrap();
+            store.mark_indexed().unwrap();
+        }
+
+        // When it is opened again
+        let store = MetadataSqliteAdapter::open(&database.path()).unwrap();
+
+        // Then the schema is not migrated twice and the data is still there
+        assert!(store.has_index().unwrap());
+        assert_eq!(store.get_file(4).unwrap().unwrap().remote.name, "notes.txt");
+        let version: usize = store.connection().pragma_query_value(None, "user_version", |row| row.get(0)).unwrap();
+        assert_eq!(version, MIGRATIONS.len());
+    }
+
+    #[test]
+    fn database_from_a_newer_version_is_refused() {
+        // Given a database with a schema this version does not know
+        let database = TempDatabase::new("metadata_too_new");
+        {
+            let store = MetadataSqliteAdapter::open(&database.path()).unwrap();
+            store.connection().pragma_update(None, "user_version", MIGRATIONS.len() + 1).unwrap();
+        }
+
+        // When it is opened
+        let result = MetadataSqliteAdapter::open(&database.path());
+
+        // Then it is left alone
+        assert_eq!(result.err().unwrap().text_key, MetadataStoreTooNew);
+    }
+
+    fn search(search: FileSearch) -> FileSearch {
+        FileSearch { limit: 100, ..search }
+    }
+
+    fn found_names(store: &MetadataSqliteAdapter, search: FileSearch) -> Vec<String> {
+        store.search_files(&search).unwrap().into_iter().map(|file| file.remote.name).collect()
+    }
+
+    #[test]
+    fn search_finds_any_part_of_a_name_ignoring_case() {
+        let store = store_with_tree();
+
+        assert_eq!(found_names(&store, search(FileSearch { name: Some("PORT".to_string()), ..Default::default() })), ["report.pdf"]);
+        assert_eq!(found_names(&store, search(FileSearch { name: Some("t".to_string()), ..Default::default() })), ["Documents", "notes.txt", "report.pdf"]);
+        assert!(found_names(&store, search(FileSearch { name: Some("%".to_string()), ..Default::default() })).is_empty());
+        assert!(found_names(&store, search(FileSearch { name: Some("\"pdf".to_string()), ..Default::default() })).is_empty());
+    }
+
+    #[test]
+    fn search_filters_on_extension_size_and_date() {
+        // Given files of different sizes and ages
+        let store = store_with_tree();
+        let mut large = file(5, 2, "scan.PDF", FileKind::File);
+        large.remote.size = Some(5_000);
+        large.remote.last_modified_at = 1_800_000_000;
+        store.save_file(&large).unwrap();
+
+        // When searching with each filter, then only the matching files are found
+        assert_eq!(found_names(&store, search(FileSearch { extension: Some("pdf".to_string()), ..Default::default() })), ["report.pdf", "scan.PDF"]);
+        assert_eq!(found_names(&store, search(FileSearch { min_size: Some(1_000), ..Default::default() })), ["scan.PDF"]);
+        assert_eq!(found_names(&store, search(FileSearch { max_size: Some(1_000), ..Default::default() })), ["notes.txt", "report.pdf"]);
+        assert_eq!(found_names(&store, search(FileSearch { modified_after: Some(1_750_000_000), ..Default::default() })), ["scan.PDF"]);
+        assert_eq!(found_names(&store, search(FileSearch {
+            extension: Some("pdf".to_string()),
+            modified_before: Some(1_750_000_000),
+            ..Default::default()
+        })), ["report.pdf"]);
+    }
+
+    #[test]
+    fn search_returns_at_most_the_limit() {
+        let store = store_with_tree();
+
+        let found = store.search_files(&FileSearch { limit: 2, ..Default::default() }).unwrap();
+
+        assert_eq!(found.len(), 2);
+    }
+
+    #[test]
+    fn search_index_follows_renames_and_deletes() {
+        // Given a stored tree
+        let store = store_with_tree();
+
+        // When a file is renamed and a directory deleted
+        store.save_file(&file(4, ROOT_DIRECTORY_ID, "minutes.txt", FileKind::File)).unwrap();
+        store.delete_file(2).unwrap();
+
+        // Then the index only knows the current names
+        let by_name = |name: &str| found_names(&store, search(FileSearch { name: Some(name.to_string()), ..Default::default() }));
+        assert!(by_name("notes").is_empty());
+        assert_eq!(by_name("minutes"), ["minutes.txt"]);
+        assert!(by_name("report").is_empty());
+    }
+
+    #[test]
+    fn metadata_stored_before_the_index_existed_is_searchable() {
+        // Given a database that was created before the search index
+        let database = TempDatabase::new("metadata_before_index");
+        {
+            fs::create_dir_all(&database.0).unwrap();
+            let connection = Connection::open(database.path()).unwrap();
+            connection.execute_batch(&MIGRATIONS[..2].join("\n")).unwrap();
+            connection.pragma_update(None, "user_version", 2).unwrap();
+            connection.execute(
+                "INSERT INTO files (id, parent_id, name, kind, last_modified_at, sync_state) VALUES (4, 1, 'notes.txt', 'file', 0, 'synced')",
+                [],
+            ).unwrap();
+        }
+
+        // When it is opened by this version
+        let store = MetadataSqliteAdapter::open(&database.path()).unwrap();
+
+        // Then the index was built from the existing files
+        assert_eq!(found_names(&store, search(FileSearch { name: Some("notes".to_string()), ..Default::default() })), ["notes.txt"]);
+    }
+
+    #[test]
+    fn synced_files_are_kept_apart_from_the_remote_view() {
+        // Given a file that was synced
+        let store = store_with_tree();
+        let synced = file(3, 2, "report.pdf", FileKind::File).remote;
+        store.save_synced_file(&synced).unwrap();
+
+        // When kDrive renames it afterwards
+        store.save_file(&file(3, 2, "final.pdf", FileKind::File)).unwrap();
+
+        // Then the synced state still has the old name
+        assert_eq!(store.synced_files().unwrap(), [synced]);
+        assert_eq!(store.get_file(3).unwrap().unwrap().remote.name, "final.pdf");
+    }
+
+    #[test]
+    fn forgetting_a_synced_directory_forgets_everything_below_it() {
+        let store = store_with_tree();
+        for id in [2, 3, 4] {
+            store.save_synced_file(&store.get_file(id).unwrap().unwrap().remote).unwrap();
+        }
+
+        store.forget_synced_file(2).unwrap();
+
+        let ids: Vec<_> = store.synced_files().unwrap().iter().map(|file| file.id).collect();
+        assert_eq!(ids, [4]);
+        assert!(store.get_file(3).unwrap().is_some());
+    }
+
+    #[test]
+    fn remote_changes_are_applied_with_their_cursor() {
+        // Given a store without a cursor
+        let store = store_with_tree();
+        assert_eq!(store.remote_cursor().unwrap(), None);
+
+        // When a directory was deleted and a file was added on kDrive
+        store.apply_remote_changes(&[file(5, ROOT_DIRECTORY_ID, "photo.jpg", FileKind::File)], &[2], "cursor-1").unwrap();
+
+        // Then both changes and the cursor are stored
+        assert_eq!(store.remote_cursor().unwrap(), Some("cursor-1".to_string()));
+        assert!(store.get_file(3).unwrap().is_none());
+        assert!(store.get_file(5).unwrap().is_some());
+        store.clear().unwrap();
+        assert_eq!(store.remote_cursor().unwrap(), None);
+    }
+
+    #[test]
+    fn files_synced_before_the_synced_table_existed_are_its_start() {
+        // Given a database from before the synced state had its own table
+        let database = TempDatabase::new("synced_before_table");
+        {
+            fs::create_dir_all(&database.0).unwrap();
+            let connection = Connection::open(database.path()).unwrap();
+            connection.execute_batch(&MIGRATIONS[..3].join("\n")).unwrap();
+            connection.pragma_update(None, "user_version", 3).unwrap();
+            connection.execute_batch(
+                "INSERT INTO files (id, parent_id, name, kind, last_modified_at, sync_state) VALUES (4, 1, 'notes.txt', 'file', 0, 'synced');
+                 INSERT INTO files (id, parent_id, name, kind, last_modified_at, sync_state) VALUES (5, 1, 'photo.jpg', 'file', 0, 'pending_download');",
+            ).unwrap();
+        }
+
+        // When it is opened by this version
+        let store = MetadataSqliteAdapter::open(&database.path()).unwrap();
+
+        // Then only the synced file is in the synced state
+        let names: Vec<_> = store.synced_files().unwrap().into_iter().map(|file| file.name).collect();
+        assert_eq!(names, ["notes.txt"]);
+    }
+
+    fn upload_session(path: &str, target: UploadTarget) -> UploadSession {
+        UploadSession {
+            path: path.to_string(),
+            token: format!("token-{path}"),
+            target,
+            total_size: 10,
+            chunk_size: 4,
+            modified_at: 1_700_000_000,
+            uploaded_chunks: BTreeSet::from([1]),
+        }
+    }
+
+    #[test]
+    fn upload_sessions_are_stored_with_their_chunks() {
+        // Given a new file and a new version that are being uploaded
+        let store = store_with_tree();
+        let new_file = upload_session("video.mp4", UploadTarget::NewFile { directory_id: 2, name: "video.mp4".to_string() });
+        let new_version = upload_session("notes.txt", UploadTarget::NewVersion { file_id: 4 });
+        store.save_upload_session(&new_file).unwrap();
+        store.save_upload_session(&new_version).unwrap();
+
+        // When kDrive confirms another chunk of the new file
+        store.save_uploaded_chunk("video.mp4", 2).unwrap();
+
+        // Then both sessions are read back with the chunks confirmed so far
+        let mut expected = new_file;
+        expected.uploaded_chunks.insert(2);
+        assert_eq!(store.upload_session("video.mp4").unwrap(), Some(expected));
+        assert_eq!(store.upload_session("notes.txt").unwrap(), Some(new_version));
+        assert_eq!(store.upload_session("other.txt").unwrap(), None);
+    }
+
+    #[test]
+    fn partial_downloads_remember_the_version_they_are_from() {
+        // Given a download of notes.txt that was interrupted
+        let store = store_with_tree();
+        store.save_partial_download("notes.txt", "xxh3:1").unwrap();
+
+        // When a newer version starts over
+        store.save_partial_download("notes.txt", "xxh3:2").unwrap();
+
+        // Then only the newer version counts, until the download is done
+        assert_eq!(store.partial_download("notes.txt").unwrap(), Some("xxh3:2".to_string()));
+        store.delete_partial_download("notes.txt").unwrap();
+        assert_eq!(store.partial_download("notes.txt").unwrap(), None);
+    }
+
+    #[test]
+    fn a_new_upload_session_starts_without_the_chunks_of_the_old_one() {
+        // Given a session with two chunks
+        let store = store_with_tree();
+        let session = upload_session("video.mp4", UploadTarget::NewVersion { file_id: 4 });
+        store.save_upload_session(&session).unwrap();
+        store.save_uploaded_chunk("video.mp4", 2).unwrap();
+
+        // When a new session replaces it
+        let replacement = UploadSession { token: "new-token".to_string(), uploaded_chunks: BTreeSet::new(), ..session };
+        store.save_upload_session(&replacement).unwrap();
+
+        // Then none of the old chunks count
+        assert_eq!(store.upload_session("video.mp4").unwrap(), Some(replacement));
+    }
+
+    #[test]
+    fn upload_sessions_survive_reopening_until_they_are_deleted() {
+        // Given a session stored in a database on disk
+        let database = TempDatabase::new("upload_session_reopen");
+        let session = upload_session("video.mp4", UploadTarget::NewVersion { file_id: 4 });
+        MetadataSqliteAdapter::open(&database.path()).unwrap().save_upload_session(&session).unwrap();
+
+        // When the database is opened again and the upload finishes
+        let store = MetadataSqliteAdapter::open(&database.path()).unwrap();
+        let resumed = store.upload_session("video.mp4").unwrap();
+        store.delete_upload_session("video.mp4").unwrap();
+
+        // Then the session was there to resume and is gone afterwards, chunks included
+        assert_eq!(resumed, Some(session));
+        assert_eq!(store.upload_session("video.mp4").unwrap(), None);
+        store.save_uploaded_chunk("video.mp4", 3).unwrap();
+        let chunks: u64 = store.connection().query_row("SELECT COUNT(*) FROM uploaded_chunks", [], |row| row.get(0)).unwrap();
+        assert_eq!(chunks, 0);
+    }
+
+    #[test]
+    fn a_saved_selection_replaces_the_previous_one() {
+        // Given a selection that leaves out two folders
+        let store = store_with_tree();
+        store.save_sync_selection(&SyncSelection::default().with_rule(2, false).with_rule(5, false)).unwrap();
+
+        // When a new selection is saved
+        let selection = SyncSelection::default().with_rule(2, false).with_rule(3, true);
+        store.save_sync_selection(&selection).unwrap();
+
+        // Then only its rules are left, until the store is cleared
+        assert_eq!(store.sync_selection().unwrap(), selection);
+        store.clear().unwrap();
+        assert_eq!(store.sync_selection().unwrap(), SyncSelection::default());
+    }
+}
@@ -1,7 +1,12 @@
 pub mod kdrive_authenticator_adapter;
 pub mod build_time_env_var_configurator_adapter;
+pub mod toml_file_configurator_adapter;
 pub mod token_store_file_adapter;
 pub mod token_store_key_ring_adapter;
 pub mod event_bus_adapter;
 pub mod metadata_sqlite_adapter;
+pub mod system_clock_adapter;
+pub mod kdrive_api_adapter;
+pub mod local_fs_adapter;
+pub mod local_watcher_adapter;
 
@@ -0,0 +1,26 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use engine::ports::driven::clock_driven_port::ClockDrivenPort;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClockAdapter;
+
+impl ClockDrivenPort for SystemClockAdapter {
+    fn now(&self) -> i64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn system_clock_returns_a_time_after_the_unix_epoch() {
+        let clock = SystemClockAdapter;
+
+        assert!(clock.now() > 0);
+    }
+}
@@ -1,26 +1,33 @@
 use engine::domain::default_values::general_defaults::*;
 use engine::ports::driven::token_store_driven_port::TokenStoreDrivenPort;
 use serde::{Deserialize, Serialize};
-use dirs::config_dir;
 use std::fs;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use common::application_error;
 use common::domain::errors::ApplicationError;
-use common::domain::text_keys::TextKeys::{CouldNotCreateFolder, CouldNotOpenTokenFile, CouldNotParseJson, CouldNotReadTokensFromFile, CouldNotSaveTokenFile, CouldNotSerializeTokens, NoConfigFolderFound};
+use common::domain::text_keys::TextKeys::{CouldNotCreateFolder, CouldNotDeleteTokenFile, CouldNotOpenTokenFile, CouldNotParseJson, CouldNotReadTokensFromFile, CouldNotSaveTokenFile, CouldNotSerializeTokens};
 use engine::domain::tokens::Tokens;
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct TokenStoreFileAdapter;
+pub struct TokenStoreFileAdapter {
+    data_dir: PathBuf,
+}
+
+impl TokenStoreFileAdapter {
+    pub fn new(data_dir: PathBuf) -> Self {
+        TokenStoreFileAdapter { data_dir }
+    }
+}
 
 impl TokenStoreDrivenPort for TokenStoreFileAdapter {
     fn is_available(&self) -> bool {
-        tokens_file_path().is_ok()
+        tokens_file_path(&self.data_dir).is_ok()
     }
 
     fn load(&self) -> Result<Option<Tokens>, ApplicationError>
     {
-        let path = tokens_file_path()?;
+        let path = tokens_file_path(&self.data_dir)?;
         let data = match fs::read_to_string(&path) {
             Ok(d) => d,
             Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
@@ -39,7 +46,7 @@ impl TokenStoreDrivenPort for TokenStoreFileAdapter {
     }
 
     fn save(&self, tokens: &Tokens) -> Result<(), ApplicationError> {
-        let path = tokens_file_path()?;
+        let path = tokens_file_path(&self.data_dir)?;
         let json = serde_json::to_string_pretty(tokens).map_err(|e| {
             application_error!(CouldNotSerializeTokens, e.to_string())
         })?;
@@ -72,13 +79,19 @@ impl TokenStoreDrivenPort for TokenStoreFileAdapter {
 
         Ok(())
     }
-}
 
-fn tokens_file_path() -> Result<PathBuf, ApplicationError> {
-    let mut path = config_dir()
-        .ok_or_else(|| application_error!(NoConfigFolderFound) )?;
+    fn clear(&self) -> Result<(), ApplicationError> {
+        let path = tokens_file_path(&self.data_dir)?;
+        match fs::remove_file(&path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(application_error!(CouldNotDeleteTokenFile, e.to_string())),
+        }
+    }
+}
 
-    path.push(APPLICATION_NAME);
+fn tokens_file_path(data_dir: &Path) -> Result<PathBuf, ApplicationError> {
+    let mut path = data_dir.to_path_buf();
     fs::create_dir_all(&path)
         .map_err(|e| application_error!(CouldNotCreateFolder, e.to_string()) )?;
 
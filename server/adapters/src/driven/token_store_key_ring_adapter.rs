@@ -4,7 +4,7 @@ use serde::{Deserialize, Serialize};
 use keyring::Entry;
 use common::application_error;
 use common::domain::errors::ApplicationError;
-use common::domain::text_keys::TextKeys::{CouldNotAccessKeyring, CouldNotParseJson, CouldNotReadTokensFromKeyring, CouldNotSaveTokensToKeyring, CouldNotSerializeTokens, KeyringNotAvailable};
+use common::domain::text_keys::TextKeys::{CouldNotAccessKeyring, CouldNotDeleteTokensFromKeyring, CouldNotParseJson, CouldNotReadTokensFromKeyring, CouldNotSaveTokensToKeyring, CouldNotSerializeTokens, KeyringNotAvailable};
 use engine::domain::tokens::Tokens;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -62,6 +62,16 @@ impl TokenStoreDrivenPort for TokenStoreKeyRingAdapter {
 
         Ok(())
     }
+
+    fn clear(&self) -> Result<(), ApplicationError> {
+        let entry = Entry::new(KEYRING_SERVICE, KEYRING_USER)
+            .map_err(|e| application_error!(CouldNotAccessKeyring, e.to_string()) )?;
+
+        match entry.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(error) => Err(application_error!(CouldNotDeleteTokensFromKeyring, error.to_string())),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,372 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use dirs::config_dir;
+use oauth2::{AuthUrl, ClientId, RedirectUrl, RevocationUrl, TokenUrl};
+use oauth2::url::Url;
+use serde::Deserialize;
+use common::application_error;
+use common::domain::errors::ApplicationError;
+use common::domain::text_keys::TextKeys::{CouldNotParseConfigFile, CouldNotReadConfigFile, NoConfigFolderFound, ParserError};
+use engine::domain::configuration::{ConflictPolicy, Configuration, LogLevel};
+use engine::domain::default_values::general_defaults::{APPLICATION_NAME, CONFIG_ENV_PREFIX, CONFIG_FILE_NAME};
+use engine::ports::driven::configurator_driven_port::ConfiguratorPort;
+use crate::driven::build_time_env_var_configurator_adapter::{parse_listen_address, parse_patterns, parse_ports, BuildTimeEnvVarConfiguratorPort};
+
+/// Reads the configuration file and `KDRIVE_` environment variables at runtime.
+/// Environment variables win over the file, the file wins over the build-time defaults.
+pub struct TomlFileConfiguratorAdapter<Base = BuildTimeEnvVarConfiguratorPort>
+where
+    Base: ConfiguratorPort,
+{
+    base: Base,
+    path: PathBuf,
+    env: HashMap<String, String>,
+}
+
+impl TomlFileConfiguratorAdapter<BuildTimeEnvVarConfiguratorPort> {
+    pub fn new(path: PathBuf) -> Self {
+        TomlFileConfiguratorAdapter::with_base(BuildTimeEnvVarConfiguratorPort, path, config_env_vars())
+    }
+
+    pub fn from_default_location() -> Result<Self, ApplicationError> {
+        Ok(TomlFileConfiguratorAdapter::new(default_config_file_path()?))
+    }
+}
+
+impl<Base> TomlFileConfiguratorAdapter<Base>
+where
+    Base: ConfiguratorPort,
+{
+    pub fn with_base(base: Base, path: PathBuf, env: HashMap<String, String>) -> Self {
+        Self { base, path, env }
+    }
+
+    fn read_file(&self) -> Result<ConfigurationOverrides, ApplicationError> {
+        let data = match fs::read_to_string(&self.path) {
+            Ok(data) => data,
+            // Without a file the defaults are used
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(ConfigurationOverrides::default());
+            }
+            Err(e) => {
+                return Err(application_error!(CouldNotReadConfigFile, e.to_string()));
+            }
+        };
+
+        toml::from_str(&data)
+            .map_err(|e| application_error!(CouldNotParseConfigFile, e.to_string()))
+    }
+}
+
+impl<Base> ConfiguratorPort for TomlFileConfiguratorAdapter<Base>
+where
+    Base: ConfiguratorPort,
+{
+    fn load(&self) -> Result<Configuration, ApplicationError> {
+        let config = self.base.load()?;
+        let config = self.read_file()?.apply_to(config)?;
+        ConfigurationOverrides::from_env(&self.env)?.apply_to(config)
+    }
+}
+
+pub fn default_config_file_path() -> Result<PathBuf, ApplicationError> {
+    let mut path = config_dir()
+        .ok_or(application_error!(NoConfigFolderFound))?;
+
+    path.push(APPLICATION_NAME);
+    path.push(CONFIG_FILE_NAME);
+    Ok(path)
+}
+
+fn config_env_vars() -> HashMap<String, String> {
+    std::env::vars_os()
+        .filter_map(|(key, value)| Some((key.into_string().ok()?, value.into_string().ok()?)))
+        .filter(|(key, _)| key.starts_with(CONFIG_ENV_PREFIX))
+        .collect()
+}
+
+/// Every setting is optional, only the settings that are present replace the underlying value
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigurationOverrides {
+    client_id: Option<String>,
+    auth_url: Option<String>,
+    token_url: Option<String>,
+    redirect_url: Option<String>,
+    revocation_url: Option<String>,
+    api_url: Option<String>,
+    api_connect_timeout_seconds: Option<u64>,
+    api_read_timeout_seconds: Option<u64>,
+    fallback_redirect_ports: Option<Vec<u16>>,
+    auth_flow_timeout_seconds: Option<u64>,
+    grpc_listen_address: Option<String>,
+    grpc_socket_path: Option<PathBuf>,
+    session_secret_path: Option<PathBuf>,
+    sync_root: Option<PathBuf>,
+    data_dir: Option<PathBuf>,
+    log_level: Option<String>,
+    conflict_policy: Option<String>,
+    ignore_patterns: Option<Vec<String>>,
+    upload_chunk_size_bytes: Option<u64>,
+    upload_parallelism: Option<usize>,
+}
+
+impl ConfigurationOverrides {
+    fn from_env(env: &HashMap<String, String>) -> Result<Self, ApplicationError> {
+        let var = |name: &str| env.get(&format!("{CONFIG_ENV_PREFIX}{name}")).cloned();
+
+        Ok(ConfigurationOverrides {
+            client_id: var("CLIENT_ID"),
+            auth_url: var("AUTH_URL"),
+            token_url: var("TOKEN_URL"),
+            redirect_url: var("REDIRECT_URL"),
+            revocation_url: var("REVOCATION_URL"),
+            api_url: var("API_URL"),
+            api_connect_timeout_seconds: var("API_CONNECT_TIMEOUT_SECONDS")
+                .map(|seconds| seconds
+                    .parse::<u64>()
+                    .map_err(|e| application_error!(ParserError, e.to_string())))
+                .transpose()?,
+            api_read_timeout_seconds: var("API_READ_TIMEOUT_SECONDS")
+                .map(|seconds| seconds
+                    .parse::<u64>()
+                    .map_err(|e| application_error!(ParserError, e.to_string())))
+                .transpose()?,
+            fallback_redirect_ports: var("REDIRECT_FALLBACK_PORTS")
+                .map(|ports| parse_ports(&ports))
+                .transpose()?,
+            auth_flow_timeout_seconds: var("AUTH_FLOW_TIMEOUT_SECONDS")
+                .map(|seconds| seconds
+                    .parse::<u64>()
+                    .map_err(|e| application_error!(ParserError, e.to_string())))
+                .transpose()?,
+            grpc_listen_address: var("GRPC_LISTEN_ADDRESS"),
+            grpc_socket_path: var("GRPC_SOCKET_PATH").map(PathBuf::from),
+            session_secret_path: var("SESSION_SECRET_PATH").map(PathBuf::from),
+            sync_root: var("SYNC_ROOT").map(PathBuf::from),
+            data_dir: var("DATA_DIR").map(PathBuf::from),
+            log_level: var("LOG_LEVEL"),
+            conflict_policy: var("CONFLICT_POLICY"),
+            ignore_patterns: var("IGNORE_PATTERNS").map(|patterns| parse_patterns(&patterns)),
+            upload_chunk_size_bytes: var("UPLOAD_CHUNK_SIZE_BYTES")
+                .map(|bytes| bytes
+                    .parse::<u64>()
+                    .map_err(|e| application_error!(ParserError, e.to_string())))
+                .transpose()?,
+            upload_parallelism: var("UPLOAD_PARALLELISM")
+                .map(|count| count
+                    .parse::<usize>()
+                    .map_err(|e| application_error!(ParserError, e.to_string())))
+                .transpose()?,
+        })
+    }
+
+    fn apply_to(self, mut config: Configuration) -> Result<Configuration, ApplicationError> {
+        if let Some(client_id) = self.client_id {
+            config.client_id = ClientId::new(client_id);
+        }
+        if let Some(auth_url) = self.auth_url {
+            config.auth_url = AuthUrl::new(auth_url)?;
+        }
+        if let Some(token_url) = self.token_url {
+            config.token_url = TokenUrl::new(token_url)?;
+        }
+        if let Some(redirect_url) = self.redirect_url {
+            config.redirect_url = RedirectUrl::new(redirect_url)?;
+        }
+        if let Some(revocation_url) = self.revocation_url {
+            config.revocation_url = RevocationUrl::new(revocation_url)?;
+        }
+        if let Some(api_url) = self.api_url {
+            config.api_url = Url::parse(&api_url)?;
+        }
+        if let Some(seconds) = self.api_connect_timeout_seconds {
+            config.api_connect_timeout = Duration::from_secs(seconds);
+        }
+        if let Some(seconds) = self.api_read_timeout_seconds {
+            config.api_read_timeout = Duration::from_secs(seconds);
+        }
+        if let Some(ports) = self.fallback_redirect_ports {
+            config.fallback_redirect_ports = ports;
+        }
+        if let Some(seconds) = self.auth_flow_timeout_seconds {
+            config.auth_flow_timeout = Duration::from_secs(seconds);
+        }
+        if let Some(address) = self.grpc_listen_address {
+            config.grpc_listen_addr = parse_listen_address(&address)?;
+        }
+        if let Some(socket_path) = self.grpc_socket_path {
+            config.grpc_socket_path = Some(socket_path);
+        }
+        if let Some(session_secret_path) = self.session_secret_path {
+            config.session_secret_path = session_secret_path;
+        }
+        if let Some(sync_root) = self.sync_root {
+            config.sync_root = sync_root;
+        }
+        if let Some(data_dir) = self.data_dir {
+            config.data_dir = data_dir;
+        }
+        if let Some(level) = self.log_level {
+            config.log_level = level.parse::<LogLevel>()?;
+        }
+        if let Some(policy) = self.conflict_policy {
+            config.conflict_policy = policy.parse::<ConflictPolicy>()?;
+        }
+        if let Some(patterns) = self.ignore_patterns {
+            config.ignore_patterns = patterns;
+        }
+        if let Some(bytes) = self.upload_chunk_size_bytes {
+            config.upload_chunk_size = bytes;
+        }
+        if let Some(count) = self.upload_parallelism {
+            config.upload_parallelism = count;
+        }
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use engine::domain::default_values::configurator_defaults::*;
+    use engine::domain::test_helpers::fake_configurator_adapter::FakeConfiguratorPort;
+    use common::domain::text_keys::TextKeys::InvalidLogLevel;
+    use super::*;
+
+    struct TempConfigFile(PathBuf);
+
+    impl TempConfigFile {
+        fn new(name: &str, content: &str) -> Self {
+            let path = std::env::temp_dir()
+                .join(format!("kdrive_rs_{}_{}.toml", name, std::process::id()));
+            fs::write(&path, content).unwrap();
+            TempConfigFile(path)
+        }
+
+        fn path(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for TempConfigFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    fn adapter(path: &Path, env: &[(&str, &str)]) -> TomlFileConfiguratorAdapter<FakeConfiguratorPort> {
+        TomlFileConfiguratorAdapter::with_base(
+            FakeConfiguratorPort::with_client_id("build-time-client-id"),
+            path.to_path_buf(),
+            env.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect(),
+        )
+    }
+
+    #[test]
+    fn build_time_defaults_are_used_without_config_file() {
+        let path = std::env::temp_dir().join("kdrive_rs_config_that_does_not_exist.toml");
+
+        let config = adapter(&path, &[]).load().unwrap();
+
+        assert_eq!(config.client_id.as_str(), "build-time-client-id");
+        assert_eq!(config.auth_url.as_str(), DEFAULT_AUTH_URL);
+        assert_eq!(config.log_level, DEFAULT_LOG_LEVEL);
+    }
+
+    #[test]
+    fn config_file_overrides_the_build_time_defaults() {
+        let file = TempConfigFile::new("file_overrides", r#"
+            client_id = "file-client-id"
+            redirect_url = "http://localhost:13700/kdrive/auth"
+            fallback_redirect_ports = [13701, 13702]
+            auth_flow_timeout_seconds = 60
+            api_connect_timeout_seconds = 5
+            api_read_timeout_seconds = 120
+            grpc_listen_address = "127.0.0.1:50100"
+            grpc_socket_path = "/tmp/kdrive-test.sock"
+            session_secret_path = "/tmp/kdrive-test-secret"
+            sync_root = "/tmp/kdrive-sync"
+            data_dir = "/tmp/kdrive-data"
+            log_level = "debug"
+            conflict_policy = "ask"
+            ignore_patterns = ["*.tmp", "build/"]
+            upload_chunk_size_bytes = 1048576
+            upload_parallelism = 2
+        "#);
+
+        let config = adapter(file.path(), &[]).load().unwrap();
+
+        assert_eq!(config.client_id.as_str(), "file-client-id");
+        assert_eq!(config.redirect_url.as_str(), "http://localhost:13700/kdrive/auth");
+        assert_eq!(config.fallback_redirect_ports, vec![13701, 13702]);
+        assert_eq!(config.auth_flow_timeout, Duration::from_secs(60));
+        assert_eq!(config.api_connect_timeout, Duration::from_secs(5));
+        assert_eq!(config.api_read_timeout, Duration::from_secs(120));
+        assert_eq!(config.grpc_listen_addr.to_string(), "127.0.0.1:50100");
+        assert_eq!(config.grpc_socket_path, Some(PathBuf::from("/tmp/kdrive-test.sock")));
+        assert_eq!(config.session_secret_path, PathBuf::from("/tmp/kdrive-test-secret"));
+        assert_eq!(config.sync_root, PathBuf::from("/tmp/kdrive-sync"));
+        assert_eq!(config.data_dir, PathBuf::from("/tmp/kdrive-data"));
+        assert_eq!(config.log_level, LogLevel::Debug);
+        assert_eq!(config.conflict_policy, ConflictPolicy::Ask);
+        assert_eq!(config.ignore_patterns, ["*.tmp", "build/"]);
+        assert_eq!(config.upload_chunk_size, 1_048_576);
+        assert_eq!(config.upload_parallelism, 2);
+        assert_eq!(config.token_url.as_str(), DEFAULT_TOKEN_URL);
+    }
+
+    #[test]
+    fn environment_variables_override_the_config_file() {
+        let file = TempConfigFile::new("env_overrides", r#"
+            client_id = "file-client-id"
+            log_level = "debug"
+        "#);
+
+        let config = adapter(file.path(), &[
+            ("KDRIVE_CLIENT_ID", "env-client-id"),
+            ("KDRIVE_REDIRECT_FALLBACK_PORTS", "13801,13802"),
+            ("KDRIVE_API_URL", "http://127.0.0.1:13900"),
+            ("KDRIVE_IGNORE_PATTERNS", "*.log, .cache/"),
+            ("KDRIVE_UPLOAD_PARALLELISM", "8"),
+            ("KDRIVE_API_READ_TIMEOUT_SECONDS", "30"),
+        ]).load().unwrap();
+
+        assert_eq!(config.client_id.as_str(), "env-client-id");
+        assert_eq!(config.fallback_redirect_ports, vec![13801, 13802]);
+        assert_eq!(config.api_url.as_str(), "http://127.0.0.1:13900/");
+        assert_eq!(config.ignore_patterns, ["*.log", ".cache/"]);
+        assert_eq!(config.upload_parallelism, 8);
+        assert_eq!(config.api_read_timeout, Duration::from_secs(30));
+        assert_eq!(config.log_level, LogLevel::Debug);
+    }
+
+    #[test]
+    fn invalid_config_file_returns_an_error() {
+        let file = TempConfigFile::new("invalid", "client_id = ");
+
+        let err = adapter(file.path(), &[]).load().expect_err("Expected CouldNotParseConfigFile error");
+
+        assert_eq!(err.text_key, CouldNotParseConfigFile);
+    }
+
+    #[test]
+    fn unknown_setting_in_config_file_returns_an_error() {
+        let file = TempConfigFile::new("unknown_setting", r#"clientid = "typo""#);
+
+        let err = adapter(file.path(), &[]).load().expect_err("Expected CouldNotParseConfigFile error");
+
+        assert_eq!(err.text_key, CouldNotParseConfigFile);
+    }
+
+    #[test]
+    fn invalid_log_level_in_environment_returns_an_error() {
+        let path = std::env::temp_dir().join("kdrive_rs_config_that_does_not_exist.toml");
+
+        let err = adapter(&path, &[("KDRIVE_LOG_LEVEL", "loud")]).load().expect_err("Expected InvalidLogLevel error");
+
+        assert_eq!(err.text_key, InvalidLogLevel);
+    }
+}
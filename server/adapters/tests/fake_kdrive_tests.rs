@@ -0,0 +1,308 @@
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::time::Duration;
+use axum::http::StatusCode;
+use oauth2::url::Url;
+use oauth2::{AuthUrl, ClientId, RedirectUrl, RevocationUrl, TokenUrl};
+use adapters::driven::kdrive_api_adapter::KDriveApiAdapter;
+use adapters::driven::kdrive_authenticator_adapter::KDriveAuthenticator;
+use adapters::driven::local_fs_adapter::LocalFsAdapter;
+use common::domain::text_keys::TextKeys::{ApiNotAuthenticated, ApiRateLimited, ApiRequestFailed, ApiServerError, DownloadCorrupted};
+use engine::domain::default_values::general_defaults::ROOT_DIRECTORY_ID;
+use engine::domain::remote_file::RemoteChange;
+use engine::domain::sync::resumable_download::ResumableDownload;
+use engine::domain::test_helpers::fake_kdrive_api::{FakeKDriveApi, TEST_DRIVE_ID};
+use engine::domain::test_helpers::fake_metadata_store::FakeMetadataStore;
+use engine::domain::test_helpers::fake_token_store_adapter::{FakeTokenStoreFileAdapter, TEST_FILE_ACCESS_TOKEN};
+use engine::domain::upload_session::{chunk_hash, UploadTarget};
+use engine::ports::driven::authenticator_driven_port::AuthenticatorDrivenPort;
+use engine::ports::driven::kdrive_api_driven_port::KDriveApiDrivenPort;
+use engine::ports::driven::local_fs_driven_port::LocalFsDrivenPort;
+use fake_kdrive::faults::InjectedError;
+use fake_kdrive::FakeKDriveServer;
+
+async fn start_fake_kdrive() -> FakeKDriveServer {
+    let api = FakeKDriveApi::new_default()
+        .with_directory(ROOT_DIRECTORY_ID, "Documents")
+        .with_file(ROOT_DIRECTORY_ID, "notes.txt", b"some notes");
+    let server = FakeKDriveServer::start(api).await.unwrap();
+    server.auth().accept_access_token(TEST_FILE_ACCESS_TOKEN);
+    server
+}
+
+fn api_adapter(server: &FakeKDriveServer) -> KDriveApiAdapter<FakeTokenStoreFileAdapter> {
+    KDriveApiAdapter::new(Url::parse(&server.url()).unwrap(), FakeTokenStoreFileAdapter::with_tokens())
+}
+
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+}
+
+/// A sync root in the temp folder, removed again when the test ends or panics
+struct TempSyncRoot(PathBuf);
+
+impl TempSyncRoot {
+    fn new(name: &str) -> Self {
+        let root = std::env::temp_dir().join(format!("kdrive_rs_{}_{}", name, std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        TempSyncRoot(root)
+    }
+}
+
+impl Drop for TempSyncRoot {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+#[tokio::test]
+async fn user_logs_in_and_lists_the_drive() {
+    // Given a fake kDrive and an authenticator pointed at it
+    let server = start_fake_kdrive().await;
+    let mut authenticator = KDriveAuthenticator::new(
+        AuthUrl::new(server.auth_url()).unwrap(),
+        TokenUrl::new(server.token_url()).unwrap(),
+        ClientId::new("test-client-id".to_string()),
+        RedirectUrl::new(format!("http://127.0.0.1:{}/kdrive/auth", free_port())).unwrap(),
+        RevocationUrl::new(server.revocation_url()).unwrap(),
+    );
+
+    // When the browser follows the login URL back to the callback server
+    let auth_url = authenticator.start_initial_auth_flow().await.unwrap();
+    let pending_code = authenticator.pending_authorization_code().unwrap();
+    let page = reqwest::get(&auth_url).await.unwrap();
+    assert_eq!(page.status(), StatusCode::OK);
+    let code = pending_code.await.unwrap();
+    authenticator.exchange_authorization_code(&code).await.unwrap();
+    let tokens = authenticator.get_tokens().await.unwrap();
+
+    // Then the received access token opens the API
+    let adapter = KDriveApiAdapter::new(
+        Url::parse(&server.url()).unwrap(),
+        FakeTokenStoreFileAdapter { tokens: Some(tokens) },
+    );
+    let page = adapter.list_files(TEST_DRIVE_ID, ROOT_DIRECTORY_ID, None).await.unwrap();
+    let mut names: Vec<_> = page.files.into_iter().map(|file| file.name).collect();
+    names.sort();
+    assert_eq!(names, ["Documents", "notes.txt"]);
+}
+
+#[tokio::test]
+async fn file_operations_change_the_fake_drive() {
+    // Given a fake kDrive with a folder
+    let server = start_fake_kdrive().await;
+    let adapter = api_adapter(&server);
+    let documents = server.api().file_id(ROOT_DIRECTORY_ID, "Documents").unwrap();
+
+    // When a file is uploaded, renamed, moved, deleted and restored
+    let file = adapter.upload_file(TEST_DRIVE_ID, ROOT_DIRECTORY_ID, "draft.txt", b"draft".to_vec()).await.unwrap();
+    adapter.rename_file(TEST_DRIVE_ID, file.id, "final.txt").await.unwrap();
+    adapter.move_file(TEST_DRIVE_ID, file.id, documents).await.unwrap();
+    adapter.delete_file(TEST_DRIVE_ID, file.id).await.unwrap();
+    let deleted = server.api().is_in_trash(file.id);
+    adapter.restore_file(TEST_DRIVE_ID, file.id).await.unwrap();
+
+    // Then the drive reflects every step
+    assert!(deleted);
+    let restored = adapter.get_file(TEST_DRIVE_ID, file.id).await.unwrap();
+    assert_eq!(restored.name, "final.txt");
+    assert_eq!(restored.parent_id, documents);
+    assert_eq!(adapter.download_file(TEST_DRIVE_ID, file.id).await.unwrap(), b"draft");
+}
+
+#[tokio::test]
+async fn directories_are_created_and_files_get_new_content() {
+    // Given a fake kDrive with a file
+    let server = start_fake_kdrive().await;
+    let adapter = api_adapter(&server);
+    let notes = server.api().file_id(ROOT_DIRECTORY_ID, "notes.txt").unwrap();
+
+    // When a directory is created and the file gets new content
+    let directory = adapter.create_directory(TEST_DRIVE_ID, ROOT_DIRECTORY_ID, "Photos").await.unwrap();
+    let updated = adapter.update_file(TEST_DRIVE_ID, notes, b"more notes".to_vec()).await.unwrap();
+
+    // Then both are on the drive, the file keeps its id
+    assert!(directory.is_directory());
+    assert_eq!(server.api().file_id(ROOT_DIRECTORY_ID, "Photos"), Some(directory.id));
+    assert_eq!(updated.id, notes);
+    assert_eq!(updated.size, Some(10));
+    assert_eq!(adapter.download_file(TEST_DRIVE_ID, notes).await.unwrap(), b"more notes");
+}
+
+#[tokio::test]
+async fn a_file_is_put_together_from_its_chunks() {
+    // Given an upload session for a new file in two chunks
+    let server = start_fake_kdrive().await;
+    let adapter = api_adapter(&server);
+    let target = UploadTarget::NewFile { directory_id: ROOT_DIRECTORY_ID, name: "video.mp4".to_string() };
+    let token = adapter.start_upload_session(TEST_DRIVE_ID, &target, 9, 2).await.unwrap();
+
+    // When a chunk with the wrong hash is sent before the right ones
+    let corrupted = adapter.upload_chunk(TEST_DRIVE_ID, &token, 1, b"frame".to_vec(), &chunk_hash(b"frame 1")).await;
+    adapter.upload_chunk(TEST_DRIVE_ID, &token, 2, b"2, 3".to_vec(), &chunk_hash(b"2, 3")).await.unwrap();
+    adapter.upload_chunk(TEST_DRIVE_ID, &token, 1, b"frame".to_vec(), &chunk_hash(b"frame")).await.unwrap();
+    let file = adapter.finish_upload_session(TEST_DRIVE_ID, &token).await.unwrap();
+
+    // Then only the chunks that match their hash make up the file
+    assert_eq!(corrupted.expect_err("Expected ApiRequestFailed error").text_key, ApiRequestFailed);
+    assert_eq!(file.size, Some(9));
+    assert_eq!(adapter.download_file(TEST_DRIVE_ID, file.id).await.unwrap(), b"frame2, 3");
+    assert_eq!(server.api().upload_session_count(), 0);
+}
+
+#[tokio::test]
+async fn a_truncated_download_continues_with_a_range_request() {
+    // Given a file whose next download drops the connection after four bytes
+    let server = start_fake_kdrive().await;
+    let adapter = api_adapter(&server);
+    let notes = server.api().file_id(ROOT_DIRECTORY_ID, "notes.txt").unwrap();
+    let file = adapter.get_file(TEST_DRIVE_ID, notes).await.unwrap();
+    server.api().truncate_next_download(4);
+    let root = TempSyncRoot::new("resumed_download");
+    let local = LocalFsAdapter::new(root.0.clone());
+    let metadata = FakeMetadataStore::new();
+    let download = ResumableDownload::new(&metadata, &adapter, &local, TEST_DRIVE_ID);
+
+    // When the file is downloaded twice
+    let interrupted = download.run(&file, "notes.txt").await;
+    let partial_size = local.partial_size("notes.txt").await.unwrap();
+    download.run(&file, "notes.txt").await.unwrap();
+
+    // Then the second download only asks for the rest and the file matches kDrive
+    assert!(interrupted.is_err());
+    assert_eq!(partial_size, 4);
+    assert_eq!(server.api().download_offsets(), [0, 4]);
+    assert_eq!(std::fs::read(root.0.join("notes.txt")).unwrap(), b"some notes");
+}
+
+#[tokio::test]
+async fn a_download_resumed_from_a_server_without_range_support_skips_what_is_there() {
+    // Given a partial download and a server that answers the next range request with the whole file
+    let server = start_fake_kdrive().await;
+    let adapter = api_adapter(&server);
+    let notes = server.api().file_id(ROOT_DIRECTORY_ID, "notes.txt").unwrap();
+    let file = adapter.get_file(TEST_DRIVE_ID, notes).await.unwrap();
+    server.api().truncate_next_download(4);
+    let root = TempSyncRoot::new("download_without_range");
+    let local = LocalFsAdapter::new(root.0.clone());
+    let metadata = FakeMetadataStore::new();
+    let download = ResumableDownload::new(&metadata, &adapter, &local, TEST_DRIVE_ID);
+    download.run(&file, "notes.txt").await.unwrap_err();
+    server.faults().ignore_next_range();
+
+    // When the download is resumed
+    download.run(&file, "notes.txt").await.unwrap();
+
+    // Then the bytes already downloaded are not appended twice
+    assert_eq!(server.api().download_offsets(), [0, 0]);
+    assert_eq!(std::fs::read(root.0.join("notes.txt")).unwrap(), b"some notes");
+}
+
+#[tokio::test]
+async fn a_resumed_download_that_does_not_match_the_hash_is_discarded() {
+    // Given a partial download of a file that changes on kDrive before the sync sees the new version
+    let server = start_fake_kdrive().await;
+    let adapter = api_adapter(&server);
+    let notes = server.api().file_id(ROOT_DIRECTORY_ID, "notes.txt").unwrap();
+    let file = adapter.get_file(TEST_DRIVE_ID, notes).await.unwrap();
+    server.api().truncate_next_download(4);
+    let root = TempSyncRoot::new("corrupted_download");
+    let local = LocalFsAdapter::new(root.0.clone());
+    let metadata = FakeMetadataStore::new();
+    let download = ResumableDownload::new(&metadata, &adapter, &local, TEST_DRIVE_ID);
+    download.run(&file, "notes.txt").await.unwrap_err();
+    server.api().change_file(notes, b"some other");
+
+    // When the download is resumed with the version it started from
+    let err = download.run(&file, "notes.txt").await.expect_err("Expected DownloadCorrupted error");
+
+    // Then the mix of both versions is thrown away and the sync root is left alone
+    assert_eq!(err.text_key, DownloadCorrupted);
+    assert_eq!(server.api().download_offsets(), [0, 4]);
+    assert_eq!(local.partial_size("notes.txt").await.unwrap(), 0);
+    assert!(!root.0.join("notes.txt").exists());
+}
+
+#[tokio::test]
+async fn a_download_starts_over_when_the_file_changed_between_attempts() {
+    // Given a partial download of a file that gets new content on kDrive
+    let server = start_fake_kdrive().await;
+    let adapter = api_adapter(&server);
+    let notes = server.api().file_id(ROOT_DIRECTORY_ID, "notes.txt").unwrap();
+    let file = adapter.get_file(TEST_DRIVE_ID, notes).await.unwrap();
+    server.api().truncate_next_download(4);
+    let root = TempSyncRoot::new("replaced_download");
+    let local = LocalFsAdapter::new(root.0.clone());
+    let metadata = FakeMetadataStore::new();
+    let download = ResumableDownload::new(&metadata, &adapter, &local, TEST_DRIVE_ID);
+    download.run(&file, "notes.txt").await.unwrap_err();
+    server.api().change_file(notes, b"new notes from the phone");
+    let changed = adapter.get_file(TEST_DRIVE_ID, notes).await.unwrap();
+
+    // When the new version is downloaded
+    download.run(&changed, "notes.txt").await.unwrap();
+
+    // Then the partial file of the old version is not continued
+    assert_eq!(server.api().download_offsets(), [0, 0]);
+    assert_eq!(std::fs::read(root.0.join("notes.txt")).unwrap(), b"new notes from the phone");
+}
+
+#[tokio::test]
+async fn changes_on_the_drive_are_listed_after_the_cursor() {
+    // Given a cursor taken from the fake kDrive
+    let server = start_fake_kdrive().await;
+    let adapter = api_adapter(&server);
+    let cursor = adapter.list_changes(TEST_DRIVE_ID, None).await.unwrap().cursor;
+
+    // When a file is uploaded and deleted afterwards
+    let file = adapter.upload_file(TEST_DRIVE_ID, ROOT_DIRECTORY_ID, "draft.txt", b"draft".to_vec()).await.unwrap();
+    adapter.delete_file(TEST_DRIVE_ID, file.id).await.unwrap();
+    let page = adapter.list_changes(TEST_DRIVE_ID, Some(&cursor)).await.unwrap();
+
+    // Then both changes come in order
+    assert_eq!(page.changes, [RemoteChange::Created(file.clone()), RemoteChange::Deleted(file.id)]);
+    assert!(!page.has_more);
+    assert!(adapter.list_changes(TEST_DRIVE_ID, Some(&page.cursor)).await.unwrap().changes.is_empty());
+}
+
+#[tokio::test]
+async fn unknown_access_token_is_refused() {
+    let server = start_fake_kdrive().await;
+    server.auth().expire_access_tokens();
+
+    let err = api_adapter(&server).list_drives().await.expect_err("Expected ApiNotAuthenticated error");
+
+    assert_eq!(err.text_key, ApiNotAuthenticated);
+}
+
+#[tokio::test]
+async fn injected_server_error_is_reported_once() {
+    // Given a fake kDrive that fails the next drive request
+    let server = start_fake_kdrive().await;
+    server.faults().inject_error(InjectedError {
+        path_prefix: "/2/drive".to_string(),
+        status: StatusCode::BAD_GATEWAY,
+        code: "bad_gateway".to_string(),
+    });
+    let adapter = api_adapter(&server);
+
+    // When the drives are listed twice
+    let first = adapter.list_drives().await;
+    let second = adapter.list_drives().await;
+
+    // Then only the first request fails
+    assert_eq!(first.expect_err("Expected ApiServerError error").text_key, ApiServerError);
+    assert_eq!(second.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn requests_above_the_rate_limit_are_refused() {
+    let server = start_fake_kdrive().await;
+    server.faults().set_rate_limit(1, Duration::from_secs(60));
+    let adapter = api_adapter(&server);
+
+    adapter.list_drives().await.unwrap();
+    let err = adapter.list_drives().await.expect_err("Expected ApiRateLimited error");
+
+    assert_eq!(err.text_key, ApiRateLimited);
+}
@@ -0,0 +1,119 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use common::application_error;
+use common::domain::text_keys::TextKeys::AuthFlowTimedOut;
+use crate::domain::default_values::configurator_defaults::DEFAULT_AUTH_FLOW_TIMEOUT_SECONDS;
+use crate::ports::driving::authenticator_driving_port::AuthenticatorDrivingPort;
+
+/// Waits for the user to finish logging in without keeping the engine locked.
+pub struct AuthFlowRunner {
+    timeout: Duration,
+}
+
+impl AuthFlowRunner {
+    pub fn new() -> Self {
+        Self::with_timeout(Duration::from_secs(DEFAULT_AUTH_FLOW_TIMEOUT_SECONDS))
+    }
+
+    pub fn with_timeout(timeout: Duration) -> Self {
+        Self { timeout }
+    }
+
+    pub fn start<E>(self, engine: Arc<Mutex<E>>) -> JoinHandle<()>
+    where
+        E: AuthenticatorDrivingPort + Send + 'static,
+    {
+        tokio::spawn(async move {
+            let pending = engine.lock().await.pending_authorization_code();
+
+            // The lock is released here, other requests are served while the user logs in
+            let code = match pending {
+                Ok(pending) => tokio::time::timeout(self.timeout, pending)
+                    .await
+                    .unwrap_or(Err(application_error!(AuthFlowTimedOut))),
+                Err(error) => Err(error),
+            };
+
+            engine.lock().await.complete_initial_auth_flow(code).await;
+        })
+    }
+}
+
+impl Default for AuthFlowRunner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::Mutex;
+    use common::application_error;
+    use common::domain::text_keys::TextKeys::AuthFlowTimedOut;
+    use crate::domain::auth_flow_runner::AuthFlowRunner;
+    use crate::domain::events::EngineEvent;
+    use crate::domain::test_helpers::fake_authenticator_adapter::FakeAuthenticatorDrivenAdapter;
+    use crate::domain::test_helpers::fake_event_bus::FakeEventBus;
+    use crate::domain::test_helpers::test_engine_builder::TestEngineBuilder;
+    use crate::ports::driving::authenticator_driving_port::AuthenticatorDrivingPort;
+
+    #[tokio::test]
+    async fn runner_completes_the_auth_flow_when_the_code_arrives() {
+        // Given an engine without tokens
+        let event_bus = FakeEventBus::new();
+        let engine = TestEngineBuilder::new()
+            .with_empty_token_store()
+            .with_event_bus(event_bus.clone())
+            .build();
+
+        // When the runner waits for the code
+        AuthFlowRunner::new().start(Arc::new(Mutex::new(engine))).await.unwrap();
+
+        // Then the auth flow completes
+        assert!(event_bus.get_events().contains(&EngineEvent::AuthFlowCompleted));
+    }
+
+    #[tokio::test]
+    async fn runner_fails_the_auth_flow_when_the_code_does_not_arrive_in_time() {
+        // Given an engine where the user never finishes logging in
+        let event_bus = FakeEventBus::new();
+        let engine = TestEngineBuilder::new()
+            .with_auth(FakeAuthenticatorDrivenAdapter::new_default_waiting())
+            .with_empty_token_store()
+            .with_event_bus(event_bus.clone())
+            .build();
+
+        // When the timeout passes
+        AuthFlowRunner::with_timeout(Duration::from_millis(10))
+            .start(Arc::new(Mutex::new(engine)))
+            .await
+            .unwrap();
+
+        // Then the auth flow fails with the timeout as reason
+        assert!(event_bus.get_events().contains(
+            &EngineEvent::AuthFlowFailed { reason: application_error!(AuthFlowTimedOut) }
+        ));
+    }
+
+    #[tokio::test]
+    async fn runner_does_not_lock_the_engine_while_waiting() {
+        // Given a runner waiting for a user who has not logged in yet
+        let engine = Arc::new(Mutex::new(TestEngineBuilder::new()
+            .with_auth(FakeAuthenticatorDrivenAdapter::new_default_waiting())
+            .with_empty_token_store()
+            .build()));
+        let handle = AuthFlowRunner::new().start(engine.clone());
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // When another request needs the engine
+        let result = tokio::time::timeout(Duration::from_millis(100), engine.lock()).await;
+
+        // Then the engine is available
+        assert!(!result.expect("Engine stayed locked during the auth flow").is_authenticated());
+        handle.abort();
+    }
+}
@@ -1,17 +1,20 @@
-use std::net::SocketAddr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 use oauth2::RedirectUrl;
-use url::Url;
+use url::{Host, Url};
 use common::application_error;
 use common::domain::errors::ApplicationError;
 use common::domain::text_keys::TextKeys::InvalidRedirectUrl;
 
+const LOCALHOST: &str = "localhost";
 
 pub struct CallbackEndpoint {
-    pub addr: SocketAddr,
+    /// Every address must be served, "localhost" can resolve to IPv4 and IPv6 loopback
+    pub addrs: Vec<SocketAddr>,
     pub path: String,
 }
 pub trait ParseRedirectUrl {
     fn parse(&self) -> Result<CallbackEndpoint, ApplicationError>;
+    fn with_port(&self, port: u16) -> Result<RedirectUrl, ApplicationError>;
 }
 impl ParseRedirectUrl for RedirectUrl {
     fn parse(&self) -> Result<CallbackEndpoint, ApplicationError> {
@@ -19,18 +22,108 @@ impl ParseRedirectUrl for RedirectUrl {
         let parsed = Url::parse(self.as_str())
             .map_err(|_| application_error!(InvalidRedirectUrl, self.as_str()))?;
 
-        let host = parsed
-            .host_str()
+        let port = parsed
+            .port_or_known_default()
             .ok_or_else(|| application_error!(InvalidRedirectUrl, self.as_str()))?;
-
-        let port = parsed.port().unwrap_or(80);
         let path = parsed.path().to_string();
 
-        let addr = SocketAddr::new(
-            host.parse().map_err(|_| application_error!(InvalidRedirectUrl, self.as_str()))?,
-            port,
-        );
+        let ips: Vec<IpAddr> = match parsed.host() {
+            Some(Host::Ipv4(ip)) => vec![IpAddr::V4(ip)],
+            Some(Host::Ipv6(ip)) => vec![IpAddr::V6(ip)],
+            Some(Host::Domain(domain)) if domain.eq_ignore_ascii_case(LOCALHOST) =>
+                vec![IpAddr::V4(Ipv4Addr::LOCALHOST), IpAddr::V6(Ipv6Addr::LOCALHOST)],
+            // The callback server runs on this computer, other hosts can never reach it
+            _ => return Err(application_error!(InvalidRedirectUrl, self.as_str())),
+        };
+
+        let addrs = ips.into_iter()
+            .map(|ip| SocketAddr::new(ip, port))
+            .collect();
+
+        Ok(CallbackEndpoint { addrs, path })
+    }
+
+    fn with_port(&self, port: u16) -> Result<RedirectUrl, ApplicationError> {
+        let mut parsed = Url::parse(self.as_str())
+            .map_err(|_| application_error!(InvalidRedirectUrl, self.as_str()))?;
+
+        parsed
+            .set_port(Some(port))
+            .map_err(|_| application_error!(InvalidRedirectUrl, self.as_str()))?;
+
+        Ok(RedirectUrl::from_url(parsed))
+    }
+}
+
+/// The configured redirect URL first, followed by the same URL on each fallback port
+pub fn redirect_url_candidates(redirect_url: &RedirectUrl, fallback_ports: &[u16]) -> Result<Vec<RedirectUrl>, ApplicationError> {
+    let mut candidates = vec![redirect_url.clone()];
+    for port in fallback_ports {
+        candidates.push(redirect_url.with_port(*port)?);
+    }
+    Ok(candidates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redirect_url(url: &str) -> RedirectUrl {
+        RedirectUrl::new(url.to_string()).unwrap()
+    }
+
+    #[test]
+    fn localhost_resolves_to_ipv4_and_ipv6_loopback() {
+        let endpoint = redirect_url("http://localhost:13628/kdrive/auth").parse().unwrap();
 
-        Ok(CallbackEndpoint { addr, path })
+        assert_eq!(endpoint.addrs, vec![
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 13628),
+            SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), 13628),
+        ]);
+        assert_eq!(endpoint.path, "/kdrive/auth");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn ipv4_literal_is_used_as_is() {
+        let endpoint = redirect_url("http://127.0.0.1:13628/kdrive/auth").parse().unwrap();
+
+        assert_eq!(endpoint.addrs, vec![SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 13628)]);
+    }
+
+    #[test]
+    fn ipv6_literal_is_used_as_is() {
+        let endpoint = redirect_url("http://[::1]:13628/kdrive/auth").parse().unwrap();
+
+        assert_eq!(endpoint.addrs, vec![SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), 13628)]);
+    }
+
+    #[test]
+    fn missing_port_defaults_to_the_scheme_port() {
+        let endpoint = redirect_url("https://127.0.0.1/kdrive/auth").parse().unwrap();
+
+        assert_eq!(endpoint.addrs[0].port(), 443);
+    }
+
+    #[test]
+    fn remote_hostname_is_rejected() {
+        let result = redirect_url("http://example.com:13628/kdrive/auth").parse();
+
+        let err = result.err().expect("Expected InvalidRedirectUrl error");
+        assert_eq!(err.text_key, InvalidRedirectUrl);
+    }
+
+    #[test]
+    fn candidates_start_with_the_configured_url_followed_by_the_fallback_ports() {
+        let candidates = redirect_url_candidates(
+            &redirect_url("http://localhost:13628/kdrive/auth"),
+            &[13629, 13630],
+        ).unwrap();
+
+        let urls: Vec<&str> = candidates.iter().map(|url| url.as_str()).collect();
+        assert_eq!(urls, vec![
+            "http://localhost:13628/kdrive/auth",
+            "http://localhost:13629/kdrive/auth",
+            "http://localhost:13630/kdrive/auth",
+        ]);
+    }
+}
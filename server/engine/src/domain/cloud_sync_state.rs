@@ -1,6 +1,21 @@
+use common::domain::errors::ApplicationError;
+use crate::ports::driven::metadata_driven_port::MetadataDrivenPort;
+
 #[derive(Debug, PartialEq)]
 pub enum CloudSyncState {
     NoMetadata,
     MetadataPresent,
     NotIndexed,
-}
\ No newline at end of file
+}
+
+impl CloudSyncState {
+    /// How far the metadata store got with the drive
+    pub fn of<MetadataPort: MetadataDrivenPort>(metadata: &MetadataPort) -> Result<Self, ApplicationError> {
+        let state = match (metadata.has_metadata()?, metadata.has_index()?) {
+            (false, _) => CloudSyncState::NoMetadata,
+            (true, false) => CloudSyncState::NotIndexed,
+            (true, true) => CloudSyncState::MetadataPresent,
+        };
+        Ok(state)
+    }
+}
@@ -1,7 +1,14 @@
-use oauth2::{AuthUrl, ClientId, RedirectUrl, TokenUrl};
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+use oauth2::{AuthUrl, ClientId, RedirectUrl, RevocationUrl, TokenUrl};
+use oauth2::url::Url;
 use common::application_error;
 use common::domain::errors::ApplicationError;
-use common::domain::text_keys::TextKeys::{MissingClientId, MissingRedirectUrl};
+use common::domain::sync_conflict::ConflictResolution;
+use common::domain::text_keys::TextKeys::{InvalidConflictPolicy, InvalidLogLevel, MissingClientId, MissingRedirectUrl};
 use crate::domain::default_values::configurator_defaults::{DEFAULT_CLIENT_ID};
 use crate::ports::driven::configurator_driven_port::ConfiguratorPort;
 
@@ -11,6 +18,114 @@ pub struct Configuration {
     pub token_url: TokenUrl,
     pub client_id: ClientId,
     pub redirect_url: RedirectUrl,
+    pub fallback_redirect_ports: Vec<u16>,
+    pub revocation_url: RevocationUrl,
+    pub api_url: Url,
+    /// How long connecting to the kDrive API may take
+    pub api_connect_timeout: Duration,
+    /// How long the kDrive API may stay silent during a request before the request fails
+    pub api_read_timeout: Duration,
+    pub auth_flow_timeout: Duration,
+    pub grpc_listen_addr: SocketAddr,
+    /// When set the service listens on this Unix domain socket instead of the TCP address
+    pub grpc_socket_path: Option<PathBuf>,
+    /// Clients have to present the secret in this file, it is replaced on every start of the service
+    pub session_secret_path: PathBuf,
+    pub sync_root: PathBuf,
+    pub data_dir: PathBuf,
+    pub log_level: LogLevel,
+    pub conflict_policy: ConflictPolicy,
+    /// Ignored everywhere in the sync root on top of what its `.kdriveignore` files ignore
+    pub ignore_patterns: Vec<String>,
+    /// Files larger than this many bytes are uploaded in chunks of this size
+    pub upload_chunk_size: u64,
+    /// How many chunks of a file are uploaded at the same time
+    pub upload_parallelism: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl FromStr for LogLevel {
+    type Err = ApplicationError;
+
+    fn from_str(level: &str) -> Result<Self, Self::Err> {
+        match level.to_ascii_lowercase().as_str() {
+            "error" => Ok(LogLevel::Error),
+            "warn" => Ok(LogLevel::Warn),
+            "info" => Ok(LogLevel::Info),
+            "debug" => Ok(LogLevel::Debug),
+            "trace" => Ok(LogLevel::Trace),
+            _ => Err(application_error!(InvalidLogLevel, level)),
+        }
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let level = match self {
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+            LogLevel::Trace => "trace",
+        };
+        f.write_str(level)
+    }
+}
+
+/// What the sync does with a file that changed on both sides
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    KeepBoth,
+    PreferLocal,
+    PreferRemote,
+    /// Leaves both sides alone until the user resolves the conflict
+    Ask,
+}
+
+impl ConflictPolicy {
+    /// How the policy settles a conflict by itself, none when the user has to decide
+    pub fn resolution(self) -> Option<ConflictResolution> {
+        match self {
+            ConflictPolicy::KeepBoth => Some(ConflictResolution::KeepBoth),
+            ConflictPolicy::PreferLocal => Some(ConflictResolution::KeepLocal),
+            ConflictPolicy::PreferRemote => Some(ConflictResolution::KeepRemote),
+            ConflictPolicy::Ask => None,
+        }
+    }
+}
+
+impl FromStr for ConflictPolicy {
+    type Err = ApplicationError;
+
+    fn from_str(policy: &str) -> Result<Self, Self::Err> {
+        match policy.to_ascii_lowercase().as_str() {
+            "keep_both" => Ok(ConflictPolicy::KeepBoth),
+            "prefer_local" => Ok(ConflictPolicy::PreferLocal),
+            "prefer_remote" => Ok(ConflictPolicy::PreferRemote),
+            "ask" => Ok(ConflictPolicy::Ask),
+            _ => Err(application_error!(InvalidConflictPolicy, policy)),
+        }
+    }
+}
+
+impl fmt::Display for ConflictPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let policy = match self {
+            ConflictPolicy::KeepBoth => "keep_both",
+            ConflictPolicy::PreferLocal => "prefer_local",
+            ConflictPolicy::PreferRemote => "prefer_remote",
+            ConflictPolicy::Ask => "ask",
+        };
+        f.write_str(policy)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -37,6 +152,22 @@ impl Configurator {
     pub fn token_url(&self) -> &TokenUrl { &self.config.token_url }
     pub fn client_id(&self) -> &ClientId { &self.config.client_id }
     pub fn redirect_url(&self) -> &RedirectUrl { &self.config.redirect_url }
+    pub fn fallback_redirect_ports(&self) -> &[u16] { &self.config.fallback_redirect_ports }
+    pub fn revocation_url(&self) -> &RevocationUrl { &self.config.revocation_url }
+    pub fn api_url(&self) -> &Url { &self.config.api_url }
+    pub fn api_connect_timeout(&self) -> Duration { self.config.api_connect_timeout }
+    pub fn api_read_timeout(&self) -> Duration { self.config.api_read_timeout }
+    pub fn auth_flow_timeout(&self) -> Duration { self.config.auth_flow_timeout }
+    pub fn grpc_listen_addr(&self) -> SocketAddr { self.config.grpc_listen_addr }
+    pub fn grpc_socket_path(&self) -> Option<&PathBuf> { self.config.grpc_socket_path.as_ref() }
+    pub fn session_secret_path(&self) -> &PathBuf { &self.config.session_secret_path }
+    pub fn sync_root(&self) -> &PathBuf { &self.config.sync_root }
+    pub fn data_dir(&self) -> &PathBuf { &self.config.data_dir }
+    pub fn log_level(&self) -> LogLevel { self.config.log_level }
+    pub fn conflict_policy(&self) -> ConflictPolicy { self.config.conflict_policy }
+    pub fn ignore_patterns(&self) -> &[String] { &self.config.ignore_patterns }
+    pub fn upload_chunk_size(&self) -> u64 { self.config.upload_chunk_size }
+    pub fn upload_parallelism(&self) -> usize { self.config.upload_parallelism }
 }
 
 #[cfg(test)]
@@ -69,6 +200,78 @@ mod tests {
         assert_eq!(configurator.redirect_url().as_str(), DEFAULT_REDIRECT_URL);
     }
 
+    #[test]
+    fn the_configurator_has_no_fallback_redirect_ports_by_default() {
+        let port = FakeConfiguratorPort::with_client_id("real-client-id");
+        let configurator = Configurator::load(&port).unwrap();
+
+        assert_eq!(configurator.fallback_redirect_ports(), DEFAULT_REDIRECT_FALLBACK_PORTS);
+    }
+
+    #[test]
+    fn the_configurator_can_return_the_revocation_url() {
+        let port = FakeConfiguratorPort::with_client_id("real-client-id");
+        let configurator = Configurator::load(&port).unwrap();
+
+        assert_eq!(configurator.revocation_url().as_str(), DEFAULT_REVOCATION_URL);
+    }
+
+    #[test]
+    fn the_configurator_can_return_the_auth_flow_timeout() {
+        let port = FakeConfiguratorPort::with_client_id("real-client-id");
+        let configurator = Configurator::load(&port).unwrap();
+
+        assert_eq!(configurator.auth_flow_timeout(), Duration::from_secs(DEFAULT_AUTH_FLOW_TIMEOUT_SECONDS));
+    }
+
+    #[test]
+    fn the_configurator_can_return_the_grpc_listen_addr() {
+        let port = FakeConfiguratorPort::with_client_id("real-client-id");
+        let configurator = Configurator::load(&port).unwrap();
+
+        assert_eq!(configurator.grpc_listen_addr().to_string(), DEFAULT_GRPC_LISTEN_ADDRESS);
+    }
+
+    #[test]
+    fn the_configurator_can_return_the_log_level() {
+        let port = FakeConfiguratorPort::with_client_id("real-client-id");
+        let configurator = Configurator::load(&port).unwrap();
+
+        assert_eq!(configurator.log_level(), DEFAULT_LOG_LEVEL);
+    }
+
+    #[test]
+    fn log_levels_are_parsed_case_insensitive() {
+        assert_eq!("DEBUG".parse::<LogLevel>().unwrap(), LogLevel::Debug);
+        assert_eq!(LogLevel::Trace.to_string().parse::<LogLevel>().unwrap(), LogLevel::Trace);
+    }
+
+    #[test]
+    fn conflict_policies_are_parsed_case_insensitive() {
+        assert_eq!("Prefer_Remote".parse::<ConflictPolicy>().unwrap(), ConflictPolicy::PreferRemote);
+        assert_eq!(ConflictPolicy::Ask.to_string().parse::<ConflictPolicy>().unwrap(), ConflictPolicy::Ask);
+        assert_eq!("newest".parse::<ConflictPolicy>().unwrap_err().text_key, InvalidConflictPolicy);
+    }
+
+    #[test]
+    fn the_configurator_keeps_both_versions_of_a_conflict_by_default() {
+        let port = FakeConfiguratorPort::with_client_id("real-client-id");
+        let configurator = Configurator::load(&port).unwrap();
+
+        assert_eq!(configurator.conflict_policy(), DEFAULT_CONFLICT_POLICY);
+        assert_eq!(configurator.ignore_patterns(), DEFAULT_IGNORE_PATTERNS);
+        assert_eq!(configurator.upload_chunk_size(), DEFAULT_UPLOAD_CHUNK_SIZE);
+        assert_eq!(configurator.upload_parallelism(), DEFAULT_UPLOAD_PARALLELISM);
+        assert_eq!(DEFAULT_CONFLICT_POLICY.resolution(), Some(ConflictResolution::KeepBoth));
+    }
+
+    #[test]
+    fn unknown_log_level_returns_an_error() {
+        let err = "verbose".parse::<LogLevel>().expect_err("Expected InvalidLogLevel error");
+
+        assert_eq!(err.text_key, InvalidLogLevel);
+    }
+
     #[test]
     fn the_configurator_can_return_the_client_id() {
         let port = FakeConfiguratorPort::with_client_id("real-client-id");
@@ -0,0 +1,59 @@
+use tokio::io::{AsyncRead, AsyncReadExt};
+use xxhash_rust::xxh3::Xxh3;
+
+const XXH3_PREFIX: &str = "xxh3:";
+
+/// The hash of the content as `<algorithm>:<hex>`, the 64 bit XXH3 kDrive reports for files and expects for chunks
+pub fn content_hash(content: &[u8]) -> String {
+    let mut hasher = ContentHasher::default();
+    hasher.update(content);
+    hasher.finish()
+}
+
+/// Only hashes of an algorithm the engine knows can be compared
+pub fn can_verify(hash: &str) -> bool {
+    hash.starts_with(XXH3_PREFIX)
+}
+
+/// The hash of what `reader` has left, read in parts so a large file is not held in memory
+pub async fn read_hash(reader: &mut (impl AsyncRead + Unpin)) -> std::io::Result<String> {
+    let mut hasher = ContentHasher::default();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = reader.read(&mut buffer).await?;
+        if read == 0 {
+            return Ok(hasher.finish());
+        }
+        hasher.update(&buffer[..read]);
+    }
+}
+
+/// Hashes content that arrives in parts
+#[derive(Default)]
+pub struct ContentHasher(Xxh3);
+
+impl ContentHasher {
+    pub fn update(&mut self, content: &[u8]) {
+        self.0.update(content);
+    }
+
+    pub fn finish(self) -> String {
+        format!("{}{:016x}", XXH3_PREFIX, self.0.digest())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_hashed_in_parts_has_the_hash_of_the_whole() {
+        let mut hasher = ContentHasher::default();
+        hasher.update(b"a");
+        hasher.update(b"bc");
+
+        assert_eq!(hasher.finish(), content_hash(b"abc"));
+        assert!(can_verify(&content_hash(b"abc")));
+        assert!(!can_verify("sha256:1234"));
+    }
+}
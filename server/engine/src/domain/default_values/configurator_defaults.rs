@@ -1,5 +1,33 @@
+use crate::domain::configuration::{ConflictPolicy, LogLevel};
+
 pub const DEFAULT_CLIENT_ID: &str = "test-client-id";
 pub const DEFAULT_REDIRECT_URL: &str = "http://localhost:13628/kdrive/auth";
 pub const DEFAULT_REDIRECT_PATH: &str = "/kdrive/auth";
 pub const DEFAULT_AUTH_URL: &str = "https://login.infomaniak.com/authorize";
-pub const DEFAULT_TOKEN_URL: &str = "https://login.infomaniak.com/token";
\ No newline at end of file
+pub const DEFAULT_TOKEN_URL: &str = "https://login.infomaniak.com/token";
+pub const DEFAULT_REVOCATION_URL: &str = "https://login.infomaniak.com/revoke";
+pub const DEFAULT_API_URL: &str = "https://api.infomaniak.com";
+pub const DEFAULT_AUTH_FLOW_TIMEOUT_SECONDS: u64 = 300;
+pub const DEFAULT_API_CONNECT_TIMEOUT_SECONDS: u64 = 10;
+/// How long kDrive may stay silent in the middle of a request, a long transfer that keeps going is not cut off
+pub const DEFAULT_API_READ_TIMEOUT_SECONDS: u64 = 60;
+pub const DEFAULT_REDIRECT_FALLBACK_PORTS: &[u16] = &[];
+pub const DEFAULT_GRPC_LISTEN_ADDRESS: &str = common::domain::defaults::DEFAULT_SERVER_ADDRESS;
+pub const DEFAULT_SYNC_ROOT_FOLDER_NAME: &str = "kDrive";
+pub const DEFAULT_LOG_LEVEL: LogLevel = LogLevel::Info;
+pub const DEFAULT_CONFLICT_POLICY: ConflictPolicy = ConflictPolicy::KeepBoth;
+/// Larger files are uploaded in chunks of this many bytes
+pub const DEFAULT_UPLOAD_CHUNK_SIZE: u64 = 20 * 1024 * 1024;
+pub const DEFAULT_UPLOAD_PARALLELISM: usize = 4;
+/// Files the operating systems, editors and build tools leave behind
+pub const DEFAULT_IGNORE_PATTERNS: &[&str] = &[
+    ".DS_Store",
+    "Thumbs.db",
+    "desktop.ini",
+    "*.swp",
+    "*~",
+    ".~lock.*#",
+    "~$*",
+    "node_modules/",
+    "__pycache__/",
+];
@@ -1,4 +1,26 @@
 pub const APPLICATION_NAME: &str = "kdrive_rs";
 pub const TOKEN_FILE_NAME: &str = "tokens.json";
 pub const KEYRING_SERVICE: &str = "kdrive_rs";
-pub const KEYRING_USER: &str = "kdrive_rs_oauth_tokens";
\ No newline at end of file
+pub const KEYRING_USER: &str = "kdrive_rs_oauth_tokens";
+pub const TOKEN_REFRESH_MARGIN_SECONDS: i64 = 300;
+pub const TOKEN_REFRESH_CHECK_INTERVAL_SECONDS: u64 = 60;
+pub const TOKEN_REFRESH_RETRY_SECONDS: u64 = 30;
+pub const DEFAULT_ACCESS_TOKEN_LIFETIME_SECONDS: i64 = 3600;
+pub const CONFIG_FILE_NAME: &str = "config.toml";
+pub const CONFIG_ENV_PREFIX: &str = "KDRIVE_";
+pub const LOG_FILE_NAME: &str = "kdrive_service.log";
+pub const ROOT_DIRECTORY_ID: u64 = 1;
+pub const API_LIST_PAGE_SIZE: u32 = 200;
+pub const METADATA_DATABASE_FILE_NAME: &str = "metadata.sqlite3";
+pub const MAX_LISTING_DEPTH: u32 = 16;
+pub const CRAWL_CHECK_INTERVAL_SECONDS: u64 = 5;
+pub const CRAWL_RETRY_SECONDS: u64 = 30;
+pub const LOCAL_TRASH_DIRECTORY_NAME: &str = ".kdrive-trash";
+pub const PARTIAL_FILE_SUFFIX: &str = ".part";
+pub const LOCAL_CHANGE_QUIET_PERIOD_MILLIS: u64 = 500;
+pub const LOCAL_CHANGE_MAX_DELAY_SECONDS: u64 = 5;
+pub const SYNC_RETRY_SECONDS: u64 = 30;
+pub const REMOTE_POLL_INTERVAL_SECONDS: u64 = 30;
+pub const REMOTE_POLL_MAX_BACKOFF_SECONDS: u64 = 600;
+pub const IGNORE_FILE_NAME: &str = ".kdriveignore";
+pub const DOWNLOAD_PROGRESS_INTERVAL_BYTES: u64 = 1024 * 1024;
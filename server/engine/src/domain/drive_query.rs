@@ -0,0 +1,241 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, OnceLock};
+use common::application_error;
+use common::domain::directory_listing::{DirectoryEntry, DirectoryListing, EntryKind, ListingPage, SyncStatus};
+use common::domain::errors::ApplicationError;
+use common::domain::file_search::{FileSearch, SearchResult};
+use common::domain::sync_selection::SyncSelection;
+use common::domain::text_keys::TextKeys::{DirectoryNotFound, NotADirectory, SearchIndexNotReady};
+use crate::domain::cloud_sync_state::CloudSyncState;
+use crate::domain::default_values::general_defaults::{MAX_LISTING_DEPTH, ROOT_DIRECTORY_ID};
+use crate::domain::remote_file::{DriveId, FileId, RemoteFile};
+use crate::domain::sync::ignore_rules::IgnoreRules;
+use crate::domain::sync::sync_job::lookup_drive_id;
+use crate::domain::sync::sync_path::join_path;
+use crate::ports::driven::kdrive_api_driven_port::KDriveApiDrivenPort;
+use crate::ports::driven::local_fs_driven_port::LocalFsDrivenPort;
+use crate::ports::driven::metadata_driven_port::MetadataDrivenPort;
+
+/// Where a directory listing is read from
+enum ListingSource {
+    Metadata,
+    Api(DriveId),
+}
+
+type EntriesFuture<'a> = Pin<Box<dyn Future<Output = Result<Vec<DirectoryEntry>, ApplicationError>> + Send + 'a>>;
+
+fn directory_entry(file: RemoteFile, sync_status: SyncStatus) -> DirectoryEntry {
+    DirectoryEntry {
+        id: file.id,
+        kind: file.kind.into(),
+        name: file.name,
+        size: file.size,
+        modified_at: file.last_modified_at,
+        sync_status,
+        children: Vec::new(),
+    }
+}
+
+/// Gives the entries the sync leaves alone the ignored status, `directory` is the path they are in
+fn mark_ignored(entries: &mut [DirectoryEntry], directory: &str, rules: &IgnoreRules) {
+    for entry in entries {
+        let path = join_path(directory, &entry.name);
+        if rules.is_ignored(&path, entry.kind == EntryKind::Directory) {
+            entry.sync_status = SyncStatus::Ignored;
+        }
+        mark_ignored(&mut entry.children, &path, rules);
+    }
+}
+
+/// A listing, search or selection lookup that shares the ports of the engine instead of borrowing the engine,
+/// so a listing that has to ask kDrive does not keep the engine locked
+pub struct DriveQuery<MetadataPort, ApiPort, LocalPort> {
+    metadata: Arc<MetadataPort>,
+    api: Arc<ApiPort>,
+    local: Arc<LocalPort>,
+    drive_id: Arc<OnceLock<DriveId>>,
+    ignore_patterns: Vec<String>,
+}
+
+impl<MetadataPort, ApiPort, LocalPort> DriveQuery<MetadataPort, ApiPort, LocalPort>
+where
+    MetadataPort: MetadataDrivenPort + Send + Sync,
+    ApiPort: KDriveApiDrivenPort + Send + Sync,
+    LocalPort: LocalFsDrivenPort + Send + Sync,
+{
+    pub fn new(metadata: Arc<MetadataPort>, api: Arc<ApiPort>, local: Arc<LocalPort>, drive_id: Arc<OnceLock<DriveId>>) -> Self {
+        Self {
+            metadata,
+            api,
+            local,
+            drive_id,
+            ignore_patterns: Vec::new(),
+        }
+    }
+
+    pub fn with_ignore_patterns(mut self, ignore_patterns: Vec<String>) -> Self {
+        self.ignore_patterns = ignore_patterns;
+        self
+    }
+
+    /// Lists one page of the directory at `path`, `depth` 1 returns its entries, 2 also their children and so on
+    pub async fn get_directory_listing(&self, path: &str, depth: u32, page: ListingPage) -> Result<DirectoryListing, ApplicationError> {
+        let source = match CloudSyncState::of(&*self.metadata)? {
+            CloudSyncState::MetadataPresent => ListingSource::Metadata,
+            // Until the whole drive is indexed the store can miss files, kDrive itself has them all
+            CloudSyncState::NoMetadata | CloudSyncState::NotIndexed => ListingSource::Api(lookup_drive_id(&*self.api, &self.drive_id).await?),
+        };
+
+        let segments: Vec<&str> = path.split('/').filter(|segment| !segment.is_empty()).collect();
+        let path = format!("/{}", segments.join("/"));
+        let directory_id = self.resolve_directory(&source, &segments, &path).await?;
+
+        // One entry past the page tells whether another page follows
+        let mut entries = self.list_children(&source, directory_id, Some((page.offset, page.size.saturating_add(1)))).await?;
+        let next_page_token = (entries.len() > page.size).then(|| page.next_token());
+        entries.truncate(page.size);
+
+        let depth = depth.clamp(1, MAX_LISTING_DEPTH);
+        if depth > 1 {
+            for entry in entries.iter_mut().filter(|entry| entry.kind == EntryKind::Directory) {
+                entry.children = self.list_entries(&source, entry.id, depth - 1).await?;
+            }
+        }
+        let rules = self.listing_ignore_rules(&segments, &entries).await?;
+        mark_ignored(&mut entries, &segments.join("/"), &rules);
+
+        Ok(DirectoryListing {
+            path,
+            entries,
+            next_page_token,
+        })
+    }
+
+    /// Searches the stored metadata, also while the crawl is still running
+    pub fn search_files(&self, search: &FileSearch) -> Result<Vec<SearchResult>, ApplicationError> {
+        if CloudSyncState::of(&*self.metadata)? == CloudSyncState::NoMetadata {
+            return Err(application_error!(SearchIndexNotReady));
+        }
+
+        let mut paths = HashMap::new();
+        self.metadata.search_files(search)?
+            .into_iter()
+            .map(|file| {
+                let path = self.stored_path(&file.remote, &mut paths)?;
+                Ok(SearchResult { path, entry: directory_entry(file.remote, file.sync_state.into()) })
+            })
+            .collect()
+    }
+
+    pub fn get_sync_selection(&self) -> Result<SyncSelection, ApplicationError> {
+        self.metadata.sync_selection()
+    }
+
+    /// The children of a directory sorted by name, `range` is the offset and the number of them to return
+    async fn list_children(&self, source: &ListingSource, directory_id: FileId, range: Option<(usize, usize)>) -> Result<Vec<DirectoryEntry>, ApplicationError> {
+        match source {
+            ListingSource::Metadata => {
+                let files = match range {
+                    Some((offset, limit)) => self.metadata.get_children_page(directory_id, offset, limit)?,
+                    None => self.metadata.get_children(directory_id)?,
+                };
+                Ok(files.into_iter()
+                    .map(|file| directory_entry(file.remote, file.sync_state.into()))
+                    .collect())
+            }
+            ListingSource::Api(drive_id) => {
+                let mut files = self.api.list_all_files(*drive_id, directory_id).await?;
+                files.sort_by(|a, b| a.name.cmp(&b.name));
+                let (offset, limit) = range.unwrap_or((0, usize::MAX));
+
+                // Files the store already knows keep their sync status
+                files.into_iter()
+                    .skip(offset)
+                    .take(limit)
+                    .map(|file| {
+                        let sync_status = self.metadata
+                            .get_file(file.id)?
+                            .map_or(SyncStatus::Unknown, |known| known.sync_state.into());
+                        Ok(directory_entry(file, sync_status))
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Lists a directory and, while `depth` allows it, the directories below it
+    fn list_entries<'a>(&'a self, source: &'a ListingSource, directory_id: FileId, depth: u32) -> EntriesFuture<'a> {
+        Box::pin(async move {
+            let mut entries = self.list_children(source, directory_id, None).await?;
+            if depth > 1 {
+                for entry in entries.iter_mut().filter(|entry| entry.kind == EntryKind::Directory) {
+                    entry.children = self.list_entries(source, entry.id, depth - 1).await?;
+                }
+            }
+            Ok(entries)
+        })
+    }
+
+    /// The rules of the ignore files in the sync root that apply to the entries of a listing
+    async fn listing_ignore_rules(&self, segments: &[&str], entries: &[DirectoryEntry]) -> Result<IgnoreRules, ApplicationError> {
+        let mut rules = IgnoreRules::new(&self.ignore_patterns);
+        for end in 0..=segments.len() {
+            rules.load_directory(&*self.local, &segments[..end].join("/")).await?;
+        }
+        let mut pending = vec![(segments.join("/"), entries)];
+        while let Some((directory, entries)) = pending.pop() {
+            for entry in entries.iter().filter(|entry| entry.kind == EntryKind::Directory && !entry.children.is_empty()) {
+                let path = join_path(&directory, &entry.name);
+                rules.load_directory(&*self.local, &path).await?;
+                pending.push((path, &entry.children));
+            }
+        }
+        Ok(rules)
+    }
+
+    /// The path of a stored file from the root of the drive, `paths` remembers the directories already resolved
+    fn stored_path(&self, file: &RemoteFile, paths: &mut HashMap<FileId, String>) -> Result<String, ApplicationError> {
+        let mut names = vec![file.name.clone()];
+        let mut parent_id = file.parent_id;
+        let prefix = loop {
+            if parent_id == ROOT_DIRECTORY_ID {
+                break String::new();
+            }
+            if let Some(path) = paths.get(&parent_id) {
+                break path.clone();
+            }
+            // A parent that is not stored yet leaves the path relative to what is known
+            let Some(parent) = self.metadata.get_file(parent_id)? else {
+                break String::new();
+            };
+            names.push(parent.remote.name);
+            parent_id = parent.remote.parent_id;
+        };
+
+        let mut path = prefix;
+        for name in names.iter().rev() {
+            path = format!("{path}/{name}");
+        }
+        if file.is_directory() {
+            paths.insert(file.id, path.clone());
+        }
+        Ok(path)
+    }
+
+    async fn resolve_directory(&self, source: &ListingSource, segments: &[&str], path: &str) -> Result<FileId, ApplicationError> {
+        let mut directory_id = ROOT_DIRECTORY_ID;
+        for segment in segments {
+            let entry = self.list_children(source, directory_id, None).await?
+                .into_iter()
+                .find(|entry| entry.name == *segment)
+                .ok_or_else(|| application_error!(DirectoryNotFound, path))?;
+            if entry.kind != EntryKind::Directory {
+                return Err(application_error!(NotADirectory, path));
+            }
+            directory_id = entry.id;
+        }
+        Ok(directory_id)
+    }
+}
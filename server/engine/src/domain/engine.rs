@@ -1,91 +1,199 @@
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
 use async_trait::async_trait;
 use common::application_error;
-use common::domain::directory_listing::DirectoryListing;
+use common::domain::directory_listing::{DirectoryListing, ListingPage};
 use common::domain::errors::ApplicationError;
-use common::domain::text_keys::TextKeys::NotImplemented;
+use common::domain::sync_conflict::ConflictResolution;
+use common::domain::sync_selection::SyncSelection;
+use common::domain::file_search::{FileSearch, SearchResult};
+use common::domain::text_keys::TextKeys::{AuthFlowCancelled, DirectoryNotFound, NotADirectory, NotAuthenticated};
+use tokio::sync::{watch, Mutex};
 use crate::domain::cloud_sync_state::CloudSyncState;
+use crate::domain::configuration::ConflictPolicy;
+use crate::domain::default_values::configurator_defaults::{DEFAULT_CONFLICT_POLICY, DEFAULT_IGNORE_PATTERNS, DEFAULT_UPLOAD_CHUNK_SIZE, DEFAULT_UPLOAD_PARALLELISM};
+use crate::domain::default_values::general_defaults::{ROOT_DIRECTORY_ID, TOKEN_REFRESH_MARGIN_SECONDS};
+use crate::domain::metadata::{FileMetadata, SyncState};
+use crate::domain::remote_file::{DriveId, FileId};
+use crate::domain::drive_query::DriveQuery;
 use crate::domain::events::EngineEvent;
-use crate::ports::driven::authenticator_driven_port::AuthenticatorDrivenPort;
+use crate::domain::sync::sync_job::{lookup_drive_id, SyncJob};
+use crate::domain::sync::synchronizer::SyncReport;
+use crate::ports::driven::authenticator_driven_port::{AuthenticatorDrivenPort, PendingAuthorizationCode};
+use crate::ports::driven::clock_driven_port::ClockDrivenPort;
 use crate::ports::driven::event_bus_driven_port::EventBusDrivenPort;
+use crate::ports::driven::kdrive_api_driven_port::KDriveApiDrivenPort;
+use crate::ports::driven::local_fs_driven_port::LocalFsDrivenPort;
 use crate::ports::driven::metadata_driven_port::MetadataDrivenPort;
-use crate::ports::driving::authenticator_driving_port::AuthenticatorDrivingPort;
-use crate::ports::driving::data_driving_port::DataDrivingPort;
+use crate::ports::driving::authenticator_driving_port::{AuthenticatorDrivingPort, PendingLogout, PendingRefresh, RefreshedTokens};
+use crate::ports::driving::data_driving_port::{DataDrivingPort, PendingSync};
 use crate::ports::driving::token_store_driving_port::TokenStoreDrivingPort;
 
-pub struct Engine<AuthPort, TokenPort, EventPort, MetadataPort>
+pub struct Engine<AuthPort, TokenPort, EventPort, MetadataPort, ClockPort, ApiPort, LocalPort>
 where
     AuthPort: AuthenticatorDrivenPort,
     TokenPort: TokenStoreDrivingPort,
     EventPort: EventBusDrivenPort,
     MetadataPort: MetadataDrivenPort,
+    ClockPort: ClockDrivenPort,
+    ApiPort: KDriveApiDrivenPort,
+    LocalPort: LocalFsDrivenPort,
 {
     authenticator_driven_port: AuthPort,
     token_store: TokenPort,
-    event_bus: EventPort,
-    #[allow(dead_code)]
-    metadata_driven_port: MetadataPort,
+    event_bus: Arc<EventPort>,
+    metadata_driven_port: Arc<MetadataPort>,
+    clock: ClockPort,
+    api: Arc<ApiPort>,
+    local_fs: Arc<LocalPort>,
+    drive_id: Arc<OnceLock<DriveId>>,
+    sync_running: Arc<Mutex<()>>,
+    sync_stop: watch::Sender<u64>,
+    drive_id_configured: bool,
+    conflict_policy: ConflictPolicy,
+    ignore_patterns: Vec<String>,
+    upload_chunk_size: u64,
+    upload_parallelism: usize,
 }
 
-impl<AuthPort, TokenPort, EventPort, MetadataPort> Engine<AuthPort, TokenPort, EventPort, MetadataPort>
+impl<AuthPort, TokenPort, EventPort, MetadataPort, ClockPort, ApiPort, LocalPort> Engine<AuthPort, TokenPort, EventPort, MetadataPort, ClockPort, ApiPort, LocalPort>
 where
     AuthPort: AuthenticatorDrivenPort,
     TokenPort: TokenStoreDrivingPort,
     EventPort: EventBusDrivenPort,
     MetadataPort: MetadataDrivenPort,
+    ClockPort: ClockDrivenPort,
+    ApiPort: KDriveApiDrivenPort,
+    LocalPort: LocalFsDrivenPort,
 {
     pub fn new(
         authenticator_port: AuthPort,
         token_store: TokenPort,
         event_bus: EventPort,
-        metadata_driven_port: MetadataPort
-
+        metadata_driven_port: MetadataPort,
+        clock: ClockPort,
+        api: ApiPort,
+        local_fs: LocalPort,
     ) -> Self {
         Engine {
             authenticator_driven_port: authenticator_port,
             token_store,
-            event_bus,
-            metadata_driven_port
+            event_bus: Arc::new(event_bus),
+            metadata_driven_port: Arc::new(metadata_driven_port),
+            clock,
+            api: Arc::new(api),
+            local_fs: Arc::new(local_fs),
+            drive_id: Arc::new(OnceLock::new()),
+            sync_running: Arc::new(Mutex::new(())),
+            sync_stop: watch::channel(0).0,
+            drive_id_configured: false,
+            conflict_policy: DEFAULT_CONFLICT_POLICY,
+            ignore_patterns: DEFAULT_IGNORE_PATTERNS.iter().map(|pattern| pattern.to_string()).collect(),
+            upload_chunk_size: DEFAULT_UPLOAD_CHUNK_SIZE,
+            upload_parallelism: DEFAULT_UPLOAD_PARALLELISM,
         }
     }
 
+    /// Uses this drive instead of the first one the account has access to
+    pub fn with_drive_id(mut self, drive_id: DriveId) -> Self {
+        let _ = self.drive_id.set(drive_id);
+        self.drive_id_configured = true;
+        self
+    }
+
+    /// How the sync settles files that changed on both sides
+    pub fn with_conflict_policy(mut self, conflict_policy: ConflictPolicy) -> Self {
+        self.conflict_policy = conflict_policy;
+        self
+    }
+
+    /// Left out of the sync everywhere in the sync root, in the format of the lines of a `.kdriveignore`
+    pub fn with_ignore_patterns(mut self, ignore_patterns: Vec<String>) -> Self {
+        self.ignore_patterns = ignore_patterns;
+        self
+    }
+
+    /// Files larger than `chunk_size` bytes are uploaded in resumable chunks, `parallelism` of them at the same time
+    pub fn with_upload_chunks(mut self, chunk_size: u64, parallelism: usize) -> Self {
+        self.upload_chunk_size = chunk_size;
+        self.upload_parallelism = parallelism;
+        self
+    }
+
 
 
-    async fn do_auth_flow(&mut self) -> Result<(), ApplicationError> {
-        self.authenticator_driven_port.continue_initial_auth_flow().await?;
+    async fn do_auth_flow(&mut self, code: &str) -> Result<(), ApplicationError> {
+        self.authenticator_driven_port.exchange_authorization_code(code).await?;
         let tokens = self.authenticator_driven_port.get_tokens().await?;
         self.token_store.save_tokens(&tokens)?;
         Ok(())
     }
 
-    fn determine_cloud_sync_state(&self) -> CloudSyncState {
-        match (
-            self.metadata_driven_port.has_metadata(),
-            self.metadata_driven_port.has_index(),
-        ) {
-            (false, _) => CloudSyncState::NoMetadata,
-            (true, false) => CloudSyncState::NotIndexed,
-            (true, true) => CloudSyncState::MetadataPresent,
+    fn do_complete_token_refresh(&mut self, refreshed: Result<RefreshedTokens, ApplicationError>) -> Result<(), ApplicationError> {
+        let refreshed = refreshed?;
+        let tokens = refreshed.tokens?;
+        // The user logged out, or logged out and in again, while the token endpoint answered
+        if self.token_store.refresh_token() != Some(refreshed.started_with.as_str()) {
+            return Err(application_error!(NotAuthenticated));
         }
+        self.token_store.save_tokens(&tokens)
+    }
+
+    async fn do_start_logout(&mut self) -> Result<(), ApplicationError> {
+        if let Some(refresh_token) = self.token_store.refresh_token().map(str::to_string) {
+            // The local tokens are removed even when the server could not revoke them
+            let _ = self.authenticator_driven_port.revoke_tokens(&refresh_token).await;
+        }
+        self.token_store.clear_tokens()?;
+
+        // A sync that is still running would store files of this drive after the store is cleared
+        self.sync_stop.send_modify(|generation| *generation += 1);
+        if !self.drive_id_configured {
+            self.drive_id = Arc::new(OnceLock::new());
+        }
+        Ok(())
+    }
+
+    fn determine_cloud_sync_state(&self) -> Result<CloudSyncState, ApplicationError> {
+        CloudSyncState::of(&*self.metadata_driven_port)
     }
 }
 
 #[async_trait]
-impl<AuthPort, TokenPort, EventPort, MetadataPort> AuthenticatorDrivingPort for Engine<AuthPort, TokenPort, EventPort, MetadataPort>
+impl<AuthPort, TokenPort, EventPort, MetadataPort, ClockPort, ApiPort, LocalPort> AuthenticatorDrivingPort for Engine<AuthPort, TokenPort, EventPort, MetadataPort, ClockPort, ApiPort, LocalPort>
 where
     AuthPort: AuthenticatorDrivenPort + Send,
     TokenPort: TokenStoreDrivingPort + Send,
-    EventPort: EventBusDrivenPort + Send,
-    MetadataPort: MetadataDrivenPort + Send,
+    EventPort: EventBusDrivenPort + Send + Sync + 'static,
+    MetadataPort: MetadataDrivenPort + Send + Sync + 'static,
+    ClockPort: ClockDrivenPort + Send,
+    ApiPort: KDriveApiDrivenPort + Send + Sync,
+    LocalPort: LocalFsDrivenPort + Send + Sync,
 {
-    fn is_authenticated(&self) -> bool {
+    fn has_tokens(&self) -> bool {
         self.token_store.has_tokens()
     }
 
+    fn is_authenticated(&self) -> bool {
+        !self.token_store.is_expired(self.clock.now())
+    }
+
     async fn start_initial_auth_flow(&mut self) -> Result<String, ApplicationError> {
         self.authenticator_driven_port.start_initial_auth_flow().await
     }
-    async fn continue_initial_auth_flow(&mut self) {
-        let result = self.do_auth_flow().await;
+
+    fn pending_authorization_code(&mut self) -> Result<PendingAuthorizationCode, ApplicationError> {
+        self.authenticator_driven_port.pending_authorization_code()
+    }
+
+    async fn complete_initial_auth_flow(&mut self, code: Result<String, ApplicationError>) {
+        let result = match code {
+            Ok(code) => self.do_auth_flow(&code).await,
+            Err(error) => {
+                self.authenticator_driven_port.cancel_auth_flow().await;
+                Err(error)
+            }
+        };
 
         let event = match result {
             Ok(()) => EngineEvent::AuthFlowCompleted,
@@ -94,28 +202,212 @@ where
 
         let _ = self.event_bus.emit(event);
     }
+
+    async fn cancel_initial_auth_flow(&mut self) {
+        self.authenticator_driven_port.cancel_auth_flow().await;
+        let _ = self.event_bus.emit(EngineEvent::AuthFlowFailed {
+            reason: application_error!(AuthFlowCancelled),
+        });
+    }
+
+    fn token_refresh_delay(&self) -> Option<Duration> {
+        let expires_at = self.token_store.expires_at()?;
+        let refresh_at = expires_at.saturating_sub(TOKEN_REFRESH_MARGIN_SECONDS);
+        let seconds_left = refresh_at.saturating_sub(self.clock.now()).max(0);
+        Some(Duration::from_secs(seconds_left as u64))
+    }
+
+    fn pending_token_refresh(&self) -> Result<PendingRefresh, ApplicationError> {
+        let refresh_token = self.token_store
+            .refresh_token()
+            .ok_or(application_error!(NotAuthenticated))?;
+        let tokens = self.authenticator_driven_port.pending_token_refresh(refresh_token);
+        let started_with = refresh_token.to_string();
+        Ok(Box::pin(async move { RefreshedTokens { started_with, tokens: tokens.await } }))
+    }
+
+    fn complete_token_refresh(&mut self, refreshed: Result<RefreshedTokens, ApplicationError>) -> Result<(), ApplicationError> {
+        let result = self.do_complete_token_refresh(refreshed);
+
+        let event = match &result {
+            Ok(()) => EngineEvent::TokensRefreshed,
+            Err(error) => EngineEvent::TokenRefreshFailed { reason: error.clone() },
+        };
+
+        let _ = self.event_bus.emit(event);
+        result
+    }
+
+    async fn refresh_tokens(&mut self) -> Result<(), ApplicationError> {
+        let refreshed = match self.pending_token_refresh() {
+            Ok(pending) => Ok(pending.await),
+            Err(error) => Err(error),
+        };
+        self.complete_token_refresh(refreshed)
+    }
+
+    async fn start_logout(&mut self) -> Result<PendingLogout, ApplicationError> {
+        self.do_start_logout().await?;
+        let running = self.sync_running.clone();
+        let metadata = self.metadata_driven_port.clone();
+        let event_bus = self.event_bus.clone();
+        Ok(Box::pin(async move {
+            let _running = running.lock().await;
+            // The next user may log in with another account, the first sync must not start from this drive
+            metadata.clear()?;
+            let _ = event_bus.emit(EngineEvent::LoggedOut);
+            Ok(())
+        }))
+    }
 }
 
-impl<AuthPort, TokenPort, EventPort, MetadataPort> DataDrivingPort for Engine<AuthPort, TokenPort, EventPort, MetadataPort>
+impl<AuthPort, TokenPort, EventPort, MetadataPort, ClockPort, ApiPort, LocalPort> Engine<AuthPort, TokenPort, EventPort, MetadataPort, ClockPort, ApiPort, LocalPort>
 where
-    AuthPort: AuthenticatorDrivenPort,
-    TokenPort: TokenStoreDrivingPort,
-    EventPort: EventBusDrivenPort,
-    MetadataPort: MetadataDrivenPort
+    AuthPort: AuthenticatorDrivenPort + Sync,
+    TokenPort: TokenStoreDrivingPort + Sync,
+    EventPort: EventBusDrivenPort + Send + Sync,
+    MetadataPort: MetadataDrivenPort + Send + Sync,
+    ClockPort: ClockDrivenPort + Sync,
+    ApiPort: KDriveApiDrivenPort + Send + Sync,
+    LocalPort: LocalFsDrivenPort + Send + Sync,
 {
-    fn get_directory_listing(&self, path: String, depth: u32) -> Result<DirectoryListing, ApplicationError> {
-        match self.determine_cloud_sync_state() {
+    /// Takes what a poll, sync or conflict resolution needs, so it can run after the engine is unlocked
+    fn sync_job(&self) -> SyncJob<EventPort, MetadataPort, ApiPort, LocalPort> {
+        SyncJob::new(self.event_bus.clone(), self.metadata_driven_port.clone(), self.api.clone(), self.local_fs.clone(), self.drive_id.clone(), self.sync_running.clone(), self.sync_stop.subscribe())
+            .with_conflict_policy(self.conflict_policy)
+            .with_ignore_patterns(self.ignore_patterns.clone())
+            .with_upload_chunks(self.upload_chunk_size, self.upload_parallelism)
+            .with_time(self.clock.now())
+    }
+
+    /// Takes what a listing, search or selection lookup needs, so it can run after the engine is unlocked
+    fn drive_query(&self) -> DriveQuery<MetadataPort, ApiPort, LocalPort> {
+        DriveQuery::new(self.metadata_driven_port.clone(), self.api.clone(), self.local_fs.clone(), self.drive_id.clone())
+            .with_ignore_patterns(self.ignore_patterns.clone())
+    }
+
+    async fn drive_id(&self) -> Result<DriveId, ApplicationError> {
+        lookup_drive_id(&*self.api, &self.drive_id).await
+    }
+
+    async fn do_start_crawl(&self) -> Result<bool, ApplicationError> {
+        match self.determine_cloud_sync_state()? {
+            CloudSyncState::MetadataPresent => Ok(false),
+            // A crawl that was interrupted continues from its queue
+            CloudSyncState::NotIndexed => Ok(true),
             CloudSyncState::NoMetadata => {
-                Err(application_error!(NotImplemented))
-            }
-            CloudSyncState::MetadataPresent => {
-                Err(application_error!(NotImplemented))
+                let drive_id = self.drive_id().await?;
+                let root = self.api.get_file(drive_id, ROOT_DIRECTORY_ID).await?;
+                self.metadata_driven_port.start_crawl(&FileMetadata::new(root, SyncState::PendingDownload))?;
+                // Taken before the crawl, so what changes while it runs comes with the first poll
+                let cursor = self.api.list_changes(drive_id, None).await?.cursor;
+                self.metadata_driven_port.apply_remote_changes(&[], &[], &cursor)?;
+                Ok(true)
             }
-            CloudSyncState::NotIndexed => {
-                Err(application_error!(NotImplemented))
+        }
+    }
+
+    async fn do_crawl_next_directory(&self) -> Result<bool, ApplicationError> {
+        let Some(directory_id) = self.metadata_driven_port.next_crawl_directory()? else {
+            self.metadata_driven_port.mark_indexed()?;
+            return Ok(false);
+        };
+
+        let drive_id = self.drive_id().await?;
+        let children: Vec<_> = self.api.list_all_files(drive_id, directory_id).await?
+            .into_iter()
+            .map(|file| FileMetadata::new(file, SyncState::PendingDownload))
+            .collect();
+        self.metadata_driven_port.save_crawled_directory(directory_id, &children)?;
+        Ok(true)
+    }
+
+    fn do_set_sync_selection(&self, selection: SyncSelection) -> Result<(), ApplicationError> {
+        for folder_id in selection.rules.keys() {
+            match self.metadata_driven_port.get_file(*folder_id)? {
+                None => return Err(application_error!(DirectoryNotFound, folder_id)),
+                Some(file) if !file.remote.is_directory() => return Err(application_error!(NotADirectory, folder_id)),
+                Some(_) => {}
             }
         }
+        self.metadata_driven_port.save_sync_selection(&selection)
+    }
 
+    fn emit_crawl_result(&self, result: &Result<bool, ApplicationError>) {
+        let event = match result {
+            Ok(more) => match self.metadata_driven_port.crawl_progress() {
+                Ok(progress) if *more => EngineEvent::CrawlProgressed { progress },
+                Ok(progress) => EngineEvent::CrawlCompleted { progress },
+                Err(reason) => EngineEvent::CrawlFailed { reason },
+            },
+            Err(reason) => EngineEvent::CrawlFailed { reason: reason.clone() },
+        };
+        let _ = self.event_bus.emit(event);
+    }
+}
+
+#[async_trait]
+impl<AuthPort, TokenPort, EventPort, MetadataPort, ClockPort, ApiPort, LocalPort> DataDrivingPort for Engine<AuthPort, TokenPort, EventPort, MetadataPort, ClockPort, ApiPort, LocalPort>
+where
+    AuthPort: AuthenticatorDrivenPort + Sync,
+    TokenPort: TokenStoreDrivingPort + Sync,
+    EventPort: EventBusDrivenPort + Send + Sync + 'static,
+    MetadataPort: MetadataDrivenPort + Send + Sync + 'static,
+    ClockPort: ClockDrivenPort + Sync,
+    ApiPort: KDriveApiDrivenPort + Send + Sync + 'static,
+    LocalPort: LocalFsDrivenPort + Send + Sync + 'static,
+{
+    fn get_directory_listing(&self, path: String, depth: u32, page: ListingPage) -> PendingSync<DirectoryListing> {
+        let query = self.drive_query();
+        Box::pin(async move { query.get_directory_listing(&path, depth, page).await })
+    }
+
+    fn search_files(&self, search: FileSearch) -> PendingSync<Vec<SearchResult>> {
+        let query = self.drive_query();
+        Box::pin(async move { query.search_files(&search) })
+    }
+
+    async fn start_crawl(&self) -> Result<bool, ApplicationError> {
+        let result = self.do_start_crawl().await;
+        if !matches!(result, Ok(false)) {
+            self.emit_crawl_result(&result);
+        }
+        result
+    }
+
+    async fn crawl_next_directory(&self) -> Result<bool, ApplicationError> {
+        let result = self.do_crawl_next_directory().await;
+        self.emit_crawl_result(&result);
+        result
+    }
+
+    fn poll_remote_changes(&self) -> PendingSync<usize> {
+        let job = self.sync_job();
+        Box::pin(async move { job.poll_remote_changes().await })
+    }
+
+    fn synchronize(&self) -> PendingSync<SyncReport> {
+        let job = self.sync_job();
+        Box::pin(async move { job.synchronize().await })
+    }
+
+    fn synchronize_with_moves(&self, local_moves: Vec<(String, String)>) -> PendingSync<SyncReport> {
+        let job = self.sync_job().with_local_moves(local_moves);
+        Box::pin(async move { job.synchronize().await })
+    }
+
+    fn resolve_conflict(&self, file_id: FileId, path: String, resolution: ConflictResolution) -> PendingSync<()> {
+        let job = self.sync_job();
+        Box::pin(async move { job.resolve_conflict(file_id, &path, resolution).await })
+    }
+
+    fn get_sync_selection(&self) -> PendingSync<SyncSelection> {
+        let query = self.drive_query();
+        Box::pin(async move { query.get_sync_selection() })
+    }
+
+    async fn set_sync_selection(&self, selection: SyncSelection) -> Result<(), ApplicationError> {
+        self.do_set_sync_selection(selection)
     }
 }
 
@@ -123,10 +415,58 @@ where
 mod tests {
     use crate::domain::cloud_sync_state::CloudSyncState;
     use crate::domain::events::EngineEvent;
-    use crate::domain::test_helpers::fake_authenticator_adapter::FakeAuthenticatorDrivenAdapter;
+    use crate::domain::sync::resumable_download::DownloadProgress;
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+    use crate::domain::test_helpers::fake_authenticator_adapter::{FakeAuthenticatorDrivenAdapter, TEST_AUTHORIZATION_CODE};
+    use crate::domain::default_values::general_defaults::TOKEN_REFRESH_MARGIN_SECONDS;
+    use crate::domain::test_helpers::fake_clock::{FakeClock, TEST_NOW};
+    use crate::domain::test_helpers::fake_token_store_adapter::{TEST_REFRESHED_ACCESS_TOKEN, TEST_RING_ACCESS_TOKEN, TEST_RING_REFRESH_TOKEN};
     use crate::domain::test_helpers::test_engine_builder::TestEngineBuilder;
     use crate::ports::driving::authenticator_driving_port::AuthenticatorDrivingPort;
     use crate::ports::driving::data_driving_port::DataDrivingPort;
+    use crate::ports::driving::token_store_driving_port::TokenStoreDrivingPort;
+    use crate::domain::test_helpers::fake_event_bus::FakeEventBus;
+    use crate::domain::test_helpers::fake_metadata_store::FakeMetadataStore;
+    use crate::domain::test_helpers::fake_token_store::FakeTokenStore;
+    use crate::domain::engine::Engine;
+    use common::application_error;
+    use common::domain::directory_listing::{ListingPage, SyncStatus};
+    use common::domain::text_keys::TextKeys::{AuthFlowCancelled, AuthFlowTimedOut, ConflictNotFound, DirectoryNotFound, NotADirectory, SearchIndexNotReady, SyncNotReady, SyncRootOfAnotherDrive, SyncStopped};
+    use common::domain::file_search::FileSearch;
+    use common::domain::sync_conflict::ConflictResolution;
+    use common::domain::sync_selection::SyncSelection;
+    use crate::domain::configuration::ConflictPolicy;
+    use crate::domain::default_values::general_defaults::ROOT_DIRECTORY_ID;
+    use crate::domain::metadata::{FileMetadata, SyncState};
+    use crate::domain::remote_file::{FileId, FileKind, RemoteFile};
+    use crate::domain::test_helpers::fake_kdrive_api::{FakeKDriveApi, TEST_DRIVE_ID};
+    use crate::ports::driven::kdrive_api_driven_port::KDriveApiDrivenPort;
+    use crate::domain::test_helpers::fake_local_fs::FakeLocalFs;
+    use crate::ports::driven::metadata_driven_port::MetadataDrivenPort;
+
+    type TestEngine = Engine<FakeAuthenticatorDrivenAdapter, FakeTokenStore, FakeEventBus, FakeMetadataStore, FakeClock, FakeKDriveApi, FakeLocalFs>;
+
+    fn metadata_file(id: FileId, parent_id: FileId, name: &str, kind: FileKind, sync_state: SyncState) -> FileMetadata {
+        let remote = RemoteFile {
+            id,
+            parent_id,
+            name: name.to_string(),
+            kind,
+            size: (kind == FileKind::File).then_some(42),
+            last_modified_at: 0,
+            hash: None,
+        };
+        FileMetadata::new(remote, sync_state)
+    }
+
+    async fn continue_auth_flow(engine: &mut TestEngine) {
+        let code = match engine.pending_authorization_code() {
+            Ok(pending) => pending.await,
+            Err(error) => Err(error),
+        };
+        engine.complete_initial_auth_flow(code).await;
+    }
 
     #[test]
     fn engine_reports_not_indexed_when_cloud_metadata_exists_but_no_index_present() {
@@ -136,17 +476,20 @@ mod tests {
             .build();
 
         // When
-        let state = engine.determine_cloud_sync_state();
+        let state = engine.determine_cloud_sync_state().unwrap();
 
         // Then
         assert_eq!(state, CloudSyncState::NotIndexed);
     }
 
-    #[test]
-    fn bdd_user_can_view_cloud_files_for_the_first_time() {
+    #[tokio::test]
+    async fn bdd_user_can_view_cloud_files_for_the_first_time() {
         // Given: an authenticated user and no local cloud structure
         let engine = TestEngineBuilder::new()
             .without_index()
+            .with_api(FakeKDriveApi::new_default()
+                .with_directory(ROOT_DIRECTORY_ID, "Documents")
+                .with_file(ROOT_DIRECTORY_ID, "notes.txt", b"some notes"))
             .build();
 
         // And: a valid path and depth that will be provided by the user
@@ -155,10 +498,388 @@ mod tests {
 
 
         // When: the user requests a file overview
-        let result = engine.get_directory_listing(path, depth);
+        let result = engine.get_directory_listing(path, depth, ListingPage::default()).await;
 
         // Then: a file overview is returned
         assert!(result.is_ok());
+        let names: Vec<_> = result.unwrap().entries.into_iter().map(|entry| entry.name).collect();
+        assert_eq!(names, ["Documents", "notes.txt"]);
+    }
+
+    #[tokio::test]
+    async fn listing_is_read_from_metadata_once_the_drive_is_indexed() {
+        // Given: an indexed store that knows a file the API does not have
+        let metadata = FakeMetadataStore::new().with_files(&[
+            metadata_file(2, ROOT_DIRECTORY_ID, "Documents", FileKind::Directory, SyncState::Synced),
+            metadata_file(3, 2, "report.pdf", FileKind::File, SyncState::PendingUpload),
+        ]);
+        let engine = TestEngineBuilder::new()
+            .with_metadata_store(metadata)
+            .build();
+
+        // When: the user requests the folder
+        let listing = engine.get_directory_listing("/Documents/".to_string(), 1, ListingPage::default()).await.unwrap();
+
+        // Then: the stored entry is returned with its sync status
+        assert_eq!(listing.path, "/Documents");
+        assert_eq!(listing.entries.len(), 1);
+        assert_eq!(listing.entries[0].name, "report.pdf");
+        assert_eq!(listing.entries[0].size, Some(42));
+        assert_eq!(listing.entries[0].sync_status, SyncStatus::PendingUpload);
+    }
+
+    #[tokio::test]
+    async fn search_results_carry_their_path_from_the_root() {
+        // Given: a store with files at different depths, one of them while the crawl is still running
+        let metadata = FakeMetadataStore::new().without_index().with_files(&[
+            metadata_file(2, ROOT_DIRECTORY_ID, "Documents", FileKind::Directory, SyncState::Synced),
+            metadata_file(3, 2, "Reports", FileKind::Directory, SyncState::Synced),
+            metadata_file(4, 3, "report.pdf", FileKind::File, SyncState::PendingDownload),
+            metadata_file(5, 2, "old report.pdf", FileKind::File, SyncState::Synced),
+        ]);
+        let engine = TestEngineBuilder::new()
+            .with_metadata_store(metadata)
+            .build();
+
+        // When: the user searches for part of a name
+        let results = engine.search_files(FileSearch {
+            name: Some("report".to_string()),
+            limit: 10,
+            ..Default::default()
+        }).await.unwrap();
+
+        // Then: every match is returned with where it lives on the drive
+        let paths: Vec<_> = results.iter().map(|result| result.path.as_str()).collect();
+        assert_eq!(paths, ["/Documents/Reports", "/Documents/old report.pdf", "/Documents/Reports/report.pdf"]);
+        assert_eq!(results[2].entry.sync_status, SyncStatus::PendingDownload);
+    }
+
+    #[tokio::test]
+    async fn search_needs_stored_metadata() {
+        let engine = TestEngineBuilder::new()
+            .without_metadata()
+            .build();
+
+        let result = engine.search_files(FileSearch::default()).await;
+
+        assert_eq!(result.unwrap_err().text_key, SearchIndexNotReady);
+    }
+
+    #[tokio::test]
+    async fn synchronize_brings_the_drive_into_the_sync_root() {
+        // Given: an indexed drive with a file the sync root does not have yet
+        let event_bus = FakeEventBus::new();
+        let engine = TestEngineBuilder::new()
+            .with_api(FakeKDriveApi::new_default().with_file(ROOT_DIRECTORY_ID, "notes.txt", b"notes"))
+            .with_event_bus(event_bus.clone())
+            .build();
+
+        // When: the engine synchronizes
+        let report = engine.synchronize().await.unwrap();
+
+        // Then: the file is downloaded with its progress and the outcome is announced
+        assert_eq!(report.completed.len(), 1);
+        assert_eq!(engine.local_fs.content("notes.txt").unwrap(), b"notes");
+        let notes = engine.api.file_id(ROOT_DIRECTORY_ID, "notes.txt").unwrap();
+        let progress = |bytes_downloaded| EngineEvent::DownloadProgressed {
+            progress: DownloadProgress { file_id: notes, path: "notes.txt".to_string(), bytes_downloaded, bytes_total: Some(5) },
+        };
+        assert_eq!(event_bus.get_events(), [
+            progress(0),
+            progress(5),
+            EngineEvent::SyncCompleted { operations_completed: 1, operations_failed: 0 },
+        ]);
+    }
+
+    #[tokio::test]
+    async fn synchronize_waits_for_the_drive_to_be_indexed() {
+        let event_bus = FakeEventBus::new();
+        let engine = TestEngineBuilder::new()
+            .without_index()
+            .with_local_fs(FakeLocalFs::new().with_file("notes.txt", b"notes"))
+            .with_event_bus(event_bus.clone())
+            .build();
+
+        let result = engine.synchronize().await;
+
+        assert_eq!(result.unwrap_err().text_key, SyncNotReady);
+        assert!(matches!(event_bus.get_events()[..], [EngineEvent::SyncFailed { .. }]));
+        assert!(engine.api.list_all_files(TEST_DRIVE_ID, ROOT_DIRECTORY_ID).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn polling_announces_remote_changes() {
+        // Given: a polled drive that gets a new file
+        let event_bus = FakeEventBus::new();
+        let engine = TestEngineBuilder::new()
+            .with_event_bus(event_bus.clone())
+            .build();
+        engine.poll_remote_changes().await.unwrap();
+        let notes = engine.api.add_file(ROOT_DIRECTORY_ID, "notes.txt", b"notes");
+
+        // When: polling again
+        let changes = engine.poll_remote_changes().await.unwrap();
+
+        // Then: the new file is stored and announced
+        assert_eq!(changes, 1);
+        assert_eq!(engine.metadata_driven_port.get_file(notes).unwrap().unwrap().sync_state, SyncState::PendingDownload);
+        assert_eq!(event_bus.get_events(), [EngineEvent::RemoteChangesDetected { changes: 1 }]);
+    }
+
+    #[tokio::test]
+    async fn conflicts_are_announced_before_the_sync_completes() {
+        // Given: a synced file that is then edited locally and on kDrive
+        let event_bus = FakeEventBus::new();
+        let engine = TestEngineBuilder::new()
+            .with_api(FakeKDriveApi::new_default().with_file(ROOT_DIRECTORY_ID, "notes.txt", b"notes"))
+            .with_event_bus(event_bus.clone())
+            .build()
+            .with_conflict_policy(ConflictPolicy::KeepBoth);
+        engine.synchronize().await.unwrap();
+        let notes = engine.api.file_id(ROOT_DIRECTORY_ID, "notes.txt").unwrap();
+        engine.local_fs.add_file("notes.txt", b"local notes");
+        engine.api.change_file(notes, b"remote notes");
+
+        // When: the engine synchronizes again
+        engine.synchronize().await.unwrap();
+
+        // Then: the conflict is settled by the policy and announced
+        let events = event_bus.get_events();
+        let Some(EngineEvent::ConflictDetected { conflict }) = events.iter().rev().nth(1) else { panic!("no conflict in {events:?}") };
+        assert_eq!((conflict.file_id, conflict.resolution), (notes, Some(ConflictResolution::KeepBoth)));
+        assert!(matches!(events.last(), Some(EngineEvent::SyncCompleted { operations_failed: 0, .. })));
+        assert_eq!(engine.local_fs.content("notes (conflicted copy 2023-11-14).txt").unwrap(), b"local notes");
+    }
+
+    #[tokio::test]
+    async fn a_conflict_waiting_for_the_user_can_be_resolved() {
+        // Given: a conflict the policy leaves to the user
+        let engine = TestEngineBuilder::new()
+            .with_api(FakeKDriveApi::new_default().with_file(ROOT_DIRECTORY_ID, "notes.txt", b"notes"))
+            .build()
+            .with_conflict_policy(ConflictPolicy::Ask);
+        engine.synchronize().await.unwrap();
+        let notes = engine.api.file_id(ROOT_DIRECTORY_ID, "notes.txt").unwrap();
+        engine.local_fs.add_file("notes.txt", b"local notes");
+        engine.api.change_file(notes, b"remote notes");
+        engine.synchronize().await.unwrap();
+
+        // When: the user keeps the version of kDrive
+        engine.resolve_conflict(notes, "notes.txt".to_string(), ConflictResolution::KeepRemote).await.unwrap();
+
+        // Then: the sync root has it and the conflict is gone
+        assert_eq!(engine.local_fs.content("notes.txt").unwrap(), b"remote notes");
+        assert_eq!(engine.metadata_driven_port.get_file(notes).unwrap().unwrap().sync_state, SyncState::Synced);
+    }
+
+    #[tokio::test]
+    async fn only_a_waiting_conflict_can_be_resolved() {
+        let engine = TestEngineBuilder::new()
+            .with_api(FakeKDriveApi::new_default().with_file(ROOT_DIRECTORY_ID, "notes.txt", b"notes"))
+            .build();
+        engine.synchronize().await.unwrap();
+        let notes = engine.api.file_id(ROOT_DIRECTORY_ID, "notes.txt").unwrap();
+
+        let result = engine.resolve_conflict(notes, "notes.txt".to_string(), ConflictResolution::KeepLocal).await;
+
+        assert_eq!(result.unwrap_err().text_key, ConflictNotFound);
+    }
+
+    #[tokio::test]
+    async fn a_folder_left_out_of_the_selection_leaves_the_sync_root() {
+        // Given: a synced folder
+        let engine = TestEngineBuilder::new()
+            .with_api(FakeKDriveApi::new_default().with_file(ROOT_DIRECTORY_ID, "notes.txt", b"notes"))
+            .build();
+        let documents = engine.api.add_directory(ROOT_DIRECTORY_ID, "Documents");
+        engine.api.add_file(documents, "report.pdf", b"report");
+        engine.synchronize().await.unwrap();
+
+        // When: the user leaves it out and the engine synchronizes
+        let selection = SyncSelection::default().with_rule(documents, false);
+        engine.set_sync_selection(selection.clone()).await.unwrap();
+        engine.synchronize().await.unwrap();
+
+        // Then: only the rest of the drive is in the sync root
+        assert_eq!(engine.get_sync_selection().await.unwrap(), selection);
+        assert_eq!(engine.local_fs.paths(), ["notes.txt"]);
+    }
+
+    #[tokio::test]
+    async fn only_stored_folders_can_be_selected() {
+        let engine = TestEngineBuilder::new()
+            .with_api(FakeKDriveApi::new_default().with_file(ROOT_DIRECTORY_ID, "notes.txt", b"notes"))
+            .build();
+        engine.synchronize().await.unwrap();
+        let notes = engine.api.file_id(ROOT_DIRECTORY_ID, "notes.txt").unwrap();
+
+        let missing = engine.set_sync_selection(SyncSelection::default().with_rule(999, false)).await;
+        let file = engine.set_sync_selection(SyncSelection::default().with_rule(notes, false)).await;
+
+        assert_eq!(missing.unwrap_err().text_key, DirectoryNotFound);
+        assert_eq!(file.unwrap_err().text_key, NotADirectory);
+        assert_eq!(engine.get_sync_selection().await.unwrap(), SyncSelection::default());
+    }
+
+    #[tokio::test]
+    async fn starting_a_crawl_keeps_where_the_changes_of_kdrive_are() {
+        let engine = TestEngineBuilder::new()
+            .without_metadata()
+            .build();
+
+        engine.start_crawl().await.unwrap();
+
+        assert!(engine.metadata_driven_port.remote_cursor().unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn listing_includes_children_up_to_the_requested_depth() {
+        // Given: three nested folders on kDrive
+        let api = FakeKDriveApi::new_default();
+        let first = api.add_directory(ROOT_DIRECTORY_ID, "first");
+        let second = api.add_directory(first, "second");
+        api.add_directory(second, "third");
+        let engine = TestEngineBuilder::new()
+            .without_metadata()
+            .with_api(api)
+            .build();
+
+        // When: two levels are requested
+        let listing = engine.get_directory_listing("/".to_string(), 2, ListingPage::default()).await.unwrap();
+
+        // Then: the second level is listed, the third is not
+        let first = &listing.entries[0];
+        assert_eq!(first.children[0].name, "second");
+        assert!(first.children[0].children.is_empty());
+    }
+
+    #[tokio::test]
+    async fn listing_from_the_api_reads_every_page() {
+        let api = FakeKDriveApi::new_default().with_page_size(2);
+        for name in ["a", "b", "c", "d", "e"] {
+            api.add_file(ROOT_DIRECTORY_ID, name, b"");
+        }
+        let engine = TestEngineBuilder::new()
+            .without_metadata()
+            .with_api(api)
+            .build();
+
+        let listing = engine.get_directory_listing("/".to_string(), 1, ListingPage::default()).await.unwrap();
+
+        assert_eq!(listing.entries.len(), 5);
+        assert_eq!(listing.entries[0].sync_status, SyncStatus::Unknown);
+    }
+
+    #[tokio::test]
+    async fn listing_marks_what_the_ignore_patterns_leave_out() {
+        // Given: a folder on kDrive with an ignore file in the sync root for its build output
+        let api = FakeKDriveApi::new_default();
+        let app = api.add_directory(ROOT_DIRECTORY_ID, "App");
+        let build = api.add_directory(app, "build");
+        api.add_file(build, "app.bin", b"");
+        api.add_file(app, "main.rs", b"");
+        api.add_file(app, "Thumbs.db", b"");
+        let engine = TestEngineBuilder::new()
+            .without_metadata()
+            .with_api(api)
+            .with_local_fs(FakeLocalFs::new().with_file("App/.kdriveignore", b"/build\n"))
+            .build();
+
+        // When: the folder is listed with its children
+        let listing = engine.get_directory_listing("/App".to_string(), 2, ListingPage::default()).await.unwrap();
+
+        // Then: the ignored entries and what is inside them are marked, global patterns included
+        let statuses: Vec<_> = listing.entries.iter().map(|entry| (entry.name.as_str(), entry.sync_status)).collect();
+        assert_eq!(statuses, [("Thumbs.db", SyncStatus::Ignored), ("build", SyncStatus::Ignored), ("main.rs", SyncStatus::Unknown)]);
+        assert_eq!(listing.entries[1].children[0].sync_status, SyncStatus::Ignored);
+    }
+
+    #[tokio::test]
+    async fn listing_a_missing_folder_fails() {
+        let engine = TestEngineBuilder::new()
+            .without_metadata()
+            .build();
+
+        let result = engine.get_directory_listing("/missing".to_string(), 1, ListingPage::default()).await;
+
+        assert_eq!(result.unwrap_err(), application_error!(DirectoryNotFound, "/missing"));
+    }
+
+    #[tokio::test]
+    async fn listing_a_file_fails() {
+        let engine = TestEngineBuilder::new()
+            .without_metadata()
+            .with_api(FakeKDriveApi::new_default().with_file(ROOT_DIRECTORY_ID, "notes.txt", b""))
+            .build();
+
+        let result = engine.get_directory_listing("/notes.txt".to_string(), 1, ListingPage::default()).await;
+
+        assert_eq!(result.unwrap_err().text_key, NotADirectory);
+    }
+
+    async fn listed_pages(engine: &TestEngine, page_size: u32) -> Vec<Vec<String>> {
+        let mut pages = Vec::new();
+        let mut page_token = String::new();
+        loop {
+            let page = ListingPage::parse(&page_token, page_size).unwrap();
+            let listing = engine.get_directory_listing("/".to_string(), 1, page).await.unwrap();
+            pages.push(listing.entries.into_iter().map(|entry| entry.name).collect());
+            match listing.next_page_token {
+                Some(token) => page_token = token,
+                None => return pages,
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn pages_of_an_indexed_folder_follow_each_other_until_the_last_entry() {
+        // Given: an indexed store with five files in the root
+        let files: Vec<_> = ["a", "b", "c", "d", "e"].iter().enumerate()
+            .map(|(index, name)| metadata_file(index as FileId + 2, ROOT_DIRECTORY_ID, name, FileKind::File, SyncState::Synced))
+            .collect();
+        let engine = TestEngineBuilder::new()
+            .with_metadata_store(FakeMetadataStore::new().with_files(&files))
+            .build();
+
+        // When: the root is listed two entries at a time
+        let pages = listed_pages(&engine, 2).await;
+
+        // Then: every entry shows up once and the last page has no token
+        assert_eq!(pages, [vec!["a", "b"], vec!["c", "d"], vec!["e"]]);
+    }
+
+    #[tokio::test]
+    async fn pages_of_a_folder_read_from_kdrive_follow_each_other_until_the_last_entry() {
+        let api = FakeKDriveApi::new_default();
+        for name in ["a", "b", "c", "d"] {
+            api.add_file(ROOT_DIRECTORY_ID, name, b"");
+        }
+        let engine = TestEngineBuilder::new()
+            .without_metadata()
+            .with_api(api)
+            .build();
+
+        let pages = listed_pages(&engine, 2).await;
+
+        assert_eq!(pages, [vec!["a", "b"], vec!["c", "d"]]);
+    }
+
+    #[tokio::test]
+    async fn a_listing_that_waits_for_kdrive_leaves_the_engine_free() {
+        // Given: a drive that is not indexed yet and a listing of its root that never answers
+        let api = FakeKDriveApi::new_default().with_file(ROOT_DIRECTORY_ID, "notes.txt", b"notes");
+        api.stall_next_listing();
+        let engine = Arc::new(Mutex::new(TestEngineBuilder::new().without_metadata().with_api(api).build()));
+        let listing = tokio::spawn(engine.lock().await.get_directory_listing("/".to_string(), 1, ListingPage::default()));
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+        // When: another client asks for something while the listing waits
+        let selection = engine.try_lock().unwrap().get_sync_selection().await;
+
+        // Then: the engine answers it
+        assert_eq!(selection.unwrap(), SyncSelection::default());
+        assert!(!listing.is_finished());
+        listing.abort();
     }
 
     #[test]
@@ -168,7 +889,7 @@ mod tests {
             .build();
 
         // When: determining the current cloud sync state
-        let state = engine.determine_cloud_sync_state();
+        let state = engine.determine_cloud_sync_state().unwrap();
 
         // Then: the engine reports metadata is present
         assert_eq!(state, CloudSyncState::MetadataPresent);
@@ -182,7 +903,7 @@ mod tests {
             .build();
 
         // When: determining the current KDrive sync state
-        let state = engine.determine_cloud_sync_state();
+        let state = engine.determine_cloud_sync_state().unwrap();
 
         // Then: the engine reports that no metadata exists
         assert_eq!(state,CloudSyncState::NoMetadata);
@@ -216,6 +937,67 @@ mod tests {
         assert_eq!(result, true);
     }
 
+    #[test]
+    fn engine_is_not_authenticated_when_tokens_are_expired() {
+        // Given an engine with a token store with expired tokens
+        let engine = TestEngineBuilder::new()
+            .with_expiring_tokens()
+            .build();
+
+        // When is_authenticated is called
+        let result = engine.is_authenticated();
+
+        // Then it returns false, although the engine has tokens
+        assert!(!result);
+        assert!(engine.has_tokens());
+    }
+
+    #[test]
+    fn engine_is_authenticated_when_tokens_are_valid_according_to_the_clock() {
+        // Given an engine whose tokens expire one minute from now
+        let engine = TestEngineBuilder::new()
+            .with_tokens_expiring_at(TEST_NOW + 60)
+            .build();
+
+        // When is_authenticated is called
+        let result = engine.is_authenticated();
+
+        // Then it returns true
+        assert!(result);
+    }
+
+    #[test]
+    fn engine_is_not_authenticated_once_the_clock_passes_the_expiry_time() {
+        // Given an engine whose tokens expired one minute ago
+        let engine = TestEngineBuilder::new()
+            .with_tokens_expiring_at(TEST_NOW + 60)
+            .with_clock(FakeClock::at(TEST_NOW + 120))
+            .build();
+
+        // When is_authenticated is called
+        let result = engine.is_authenticated();
+
+        // Then it returns false
+        assert!(!result);
+    }
+
+    #[test]
+    fn engine_schedules_refresh_before_tokens_expire() {
+        // Given an engine whose tokens expire in one hour
+        let engine = TestEngineBuilder::new()
+            .with_tokens_expiring_at(TEST_NOW + 3600)
+            .build();
+
+        // When the refresh delay is requested
+        let delay = engine.token_refresh_delay();
+
+        // Then the refresh happens the refresh margin before expiry
+        assert_eq!(
+            delay,
+            Some(std::time::Duration::from_secs((3600 - TOKEN_REFRESH_MARGIN_SECONDS) as u64))
+        );
+    }
+
     #[tokio::test]
     async fn engine_returns_auth_url_when_starting_auth_flow() {
         // Given an unauthenticated engine
@@ -241,8 +1023,8 @@ mod tests {
         // what is needed is that the auth flow is completed
         _ = engine.start_initial_auth_flow().await;
 
-        // And continue_initial_auth_flow is called
-        engine.continue_initial_auth_flow().await;
+        // And the auth flow is continued
+        continue_auth_flow(&mut engine).await;
 
         // Then both succeed
         assert!(engine.event_bus.get_events().contains(
@@ -256,8 +1038,8 @@ mod tests {
         let mut engine = TestEngineBuilder::new()
             .build();
 
-        // When continue_initial_auth_flow is called
-        _ = engine.continue_initial_auth_flow().await;
+        // When the auth flow is continued
+        _ = continue_auth_flow(&mut engine).await;
 
         // Then TokensStored event is emitted
         assert!(engine.event_bus.get_events().contains(&crate::domain::events::EngineEvent::AuthFlowCompleted));
@@ -271,8 +1053,8 @@ mod tests {
             .with_auth(adapter)
             .build();
 
-        // When continue_initial_auth_flow fails
-       _  = engine.continue_initial_auth_flow().await;
+        // When continuing the auth flow fails
+       _  = continue_auth_flow(&mut engine).await;
 
 
         // Then AuthFlowFailed event is emitted
@@ -289,9 +1071,294 @@ mod tests {
 
         // When auth flow completes
         _ = engine.start_initial_auth_flow().await;
-        _ = engine.continue_initial_auth_flow().await;
+        _ = continue_auth_flow(&mut engine).await;
 
         // Then tokens are persisted
         assert!(engine.is_authenticated());
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn engine_emits_auth_flow_failed_event_when_no_code_arrives() {
+        // Given an engine that started the auth flow
+        let mut engine = TestEngineBuilder::new()
+            .with_empty_token_store()
+            .build();
+        _ = engine.start_initial_auth_flow().await;
+
+        // When waiting for the authorization code timed out
+        engine.complete_initial_auth_flow(Err(application_error!(AuthFlowTimedOut))).await;
+
+        // Then AuthFlowFailed is emitted with the timeout as reason and no tokens are stored
+        assert!(engine.event_bus.get_events().contains(
+            &EngineEvent::AuthFlowFailed { reason: application_error!(AuthFlowTimedOut) }
+        ));
+        assert!(!engine.has_tokens());
+    }
+
+    #[tokio::test]
+    async fn engine_emits_auth_flow_failed_event_when_auth_flow_is_cancelled() {
+        // Given an engine that started the auth flow
+        let mut engine = TestEngineBuilder::new()
+            .with_empty_token_store()
+            .build();
+        _ = engine.start_initial_auth_flow().await;
+
+        // When the auth flow is cancelled
+        engine.cancel_initial_auth_flow().await;
+
+        // Then AuthFlowFailed is emitted with the cancellation as reason
+        assert!(engine.event_bus.get_events().contains(
+            &EngineEvent::AuthFlowFailed { reason: application_error!(AuthFlowCancelled) }
+        ));
+    }
+
+    #[test]
+    fn engine_schedules_immediate_refresh_for_expiring_tokens() {
+        // Given an engine with tokens that expire now
+        let engine = TestEngineBuilder::new()
+            .with_expiring_tokens()
+            .build();
+
+        // When the refresh delay is requested
+        let delay = engine.token_refresh_delay();
+
+        // Then the tokens should be refreshed right away
+        assert_eq!(delay, Some(std::time::Duration::ZERO));
+    }
+
+    #[test]
+    fn engine_has_no_refresh_delay_without_tokens() {
+        // Given an engine without tokens
+        let engine = TestEngineBuilder::new()
+            .with_empty_token_store()
+            .build();
+
+        // When the refresh delay is requested
+        let delay = engine.token_refresh_delay();
+
+        // Then there is nothing to refresh
+        assert_eq!(delay, None);
+    }
+
+    #[tokio::test]
+    async fn engine_persists_refreshed_tokens() {
+        // Given an engine with tokens that expire now
+        let mut engine = TestEngineBuilder::new()
+            .with_expiring_tokens()
+            .build();
+
+        // When the tokens are refreshed
+        let result = engine.refresh_tokens().await;
+
+        // Then the new tokens are stored
+        assert!(result.is_ok());
+        assert_eq!(engine.token_store.access_token(), Some(TEST_REFRESHED_ACCESS_TOKEN));
+    }
+
+    #[tokio::test]
+    async fn engine_emits_tokens_refreshed_event_when_refreshing_tokens() {
+        // Given an engine with tokens
+        let mut engine = TestEngineBuilder::new()
+            .build();
+
+        // When the tokens are refreshed
+        _ = engine.refresh_tokens().await;
+
+        // Then TokensRefreshed event is emitted
+        assert!(engine.event_bus.get_events().contains(&EngineEvent::TokensRefreshed));
+    }
+
+    #[tokio::test]
+    async fn engine_emits_token_refresh_failed_event_when_refresh_fails() {
+        // Given an engine whose authenticator fails
+        let adapter = FakeAuthenticatorDrivenAdapter::new_default_failing();
+        let mut engine = TestEngineBuilder::new()
+            .with_auth(adapter)
+            .build();
+
+        // When the tokens are refreshed
+        let result = engine.refresh_tokens().await;
+
+        // Then the refresh fails and TokenRefreshFailed event is emitted
+        assert!(result.is_err());
+        assert!(engine.event_bus.get_events().iter().any(|e|
+            matches!(e, EngineEvent::TokenRefreshFailed { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn engine_cannot_refresh_without_tokens() {
+        // Given an engine without tokens
+        let mut engine = TestEngineBuilder::new()
+            .with_empty_token_store()
+            .build();
+
+        // When the tokens are refreshed
+        let result = engine.refresh_tokens().await;
+
+        // Then it fails because the engine is not authenticated
+        let err = result.expect_err("Expected NotAuthenticated error");
+        assert_eq!(err.text_key, common::domain::text_keys::TextKeys::NotAuthenticated);
+    }
+
+    #[tokio::test]
+    async fn engine_drops_a_refresh_that_finishes_after_logout() {
+        // Given a refresh that was started before the user logged out
+        let mut engine = TestEngineBuilder::new()
+            .with_expiring_tokens()
+            .build();
+        let pending = engine.pending_token_refresh().unwrap();
+        engine.logout().await.unwrap();
+
+        // When the token endpoint answers
+        let result = engine.complete_token_refresh(Ok(pending.await));
+
+        // Then the new tokens are not stored
+        assert!(result.is_err());
+        assert!(!engine.has_tokens());
+    }
+
+    #[tokio::test]
+    async fn engine_drops_a_refresh_that_finishes_after_logging_out_and_in_again() {
+        // Given a refresh that is still waiting for the token endpoint
+        let mut engine = TestEngineBuilder::new()
+            .with_expiring_tokens()
+            .build();
+        engine.refresh_tokens().await.unwrap();
+        let pending = engine.pending_token_refresh().unwrap();
+
+        // When the user logs out and in again before the token endpoint answers
+        engine.logout().await.unwrap();
+        engine.complete_initial_auth_flow(Ok(TEST_AUTHORIZATION_CODE.to_string())).await;
+        let result = engine.complete_token_refresh(Ok(pending.await));
+
+        // Then the answer of the old session does not replace the tokens of the new one
+        assert_eq!(result.unwrap_err().text_key, common::domain::text_keys::TextKeys::NotAuthenticated);
+        assert_eq!(engine.token_store.refresh_token(), Some(TEST_RING_REFRESH_TOKEN));
+        assert_eq!(engine.token_store.access_token(), Some(TEST_RING_ACCESS_TOKEN));
+    }
+
+    #[tokio::test]
+    async fn engine_is_not_authenticated_after_logout() {
+        // Given an authenticated engine
+        let mut engine = TestEngineBuilder::new()
+            .build();
+
+        // When the user logs out
+        let result = engine.logout().await;
+
+        // Then the tokens are gone
+        assert!(result.is_ok());
+        assert!(!engine.has_tokens());
+        assert!(!engine.is_authenticated());
+    }
+
+    #[tokio::test]
+    async fn engine_forgets_the_drive_after_logout() {
+        // Given an engine with an indexed drive
+        let mut engine = TestEngineBuilder::new()
+            .build();
+
+        // When the user logs out
+        engine.logout().await.unwrap();
+
+        // Then the next login starts without metadata
+        assert!(!engine.metadata_driven_port.has_metadata().unwrap());
+        assert_eq!(engine.determine_cloud_sync_state().unwrap(), CloudSyncState::NoMetadata);
+    }
+
+    #[tokio::test]
+    async fn engine_emits_logged_out_event_when_logging_out() {
+        // Given an authenticated engine
+        let mut engine = TestEngineBuilder::new()
+            .build();
+
+        // When the user logs out
+        _ = engine.logout().await;
+
+        // Then LoggedOut event is emitted
+        assert!(engine.event_bus.get_events().contains(&EngineEvent::LoggedOut));
+    }
+
+    #[tokio::test]
+    async fn engine_logs_out_locally_when_revocation_fails() {
+        // Given an authenticated engine whose authenticator cannot revoke tokens
+        let adapter = FakeAuthenticatorDrivenAdapter::new_default_failing();
+        let mut engine = TestEngineBuilder::new()
+            .with_auth(adapter)
+            .build();
+
+        // When the user logs out
+        let result = engine.logout().await;
+
+        // Then the local tokens are removed anyway
+        assert!(result.is_ok());
+        assert!(!engine.has_tokens());
+    }
+
+    #[tokio::test]
+    async fn logout_stops_the_running_sync_without_holding_the_engine() {
+        // Given a sync that waits for a download that never answers
+        let api = FakeKDriveApi::new_default().with_file(ROOT_DIRECTORY_ID, "notes.txt", b"notes");
+        api.stall_next_download();
+        let engine = Arc::new(Mutex::new(TestEngineBuilder::new().with_api(api).build()));
+        let sync = tokio::spawn(engine.lock().await.synchronize());
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+        // When the user logs out
+        let logout = engine.lock().await.start_logout().await.unwrap();
+        logout.await.unwrap();
+
+        // Then the sync stopped, the store is cleared and the engine was free all along
+        assert_eq!(sync.await.unwrap().unwrap_err().text_key, SyncStopped);
+        let engine = engine.try_lock().unwrap();
+        assert!(!engine.metadata_driven_port.has_metadata().unwrap());
+        assert!(engine.event_bus.get_events().contains(&EngineEvent::LoggedOut));
+    }
+
+    #[tokio::test]
+    async fn logout_log_in_to_another_drive_and_sync_leaves_the_files_of_the_first_drive_alone() {
+        // Given a sync root that holds the files of the first drive
+        let mut engine = TestEngineBuilder::new()
+            .with_api(FakeKDriveApi::new_default().with_file(ROOT_DIRECTORY_ID, "notes.txt", b"notes"))
+            .build();
+        engine.synchronize().await.unwrap();
+
+        // When the user logs out, logs in to an account with another drive and syncs once it is crawled
+        engine.logout().await.unwrap();
+        engine.api.replace_drive(200);
+        engine.complete_initial_auth_flow(Ok(TEST_AUTHORIZATION_CODE.to_string())).await;
+        assert!(engine.start_crawl().await.unwrap());
+        while engine.crawl_next_directory().await.unwrap() {}
+        let result = engine.synchronize().await;
+
+        // Then the sync is refused and nothing is uploaded to the other drive
+        assert_eq!(result.unwrap_err().text_key, SyncRootOfAnotherDrive);
+        assert!(engine.api.list_all_files(200, ROOT_DIRECTORY_ID).await.unwrap().is_empty());
+        assert_eq!(engine.local_fs.content("notes.txt").unwrap(), b"notes");
+    }
+
+    #[tokio::test]
+    async fn an_emptied_sync_root_can_be_synced_with_another_drive() {
+        // Given a sync root of the first drive that the user emptied after logging in to another drive
+        let mut engine = TestEngineBuilder::new()
+            .with_api(FakeKDriveApi::new_default().with_file(ROOT_DIRECTORY_ID, "notes.txt", b"notes"))
+            .build();
+        engine.synchronize().await.unwrap();
+        engine.logout().await.unwrap();
+        engine.api.replace_drive(200);
+        engine.api.add_file(ROOT_DIRECTORY_ID, "report.pdf", b"report");
+        engine.complete_initial_auth_flow(Ok(TEST_AUTHORIZATION_CODE.to_string())).await;
+        assert!(engine.start_crawl().await.unwrap());
+        while engine.crawl_next_directory().await.unwrap() {}
+        engine.local_fs.remove("notes.txt");
+
+        // When the engine synchronizes
+        engine.synchronize().await.unwrap();
+
+        // Then the sync root belongs to the other drive
+        assert_eq!(engine.local_fs.paths(), ["report.pdf"]);
+        assert_eq!(engine.local_fs.content("report.pdf").unwrap(), b"report");
+        assert_eq!(engine.metadata_driven_port.sync_root_drive().unwrap(), Some(200));
+    }
+}
@@ -1,7 +1,22 @@
 use common::domain::errors::ApplicationError;
+use common::domain::sync_conflict::SyncConflict;
+use crate::domain::metadata::CrawlProgress;
+use crate::domain::sync::resumable_download::DownloadProgress;
 
 #[derive(PartialEq, Clone, Debug)]
 pub enum EngineEvent {
     AuthFlowCompleted,
     AuthFlowFailed { reason: ApplicationError },
+    TokensRefreshed,
+    TokenRefreshFailed { reason: ApplicationError },
+    LoggedOut,
+    CrawlProgressed { progress: CrawlProgress },
+    CrawlCompleted { progress: CrawlProgress },
+    CrawlFailed { reason: ApplicationError },
+    SyncCompleted { operations_completed: usize, operations_failed: usize },
+    SyncFailed { reason: ApplicationError },
+    RemoteChangesDetected { changes: usize },
+    /// Emitted before the SyncCompleted of the sync that found it
+    ConflictDetected { conflict: SyncConflict },
+    DownloadProgressed { progress: DownloadProgress },
 }
\ No newline at end of file
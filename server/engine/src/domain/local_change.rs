@@ -0,0 +1,244 @@
+use crate::domain::sync::sync_path::{is_below, moved_path};
+
+/// What the watcher of the sync root reports, paths are relative to the root
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LocalFsEvent {
+    /// Created or written to
+    Changed { path: String },
+    Removed { path: String },
+    /// The first half of a rename, the half with the same cookie tells where it went
+    MovedFrom { path: String, cookie: usize },
+    MovedTo { path: String, cookie: usize },
+    /// A rename the platform reports in one go
+    Moved { from: String, to: String },
+    /// The platform dropped events, only a full scan tells what changed
+    Overflowed,
+}
+
+/// A change in the sync root after the events of a burst were put together
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LocalChange {
+    Changed { path: String },
+    Removed { path: String },
+    Moved { from: String, to: String },
+    Rescan,
+}
+
+/// Collects the events of a burst, so a file written in many steps is one change and a rename is a move
+/// instead of a delete and a create. The first half of a rename waits one more burst for its second half.
+#[derive(Debug, Default)]
+pub struct LocalChangeBatch {
+    changes: Vec<LocalChange>,
+    moved_from: Vec<(usize, String)>,
+    /// First halves of renames from the burst before
+    carried: Vec<(usize, String)>,
+    rescan: bool,
+}
+
+impl LocalChangeBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.rescan && self.changes.is_empty() && self.moved_from.is_empty()
+    }
+
+    pub fn push(&mut self, event: LocalFsEvent) {
+        if self.rescan {
+            return;
+        }
+        match event {
+            LocalFsEvent::Changed { path } => self.changed(path),
+            LocalFsEvent::Removed { path } => self.removed(path),
+            LocalFsEvent::MovedFrom { path, cookie } => self.moved_from.push((cookie, path)),
+            LocalFsEvent::MovedTo { path, cookie } => match self.first_half(cookie) {
+                Some(from) => self.moved(from, path),
+                // Moved in from outside the sync root
+                None => self.changed(path),
+            },
+            LocalFsEvent::Moved { from, to } => self.moved(from, to),
+            LocalFsEvent::Overflowed => {
+                self.rescan = true;
+                self.changes.clear();
+                self.moved_from.clear();
+                self.carried.clear();
+            }
+        }
+    }
+
+    /// The changes in the order they happened. The first half of a rename of this burst is kept for the next one,
+    /// one that did not get its second half in that burst either moved the file out of the sync root.
+    pub fn take(&mut self) -> Vec<LocalChange> {
+        let rescan = std::mem::take(&mut self.rescan);
+        let mut changes = std::mem::take(&mut self.changes);
+        let gone = std::mem::replace(&mut self.carried, std::mem::take(&mut self.moved_from));
+        if rescan {
+            return vec![LocalChange::Rescan];
+        }
+        changes.extend(gone.into_iter().map(|(_, path)| LocalChange::Removed { path }));
+        changes
+    }
+
+    /// The renames of the changes as (from, to), in the order they happened
+    pub fn moves(changes: &[LocalChange]) -> Vec<(String, String)> {
+        changes.iter()
+            .filter_map(|change| match change {
+                LocalChange::Moved { from, to } => Some((from.clone(), to.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn first_half(&mut self, cookie: usize) -> Option<String> {
+        [&mut self.moved_from, &mut self.carried].into_iter().find_map(|halves| {
+            let index = halves.iter().position(|(pending, _)| *pending == cookie)?;
+            Some(halves.remove(index).1)
+        })
+    }
+
+    fn changed(&mut self, path: String) {
+        let known = self.changes.iter().any(|change| matches!(change, LocalChange::Changed { path: changed } if *changed == path));
+        if known {
+            return;
+        }
+        self.changes.retain(|change| !matches!(change, LocalChange::Removed { path: removed } if *removed == path));
+        self.changes.push(LocalChange::Changed { path });
+    }
+
+    /// Earlier changes at or below the path no longer matter
+    fn removed(&mut self, path: String) {
+        self.changes.retain(|change| match change {
+            LocalChange::Changed { path: changed } | LocalChange::Removed { path: changed } => !covers(&path, changed),
+            LocalChange::Moved { .. } | LocalChange::Rescan => true,
+        });
+        self.changes.push(LocalChange::Removed { path });
+    }
+
+    /// Earlier changes below the old path now happened at the new one
+    fn moved(&mut self, from: String, to: String) {
+        for change in &mut self.changes {
+            if let LocalChange::Changed { path } = change
+                && let Some(new_path) = moved_path(path, &from, &to)
+            {
+                *path = new_path;
+            }
+        }
+        self.changes.push(LocalChange::Moved { from, to });
+    }
+}
+
+fn covers(ancestor: &str, path: &str) -> bool {
+    path == ancestor || is_below(path, ancestor)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::local_change::{LocalChange, LocalChangeBatch, LocalFsEvent};
+
+    fn changed(path: &str) -> LocalFsEvent {
+        LocalFsEvent::Changed { path: path.to_string() }
+    }
+
+    fn removed(path: &str) -> LocalFsEvent {
+        LocalFsEvent::Removed { path: path.to_string() }
+    }
+
+    #[test]
+    fn repeated_writes_are_one_change() {
+        let mut batch = LocalChangeBatch::new();
+
+        batch.push(changed("notes.txt"));
+        batch.push(changed("notes.txt"));
+        batch.push(changed("notes.txt"));
+
+        assert_eq!(batch.take(), [LocalChange::Changed { path: "notes.txt".to_string() }]);
+        assert!(batch.is_empty());
+    }
+
+    #[test]
+    fn removing_a_directory_drops_the_changes_inside_it() {
+        // Given writes inside a directory
+        let mut batch = LocalChangeBatch::new();
+        batch.push(changed("Documents/report.pdf"));
+        batch.push(changed("notes.txt"));
+
+        // When the directory is removed
+        batch.push(removed("Documents"));
+
+        // Then only the removal is left of it
+        assert_eq!(batch.take(), [
+            LocalChange::Changed { path: "notes.txt".to_string() },
+            LocalChange::Removed { path: "Documents".to_string() },
+        ]);
+    }
+
+    #[test]
+    fn rename_halves_with_the_same_cookie_are_a_move() {
+        // Given a file that was written and then renamed
+        let mut batch = LocalChangeBatch::new();
+        batch.push(changed("Documents/draft.txt"));
+        batch.push(LocalFsEvent::MovedFrom { path: "Documents".to_string(), cookie: 7 });
+        batch.push(LocalFsEvent::MovedFrom { path: "notes.txt".to_string(), cookie: 8 });
+
+        // When the second half of the first rename arrives
+        batch.push(LocalFsEvent::MovedTo { path: "Archive".to_string(), cookie: 7 });
+
+        // Then the folder moved with the change inside it and the unpaired half waits for the next burst
+        assert_eq!(batch.take(), [
+            LocalChange::Changed { path: "Archive/draft.txt".to_string() },
+            LocalChange::Moved { from: "Documents".to_string(), to: "Archive".to_string() },
+        ]);
+        assert!(batch.is_empty());
+    }
+
+    #[test]
+    fn rename_halves_in_consecutive_bursts_are_a_move() {
+        // Given a burst that ended between the halves of a rename
+        let mut batch = LocalChangeBatch::new();
+        batch.push(LocalFsEvent::MovedFrom { path: "notes.txt".to_string(), cookie: 8 });
+        assert!(batch.take().is_empty());
+
+        // When the next burst brings the second half
+        batch.push(LocalFsEvent::MovedTo { path: "todo.txt".to_string(), cookie: 8 });
+
+        // Then the file was moved
+        let changes = batch.take();
+        assert_eq!(changes, [LocalChange::Moved { from: "notes.txt".to_string(), to: "todo.txt".to_string() }]);
+        assert_eq!(LocalChangeBatch::moves(&changes), [("notes.txt".to_string(), "todo.txt".to_string())]);
+    }
+
+    #[test]
+    fn a_rename_half_without_a_second_half_in_the_next_burst_left_the_sync_root() {
+        let mut batch = LocalChangeBatch::new();
+        batch.push(LocalFsEvent::MovedFrom { path: "notes.txt".to_string(), cookie: 8 });
+        batch.take();
+
+        batch.push(changed("todo.txt"));
+
+        assert_eq!(batch.take(), [
+            LocalChange::Changed { path: "todo.txt".to_string() },
+            LocalChange::Removed { path: "notes.txt".to_string() },
+        ]);
+    }
+
+    #[test]
+    fn a_file_moved_in_from_outside_is_a_change() {
+        let mut batch = LocalChangeBatch::new();
+
+        batch.push(LocalFsEvent::MovedTo { path: "notes.txt".to_string(), cookie: 3 });
+
+        assert_eq!(batch.take(), [LocalChange::Changed { path: "notes.txt".to_string() }]);
+    }
+
+    #[test]
+    fn lost_events_make_the_whole_batch_a_rescan() {
+        let mut batch = LocalChangeBatch::new();
+        batch.push(changed("notes.txt"));
+
+        batch.push(LocalFsEvent::Overflowed);
+        batch.push(removed("notes.txt"));
+
+        assert_eq!(batch.take(), [LocalChange::Rescan]);
+    }
+}
@@ -0,0 +1,209 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tokio::time::{timeout, Instant};
+use crate::domain::default_values::general_defaults::{LOCAL_CHANGE_MAX_DELAY_SECONDS, LOCAL_CHANGE_QUIET_PERIOD_MILLIS, SYNC_RETRY_SECONDS};
+use crate::domain::local_change::{LocalChangeBatch, LocalFsEvent};
+use crate::ports::driven::local_watcher_driven_port::LocalWatcherDrivenPort;
+use crate::ports::driving::authenticator_driving_port::AuthenticatorDrivingPort;
+use crate::ports::driving::data_driving_port::DataDrivingPort;
+
+/// Syncs when the sync root changes. Events are collected until the sync root has been quiet for a moment,
+/// or for at most the maximum delay while something keeps writing, so one burst of edits is one sync.
+pub struct LocalChangeWatcher {
+    quiet_period: Duration,
+    max_delay: Duration,
+    retry_interval: Duration,
+}
+
+impl LocalChangeWatcher {
+    pub fn new() -> Self {
+        Self::with_intervals(
+            Duration::from_millis(LOCAL_CHANGE_QUIET_PERIOD_MILLIS),
+            Duration::from_secs(LOCAL_CHANGE_MAX_DELAY_SECONDS),
+            Duration::from_secs(SYNC_RETRY_SECONDS),
+        )
+    }
+
+    pub fn with_intervals(quiet_period: Duration, max_delay: Duration, retry_interval: Duration) -> Self {
+        Self { quiet_period, max_delay, retry_interval }
+    }
+
+    /// Ends when the watcher stops sending events, or right away when the sync root cannot be watched
+    pub fn start<E, W>(self, engine: Arc<Mutex<E>>, mut watcher: W) -> JoinHandle<()>
+    where
+        E: DataDrivingPort + AuthenticatorDrivingPort + Send + Sync + 'static,
+        W: LocalWatcherDrivenPort + Send + 'static,
+    {
+        tokio::spawn(async move {
+            let Ok(mut events) = watcher.watch() else {
+                return;
+            };
+            // The sync root may have changed while the service was not running
+            let mut batch = LocalChangeBatch::new();
+            batch.push(LocalFsEvent::Overflowed);
+            let mut local_moves = Vec::new();
+
+            let mut open = true;
+            while open {
+                if batch.is_empty() {
+                    match events.recv().await {
+                        Some(event) => batch.push(event),
+                        None => break,
+                    }
+                }
+
+                let deadline = Instant::now() + self.max_delay;
+                loop {
+                    let wait = self.quiet_period.min(deadline.saturating_duration_since(Instant::now()));
+                    match timeout(wait, events.recv()).await {
+                        Ok(Some(event)) => batch.push(event),
+                        Ok(None) => {
+                            open = false;
+                            break;
+                        }
+                        Err(_) => break,
+                    }
+                }
+
+                // The planner compares both sides in full, the renames tell it what it cannot see from the files
+                local_moves.extend(LocalChangeBatch::moves(&batch.take()));
+                let sync = {
+                    let engine = engine.lock().await;
+                    // The API can only be used once the user logged in
+                    engine.has_tokens().then(|| engine.synchronize_with_moves(local_moves.clone()))
+                };
+                // The engine is unlocked while the files are transferred, so the clients are answered meanwhile
+                let synced = match sync {
+                    Some(sync) => sync.await.is_ok(),
+                    None => false,
+                };
+                // The renames stay until a sync took them in
+                if synced {
+                    local_moves.clear();
+                } else {
+                    batch.push(LocalFsEvent::Overflowed);
+                    tokio::time::sleep(self.retry_interval).await;
+                }
+            }
+        })
+    }
+}
+
+impl Default for LocalChangeWatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::Mutex;
+    use crate::domain::default_values::general_defaults::ROOT_DIRECTORY_ID;
+    use crate::domain::events::EngineEvent;
+    use crate::domain::local_change::LocalFsEvent;
+    use crate::domain::local_change_watcher::LocalChangeWatcher;
+    use crate::domain::test_helpers::fake_event_bus::FakeEventBus;
+    use crate::domain::test_helpers::fake_kdrive_api::FakeKDriveApi;
+    use crate::domain::test_helpers::fake_local_watcher::FakeLocalWatcher;
+    use crate::domain::test_helpers::test_engine_builder::TestEngineBuilder;
+    use crate::ports::driving::authenticator_driving_port::AuthenticatorDrivingPort;
+
+    fn fast_watcher() -> LocalChangeWatcher {
+        LocalChangeWatcher::with_intervals(Duration::from_millis(20), Duration::from_millis(200), Duration::from_millis(10))
+    }
+
+    fn changed(path: &str) -> LocalFsEvent {
+        LocalFsEvent::Changed { path: path.to_string() }
+    }
+
+    fn syncs(event_bus: &FakeEventBus) -> usize {
+        event_bus.get_events().iter().filter(|event| matches!(event, EngineEvent::SyncCompleted { .. })).count()
+    }
+
+    #[tokio::test]
+    async fn a_burst_of_changes_is_one_sync() {
+        // Given a running watcher that did its first sync
+        let event_bus = FakeEventBus::new();
+        let engine = TestEngineBuilder::new()
+            .with_api(FakeKDriveApi::new_default().with_file(ROOT_DIRECTORY_ID, "notes.txt", b"notes"))
+            .with_event_bus(event_bus.clone())
+            .build();
+        let watcher = FakeLocalWatcher::new();
+        let events = watcher.events();
+        let handle = fast_watcher().start(Arc::new(Mutex::new(engine)), watcher);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // When a file is written several times in a row
+        for _ in 0..5 {
+            events.send(changed("notes.txt")).unwrap();
+            tokio::time::sleep(Duration::from_millis(2)).await;
+        }
+        drop(events);
+        handle.await.unwrap();
+
+        // Then the startup sync is followed by a single one for the burst
+        assert_eq!(syncs(&event_bus), 2);
+    }
+
+    #[tokio::test]
+    async fn syncing_is_retried_until_the_drive_is_indexed() {
+        let event_bus = FakeEventBus::new();
+        let engine = TestEngineBuilder::new()
+            .without_index()
+            .with_event_bus(event_bus.clone())
+            .build();
+
+        let watcher = FakeLocalWatcher::new();
+        let _events = watcher.events();
+
+        let handle = fast_watcher().start(Arc::new(Mutex::new(engine)), watcher);
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        handle.abort();
+
+        let events = event_bus.get_events();
+        assert!(events.len() > 1);
+        assert!(events.iter().all(|event| matches!(event, EngineEvent::SyncFailed { .. })));
+    }
+
+    #[tokio::test]
+    async fn nothing_is_synced_before_the_user_logs_in() {
+        let event_bus = FakeEventBus::new();
+        let engine = TestEngineBuilder::new()
+            .with_empty_token_store()
+            .with_event_bus(event_bus.clone())
+            .build();
+        let watcher = FakeLocalWatcher::new();
+        let events = watcher.events();
+
+        let handle = fast_watcher().start(Arc::new(Mutex::new(engine)), watcher);
+        events.send(changed("notes.txt")).unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        handle.abort();
+
+        assert!(event_bus.get_events().is_empty());
+    }
+    #[tokio::test]
+    async fn the_engine_is_not_locked_while_syncing() {
+        // Given a watcher whose first sync hangs on a download
+        let api = FakeKDriveApi::new_default().with_file(ROOT_DIRECTORY_ID, "notes.txt", b"notes");
+        api.stall_next_download();
+        let event_bus = FakeEventBus::new();
+        let engine = Arc::new(Mutex::new(TestEngineBuilder::new().with_api(api).with_event_bus(event_bus.clone()).build()));
+        let watcher = FakeLocalWatcher::new();
+        let _events = watcher.events();
+        let handle = fast_watcher().start(engine.clone(), watcher);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // When a client asks the engine something meanwhile
+        let answer = tokio::time::timeout(Duration::from_millis(50), async { engine.lock().await.has_tokens() }).await;
+        handle.abort();
+
+        // Then it is answered while the sync is still running
+        assert_eq!(answer, Ok(true));
+        assert_eq!(syncs(&event_bus), 0);
+    }
+}
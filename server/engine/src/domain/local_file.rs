@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+use crate::domain::remote_file::FileKind;
+
+/// A file or directory in the sync root as the disk reports it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LocalFile {
+    /// Relative to the sync root with `/` between the names, the sync root itself is the empty path
+    pub path: String,
+    pub kind: FileKind,
+    /// Directories have no size
+    pub size: Option<u64>,
+    /// Seconds since the Unix epoch
+    pub modified_at: i64,
+}
+
+impl LocalFile {
+    pub fn is_directory(&self) -> bool {
+        self.kind == FileKind::Directory
+    }
+}
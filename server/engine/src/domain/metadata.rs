@@ -1 +1,49 @@
-// pub struct Metadata {}
\ No newline at end of file
+use serde::{Deserialize, Serialize};
+use common::domain::directory_listing::SyncStatus;
+use crate::domain::remote_file::RemoteFile;
+
+/// Where a file stands between the local sync folder and kDrive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SyncState {
+    /// Known remotely, not yet on disk
+    PendingDownload,
+    /// Changed on disk, not yet sent to kDrive
+    PendingUpload,
+    /// The same on disk and on kDrive
+    Synced,
+    /// Changed on both sides since the last sync
+    Conflict,
+}
+
+/// What the service remembers about a file or directory on kDrive
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileMetadata {
+    pub remote: RemoteFile,
+    pub sync_state: SyncState,
+}
+
+impl FileMetadata {
+    pub fn new(remote: RemoteFile, sync_state: SyncState) -> Self {
+        Self { remote, sync_state }
+    }
+}
+
+impl From<SyncState> for SyncStatus {
+    fn from(sync_state: SyncState) -> Self {
+        match sync_state {
+            SyncState::PendingDownload => SyncStatus::PendingDownload,
+            SyncState::PendingUpload => SyncStatus::PendingUpload,
+            SyncState::Synced => SyncStatus::Synced,
+            SyncState::Conflict => SyncStatus::Conflict,
+        }
+    }
+}
+
+/// How far the crawl of the drive has come, counted from what the store holds so it survives a restart
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrawlProgress {
+    pub directories_visited: u64,
+    pub directories_pending: u64,
+    pub files_found: u64,
+    pub bytes_total: u64,
+}
@@ -0,0 +1,247 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use crate::domain::default_values::general_defaults::{CRAWL_CHECK_INTERVAL_SECONDS, CRAWL_RETRY_SECONDS};
+use crate::ports::driving::authenticator_driving_port::AuthenticatorDrivingPort;
+use crate::ports::driving::data_driving_port::DataDrivingPort;
+
+/// Fills the metadata store with the whole drive, one directory at a time so other requests get a turn.
+/// The queue lives in the store, a crawl that was interrupted by a restart picks up where it stopped.
+/// Once the drive is indexed it keeps checking, a logout empties the store and the next login crawls again.
+pub struct MetadataCrawler {
+    check_interval: Duration,
+    retry_interval: Duration,
+}
+
+impl MetadataCrawler {
+    pub fn new() -> Self {
+        Self::with_intervals(
+            Duration::from_secs(CRAWL_CHECK_INTERVAL_SECONDS),
+            Duration::from_secs(CRAWL_RETRY_SECONDS),
+        )
+    }
+
+    pub fn with_intervals(check_interval: Duration, retry_interval: Duration) -> Self {
+        Self { check_interval, retry_interval }
+    }
+
+    /// Runs until the task is aborted
+    pub fn start<E>(self, engine: Arc<Mutex<E>>) -> JoinHandle<()>
+    where
+        E: DataDrivingPort + AuthenticatorDrivingPort + Send + Sync + 'static,
+    {
+        tokio::spawn(async move {
+            let mut started = false;
+            loop {
+                let step = {
+                    let engine = engine.lock().await;
+                    // The API can only be used once the user logged in
+                    if !engine.has_tokens() {
+                        None
+                    } else if started {
+                        Some(engine.crawl_next_directory().await)
+                    } else {
+                        Some(engine.start_crawl().await)
+                    }
+                };
+
+                match step {
+                    // A crawl that was going on belongs to the user who logged out
+                    None => {
+                        started = false;
+                        tokio::time::sleep(self.check_interval).await;
+                    }
+                    Some(Ok(true)) => started = true,
+                    Some(Ok(false)) => {
+                        started = false;
+                        tokio::time::sleep(self.check_interval).await;
+                    }
+                    Some(Err(_)) => tokio::time::sleep(self.retry_interval).await,
+                }
+            }
+        })
+    }
+}
+
+impl Default for MetadataCrawler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::Mutex;
+    use common::application_error;
+    use common::domain::text_keys::TextKeys::ApiServerError;
+    use crate::domain::default_values::general_defaults::ROOT_DIRECTORY_ID;
+    use crate::domain::events::EngineEvent;
+    use crate::domain::metadata::{CrawlProgress, FileMetadata, SyncState};
+    use crate::domain::metadata_crawler::MetadataCrawler;
+    use crate::domain::test_helpers::fake_authenticator_adapter::TEST_AUTHORIZATION_CODE;
+    use crate::domain::test_helpers::fake_event_bus::FakeEventBus;
+    use crate::domain::test_helpers::fake_kdrive_api::{FakeKDriveApi, TEST_DRIVE_ID};
+    use crate::domain::test_helpers::fake_metadata_store::FakeMetadataStore;
+    use crate::domain::test_helpers::test_engine_builder::TestEngineBuilder;
+    use crate::ports::driven::kdrive_api_driven_port::KDriveApiDrivenPort;
+    use crate::ports::driven::metadata_driven_port::MetadataDrivenPort;
+    use crate::ports::driving::authenticator_driving_port::AuthenticatorDrivingPort;
+
+    fn fast_crawler() -> MetadataCrawler {
+        MetadataCrawler::with_intervals(Duration::from_millis(10), Duration::from_millis(10))
+    }
+
+    fn drive() -> FakeKDriveApi {
+        let api = FakeKDriveApi::new_default();
+        let documents = api.add_directory(ROOT_DIRECTORY_ID, "Documents");
+        api.add_file(documents, "report.pdf", b"report");
+        api.add_file(ROOT_DIRECTORY_ID, "notes.txt", b"notes");
+        api
+    }
+
+    #[tokio::test]
+    async fn crawler_indexes_the_whole_drive() {
+        // Given a logged in user without metadata
+        let event_bus = FakeEventBus::new();
+        let engine = TestEngineBuilder::new()
+            .without_metadata()
+            .with_api(drive())
+            .with_event_bus(event_bus.clone())
+            .build();
+
+        // When the crawler runs until the drive is indexed
+        let handle = fast_crawler().start(Arc::new(Mutex::new(engine)));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        handle.abort();
+
+        // Then progress was reported per directory and the totals cover every file
+        let events = event_bus.get_events();
+        assert_eq!(events.len(), 4);
+        assert_eq!(events.last(), Some(&EngineEvent::CrawlCompleted {
+            progress: CrawlProgress {
+                directories_visited: 2,
+                directories_pending: 0,
+                files_found: 2,
+                bytes_total: 11,
+            },
+        }));
+    }
+
+    #[tokio::test]
+    async fn crawler_continues_an_interrupted_crawl() {
+        // Given a store where the root was crawled before the service stopped
+        let api = drive();
+        let root = api.get_file(TEST_DRIVE_ID, ROOT_DIRECTORY_ID).await.unwrap();
+        let root_children: Vec<_> = api.list_files(TEST_DRIVE_ID, ROOT_DIRECTORY_ID, None).await.unwrap()
+            .files
+            .into_iter()
+            .map(|file| FileMetadata::new(file, SyncState::PendingDownload))
+            .collect();
+        let metadata = FakeMetadataStore::new().without_index();
+        metadata.start_crawl(&FileMetadata::new(root, SyncState::PendingDownload)).unwrap();
+        metadata.save_crawled_directory(ROOT_DIRECTORY_ID, &root_children).unwrap();
+
+        let event_bus = FakeEventBus::new();
+        let engine = TestEngineBuilder::new()
+            .with_metadata_store(metadata)
+            .with_api(api)
+            .with_event_bus(event_bus.clone())
+            .build();
+
+        // When the crawler starts again
+        let handle = fast_crawler().start(Arc::new(Mutex::new(engine)));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        handle.abort();
+
+        // Then it only visits the directory that was still queued
+        let events = event_bus.get_events();
+        assert_eq!(events[0], EngineEvent::CrawlProgressed {
+            progress: CrawlProgress { directories_visited: 1, directories_pending: 1, files_found: 1, bytes_total: 5 },
+        });
+        assert_eq!(events.len(), 3);
+        assert!(matches!(events[1], EngineEvent::CrawlProgressed { progress } if progress.directories_visited == 2));
+    }
+
+    #[tokio::test]
+    async fn crawler_waits_for_the_user_to_log_in() {
+        let event_bus = FakeEventBus::new();
+        let engine = TestEngineBuilder::new()
+            .with_empty_token_store()
+            .without_metadata()
+            .with_event_bus(event_bus.clone())
+            .build();
+
+        let handle = fast_crawler().start(Arc::new(Mutex::new(engine)));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        handle.abort();
+
+        assert!(event_bus.get_events().is_empty());
+    }
+
+    #[tokio::test]
+    async fn crawler_does_nothing_when_the_drive_is_indexed() {
+        let event_bus = FakeEventBus::new();
+        let engine = TestEngineBuilder::new()
+            .with_event_bus(event_bus.clone())
+            .build();
+
+        let handle = fast_crawler().start(Arc::new(Mutex::new(engine)));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        handle.abort();
+
+        assert!(event_bus.get_events().is_empty());
+    }
+
+    #[tokio::test]
+    async fn crawler_crawls_again_after_the_user_logs_in_again() {
+        // Given a crawler that indexed the drive
+        let event_bus = FakeEventBus::new();
+        let engine = Arc::new(Mutex::new(TestEngineBuilder::new()
+            .without_metadata()
+            .with_api(drive())
+            .with_event_bus(event_bus.clone())
+            .build()));
+        let handle = fast_crawler().start(engine.clone());
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // When the user logs out and logs in again
+        {
+            let mut engine = engine.lock().await;
+            engine.logout().await.unwrap();
+            engine.complete_initial_auth_flow(Ok(TEST_AUTHORIZATION_CODE.to_string())).await;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        handle.abort();
+
+        // Then the drive is crawled once more
+        let crawls = event_bus.get_events()
+            .iter()
+            .filter(|event| matches!(event, EngineEvent::CrawlCompleted { .. }))
+            .count();
+        assert_eq!(crawls, 2);
+    }
+
+    #[tokio::test]
+    async fn crawler_retries_after_a_failure() {
+        // Given a drive that cannot be reached
+        let event_bus = FakeEventBus::new();
+        let engine = TestEngineBuilder::new()
+            .without_metadata()
+            .with_api(FakeKDriveApi::new_default().failing_with(application_error!(ApiServerError)))
+            .with_event_bus(event_bus.clone())
+            .build();
+
+        // When the crawler runs for a while
+        let handle = fast_crawler().start(Arc::new(Mutex::new(engine)));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        handle.abort();
+
+        // Then every attempt reports the failure
+        let events = event_bus.get_events();
+        assert!(events.len() > 1);
+        assert!(events.iter().all(|event| matches!(event, EngineEvent::CrawlFailed { .. })));
+    }
+}
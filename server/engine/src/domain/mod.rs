@@ -5,5 +5,17 @@ pub mod default_values;
 pub mod tokens;
 pub mod configuration;
 pub mod events;
+pub mod token_refresh_scheduler;
+pub mod auth_flow_runner;
+pub mod metadata_crawler;
+pub mod drive_query;
+pub mod remote_file;
+pub mod local_file;
+pub mod sync;
+pub mod local_change;
+pub mod local_change_watcher;
+pub mod remote_poller;
 mod cloud_sync_state;
-mod metadata;
+pub mod metadata;
+pub mod upload_session;
+pub mod content_hash;
@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+use common::domain::directory_listing::EntryKind;
+
+pub type DriveId = u64;
+pub type FileId = u64;
+
+/// A kDrive the user has access to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Drive {
+    pub id: DriveId,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FileKind {
+    File,
+    Directory,
+}
+
+impl From<FileKind> for EntryKind {
+    fn from(kind: FileKind) -> Self {
+        match kind {
+            FileKind::File => EntryKind::File,
+            FileKind::Directory => EntryKind::Directory,
+        }
+    }
+}
+
+/// A file or directory as kDrive knows it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RemoteFile {
+    pub id: FileId,
+    pub parent_id: FileId,
+    pub name: String,
+    pub kind: FileKind,
+    /// Directories have no size
+    pub size: Option<u64>,
+    /// Seconds since the Unix epoch
+    pub last_modified_at: i64,
+    pub hash: Option<String>,
+}
+
+impl RemoteFile {
+    pub fn is_directory(&self) -> bool {
+        self.kind == FileKind::Directory
+    }
+}
+
+/// One page of a directory listing, the cursor asks for the next page
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct FilePage {
+    pub files: Vec<RemoteFile>,
+    pub cursor: Option<String>,
+    pub has_more: bool,
+}
+
+/// Something that happened to a file on kDrive, with the file as it is afterwards. Renames are moves.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RemoteChange {
+    Created(RemoteFile),
+    Updated(RemoteFile),
+    Moved(RemoteFile),
+    /// Moved to the trash, a directory takes everything below it along
+    Deleted(FileId),
+}
+
+/// The changes on a drive since a cursor, in the order they happened. The cursor points after the last one.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct ChangePage {
+    pub changes: Vec<RemoteChange>,
+    pub cursor: String,
+    pub has_more: bool,
+}
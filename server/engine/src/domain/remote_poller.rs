@@ -0,0 +1,179 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use common::domain::errors::ApplicationError;
+use common::domain::text_keys::TextKeys::ApiRateLimited;
+use crate::domain::default_values::general_defaults::{REMOTE_POLL_INTERVAL_SECONDS, REMOTE_POLL_MAX_BACKOFF_SECONDS, SYNC_RETRY_SECONDS};
+use crate::ports::driving::authenticator_driving_port::AuthenticatorDrivingPort;
+use crate::ports::driving::data_driving_port::{DataDrivingPort, PendingSync};
+
+/// Asks kDrive what changed at a fixed interval and syncs when something did. When kDrive says it gets too
+/// many requests the wait doubles every time, up to the maximum, until a poll goes through again.
+pub struct RemotePoller {
+    interval: Duration,
+    retry_interval: Duration,
+    max_backoff: Duration,
+}
+
+impl RemotePoller {
+    pub fn new() -> Self {
+        Self::with_intervals(
+            Duration::from_secs(REMOTE_POLL_INTERVAL_SECONDS),
+            Duration::from_secs(SYNC_RETRY_SECONDS),
+            Duration::from_secs(REMOTE_POLL_MAX_BACKOFF_SECONDS),
+        )
+    }
+
+    pub fn with_intervals(interval: Duration, retry_interval: Duration, max_backoff: Duration) -> Self {
+        Self { interval, retry_interval, max_backoff }
+    }
+
+    /// Runs until the task is aborted
+    pub fn start<E>(self, engine: Arc<Mutex<E>>) -> JoinHandle<()>
+    where
+        E: DataDrivingPort + AuthenticatorDrivingPort + Send + Sync + 'static,
+    {
+        tokio::spawn(async move {
+            let mut delay = self.interval;
+            loop {
+                tokio::time::sleep(delay).await;
+                let poll = {
+                    let engine = engine.lock().await;
+                    // The API can only be used once the user logged in
+                    engine.has_tokens().then(|| engine.poll_remote_changes())
+                };
+                let result = match poll {
+                    Some(poll) => Some(sync_changes(&engine, poll).await),
+                    None => None,
+                };
+                delay = self.next_delay(delay, result.as_ref());
+            }
+        })
+    }
+
+    fn next_delay(&self, delay: Duration, result: Option<&Result<(), ApplicationError>>) -> Duration {
+        match result {
+            Some(Err(error)) if error.text_key == ApiRateLimited => (delay.max(self.interval) * 2).min(self.max_backoff),
+            Some(Err(_)) => self.retry_interval,
+            Some(Ok(())) | None => self.interval,
+        }
+    }
+}
+
+impl Default for RemotePoller {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The engine is only locked to take the sync, kDrive is asked and the files are transferred without it
+async fn sync_changes<E: DataDrivingPort>(engine: &Mutex<E>, poll: PendingSync<usize>) -> Result<(), ApplicationError> {
+    if poll.await? > 0 {
+        let sync = engine.lock().await.synchronize();
+        sync.await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::Mutex;
+    use common::application_error;
+    use common::domain::text_keys::TextKeys::{ApiRateLimited, ApiServerError};
+    use crate::domain::default_values::general_defaults::ROOT_DIRECTORY_ID;
+    use crate::domain::events::EngineEvent;
+    use crate::domain::remote_poller::RemotePoller;
+    use crate::domain::test_helpers::fake_event_bus::FakeEventBus;
+    use crate::domain::test_helpers::fake_kdrive_api::FakeKDriveApi;
+    use crate::domain::test_helpers::test_engine_builder::TestEngineBuilder;
+    use crate::ports::driving::authenticator_driving_port::AuthenticatorDrivingPort;
+
+    fn fast_poller() -> RemotePoller {
+        RemotePoller::with_intervals(Duration::from_millis(10), Duration::from_millis(10), Duration::from_millis(80))
+    }
+
+    #[tokio::test]
+    async fn remote_changes_are_synced() {
+        // Given an indexed drive with a file the sync root does not have yet
+        let event_bus = FakeEventBus::new();
+        let engine = TestEngineBuilder::new()
+            .with_api(FakeKDriveApi::new_default().with_file(ROOT_DIRECTORY_ID, "notes.txt", b"notes"))
+            .with_event_bus(event_bus.clone())
+            .build();
+
+        // When the poller runs for a while
+        let handle = fast_poller().start(Arc::new(Mutex::new(engine)));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        handle.abort();
+
+        // Then the change was announced and synced once, later polls found nothing new
+        let events: Vec<_> = event_bus.get_events()
+            .into_iter()
+            .filter(|event| !matches!(event, EngineEvent::DownloadProgressed { .. }))
+            .collect();
+        assert_eq!(events, [
+            EngineEvent::RemoteChangesDetected { changes: 1 },
+            EngineEvent::SyncCompleted { operations_completed: 1, operations_failed: 0 },
+        ]);
+    }
+
+    #[tokio::test]
+    async fn nothing_is_polled_before_the_user_logs_in() {
+        let event_bus = FakeEventBus::new();
+        let engine = TestEngineBuilder::new()
+            .with_empty_token_store()
+            .with_api(FakeKDriveApi::new_default().with_file(ROOT_DIRECTORY_ID, "notes.txt", b"notes"))
+            .with_event_bus(event_bus.clone())
+            .build();
+
+        let handle = fast_poller().start(Arc::new(Mutex::new(engine)));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        handle.abort();
+
+        assert!(event_bus.get_events().is_empty());
+    }
+
+    #[tokio::test]
+    async fn the_engine_is_not_locked_while_syncing() {
+        // Given a poller whose sync hangs on a download
+        let api = FakeKDriveApi::new_default().with_file(ROOT_DIRECTORY_ID, "notes.txt", b"notes");
+        api.stall_next_download();
+        let event_bus = FakeEventBus::new();
+        let engine = Arc::new(Mutex::new(TestEngineBuilder::new().with_api(api).with_event_bus(event_bus.clone()).build()));
+        let handle = fast_poller().start(engine.clone());
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // When a client asks the engine something meanwhile
+        let answer = tokio::time::timeout(Duration::from_millis(50), async { engine.lock().await.has_tokens() }).await;
+        handle.abort();
+
+        // Then it is answered while the sync is still running
+        assert_eq!(answer, Ok(true));
+        assert!(!event_bus.get_events().iter().any(|event| matches!(event, EngineEvent::SyncCompleted { .. })));
+    }
+
+    #[test]
+    fn rate_limiting_doubles_the_wait_up_to_the_maximum() {
+        let poller = fast_poller();
+        let rate_limited = Err(application_error!(ApiRateLimited));
+
+        let first = poller.next_delay(Duration::from_millis(10), Some(&rate_limited));
+        let second = poller.next_delay(first, Some(&rate_limited));
+        let capped = poller.next_delay(Duration::from_millis(60), Some(&rate_limited));
+
+        assert_eq!(first, Duration::from_millis(20));
+        assert_eq!(second, Duration::from_millis(40));
+        assert_eq!(capped, Duration::from_millis(80));
+    }
+
+    #[test]
+    fn a_poll_that_goes_through_resets_the_wait() {
+        let poller = fast_poller();
+
+        assert_eq!(poller.next_delay(Duration::from_millis(80), Some(&Ok(()))), Duration::from_millis(10));
+        assert_eq!(poller.next_delay(Duration::from_millis(80), Some(&Err(application_error!(ApiServerError)))), Duration::from_millis(10));
+    }
+}
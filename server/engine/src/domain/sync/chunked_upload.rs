@@ -0,0 +1,144 @@
+use std::collections::BTreeSet;
+use futures_util::future::try_join_all;
+use tokio::io::AsyncReadExt;
+use common::application_error;
+use common::domain::errors::ApplicationError;
+use common::domain::text_keys::TextKeys::{ApiNotFound, LocalFileError};
+use crate::domain::default_values::configurator_defaults::{DEFAULT_UPLOAD_CHUNK_SIZE, DEFAULT_UPLOAD_PARALLELISM};
+use crate::domain::local_file::LocalFile;
+use crate::domain::remote_file::{DriveId, RemoteFile};
+use crate::domain::upload_session::{chunk_count, chunk_hash, UploadSession, UploadTarget};
+use crate::ports::driven::kdrive_api_driven_port::KDriveApiDrivenPort;
+use crate::ports::driven::local_fs_driven_port::{LocalFileReader, LocalFsDrivenPort};
+use crate::ports::driven::metadata_driven_port::MetadataDrivenPort;
+
+/// Sends a file to kDrive in chunks through an upload session. Every chunk kDrive confirms is stored with the
+/// session, so an upload that was interrupted by a crash or a dropped connection only sends what is missing.
+pub struct ChunkedUpload<'a, MetadataPort, ApiPort, LocalPort> {
+    metadata: &'a MetadataPort,
+    api: &'a ApiPort,
+    local: &'a LocalPort,
+    drive_id: DriveId,
+    chunk_size: u64,
+    parallelism: usize,
+}
+
+impl<'a, MetadataPort, ApiPort, LocalPort> ChunkedUpload<'a, MetadataPort, ApiPort, LocalPort>
+where
+    MetadataPort: MetadataDrivenPort + Sync,
+    ApiPort: KDriveApiDrivenPort + Sync,
+    LocalPort: LocalFsDrivenPort + Sync,
+{
+    pub fn new(metadata: &'a MetadataPort, api: &'a ApiPort, local: &'a LocalPort, drive_id: DriveId) -> Self {
+        Self {
+            metadata,
+            api,
+            local,
+            drive_id,
+            chunk_size: DEFAULT_UPLOAD_CHUNK_SIZE,
+            parallelism: DEFAULT_UPLOAD_PARALLELISM,
+        }
+    }
+
+    /// Sends up to `parallelism` chunks of `chunk_size` bytes at the same time, both are at least 1
+    pub fn with_chunks(mut self, chunk_size: u64, parallelism: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self.parallelism = parallelism.max(1);
+        self
+    }
+
+    /// Expects `local` as the file is now, a stored session that was started for other content is cancelled.
+    /// A failed upload keeps its session for the next attempt unless kDrive no longer knows it.
+    pub async fn run(&self, local: &LocalFile, target: UploadTarget) -> Result<RemoteFile, ApplicationError> {
+        let session = self.session(local, target).await?;
+        let result = self.send(&session).await;
+        match &result {
+            Ok(_) => self.metadata.delete_upload_session(&session.path)?,
+            // kDrive dropped the session, it expired or its target is gone
+            Err(error) if error.text_key == ApiNotFound => self.metadata.delete_upload_session(&session.path)?,
+            Err(_) => {}
+        }
+        result
+    }
+
+    async fn session(&self, local: &LocalFile, target: UploadTarget) -> Result<UploadSession, ApplicationError> {
+        if let Some(session) = self.metadata.upload_session(&local.path)? {
+            if session.matches(local, &target, self.chunk_size) {
+                return Ok(session);
+            }
+            self.abandon(&session).await?;
+        }
+
+        let total_size = local.size.unwrap_or(0);
+        let total_chunks = chunk_count(total_size, self.chunk_size);
+        let token = self.api.start_upload_session(self.drive_id, &target, total_size, total_chunks).await?;
+        let session = UploadSession {
+            path: local.path.clone(),
+            token,
+            target,
+            total_size,
+            chunk_size: self.chunk_size,
+            modified_at: local.modified_at,
+            uploaded_chunks: BTreeSet::new(),
+        };
+        self.metadata.save_upload_session(&session)?;
+        Ok(session)
+    }
+
+    async fn send(&self, session: &UploadSession) -> Result<RemoteFile, ApplicationError> {
+        let mut reader = self.local.open_file(&session.path).await?;
+        let mut batch = Vec::new();
+        for chunk_number in 1..=session.chunk_count() {
+            let length = session.chunk_length(chunk_number);
+            // The chunks kDrive has are skipped but still read, the file can only be read from the start
+            let content = if session.uploaded_chunks.contains(&chunk_number) {
+                let skipped = tokio::io::copy(&mut (&mut reader).take(length), &mut tokio::io::sink())
+                    .await
+                    .map_err(|e| application_error!(LocalFileError, "{}: {}", session.path, e))?;
+                (skipped == length).then(Vec::new)
+            } else {
+                let content = read_chunk(&mut reader, length, &session.path).await?;
+                (content.len() as u64 == length).then_some(content)
+            };
+
+            let Some(content) = content else {
+                self.abandon(session).await?;
+                return Err(application_error!(LocalFileError, "{} changed during its upload", session.path));
+            };
+            if !session.uploaded_chunks.contains(&chunk_number) {
+                batch.push((chunk_number, content));
+            }
+            if batch.len() == self.parallelism {
+                self.send_batch(session, std::mem::take(&mut batch)).await?;
+            }
+        }
+        self.send_batch(session, batch).await?;
+
+        self.api.finish_upload_session(self.drive_id, &session.token).await
+    }
+
+    async fn send_batch(&self, session: &UploadSession, batch: Vec<(u64, Vec<u8>)>) -> Result<(), ApplicationError> {
+        try_join_all(batch.into_iter().map(|(chunk_number, content)| async move {
+            let hash = chunk_hash(&content);
+            self.api.upload_chunk(self.drive_id, &session.token, chunk_number, content, &hash).await?;
+            self.metadata.save_uploaded_chunk(&session.path, chunk_number)
+        })).await?;
+        Ok(())
+    }
+
+    /// kDrive may have dropped the session already, it is forgotten either way
+    async fn abandon(&self, session: &UploadSession) -> Result<(), ApplicationError> {
+        self.api.cancel_upload_session(self.drive_id, &session.token).await.ok();
+        self.metadata.delete_upload_session(&session.path)
+    }
+}
+
+async fn read_chunk(reader: &mut LocalFileReader, length: u64, path: &str) -> Result<Vec<u8>, ApplicationError> {
+    let mut content = Vec::with_capacity(length as usize);
+    reader
+        .take(length)
+        .read_to_end(&mut content)
+        .await
+        .map_err(|e| application_error!(LocalFileError, "{}: {}", path, e))?;
+    Ok(content)
+}
@@ -0,0 +1,94 @@
+use std::collections::{BTreeSet, HashMap};
+use crate::domain::default_values::general_defaults::ROOT_DIRECTORY_ID;
+use crate::domain::remote_file::{FileId, RemoteFile};
+use crate::domain::sync::sync_path::join_path;
+
+/// Files of the drive found by id and by their path from the root of the drive
+pub struct FileTree {
+    files: HashMap<FileId, RemoteFile>,
+    paths: HashMap<FileId, String>,
+    ids: HashMap<String, FileId>,
+}
+
+impl FileTree {
+    /// Leaves out the root itself and every file whose parent is not in the tree
+    pub fn new(files: impl IntoIterator<Item = RemoteFile>) -> Self {
+        let files: HashMap<FileId, RemoteFile> = files.into_iter()
+            .filter(|file| file.id != file.parent_id)
+            .map(|file| (file.id, file))
+            .collect();
+
+        let mut paths = HashMap::new();
+        for id in files.keys() {
+            resolve_path(*id, &files, &mut paths);
+        }
+        let paths: HashMap<FileId, String> = paths.into_iter()
+            .filter_map(|(id, path)| path.map(|path| (id, path)))
+            .collect();
+        let ids = paths.iter().map(|(id, path)| (path.clone(), *id)).collect();
+
+        Self {
+            files: files.into_iter().filter(|(id, _)| paths.contains_key(id)).collect(),
+            paths,
+            ids,
+        }
+    }
+
+    pub fn get(&self, id: FileId) -> Option<&RemoteFile> {
+        self.files.get(&id)
+    }
+
+    pub fn contains(&self, id: FileId) -> bool {
+        self.files.contains_key(&id)
+    }
+
+    pub fn path(&self, id: FileId) -> Option<&str> {
+        self.paths.get(&id).map(String::as_str)
+    }
+
+    pub fn id_at(&self, path: &str) -> Option<FileId> {
+        self.ids.get(path).copied()
+    }
+
+    /// Every file with its path, sorted by path so parents come before their children
+    pub fn sorted(&self) -> Vec<(&str, &RemoteFile)> {
+        let mut sorted: Vec<_> = self.paths.iter()
+            .map(|(id, path)| (path.as_str(), &self.files[id]))
+            .collect();
+        sorted.sort_by_key(|(path, _)| *path);
+        sorted
+    }
+
+    pub fn child_names(&self, id: FileId) -> BTreeSet<&str> {
+        self.files.values()
+            .filter(|file| file.parent_id == id)
+            .map(|file| file.name.as_str())
+            .collect()
+    }
+}
+
+/// Walks up to the root, a file under a missing parent or in a cycle has no path
+fn resolve_path(id: FileId, files: &HashMap<FileId, RemoteFile>, paths: &mut HashMap<FileId, Option<String>>) {
+    let mut chain = Vec::new();
+    let mut current = id;
+    let mut prefix = loop {
+        if current == ROOT_DIRECTORY_ID {
+            break Some(String::new());
+        }
+        if let Some(known) = paths.get(&current) {
+            break known.clone();
+        }
+        match files.get(&current) {
+            Some(file) if !chain.contains(&current) => {
+                chain.push(current);
+                current = file.parent_id;
+            }
+            _ => break None,
+        }
+    };
+
+    for id in chain.iter().rev() {
+        prefix = prefix.map(|prefix| join_path(&prefix, &files[id].name));
+        paths.insert(*id, prefix.clone());
+    }
+}
@@ -0,0 +1,322 @@
+use std::collections::BTreeMap;
+use common::domain::errors::ApplicationError;
+use crate::domain::default_values::general_defaults::IGNORE_FILE_NAME;
+use crate::domain::sync::sync_path::join_path;
+use crate::ports::driven::local_fs_driven_port::LocalFsDrivenPort;
+
+/// Which paths the sync leaves alone, from the global patterns of the configuration and the `.kdriveignore` files
+/// in the sync root. The files use the format of `.gitignore`: one pattern per line, `#` starts a comment, `!`
+/// includes again what an earlier pattern ignored, a trailing `/` only matches directories and a pattern with a `/`
+/// anywhere else is relative to the directory of its file. `*`, `?`, `[...]` and `**` work as they do in git.
+///
+/// The last pattern that matches decides, the global ones come first and a file in a directory comes after the
+/// files of the directories above it. Nothing inside an ignored directory can be included again.
+#[derive(Debug, Default)]
+pub struct IgnoreRules {
+    global: Vec<IgnorePattern>,
+    /// The patterns of each `.kdriveignore` by the path of its directory
+    files: BTreeMap<String, Vec<IgnorePattern>>,
+}
+
+#[derive(Debug)]
+struct IgnorePattern {
+    /// Where the pattern applies, the root for the global patterns
+    directory: String,
+    /// Matched against the names of a path relative to `directory`, `**` stands for any number of names
+    segments: Vec<String>,
+    negated: bool,
+    directory_only: bool,
+}
+
+impl IgnoreRules {
+    pub fn new(global: &[String]) -> Self {
+        Self {
+            global: global.iter().filter_map(|line| IgnorePattern::parse(line, "")).collect(),
+            files: BTreeMap::new(),
+        }
+    }
+
+    /// Adds the patterns of the `.kdriveignore` in `directory`
+    pub fn add_ignore_file(&mut self, directory: &str, content: &str) {
+        let patterns = content.lines().filter_map(|line| IgnorePattern::parse(line, directory)).collect();
+        self.files.insert(directory.to_string(), patterns);
+    }
+
+    /// Reads the `.kdriveignore` of a directory in the sync root, a directory without one adds nothing
+    pub async fn load_directory<LocalPort>(&mut self, local: &LocalPort, directory: &str) -> Result<(), ApplicationError>
+    where
+        LocalPort: LocalFsDrivenPort + Sync,
+    {
+        let path = join_path(directory, IGNORE_FILE_NAME);
+        if local.stat(&path).await?.is_some_and(|file| !file.is_directory()) {
+            let content = local.read_file(&path).await?;
+            self.add_ignore_file(directory, &String::from_utf8_lossy(&content));
+        }
+        Ok(())
+    }
+
+    /// For a path relative to the sync root or the root of the drive, they are the same
+    pub fn is_ignored(&self, path: &str, is_directory: bool) -> bool {
+        let names: Vec<&str> = path.split('/').filter(|name| !name.is_empty()).collect();
+        (1..=names.len()).any(|end| self.matches(&names[..end].join("/"), end < names.len() || is_directory))
+    }
+
+    /// Whether the last pattern that matches the path itself ignores it
+    fn matches(&self, path: &str, is_directory: bool) -> bool {
+        self.global.iter()
+            .chain(self.files.values().flatten())
+            .rev()
+            .find(|pattern| pattern.matches(path, is_directory))
+            .is_some_and(|pattern| !pattern.negated)
+    }
+}
+
+impl IgnorePattern {
+    fn parse(line: &str, directory: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, pattern) = match line.strip_prefix('!') {
+            Some(pattern) => (true, pattern),
+            None => (false, line),
+        };
+        // A backslash lets a pattern start with a `#` or a `!` of its own
+        let pattern = pattern.strip_prefix('\\').filter(|rest| rest.starts_with(['#', '!'])).unwrap_or(pattern);
+
+        let directory_only = pattern.ends_with('/');
+        let pattern = pattern.trim_end_matches('/');
+        let anchored = pattern.contains('/');
+        let mut segments: Vec<String> = pattern.split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(str::to_string)
+            .collect();
+        if segments.is_empty() {
+            return None;
+        }
+        if !anchored {
+            segments.insert(0, "**".to_string());
+        }
+        Some(Self { directory: directory.to_string(), segments, negated, directory_only })
+    }
+
+    fn matches(&self, path: &str, is_directory: bool) -> bool {
+        if self.directory_only && !is_directory {
+            return false;
+        }
+        let relative = if self.directory.is_empty() {
+            Some(path)
+        } else {
+            path.strip_prefix(self.directory.as_str()).and_then(|rest| rest.strip_prefix('/'))
+        };
+        relative.is_some_and(|relative| {
+            let names: Vec<&str> = relative.split('/').collect();
+            segments_match(&self.segments, &names)
+        })
+    }
+}
+
+fn segments_match(segments: &[String], names: &[&str]) -> bool {
+    match segments.split_first() {
+        None => names.is_empty(),
+        // A trailing `**` matches what is inside a directory, not the directory itself
+        Some((segment, [])) if segment == "**" => !names.is_empty(),
+        Some((segment, rest)) if segment == "**" => (0..=names.len()).any(|skipped| segments_match(rest, &names[skipped..])),
+        Some((segment, rest)) => names.split_first().is_some_and(|(name, names)| {
+            let pattern: Vec<char> = segment.chars().collect();
+            let name: Vec<char> = name.chars().collect();
+            glob_matches(&pattern, &name) && segments_match(rest, names)
+        }),
+    }
+}
+
+/// Matches a single name, `*` and `?` never match a `/` as there is none in a name
+fn glob_matches(pattern: &[char], name: &[char]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0..=name.len()).any(|skipped| glob_matches(rest, &name[skipped..])),
+        Some(('?', rest)) => !name.is_empty() && glob_matches(rest, &name[1..]),
+        Some(('[', rest)) => match character_class(rest) {
+            Some((class, rest)) => name.first().is_some_and(|c| class.contains(*c)) && glob_matches(rest, &name[1..]),
+            // Without a closing bracket it is an ordinary character
+            None => name.first() == Some(&'[') && glob_matches(rest, &name[1..]),
+        },
+        Some(('\\', [escaped, rest @ ..])) => name.first() == Some(escaped) && glob_matches(rest, &name[1..]),
+        Some((c, rest)) => name.first() == Some(c) && glob_matches(rest, &name[1..]),
+    }
+}
+
+/// The characters of a `[...]` with ranges like `a-z`, a leading `!` or `^` turns it around
+struct CharacterClass {
+    ranges: Vec<(char, char)>,
+    negated: bool,
+}
+
+impl CharacterClass {
+    fn contains(&self, c: char) -> bool {
+        self.ranges.iter().any(|(from, to)| (*from..=*to).contains(&c)) != self.negated
+    }
+}
+
+/// Parses what follows a `[`, returns the class and the rest of the pattern after its `]`
+fn character_class(pattern: &[char]) -> Option<(CharacterClass, &[char])> {
+    let (negated, mut rest) = match pattern.split_first() {
+        Some(('!' | '^', rest)) => (true, rest),
+        _ => (false, pattern),
+    };
+    let mut ranges = Vec::new();
+    let mut first = true;
+    loop {
+        match rest {
+            [] => return None,
+            // A `]` right at the start is part of the class
+            [']', after @ ..] if !first => return Some((CharacterClass { ranges, negated }, after)),
+            [from, '-', to, after @ ..] if *to != ']' => {
+                ranges.push((*from, *to));
+                rest = after;
+            }
+            [c, after @ ..] => {
+                ranges.push((*c, *c));
+                rest = after;
+            }
+        }
+        first = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn global(patterns: &[&str]) -> IgnoreRules {
+        IgnoreRules::new(&patterns.iter().map(|pattern| pattern.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn a_name_without_a_slash_is_ignored_at_any_depth() {
+        let rules = global(&["*.swp", "Thumbs.db"]);
+
+        assert!(rules.is_ignored(".notes.txt.swp", false));
+        assert!(rules.is_ignored("Photos/2024/Thumbs.db", false));
+        assert!(!rules.is_ignored("Photos/thumbs.db.jpg", false));
+    }
+
+    #[test]
+    fn comments_and_blank_lines_are_skipped() {
+        let mut rules = IgnoreRules::default();
+        rules.add_ignore_file("", "# build output\n\n  \n\\#notes.txt\nbuild/\n");
+
+        assert!(!rules.is_ignored("# build output", false));
+        assert!(rules.is_ignored("#notes.txt", false));
+        assert!(rules.is_ignored("build", true));
+    }
+
+    #[test]
+    fn a_pattern_with_a_trailing_slash_only_matches_directories() {
+        let rules = global(&["logs/"]);
+
+        assert!(rules.is_ignored("App/logs", true));
+        assert!(rules.is_ignored("App/logs/today.txt", false));
+        assert!(!rules.is_ignored("App/logs", false));
+    }
+
+    #[test]
+    fn a_pattern_with_a_slash_is_relative_to_its_file() {
+        // Given an ignore file in Projects with an anchored pattern
+        let mut rules = IgnoreRules::default();
+        rules.add_ignore_file("Projects", "/out\ndocs/*.pdf\n");
+
+        // Then it only matches right below Projects
+        assert!(rules.is_ignored("Projects/out", true));
+        assert!(!rules.is_ignored("Projects/App/out", true));
+        assert!(!rules.is_ignored("out", true));
+        assert!(rules.is_ignored("Projects/docs/manual.pdf", false));
+        assert!(!rules.is_ignored("Projects/docs/old/manual.pdf", false));
+    }
+
+    #[test]
+    fn the_patterns_of_a_file_do_not_apply_outside_its_directory() {
+        let mut rules = IgnoreRules::default();
+        rules.add_ignore_file("Projects", "*.tmp\n");
+
+        assert!(rules.is_ignored("Projects/App/cache.tmp", false));
+        assert!(!rules.is_ignored("cache.tmp", false));
+        assert!(!rules.is_ignored("Projects old/cache.tmp", false));
+    }
+
+    #[test]
+    fn a_later_pattern_wins_over_an_earlier_one() {
+        // Given a file that ignores all logs but one and then that one again in a directory
+        let mut rules = IgnoreRules::default();
+        rules.add_ignore_file("", "*.log\n!keep.log\nDebug/keep.log\n");
+
+        // Then the last pattern that matches decides
+        assert!(rules.is_ignored("server.log", false));
+        assert!(!rules.is_ignored("keep.log", false));
+        assert!(!rules.is_ignored("Release/keep.log", false));
+        assert!(rules.is_ignored("Debug/keep.log", false));
+    }
+
+    #[test]
+    fn an_ignore_file_wins_over_the_global_patterns() {
+        // Given the global patterns ignore Thumbs.db and the root includes it again
+        let mut rules = global(&["Thumbs.db", "*.bak"]);
+        rules.add_ignore_file("", "!Thumbs.db\n");
+
+        // Then only what the ignore file leaves alone stays ignored
+        assert!(!rules.is_ignored("Photos/Thumbs.db", false));
+        assert!(rules.is_ignored("Photos/old.bak", false));
+    }
+
+    #[test]
+    fn a_deeper_ignore_file_wins_over_the_ones_above_it() {
+        // Given the root ignores the logs and Server includes them again while its Archive ignores them once more
+        let mut rules = IgnoreRules::default();
+        rules.add_ignore_file("Server/Archive", "*.log\n");
+        rules.add_ignore_file("", "*.log\n");
+        rules.add_ignore_file("Server", "!*.log\n");
+
+        // Then the file closest to the path decides
+        assert!(rules.is_ignored("app.log", false));
+        assert!(!rules.is_ignored("Server/app.log", false));
+        assert!(rules.is_ignored("Server/Archive/app.log", false));
+    }
+
+    #[test]
+    fn nothing_inside_an_ignored_directory_can_be_included_again() {
+        // Given a directory that is ignored and one of its files that is included again
+        let mut rules = global(&["node_modules/"]);
+        rules.add_ignore_file("", "!node_modules/app/index.js\n");
+        rules.add_ignore_file("App/node_modules", "!*\n");
+
+        // Then the file stays ignored with its directory
+        assert!(rules.is_ignored("node_modules/app/index.js", false));
+        assert!(rules.is_ignored("App/node_modules/app.js", false));
+    }
+
+    #[test]
+    fn a_double_star_matches_any_number_of_directories() {
+        let mut rules = IgnoreRules::default();
+        rules.add_ignore_file("", "docs/**/draft.md\ncache/**\n");
+
+        assert!(rules.is_ignored("docs/draft.md", false));
+        assert!(rules.is_ignored("docs/a/b/draft.md", false));
+        assert!(!rules.is_ignored("other/docs/draft.md", false));
+        assert!(rules.is_ignored("cache/images/a.png", false));
+        assert!(!rules.is_ignored("cache", true));
+    }
+
+    #[test]
+    fn wildcards_and_classes_match_within_a_name() {
+        let rules = global(&["report-?.txt", "*.[oa]", "data[!0-9].csv", "[broken"]);
+
+        assert!(rules.is_ignored("report-1.txt", false));
+        assert!(!rules.is_ignored("report-12.txt", false));
+        assert!(rules.is_ignored("src/main.o", false));
+        assert!(rules.is_ignored("lib/libz.a", false));
+        assert!(!rules.is_ignored("src/main.c", false));
+        assert!(rules.is_ignored("datax.csv", false));
+        assert!(!rules.is_ignored("data1.csv", false));
+        assert!(rules.is_ignored("[broken", false));
+    }
+}
@@ -0,0 +1,11 @@
+pub mod chunked_upload;
+pub mod file_tree;
+pub mod ignore_rules;
+pub mod remote_change_tracker;
+pub mod resumable_download;
+pub mod selection_filter;
+pub mod sync_job;
+pub mod sync_operation;
+pub mod sync_path;
+pub mod sync_planner;
+pub mod synchronizer;
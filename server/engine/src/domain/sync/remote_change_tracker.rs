@@ -0,0 +1,265 @@
+use std::collections::{BTreeMap, HashSet};
+use common::domain::errors::ApplicationError;
+use common::domain::text_keys::TextKeys::ApiNotFound;
+use crate::domain::default_values::general_defaults::ROOT_DIRECTORY_ID;
+use crate::domain::metadata::{FileMetadata, SyncState};
+use crate::domain::remote_file::{DriveId, FileId, RemoteChange, RemoteFile};
+use crate::ports::driven::kdrive_api_driven_port::KDriveApiDrivenPort;
+use crate::ports::driven::metadata_driven_port::MetadataDrivenPort;
+
+/// Follows the changes on kDrive into the metadata store, so the store is the remote side of the next sync
+/// without walking the whole drive every time.
+pub struct RemoteChangeTracker<'a, MetadataPort, ApiPort> {
+    metadata: &'a MetadataPort,
+    api: &'a ApiPort,
+    drive_id: DriveId,
+}
+
+impl<'a, MetadataPort, ApiPort> RemoteChangeTracker<'a, MetadataPort, ApiPort>
+where
+    MetadataPort: MetadataDrivenPort + Sync,
+    ApiPort: KDriveApiDrivenPort + Sync,
+{
+    pub fn new(metadata: &'a MetadataPort, api: &'a ApiPort, drive_id: DriveId) -> Self {
+        Self { metadata, api, drive_id }
+    }
+
+    /// Stores what changed on kDrive since the last poll and returns for how many files the store changed.
+    /// Without a cursor, or with one kDrive no longer knows, the whole drive is walked once.
+    pub async fn poll(&self) -> Result<usize, ApplicationError> {
+        let Some(cursor) = self.metadata.remote_cursor()? else {
+            return self.rebuild().await;
+        };
+        match self.follow(cursor).await {
+            Err(error) if error.text_key == ApiNotFound => self.rebuild().await,
+            result => result,
+        }
+    }
+
+    /// Every page is stored with its cursor, so an interrupted poll continues where it stopped
+    async fn follow(&self, mut cursor: String) -> Result<usize, ApplicationError> {
+        let mut changed = 0;
+        loop {
+            let page = self.api.list_changes(self.drive_id, Some(&cursor)).await?;
+            // Only the last change of a file in the page matters
+            let mut latest = BTreeMap::new();
+            for change in page.changes {
+                match change {
+                    RemoteChange::Created(file) | RemoteChange::Updated(file) | RemoteChange::Moved(file) => {
+                        latest.insert(file.id, Some(file));
+                    }
+                    RemoteChange::Deleted(id) => {
+                        latest.insert(id, None);
+                    }
+                }
+            }
+            changed += self.apply(latest, &page.cursor)?;
+            cursor = page.cursor;
+            if !page.has_more {
+                return Ok(changed);
+            }
+        }
+    }
+
+    /// The cursor is taken before the walk, changes made during it come again with the next poll
+    async fn rebuild(&self) -> Result<usize, ApplicationError> {
+        let cursor = self.api.list_changes(self.drive_id, None).await?.cursor;
+        let mut latest: BTreeMap<FileId, Option<RemoteFile>> = self.metadata.all_files()?
+            .into_iter()
+            .map(|file| (file.remote.id, None))
+            .collect();
+        latest.extend(self.remote_files().await?.into_iter().map(|file| (file.id, Some(file))));
+        self.apply(latest, &cursor)
+    }
+
+    /// Everything on the drive, one directory at a time
+    async fn remote_files(&self) -> Result<Vec<RemoteFile>, ApplicationError> {
+        let mut files = Vec::new();
+        let mut pending = vec![ROOT_DIRECTORY_ID];
+        while let Some(directory_id) = pending.pop() {
+            let children = self.api.list_all_files(self.drive_id, directory_id).await?;
+            pending.extend(children.iter().filter(|child| child.is_directory()).map(|child| child.id));
+            files.extend(children);
+        }
+        Ok(files)
+    }
+
+    /// A file that is already stored as it is on kDrive, like one the sync itself uploaded, is no change
+    fn apply(&self, mut latest: BTreeMap<FileId, Option<RemoteFile>>, cursor: &str) -> Result<usize, ApplicationError> {
+        drop_below_deleted(&mut latest);
+        let mut saved = Vec::new();
+        let mut deleted = Vec::new();
+        for (id, file) in latest {
+            let stored = self.metadata.get_file(id)?;
+            match (file, stored) {
+                (Some(file), Some(stored)) if stored.remote == file => {}
+                (Some(file), stored) => {
+                    let sync_state = match stored {
+                        Some(stored) if stored.sync_state == SyncState::Conflict || same_content(&stored.remote, &file) => {
+                            stored.sync_state
+                        }
+                        _ => SyncState::PendingDownload,
+                    };
+                    saved.push(FileMetadata::new(file, sync_state));
+                }
+                (None, Some(_)) if id != ROOT_DIRECTORY_ID => deleted.push(id),
+                (None, _) => {}
+            }
+        }
+        self.metadata.apply_remote_changes(&saved, &deleted, cursor)?;
+        Ok(saved.len() + deleted.len())
+    }
+}
+
+/// A file that changed before its directory was deleted went along with the directory
+fn drop_below_deleted(latest: &mut BTreeMap<FileId, Option<RemoteFile>>) {
+    let mut gone: HashSet<FileId> = latest.iter().filter(|(_, file)| file.is_none()).map(|(id, _)| *id).collect();
+    loop {
+        let below: Vec<FileId> = latest.values()
+            .flatten()
+            .filter(|file| gone.contains(&file.parent_id))
+            .map(|file| file.id)
+            .collect();
+        if below.is_empty() {
+            return;
+        }
+        for id in below {
+            latest.remove(&id);
+            gone.insert(id);
+        }
+    }
+}
+
+/// Moves and renames leave the content alone
+fn same_content(stored: &RemoteFile, file: &RemoteFile) -> bool {
+    stored.kind == file.kind
+        && stored.size == file.size
+        && stored.last_modified_at == file.last_modified_at
+        && stored.hash == file.hash
+}
+
+#[cfg(test)]
+mod tests {
+    use common::application_error;
+    use common::domain::text_keys::TextKeys::ApiNotFound;
+    use crate::domain::default_values::general_defaults::ROOT_DIRECTORY_ID;
+    use crate::domain::metadata::SyncState;
+    use crate::domain::sync::remote_change_tracker::RemoteChangeTracker;
+    use crate::domain::test_helpers::fake_kdrive_api::{FakeKDriveApi, TEST_DRIVE_ID};
+    use crate::domain::test_helpers::fake_metadata_store::FakeMetadataStore;
+    use crate::ports::driven::kdrive_api_driven_port::KDriveApiDrivenPort;
+    use crate::ports::driven::metadata_driven_port::MetadataDrivenPort;
+
+    async fn poll(metadata: &FakeMetadataStore, api: &FakeKDriveApi) -> usize {
+        RemoteChangeTracker::new(metadata, api, TEST_DRIVE_ID).poll().await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn first_poll_walks_the_drive_and_keeps_the_cursor() {
+        // Given: a drive with a folder and a file and an empty store
+        let api = FakeKDriveApi::new_default();
+        let documents = api.add_directory(ROOT_DIRECTORY_ID, "Documents");
+        let report = api.add_file(documents, "report.pdf", b"report");
+        let metadata = FakeMetadataStore::new();
+
+        // When: polling for the first time
+        let changed = poll(&metadata, &api).await;
+
+        // Then: both are stored to be downloaded and the next poll starts from here
+        assert_eq!(changed, 2);
+        assert_eq!(metadata.get_file(report).unwrap().unwrap().sync_state, SyncState::PendingDownload);
+        assert!(metadata.remote_cursor().unwrap().is_some());
+        assert_eq!(poll(&metadata, &api).await, 0);
+    }
+
+    #[tokio::test]
+    async fn creates_updates_moves_and_deletes_reach_the_store() {
+        // Given: a polled drive that changes afterwards
+        let api = FakeKDriveApi::new_default().with_page_size(2);
+        let documents = api.add_directory(ROOT_DIRECTORY_ID, "Documents");
+        let notes = api.add_file(ROOT_DIRECTORY_ID, "notes.txt", b"notes");
+        let report = api.add_file(documents, "report.pdf", b"report");
+        let metadata = FakeMetadataStore::new();
+        poll(&metadata, &api).await;
+        metadata.set_sync_state(notes, SyncState::Synced).unwrap();
+        let photo = api.add_file(ROOT_DIRECTORY_ID, "photo.jpg", b"photo");
+        api.change_file(report, b"final report");
+        api.rename_file(TEST_DRIVE_ID, notes, "todo.txt").await.unwrap();
+        api.delete_file(TEST_DRIVE_ID, documents).await.unwrap();
+
+        // When: polling again over several pages
+        let changed = poll(&metadata, &api).await;
+
+        // Then: the store follows kDrive and a rename does not ask for a download
+        assert_eq!(changed, 4);
+        assert!(metadata.get_file(photo).unwrap().is_some());
+        assert!(metadata.get_file(documents).unwrap().is_none());
+        assert!(metadata.get_file(report).unwrap().is_none());
+        let todo = metadata.get_file(notes).unwrap().unwrap();
+        assert_eq!(todo.remote.name, "todo.txt");
+        assert_eq!(todo.sync_state, SyncState::Synced);
+    }
+
+    #[tokio::test]
+    async fn a_file_changed_before_its_folder_was_deleted_is_not_brought_back() {
+        // Given: a polled drive where a file is edited and then its folder deleted
+        let api = FakeKDriveApi::new_default();
+        let documents = api.add_directory(ROOT_DIRECTORY_ID, "Documents");
+        let report = api.add_file(documents, "report.pdf", b"report");
+        let metadata = FakeMetadataStore::new();
+        poll(&metadata, &api).await;
+        api.change_file(report, b"final report");
+        api.delete_file(TEST_DRIVE_ID, documents).await.unwrap();
+
+        // When: both changes come in the same page
+        poll(&metadata, &api).await;
+
+        // Then: the file went with its folder
+        assert!(metadata.get_file(documents).unwrap().is_none());
+        assert!(metadata.get_file(report).unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn a_conflict_stays_a_conflict_when_kdrive_changes_again() {
+        let api = FakeKDriveApi::new_default();
+        let notes = api.add_file(ROOT_DIRECTORY_ID, "notes.txt", b"notes");
+        let metadata = FakeMetadataStore::new();
+        poll(&metadata, &api).await;
+        metadata.set_sync_state(notes, SyncState::Conflict).unwrap();
+
+        api.change_file(notes, b"more notes");
+        poll(&metadata, &api).await;
+
+        assert_eq!(metadata.get_file(notes).unwrap().unwrap().sync_state, SyncState::Conflict);
+    }
+
+    #[tokio::test]
+    async fn an_unknown_cursor_walks_the_drive_again() {
+        // Given: a store whose cursor kDrive no longer knows and a file that is gone from kDrive
+        let api = FakeKDriveApi::new_default();
+        let notes = api.add_file(ROOT_DIRECTORY_ID, "notes.txt", b"notes");
+        let metadata = FakeMetadataStore::new();
+        poll(&metadata, &api).await;
+        api.delete_file(TEST_DRIVE_ID, notes).await.unwrap();
+        metadata.apply_remote_changes(&[], &[], "expired").unwrap();
+
+        // When: polling
+        let changed = poll(&metadata, &api).await;
+
+        // Then: the walk finds out the file is gone
+        assert_eq!(changed, 1);
+        assert!(metadata.get_file(notes).unwrap().is_none());
+        assert_ne!(metadata.remote_cursor().unwrap(), Some("expired".to_string()));
+    }
+
+    #[tokio::test]
+    async fn a_failing_poll_keeps_the_store() {
+        let metadata = FakeMetadataStore::new();
+        let api = FakeKDriveApi::new_default().failing_with(application_error!(ApiNotFound));
+
+        let result = RemoteChangeTracker::new(&metadata, &api, TEST_DRIVE_ID).poll().await;
+
+        assert!(result.is_err());
+        assert_eq!(metadata.remote_cursor().unwrap(), None);
+    }
+}
@@ -0,0 +1,167 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, ReadBuf};
+use common::application_error;
+use common::domain::errors::ApplicationError;
+use common::domain::text_keys::TextKeys::{ApiRequestFailed, DownloadCorrupted, LocalFileChanged, LocalFileError};
+use crate::domain::content_hash::{can_verify, read_hash};
+use crate::domain::default_values::general_defaults::DOWNLOAD_PROGRESS_INTERVAL_BYTES;
+use crate::domain::local_file::LocalFile;
+use crate::domain::remote_file::{DriveId, FileId, RemoteFile};
+use crate::ports::driven::kdrive_api_driven_port::{KDriveApiDrivenPort, RemoteFileReader};
+use crate::ports::driven::local_fs_driven_port::LocalFsDrivenPort;
+use crate::ports::driven::metadata_driven_port::MetadataDrivenPort;
+
+/// How far the download of a file is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DownloadProgress {
+    pub file_id: FileId,
+    pub path: String,
+    pub bytes_downloaded: u64,
+    /// Unknown when kDrive does not report the size
+    pub bytes_total: Option<u64>,
+}
+
+pub type DownloadProgressListener<'a> = &'a (dyn Fn(DownloadProgress) + Sync);
+
+/// Downloads a file into a partial file next to where it goes. A download that is interrupted keeps what arrived
+/// and continues from there the next time, as long as kDrive still has the version it started from. The file only
+/// replaces what is in the sync root once it matches the size and hash kDrive reports, and only when the sync root
+/// still has what the sync expected there.
+pub struct ResumableDownload<'a, MetadataPort, ApiPort, LocalPort> {
+    metadata: &'a MetadataPort,
+    api: &'a ApiPort,
+    local: &'a LocalPort,
+    drive_id: DriveId,
+    on_progress: Option<DownloadProgressListener<'a>>,
+    replacing: Option<&'a LocalFile>,
+}
+
+impl<'a, MetadataPort, ApiPort, LocalPort> ResumableDownload<'a, MetadataPort, ApiPort, LocalPort>
+where
+    MetadataPort: MetadataDrivenPort + Sync,
+    ApiPort: KDriveApiDrivenPort + Sync,
+    LocalPort: LocalFsDrivenPort + Sync,
+{
+    pub fn new(metadata: &'a MetadataPort, api: &'a ApiPort, local: &'a LocalPort, drive_id: DriveId) -> Self {
+        Self { metadata, api, local, drive_id, on_progress: None, replacing: None }
+    }
+
+    /// What the sync planned with at the path, none when the file is new there
+    pub fn replacing(mut self, replacing: Option<&'a LocalFile>) -> Self {
+        self.replacing = replacing;
+        self
+    }
+
+    /// Told when the download starts or resumes, about every megabyte and at the end
+    pub fn with_progress(mut self, on_progress: Option<DownloadProgressListener<'a>>) -> Self {
+        self.on_progress = on_progress;
+        self
+    }
+
+    /// A partial file that does not match `file` in the end is discarded, the next download starts over
+    pub async fn run(&self, file: &RemoteFile, path: &str) -> Result<(), ApplicationError> {
+        let version = remote_version(file);
+        let mut downloaded = self.local.partial_size(path).await?;
+        // Left over from content kDrive replaced since, the rest of the new version would not fit what is there
+        let replaced = self.metadata.partial_download(path)?.as_deref() != Some(version.as_str());
+        if downloaded > 0 && (replaced || file.size.is_some_and(|size| downloaded > size)) {
+            self.local.discard_partial(path).await?;
+            downloaded = 0;
+        }
+        if downloaded == 0 {
+            self.metadata.save_partial_download(path, &version)?;
+        }
+
+        // An empty file is downloaded anyway, that creates its partial file
+        if downloaded == 0 || file.size != Some(downloaded) {
+            self.report(file, path, downloaded);
+            let content = self.api.download_file_from(self.drive_id, file.id, downloaded).await?;
+            let mut content = ProgressReader::new(content, |read| self.report(file, path, downloaded + read));
+            self.local.append_partial(path, &mut content).await?;
+        }
+
+        self.verify(file, path).await?;
+        self.ensure_unchanged(path).await?;
+        self.local.finish_partial(path).await?;
+        self.metadata.delete_partial_download(path)
+    }
+
+    async fn verify(&self, file: &RemoteFile, path: &str) -> Result<(), ApplicationError> {
+        let size = self.local.partial_size(path).await?;
+        if let Some(expected) = file.size.filter(|expected| size < *expected) {
+            // The connection ended without an error, what arrived is kept for the next attempt
+            return Err(application_error!(ApiRequestFailed, "{} stopped after {} of {} bytes", path, size, expected));
+        }
+
+        let size_matches = file.size.is_none_or(|expected| size == expected);
+        let hash_matches = match file.hash.as_deref() {
+            Some(expected) if size_matches && can_verify(expected) => self.partial_hash(path).await? == expected,
+            _ => true,
+        };
+        if !(size_matches && hash_matches) {
+            self.local.discard_partial(path).await?;
+            return Err(application_error!(DownloadCorrupted, path));
+        }
+        Ok(())
+    }
+
+    /// An edit made in the sync root since the sync was planned is not overwritten, the next sync sees it
+    async fn ensure_unchanged(&self, path: &str) -> Result<(), ApplicationError> {
+        if self.local.stat(path).await?.as_ref() != self.replacing {
+            return Err(application_error!(LocalFileChanged, path));
+        }
+        Ok(())
+    }
+
+    async fn partial_hash(&self, path: &str) -> Result<String, ApplicationError> {
+        let mut reader = self.local.open_partial(path).await?;
+        read_hash(&mut reader).await.map_err(|e| application_error!(LocalFileError, "{}: {}", path, e))
+    }
+
+    fn report(&self, file: &RemoteFile, path: &str, bytes_downloaded: u64) {
+        if let Some(on_progress) = self.on_progress {
+            on_progress(DownloadProgress { file_id: file.id, path: path.to_string(), bytes_downloaded, bytes_total: file.size });
+        }
+    }
+}
+
+/// What tells the versions of a file on kDrive apart, its hash or else when it was last changed
+fn remote_version(file: &RemoteFile) -> String {
+    match &file.hash {
+        Some(hash) => hash.clone(),
+        None => format!("modified:{}", file.last_modified_at),
+    }
+}
+
+/// Passes on the number of bytes read so far, once per interval and at the end
+struct ProgressReader<OnRead> {
+    inner: RemoteFileReader,
+    read: u64,
+    reported: u64,
+    on_read: OnRead,
+}
+
+impl<OnRead: Fn(u64)> ProgressReader<OnRead> {
+    fn new(inner: RemoteFileReader, on_read: OnRead) -> Self {
+        Self { inner, read: 0, reported: 0, on_read }
+    }
+}
+
+impl<OnRead: Fn(u64) + Unpin> AsyncRead for ProgressReader<OnRead> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<std::io::Result<()>> {
+        let before = buf.filled().len();
+        let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = poll {
+            let this = &mut *self;
+            let read = (buf.filled().len() - before) as u64;
+            this.read += read;
+            let at_end = read == 0 && this.read > this.reported;
+            if at_end || this.read - this.reported >= DOWNLOAD_PROGRESS_INTERVAL_BYTES {
+                this.reported = this.read;
+                (this.on_read)(this.read);
+            }
+        }
+        poll
+    }
+}
@@ -0,0 +1,207 @@
+use std::collections::{BTreeMap, HashSet};
+use common::domain::sync_selection::SyncSelection;
+use crate::domain::local_file::LocalFile;
+use crate::domain::remote_file::{FileId, RemoteFile};
+use crate::domain::sync::file_tree::FileTree;
+use crate::domain::sync::sync_path::is_below;
+
+/// Narrows both sides of a sync down to the folders the user selected.
+///
+/// A folder that is left out is taken off the side of kDrive, so the planner removes the copy a previous sync
+/// made of it. That only happens once nothing in the copy still has to go to kDrive, until then the folder stays
+/// in the sync. What the sync root has at the path of a folder that was never synced is left alone.
+pub struct SelectionFilter<'a> {
+    selection: &'a SyncSelection,
+    base: &'a FileTree,
+}
+
+impl<'a> SelectionFilter<'a> {
+    pub fn new(selection: &'a SyncSelection, base: &'a FileTree) -> Self {
+        Self { selection, base }
+    }
+
+    pub fn apply(&self, remote: Vec<RemoteFile>, mut local: BTreeMap<String, LocalFile>) -> (FileTree, BTreeMap<String, LocalFile>) {
+        let tree = FileTree::new(remote.iter().cloned());
+        if self.selection.rules.is_empty() {
+            return (tree, local);
+        }
+
+        // The selected files and the folders on the way to them, the rest is left out as a whole
+        let mut kept: HashSet<FileId> = HashSet::new();
+        for (_, file) in tree.sorted() {
+            if self.selection.includes(ancestry(&tree, file.id)) {
+                let mut current = Some(file);
+                while let Some(file) = current.filter(|file| kept.insert(file.id)) {
+                    current = tree.get(file.parent_id);
+                }
+            }
+        }
+
+        let mut left_out: Vec<&str> = Vec::new();
+        let mut ignored: Vec<&str> = Vec::new();
+        for (path, file) in tree.sorted() {
+            let parent_kept = tree.get(file.parent_id).is_none_or(|parent| kept.contains(&parent.id));
+            if kept.contains(&file.id) || !parent_kept {
+                continue;
+            }
+            match self.base.path(file.id) {
+                Some(synced_path) if self.has_unsynced_changes(&local, synced_path) => {}
+                Some(_) => left_out.push(path),
+                None => {
+                    left_out.push(path);
+                    ignored.push(path);
+                }
+            }
+        }
+
+        let removed: HashSet<FileId> = tree.sorted()
+            .into_iter()
+            .filter(|(path, _)| left_out.iter().any(|top| path == top || is_below(path, top)))
+            .map(|(_, file)| file.id)
+            .collect();
+        local.retain(|path, _| !ignored.iter().any(|top| path == top || is_below(path, top)));
+        (FileTree::new(remote.into_iter().filter(|file| !removed.contains(&file.id))), local)
+    }
+
+    /// Anything at or below `path` in the sync root that is not as the last sync left it
+    fn has_unsynced_changes(&self, local: &BTreeMap<String, LocalFile>, path: &str) -> bool {
+        local.values()
+            .filter(|file| file.path == path || is_below(&file.path, path))
+            .any(|file| match self.base.id_at(&file.path).and_then(|id| self.base.get(id)) {
+                Some(base) => base.kind != file.kind
+                    || (!file.is_directory() && (file.size != base.size || file.modified_at != base.last_modified_at)),
+                None => true,
+            })
+    }
+}
+
+/// The id of the file followed by those of its parents, up to and including the root
+fn ancestry(tree: &FileTree, id: FileId) -> Vec<FileId> {
+    let mut ids = vec![id];
+    let mut current = id;
+    while let Some(file) = tree.get(current) {
+        current = file.parent_id;
+        ids.push(current);
+    }
+    ids
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use common::domain::sync_selection::SyncSelection;
+    use crate::domain::default_values::general_defaults::ROOT_DIRECTORY_ID;
+    use crate::domain::local_file::LocalFile;
+    use crate::domain::remote_file::{FileId, FileKind, RemoteFile};
+    use crate::domain::sync::file_tree::FileTree;
+    use crate::domain::sync::selection_filter::SelectionFilter;
+
+    const PROJECTS: FileId = 2;
+    const CURRENT: FileId = 3;
+
+    fn remote(id: FileId, parent_id: FileId, name: &str, kind: FileKind) -> RemoteFile {
+        RemoteFile {
+            id,
+            parent_id,
+            name: name.to_string(),
+            kind,
+            size: (kind == FileKind::File).then_some(5),
+            last_modified_at: 100,
+            hash: None,
+        }
+    }
+
+    /// Projects with plan.txt and Current/todo.txt in it, and notes.txt at the top
+    fn drive() -> Vec<RemoteFile> {
+        vec![
+            remote(PROJECTS, ROOT_DIRECTORY_ID, "Projects", FileKind::Directory),
+            remote(CURRENT, PROJECTS, "Current", FileKind::Directory),
+            remote(4, PROJECTS, "plan.txt", FileKind::File),
+            remote(5, CURRENT, "todo.txt", FileKind::File),
+            remote(6, ROOT_DIRECTORY_ID, "notes.txt", FileKind::File),
+        ]
+    }
+
+    /// The sync root as a sync of the whole drive left it
+    fn synced_root() -> BTreeMap<String, LocalFile> {
+        FileTree::new(drive())
+            .sorted()
+            .into_iter()
+            .map(|(path, file)| (path.to_string(), LocalFile {
+                path: path.to_string(),
+                kind: file.kind,
+                size: file.size,
+                modified_at: file.last_modified_at,
+            }))
+            .collect()
+    }
+
+    fn paths(tree: &FileTree) -> Vec<&str> {
+        tree.sorted().into_iter().map(|(path, _)| path).collect()
+    }
+
+    #[test]
+    fn a_folder_selected_inside_one_that_is_left_out_keeps_its_parents() {
+        // Given Projects left out except for Current, and nothing synced yet
+        let selection = SyncSelection::default().with_rule(PROJECTS, false).with_rule(CURRENT, true);
+        let base = FileTree::new(Vec::new());
+
+        // When the filter is applied
+        let (remote, _) = SelectionFilter::new(&selection, &base).apply(drive(), BTreeMap::new());
+
+        // Then only Current and the way to it stay on the side of kDrive
+        assert_eq!(paths(&remote), ["Projects", "Projects/Current", "Projects/Current/todo.txt", "notes.txt"]);
+    }
+
+    #[test]
+    fn a_synced_folder_that_is_left_out_is_taken_off_the_side_of_kdrive() {
+        // Given a synced drive of which Projects is left out afterwards
+        let selection = SyncSelection::default().with_rule(PROJECTS, false);
+        let base = FileTree::new(drive());
+
+        // When the filter is applied
+        let (remote, local) = SelectionFilter::new(&selection, &base).apply(drive(), synced_root());
+
+        // Then the planner finds Projects gone from kDrive but still in the sync root
+        assert_eq!(paths(&remote), ["notes.txt"]);
+        assert!(local.contains_key("Projects/Current/todo.txt"));
+    }
+
+    #[test]
+    fn a_folder_with_unsynced_changes_stays_in_the_sync() {
+        // Given Projects left out while it has a file that was not uploaded yet
+        let selection = SyncSelection::default().with_rule(PROJECTS, false);
+        let base = FileTree::new(drive());
+        let mut local = synced_root();
+        local.insert("Projects/draft.txt".to_string(), LocalFile {
+            path: "Projects/draft.txt".to_string(),
+            kind: FileKind::File,
+            size: Some(3),
+            modified_at: 200,
+        });
+
+        // When the filter is applied
+        let (remote, _) = SelectionFilter::new(&selection, &base).apply(drive(), local);
+
+        // Then nothing of Projects is left out yet
+        assert_eq!(remote.sorted().len(), 5);
+    }
+
+    #[test]
+    fn a_local_folder_at_the_path_of_one_that_is_left_out_is_left_alone() {
+        let selection = SyncSelection::default().with_rule(PROJECTS, false);
+        let base = FileTree::new(Vec::new());
+        let mut local = BTreeMap::new();
+        local.insert("Projects".to_string(), LocalFile {
+            path: "Projects".to_string(),
+            kind: FileKind::Directory,
+            size: None,
+            modified_at: 200,
+        });
+
+        let (remote, local) = SelectionFilter::new(&selection, &base).apply(drive(), local);
+
+        assert_eq!(paths(&remote), ["notes.txt"]);
+        assert!(local.is_empty());
+    }
+}
@@ -0,0 +1,233 @@
+use std::future::Future;
+use std::sync::{Arc, OnceLock};
+use common::application_error;
+use common::domain::errors::ApplicationError;
+use common::domain::sync_conflict::ConflictResolution;
+use common::domain::text_keys::TextKeys::{ConflictNotFound, NoDriveAvailable, SyncNotReady, SyncRootOfAnotherDrive, SyncStopped};
+use tokio::sync::{watch, Mutex};
+use crate::domain::cloud_sync_state::CloudSyncState;
+use crate::domain::configuration::ConflictPolicy;
+use crate::domain::default_values::configurator_defaults::{DEFAULT_CONFLICT_POLICY, DEFAULT_UPLOAD_CHUNK_SIZE, DEFAULT_UPLOAD_PARALLELISM};
+use crate::domain::events::EngineEvent;
+use crate::domain::metadata::SyncState;
+use crate::domain::remote_file::{DriveId, FileId};
+use crate::domain::sync::remote_change_tracker::RemoteChangeTracker;
+use crate::domain::sync::resumable_download::DownloadProgress;
+use crate::domain::sync::synchronizer::{SyncReport, Synchronizer};
+use crate::ports::driven::event_bus_driven_port::EventBusDrivenPort;
+use crate::ports::driven::kdrive_api_driven_port::KDriveApiDrivenPort;
+use crate::ports::driven::local_fs_driven_port::LocalFsDrivenPort;
+use crate::ports::driven::metadata_driven_port::MetadataDrivenPort;
+
+/// The drive of `drive_id`, or the first one the account has access to, which is then kept in `drive_id`
+pub async fn lookup_drive_id<ApiPort>(api: &ApiPort, drive_id: &OnceLock<DriveId>) -> Result<DriveId, ApplicationError>
+where
+    ApiPort: KDriveApiDrivenPort + Sync,
+{
+    if let Some(drive_id) = drive_id.get() {
+        return Ok(*drive_id);
+    }
+    let drive = api.list_drives().await?
+        .into_iter()
+        .next()
+        .ok_or(application_error!(NoDriveAvailable))?;
+    Ok(*drive_id.get_or_init(|| drive.id))
+}
+
+/// A poll, sync or conflict resolution that shares the ports of the engine instead of borrowing the engine,
+/// so it runs while the engine is unlocked and keeps answering the clients. Jobs wait for each other on
+/// `running`, the store only ever sees one of them at a time. A job ends as soon as `stop` moves past the
+/// generation it was taken in, also in the middle of a transfer.
+pub struct SyncJob<EventPort, MetadataPort, ApiPort, LocalPort> {
+    event_bus: Arc<EventPort>,
+    metadata: Arc<MetadataPort>,
+    api: Arc<ApiPort>,
+    local: Arc<LocalPort>,
+    drive_id: Arc<OnceLock<DriveId>>,
+    running: Arc<Mutex<()>>,
+    stop: watch::Receiver<u64>,
+    generation: u64,
+    conflict_policy: ConflictPolicy,
+    ignore_patterns: Vec<String>,
+    local_moves: Vec<(String, String)>,
+    upload_chunk_size: u64,
+    upload_parallelism: usize,
+    now: i64,
+}
+
+impl<EventPort, MetadataPort, ApiPort, LocalPort> SyncJob<EventPort, MetadataPort, ApiPort, LocalPort>
+where
+    EventPort: EventBusDrivenPort + Send + Sync,
+    MetadataPort: MetadataDrivenPort + Send + Sync,
+    ApiPort: KDriveApiDrivenPort + Send + Sync,
+    LocalPort: LocalFsDrivenPort + Send + Sync,
+{
+    pub fn new(
+        event_bus: Arc<EventPort>,
+        metadata: Arc<MetadataPort>,
+        api: Arc<ApiPort>,
+        local: Arc<LocalPort>,
+        drive_id: Arc<OnceLock<DriveId>>,
+        running: Arc<Mutex<()>>,
+        stop: watch::Receiver<u64>,
+    ) -> Self {
+        let generation = *stop.borrow();
+        Self {
+            event_bus,
+            metadata,
+            api,
+            local,
+            drive_id,
+            running,
+            stop,
+            generation,
+            conflict_policy: DEFAULT_CONFLICT_POLICY,
+            ignore_patterns: Vec::new(),
+            local_moves: Vec::new(),
+            upload_chunk_size: DEFAULT_UPLOAD_CHUNK_SIZE,
+            upload_parallelism: DEFAULT_UPLOAD_PARALLELISM,
+            now: 0,
+        }
+    }
+
+    pub fn with_conflict_policy(mut self, conflict_policy: ConflictPolicy) -> Self {
+        self.conflict_policy = conflict_policy;
+        self
+    }
+
+    pub fn with_ignore_patterns(mut self, ignore_patterns: Vec<String>) -> Self {
+        self.ignore_patterns = ignore_patterns;
+        self
+    }
+
+    /// The renames seen in the sync root since the last sync, as (from, to)
+    pub fn with_local_moves(mut self, local_moves: Vec<(String, String)>) -> Self {
+        self.local_moves = local_moves;
+        self
+    }
+
+    pub fn with_upload_chunks(mut self, chunk_size: u64, parallelism: usize) -> Self {
+        self.upload_chunk_size = chunk_size;
+        self.upload_parallelism = parallelism;
+        self
+    }
+
+    /// The time the job was taken, it dates the conflicted copies
+    pub fn with_time(mut self, now: i64) -> Self {
+        self.now = now;
+        self
+    }
+
+    pub async fn poll_remote_changes(&self) -> Result<usize, ApplicationError> {
+        let changes = self.unless_stopped(async {
+            let _running = self.running.lock().await;
+            self.do_poll_remote_changes().await
+        }).await?;
+        if changes > 0 {
+            let _ = self.event_bus.emit(EngineEvent::RemoteChangesDetected { changes });
+        }
+        Ok(changes)
+    }
+
+    pub async fn synchronize(&self) -> Result<SyncReport, ApplicationError> {
+        let result = self.unless_stopped(async {
+            let _running = self.running.lock().await;
+            self.do_synchronize().await
+        }).await;
+        if let Ok(report) = &result {
+            for conflict in &report.conflicts {
+                let _ = self.event_bus.emit(EngineEvent::ConflictDetected { conflict: conflict.clone() });
+            }
+        }
+        let event = match &result {
+            Ok(report) => EngineEvent::SyncCompleted {
+                operations_completed: report.completed.len(),
+                operations_failed: report.failed.len(),
+            },
+            Err(reason) => EngineEvent::SyncFailed { reason: reason.clone() },
+        };
+        let _ = self.event_bus.emit(event);
+        result
+    }
+
+    pub async fn resolve_conflict(&self, file_id: FileId, path: &str, resolution: ConflictResolution) -> Result<(), ApplicationError> {
+        self.unless_stopped(async {
+            let _running = self.running.lock().await;
+            self.do_resolve_conflict(file_id, path, resolution).await
+        }).await
+    }
+
+    /// Dropping `work` when the job is stopped lets go of `running` and of the transfer it waits for
+    async fn unless_stopped<T>(&self, work: impl Future<Output = Result<T, ApplicationError>>) -> Result<T, ApplicationError> {
+        let mut stop = self.stop.clone();
+        tokio::select! {
+            result = work => result,
+            _ = stop.wait_for(|generation| *generation != self.generation) => Err(application_error!(SyncStopped)),
+        }
+    }
+
+    async fn do_resolve_conflict(&self, file_id: FileId, path: &str, resolution: ConflictResolution) -> Result<(), ApplicationError> {
+        self.ensure_indexed()?;
+        let waiting = self.metadata.get_file(file_id)?.is_some_and(|file| file.sync_state == SyncState::Conflict);
+        if !waiting {
+            return Err(application_error!(ConflictNotFound, file_id));
+        }
+        let drive_id = lookup_drive_id(&*self.api, &self.drive_id).await?;
+        let on_download_progress = |progress: DownloadProgress| self.emit_download_progress(progress);
+        Synchronizer::new(&*self.metadata, &*self.api, &*self.local, drive_id)
+            .with_upload_chunks(self.upload_chunk_size, self.upload_parallelism)
+            .with_download_progress(&on_download_progress)
+            .with_time(self.now)
+            .resolve_conflict(file_id, path, resolution)
+            .await
+    }
+
+    /// Until the crawl is done the store can miss files that are already in the sync root
+    fn ensure_indexed(&self) -> Result<(), ApplicationError> {
+        if CloudSyncState::of(&*self.metadata)? != CloudSyncState::MetadataPresent {
+            return Err(application_error!(SyncNotReady));
+        }
+        Ok(())
+    }
+
+    async fn do_poll_remote_changes(&self) -> Result<usize, ApplicationError> {
+        self.ensure_indexed()?;
+        let drive_id = lookup_drive_id(&*self.api, &self.drive_id).await?;
+        RemoteChangeTracker::new(&*self.metadata, &*self.api, drive_id).poll().await
+    }
+
+    /// The sync root keeps the files of the drive it was first synced with, so the files of one account never go
+    /// to the drive of another. An empty sync root can be taken by any drive.
+    async fn claim_sync_root(&self, drive_id: DriveId) -> Result<(), ApplicationError> {
+        match self.metadata.sync_root_drive()? {
+            Some(owner) if owner == drive_id => return Ok(()),
+            Some(owner) => {
+                if !self.local.read_dir("").await?.is_empty() {
+                    return Err(application_error!(SyncRootOfAnotherDrive, owner));
+                }
+            }
+            None => {}
+        }
+        self.metadata.save_sync_root_drive(drive_id)
+    }
+
+    async fn do_synchronize(&self) -> Result<SyncReport, ApplicationError> {
+        self.do_poll_remote_changes().await?;
+        let drive_id = lookup_drive_id(&*self.api, &self.drive_id).await?;
+        self.claim_sync_root(drive_id).await?;
+        let on_download_progress = |progress: DownloadProgress| self.emit_download_progress(progress);
+        Synchronizer::new(&*self.metadata, &*self.api, &*self.local, drive_id)
+            .with_conflict_policy(self.conflict_policy)
+            .with_ignore_patterns(&self.ignore_patterns)
+            .with_local_moves(&self.local_moves)
+            .with_upload_chunks(self.upload_chunk_size, self.upload_parallelism)
+            .with_download_progress(&on_download_progress)
+            .with_time(self.now)
+            .run()
+            .await
+    }
+
+    fn emit_download_progress(&self, progress: DownloadProgress) {
+        let _ = self.event_bus.emit(EngineEvent::DownloadProgressed { progress });
+    }
+}
@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+use crate::domain::remote_file::FileId;
+
+/// One step that brings the sync root and kDrive closer together, paths are where the file ends up
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SyncOperation {
+    CreateLocalDirectory { file_id: FileId, path: String },
+    CreateRemoteDirectory { path: String },
+    /// A rename on kDrive applied to the sync root
+    RenameLocal { file_id: FileId, from: String, to: String },
+    /// A move to another directory on kDrive applied to the sync root, the name can change along
+    MoveLocal { file_id: FileId, from: String, to: String },
+    /// A rename in the sync root applied to kDrive
+    RenameRemote { file_id: FileId, to: String },
+    /// A move to another directory in the sync root applied to kDrive, the name can change along
+    MoveRemote { file_id: FileId, to: String },
+    Download { file_id: FileId, path: String },
+    /// Without a file id the file is new on kDrive, with one its content is replaced
+    Upload { file_id: Option<FileId>, path: String },
+    DeleteLocal { file_id: FileId, path: String },
+    DeleteRemote { file_id: FileId, path: String },
+    /// Changed on both sides since the last sync, neither side is touched
+    Conflict { file_id: FileId, path: String },
+}
+
+impl SyncOperation {
+    /// The path the operation leaves the file at, or removes it from
+    pub fn path(&self) -> &str {
+        match self {
+            SyncOperation::CreateLocalDirectory { path, .. }
+            | SyncOperation::CreateRemoteDirectory { path }
+            | SyncOperation::Download { path, .. }
+            | SyncOperation::Upload { path, .. }
+            | SyncOperation::DeleteLocal { path, .. }
+            | SyncOperation::DeleteRemote { path, .. }
+            | SyncOperation::Conflict { path, .. } => path,
+            SyncOperation::RenameLocal { to, .. }
+            | SyncOperation::MoveLocal { to, .. }
+            | SyncOperation::RenameRemote { to, .. }
+            | SyncOperation::MoveRemote { to, .. } => to,
+        }
+    }
+}
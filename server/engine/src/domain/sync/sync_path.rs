@@ -0,0 +1,101 @@
+//! Paths relative to the root of the drive or the sync root, with `/` between the names and the root as the empty path
+
+pub fn join_path(parent: &str, name: &str) -> String {
+    if parent.is_empty() {
+        name.to_string()
+    } else {
+        format!("{parent}/{name}")
+    }
+}
+
+/// The directory the path is in, the root for a path at the top level
+pub fn parent_path(path: &str) -> &str {
+    path.rsplit_once('/').map_or("", |(parent, _)| parent)
+}
+
+pub fn file_name(path: &str) -> &str {
+    path.rsplit_once('/').map_or(path, |(_, name)| name)
+}
+
+/// True for everything inside the directory at `ancestor`, not for the directory itself
+pub fn is_below(path: &str, ancestor: &str) -> bool {
+    if ancestor.is_empty() {
+        return !path.is_empty();
+    }
+    path.strip_prefix(ancestor).is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// Where `path` ends up when the file or directory at `from` moves to `to`
+pub fn moved_path(path: &str, from: &str, to: &str) -> Option<String> {
+    if path == from {
+        Some(to.to_string())
+    } else if is_below(path, from) {
+        Some(format!("{to}{}", &path[from.len()..]))
+    } else {
+        None
+    }
+}
+
+/// Where the local version of a conflict is kept, next to the original and with the date in the name:
+/// `notes (conflicted copy 2023-11-14).txt`. Attempts after the first add a number for when that name is taken.
+pub fn conflicted_copy_path(path: &str, now: i64, attempt: usize) -> String {
+    let name = file_name(path);
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem, format!(".{extension}")),
+        _ => (name, String::new()),
+    };
+    let number = if attempt > 1 { format!(" {attempt}") } else { String::new() };
+    join_path(parent_path(path), &format!("{stem} (conflicted copy {}{number}){extension}", utc_date(now)))
+}
+
+/// The day of a Unix timestamp in UTC as `yyyy-mm-dd`
+fn utc_date(seconds: i64) -> String {
+    // Days to the civil calendar, counted in eras of 400 years starting on the 1st of March
+    let days = seconds.div_euclid(86_400) + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_are_split_and_joined_at_slashes() {
+        assert_eq!(join_path("", "Documents"), "Documents");
+        assert_eq!(join_path("Documents", "report.pdf"), "Documents/report.pdf");
+        assert_eq!(parent_path("Documents/report.pdf"), "Documents");
+        assert_eq!(parent_path("Documents"), "");
+        assert_eq!(file_name("Documents/report.pdf"), "report.pdf");
+    }
+
+    #[test]
+    fn only_paths_inside_a_directory_are_below_it() {
+        assert!(is_below("Documents/report.pdf", "Documents"));
+        assert!(is_below("Documents", ""));
+        assert!(!is_below("Documents", "Documents"));
+        assert!(!is_below("Documents old/report.pdf", "Documents"));
+    }
+
+    #[test]
+    fn moving_a_directory_moves_what_is_inside() {
+        assert_eq!(moved_path("A/b/c.txt", "A/b", "B").as_deref(), Some("B/c.txt"));
+        assert_eq!(moved_path("A/b", "A/b", "B").as_deref(), Some("B"));
+        assert_eq!(moved_path("A/bc", "A/b", "B"), None);
+    }
+
+    #[test]
+    fn conflicted_copies_keep_the_extension_and_get_the_date() {
+        assert_eq!(conflicted_copy_path("Documents/notes.txt", 1_700_000_000, 1), "Documents/notes (conflicted copy 2023-11-14).txt");
+        assert_eq!(conflicted_copy_path("notes.tar.gz", 1_700_000_000, 2), "notes.tar (conflicted copy 2023-11-14 2).gz");
+        assert_eq!(conflicted_copy_path(".bashrc", 951_782_400, 1), ".bashrc (conflicted copy 2000-02-29)");
+        assert_eq!(conflicted_copy_path("Photos", 0, 1), "Photos (conflicted copy 1970-01-01)");
+    }
+}
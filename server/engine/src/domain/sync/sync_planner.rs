@@ -0,0 +1,760 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use common::application_error;
+use common::domain::errors::ApplicationError;
+use common::domain::text_keys::TextKeys::SyncPlanInconsistent;
+use crate::domain::default_values::general_defaults::ROOT_DIRECTORY_ID;
+use crate::domain::local_file::LocalFile;
+use crate::domain::remote_file::{FileId, RemoteFile};
+use crate::domain::sync::file_tree::FileTree;
+use crate::domain::sync::sync_operation::SyncOperation;
+use crate::domain::sync::sync_path::{file_name, is_below, join_path, moved_path, parent_path};
+
+/// What a sync has to do, the operations in the order they have to run
+#[derive(Debug, Default, PartialEq)]
+pub struct SyncPlan {
+    /// Directories and moves first with parents before children, then content, then deletes
+    pub operations: Vec<SyncOperation>,
+    /// Stored files that are gone from both sides, or that this sync treats as new again
+    pub forgotten: Vec<FileId>,
+    /// New on both sides with the same kind and content, taken as in sync, with their path
+    pub adopted: Vec<(FileId, String)>,
+    /// The path every directory on kDrive ends up at, the parents for the operations
+    pub directory_ids: HashMap<String, FileId>,
+}
+
+/// Decides what a sync has to do by comparing both sides with how they looked after the last sync.
+///
+/// `base` holds the files that were in sync, `remote` what kDrive has now and `local` what is in
+/// the sync root now. Remote files keep their id through renames and moves, local ones are found
+/// where the renames the watcher saw took them, else at their old path or, when they are gone from
+/// there, by an unknown path with the same size and modification time, or for directories with
+/// every name that was inside.
+pub struct SyncPlanner<'a> {
+    base: &'a FileTree,
+    remote: &'a FileTree,
+    local: &'a BTreeMap<String, LocalFile>,
+    local_hashes: HashMap<String, String>,
+    local_moves: &'a [(String, String)],
+}
+
+/// Where the files that were in sync are in the sync root now
+struct LocalMatches {
+    paths: HashMap<FileId, String>,
+    moved: HashSet<FileId>,
+}
+
+/// The paths of the sync root a file that is gone from its old path can be found at, indexed so finding one does
+/// not compare it with every local file
+struct MoveCandidates<'a> {
+    files: HashMap<(Option<u64>, i64), Vec<&'a LocalFile>>,
+    directories_by_child: HashMap<&'a str, Vec<&'a LocalFile>>,
+    children: HashMap<&'a str, BTreeSet<&'a str>>,
+}
+
+impl<'a> MoveCandidates<'a> {
+    fn new(local: &'a BTreeMap<String, LocalFile>, excluded: impl Fn(&str) -> bool) -> Self {
+        let mut candidates = Self { files: HashMap::new(), directories_by_child: HashMap::new(), children: HashMap::new() };
+        for (path, file) in local {
+            let parent = parent_path(path);
+            candidates.children.entry(parent).or_default().insert(file_name(path));
+            if let Some(directory) = local.get(parent).filter(|directory| directory.is_directory() && !excluded(parent)) {
+                candidates.directories_by_child.entry(file_name(path)).or_default().push(directory);
+            }
+            if !file.is_directory() && !excluded(path) {
+                candidates.files.entry((file.size, file.modified_at)).or_default().push(file);
+            }
+        }
+        for directories in candidates.directories_by_child.values_mut() {
+            directories.sort_by(|a, b| a.path.cmp(&b.path));
+        }
+        candidates
+    }
+
+    /// A file with the same size and modification time, or a directory with every name that was inside. Files added
+    /// to the directory since do not hide the rename, files removed from it do.
+    fn find(&self, base: &RemoteFile, base_names: Option<&BTreeSet<&str>>, claimed: &HashSet<&str>) -> Option<&'a LocalFile> {
+        if !base.is_directory() {
+            return self.files.get(&(base.size, base.last_modified_at))?
+                .iter()
+                .find(|file| !claimed.contains(file.path.as_str()))
+                .copied();
+        }
+        let names = base_names?;
+        self.directories_by_child.get(names.first()?)?
+            .iter()
+            .find(|directory| {
+                !claimed.contains(directory.path.as_str())
+                    && self.children.get(directory.path.as_str()).is_some_and(|local_names| names.is_subset(local_names))
+            })
+            .copied()
+    }
+}
+
+/// Where a kept file ends up, below a directory known by id or below a directory that is new in the sync root
+enum FinalParent {
+    Id(FileId),
+    LocalPath(String),
+}
+
+fn apply_moves(moves: &[(String, String)], path: &str) -> String {
+    moves.iter().fold(path.to_string(), |path, (from, to)| moved_path(&path, from, to).unwrap_or(path))
+}
+
+fn local_move(file_id: FileId, from: String, to: String) -> SyncOperation {
+    if parent_path(&from) == parent_path(&to) {
+        SyncOperation::RenameLocal { file_id, from, to }
+    } else {
+        SyncOperation::MoveLocal { file_id, from, to }
+    }
+}
+
+fn remote_move(file_id: FileId, from: &str, to: String) -> SyncOperation {
+    if parent_path(from) == parent_path(&to) {
+        SyncOperation::RenameRemote { file_id, to }
+    } else {
+        SyncOperation::MoveRemote { file_id, to }
+    }
+}
+
+impl<'a> SyncPlanner<'a> {
+    pub fn new(base: &'a FileTree, remote: &'a FileTree, local: &'a BTreeMap<String, LocalFile>) -> Self {
+        Self { base, remote, local, local_hashes: HashMap::new(), local_moves: &[] }
+    }
+
+    /// The content hashes of local files by path. A file that is new on both sides is only taken as in sync when
+    /// its hash matches the one kDrive has, without a hash it is a conflict.
+    pub fn with_local_hashes(mut self, local_hashes: HashMap<String, String>) -> Self {
+        self.local_hashes = local_hashes;
+        self
+    }
+
+    /// Renames in the sync root as (from, to) in the order they happened, an empty directory or a file with the
+    /// same size and modification time as another is only found again through them
+    pub fn with_local_moves(mut self, local_moves: &'a [(String, String)]) -> Self {
+        self.local_moves = local_moves;
+        self
+    }
+
+    pub fn plan(&self) -> Result<SyncPlan, ApplicationError> {
+        let matches = self.match_local();
+        let claimed: HashSet<&str> = matches.paths.values().map(String::as_str).collect();
+        let mut local_dirty: Vec<&str> = self.local.keys()
+            .map(String::as_str)
+            .filter(|path| !claimed.contains(path))
+            .collect();
+        for (id, path) in &matches.paths {
+            if self.local_changed(self.base_file(*id)?, path) {
+                local_dirty.push(path.as_str());
+            }
+        }
+        let remote_dirty: Vec<&str> = self.remote.sorted().into_iter()
+            .filter(|(_, file)| self.base.get(file.id).is_none_or(|base| self.remote_changed(base, file)))
+            .map(|(path, _)| path)
+            .collect();
+
+        let mut plan = SyncPlan::default();
+        let mut deletes = Vec::new();
+        let mut deleted_base: Vec<&str> = Vec::new();
+        let mut kept = Vec::new();
+        let mut released_local = HashSet::new();
+        let mut released_remote = HashSet::new();
+
+        for (base_path, base) in self.base.sorted() {
+            if deleted_base.iter().any(|deleted| is_below(base_path, deleted)) {
+                continue;
+            }
+            let local_path = matches.paths.get(&base.id);
+            match (self.remote.get(base.id), local_path) {
+                (None, None) => plan.forgotten.push(base.id),
+                // Gone from kDrive, the local copy goes too unless something in it changed since
+                (None, Some(local_path)) => {
+                    let dirty = if base.is_directory() {
+                        local_dirty.iter().any(|path| is_below(path, local_path))
+                    } else {
+                        self.local_changed(base, local_path)
+                    };
+                    if dirty {
+                        plan.forgotten.push(base.id);
+                        released_local.insert(base.id);
+                    } else {
+                        deletes.push((base.id, local_path.clone(), true));
+                        deleted_base.push(base_path);
+                    }
+                }
+                // Gone from the sync root, the remote file goes too unless something in it changed since
+                (Some(remote), None) => {
+                    let remote_path = self.remote.path(remote.id).unwrap_or_default();
+                    let dirty = if base.is_directory() {
+                        remote_dirty.iter().any(|path| is_below(path, remote_path))
+                    } else {
+                        self.remote_changed(base, remote)
+                    };
+                    if dirty {
+                        plan.forgotten.push(base.id);
+                        released_remote.insert(base.id);
+                    } else {
+                        deletes.push((base.id, remote_path.to_string(), false));
+                        deleted_base.push(base_path);
+                    }
+                }
+                (Some(_), Some(_)) => kept.push(base.id),
+            }
+        }
+
+        let layout = Layout {
+            planner: self,
+            matches: &matches,
+            kept: kept.iter().copied().collect(),
+            local_ids: kept.iter().filter_map(|id| Some((matches.paths.get(id)?.as_str(), *id))).collect(),
+        };
+
+        let mut structure = Vec::new();
+        let mut content = Vec::new();
+        let mut local_moves = Vec::new();
+        let mut remote_moves = Vec::new();
+
+        let mut kept: Vec<(String, FileId)> = kept.into_iter().map(|id| (layout.final_path(id), id)).collect();
+        kept.sort();
+        for (to, id) in kept {
+            let base = self.base_file(id)?;
+            let local_path = matches.paths.get(&id).ok_or_else(|| application_error!(SyncPlanInconsistent, id))?;
+            let remote_path = self.remote.path(id).unwrap_or_default();
+
+            let from = apply_moves(&local_moves, local_path);
+            if from != to {
+                local_moves.push((from.clone(), to.clone()));
+                structure.push(local_move(id, from, to.clone()));
+            }
+            let from = apply_moves(&remote_moves, remote_path);
+            if from != to {
+                structure.push(remote_move(id, &from, to.clone()));
+                remote_moves.push((from, to.clone()));
+            }
+
+            if base.is_directory() {
+                plan.directory_ids.insert(to, id);
+                continue;
+            }
+            let remote = self.remote.get(id).ok_or_else(|| application_error!(SyncPlanInconsistent, id))?;
+            match (self.local_changed(base, local_path), self.remote_changed(base, remote)) {
+                (true, true) => content.push(SyncOperation::Conflict { file_id: id, path: to }),
+                (true, false) => content.push(SyncOperation::Upload { file_id: Some(id), path: to }),
+                (false, true) => content.push(SyncOperation::Download { file_id: id, path: to }),
+                (false, false) => {}
+            }
+        }
+
+        let still_claimed: HashSet<&str> = matches.paths.iter()
+            .filter(|(id, _)| !released_local.contains(*id))
+            .map(|(_, path)| path.as_str())
+            .collect();
+        let mut new_local: BTreeMap<String, &LocalFile> = self.local.values()
+            .filter(|file| !still_claimed.contains(file.path.as_str()))
+            .map(|file| (layout.final_local_path(&file.path), file))
+            .collect();
+
+        let mut new_remote: Vec<(String, &RemoteFile)> = self.remote.sorted().into_iter()
+            .filter(|(_, file)| !self.base.contains(file.id) || released_remote.contains(&file.id))
+            .map(|(_, file)| (layout.final_path(file.id), file))
+            .collect();
+        new_remote.sort_by(|a, b| a.0.cmp(&b.0));
+        for (path, remote) in new_remote {
+            match new_local.remove(&path) {
+                Some(local) if local.kind == remote.kind && (local.is_directory() || self.same_content(local, remote)) => {
+                    if remote.is_directory() {
+                        plan.directory_ids.insert(path.clone(), remote.id);
+                    }
+                    plan.adopted.push((remote.id, path));
+                }
+                Some(_) => content.push(SyncOperation::Conflict { file_id: remote.id, path }),
+                None if remote.is_directory() => {
+                    plan.directory_ids.insert(path.clone(), remote.id);
+                    structure.push(SyncOperation::CreateLocalDirectory { file_id: remote.id, path });
+                }
+                None => content.push(SyncOperation::Download { file_id: remote.id, path }),
+            }
+        }
+
+        for (path, local) in new_local {
+            if local.is_directory() {
+                structure.push(SyncOperation::CreateRemoteDirectory { path });
+            } else {
+                content.push(SyncOperation::Upload { file_id: None, path });
+            }
+        }
+
+        // Sorting on the path keeps parents before their children and the moves in the order they were planned
+        structure.sort_by(|a, b| a.path().cmp(b.path()));
+        plan.operations = structure;
+        plan.operations.extend(content);
+        plan.operations.extend(deletes.into_iter().map(|(file_id, path, local)| {
+            if local {
+                SyncOperation::DeleteLocal { file_id, path: apply_moves(&local_moves, &path) }
+            } else {
+                SyncOperation::DeleteRemote { file_id, path }
+            }
+        }));
+        Ok(plan)
+    }
+
+    fn same_content(&self, local: &LocalFile, remote: &RemoteFile) -> bool {
+        local.size == remote.size
+            && remote.hash.as_ref().is_some_and(|hash| self.local_hashes.get(&local.path) == Some(hash))
+    }
+
+    fn base_file(&self, id: FileId) -> Result<&'a RemoteFile, ApplicationError> {
+        self.base.get(id).ok_or_else(|| application_error!(SyncPlanInconsistent, id))
+    }
+
+    /// Finds every file of the base in the sync root, following directories that were moved
+    fn match_local(&self) -> LocalMatches {
+        let base_paths: HashSet<&str> = self.base.sorted().into_iter().map(|(path, _)| path).collect();
+        let mut moves: Vec<(String, String)> = Vec::new();
+        let mut matches = LocalMatches { paths: HashMap::new(), moved: HashSet::new() };
+        let mut claimed: HashSet<&str> = HashSet::new();
+        let hinted: HashMap<FileId, String> = self.base.sorted().into_iter()
+            .filter_map(|(path, file)| {
+                let hinted = apply_moves(self.local_moves, path);
+                (hinted != path).then_some((file.id, hinted))
+            })
+            .collect();
+        let hint_targets: HashSet<&str> = hinted.values().map(String::as_str).collect();
+        let candidates = MoveCandidates::new(self.local, |path| base_paths.contains(path) || hint_targets.contains(path));
+        let mut base_names: HashMap<FileId, BTreeSet<&str>> = HashMap::new();
+        for (_, file) in self.base.sorted() {
+            base_names.entry(file.parent_id).or_default().insert(file.name.as_str());
+        }
+
+        for (base_path, base) in self.base.sorted() {
+            let expected = apply_moves(&moves, base_path);
+            // A rename the watcher saw wins over finding the file at its old path, another file may have taken it
+            if let Some(hinted) = hinted.get(&base.id).filter(|hinted| **hinted != expected)
+                && let Some((path, local)) = self.local.get_key_value(hinted)
+                && local.kind == base.kind
+                && !claimed.contains(path.as_str())
+            {
+                claimed.insert(path);
+                moves.push((expected, hinted.clone()));
+                matches.moved.insert(base.id);
+                matches.paths.insert(base.id, hinted.clone());
+                continue;
+            }
+            let taken_by_rename = hint_targets.contains(expected.as_str()) && hinted.get(&base.id) != Some(&expected);
+            if let Some((path, local)) = self.local.get_key_value(&expected)
+                && local.kind == base.kind
+                && !claimed.contains(path.as_str())
+                && !taken_by_rename
+            {
+                claimed.insert(path);
+                matches.paths.insert(base.id, expected);
+                continue;
+            }
+
+            if let Some(local) = candidates.find(base, base_names.get(&base.id), &claimed) {
+                claimed.insert(&local.path);
+                moves.push((expected, local.path.clone()));
+                matches.moved.insert(base.id);
+                matches.paths.insert(base.id, local.path.clone());
+            }
+        }
+        matches
+    }
+
+    fn local_changed(&self, base: &RemoteFile, local_path: &str) -> bool {
+        self.local.get(local_path).is_some_and(|local| {
+            !local.is_directory() && (local.size != base.size || local.modified_at != base.last_modified_at)
+        })
+    }
+
+    fn remote_changed(&self, base: &RemoteFile, remote: &RemoteFile) -> bool {
+        !remote.is_directory()
+            && (remote.size != base.size || remote.last_modified_at != base.last_modified_at || remote.hash != base.hash)
+    }
+}
+
+/// Where files end up once the sync is done: a move on kDrive wins over one in the sync root
+struct Layout<'p, 'a> {
+    planner: &'p SyncPlanner<'a>,
+    matches: &'p LocalMatches,
+    kept: HashSet<FileId>,
+    local_ids: HashMap<&'p str, FileId>,
+}
+
+impl Layout<'_, '_> {
+    fn final_path(&self, id: FileId) -> String {
+        self.final_path_from(id, &mut Vec::new())
+            .unwrap_or_else(|| self.planner.remote.path(id).unwrap_or_default().to_string())
+    }
+
+    fn final_local_path(&self, path: &str) -> String {
+        self.final_local_path_from(path, &mut Vec::new()).unwrap_or_else(|| path.to_string())
+    }
+
+    /// None when the path leads back into a directory in `visiting`, the moves on both sides formed a cycle
+    fn final_path_from(&self, id: FileId, visiting: &mut Vec<FileId>) -> Option<String> {
+        let remote = self.planner.remote.get(id);
+        if id == ROOT_DIRECTORY_ID {
+            return Some(remote.and(self.planner.remote.path(id)).unwrap_or_default().to_string());
+        }
+        if visiting.contains(&id) {
+            return None;
+        }
+        let Some(remote) = remote else {
+            return Some(String::new());
+        };
+
+        visiting.push(id);
+        let local_place = match self.kept.contains(&id) {
+            true => self.local_place(id, remote),
+            false => None,
+        };
+        // A move in the sync root that would put a directory inside itself gives way to the one on kDrive
+        let path = local_place
+            .and_then(|(parent, name)| Some(join_path(&self.parent_path_from(parent, visiting)?, &name)))
+            .or_else(|| Some(join_path(&self.final_path_from(remote.parent_id, visiting)?, &remote.name)));
+        visiting.pop();
+        path
+    }
+
+    fn final_local_path_from(&self, path: &str, visiting: &mut Vec<FileId>) -> Option<String> {
+        if path.is_empty() {
+            return Some(String::new());
+        }
+        if let Some(id) = self.local_ids.get(path) {
+            return self.final_path_from(*id, visiting);
+        }
+        Some(join_path(&self.final_local_path_from(parent_path(path), visiting)?, file_name(path)))
+    }
+
+    fn parent_path_from(&self, parent: FinalParent, visiting: &mut Vec<FileId>) -> Option<String> {
+        match parent {
+            FinalParent::Id(parent_id) => self.final_path_from(parent_id, visiting),
+            FinalParent::LocalPath(path) => self.final_local_path_from(&path, visiting),
+        }
+    }
+
+    /// Where a file moved in the sync root goes, none when it stays where kDrive has it
+    fn local_place(&self, id: FileId, remote: &RemoteFile) -> Option<(FinalParent, String)> {
+        let moved_remotely = self.planner.base.get(id).is_none_or(|base| remote.parent_id != base.parent_id || remote.name != base.name);
+        if moved_remotely || !self.matches.moved.contains(&id) {
+            return None;
+        }
+
+        let local_path = self.matches.paths.get(&id)?;
+        let parent = parent_path(local_path);
+        let parent = if parent.is_empty() {
+            FinalParent::Id(ROOT_DIRECTORY_ID)
+        } else {
+            match self.local_ids.get(parent) {
+                Some(parent_id) => FinalParent::Id(*parent_id),
+                None => FinalParent::LocalPath(parent.to_string()),
+            }
+        };
+        Some((parent, file_name(local_path).to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap};
+    use crate::domain::content_hash::content_hash;
+    use crate::domain::default_values::general_defaults::ROOT_DIRECTORY_ID;
+    use crate::domain::local_file::LocalFile;
+    use crate::domain::remote_file::{FileId, FileKind, RemoteFile};
+    use crate::domain::sync::file_tree::FileTree;
+    use crate::domain::sync::sync_operation::SyncOperation;
+    use crate::domain::sync::sync_planner::SyncPlanner;
+
+    const MODIFIED_AT: i64 = 1_700_000_000;
+
+    fn remote(id: FileId, parent_id: FileId, name: &str, kind: FileKind) -> RemoteFile {
+        RemoteFile {
+            id,
+            parent_id,
+            name: name.to_string(),
+            kind,
+            size: (kind == FileKind::File).then_some(5),
+            last_modified_at: MODIFIED_AT,
+            hash: None,
+        }
+    }
+
+    fn local(path: &str, kind: FileKind, modified_at: i64) -> (String, LocalFile) {
+        let file = LocalFile {
+            path: path.to_string(),
+            kind,
+            size: (kind == FileKind::File).then_some(5),
+            modified_at,
+        };
+        (path.to_string(), file)
+    }
+
+    /// Documents with report.pdf inside, in sync on both sides
+    fn synced_base() -> Vec<RemoteFile> {
+        vec![
+            remote(2, ROOT_DIRECTORY_ID, "Documents", FileKind::Directory),
+            remote(3, 2, "report.pdf", FileKind::File),
+        ]
+    }
+
+    #[test]
+    fn local_edit_follows_a_remote_rename_of_its_directory() {
+        // Given a directory renamed on kDrive and a file in it edited locally
+        let base = FileTree::new(synced_base());
+        let mut renamed = synced_base();
+        renamed[0].name = "Papers".to_string();
+        let remote = FileTree::new(renamed);
+        let local = BTreeMap::from([
+            local("Documents", FileKind::Directory, 0),
+            local("Documents/report.pdf", FileKind::File, MODIFIED_AT + 60),
+            local("Documents/new.txt", FileKind::File, MODIFIED_AT),
+        ]);
+
+        // When planning
+        let plan = SyncPlanner::new(&base, &remote, &local).plan().unwrap();
+
+        // Then the directory is renamed first and the content goes to kDrive from the new path
+        assert_eq!(plan.operations, [
+            SyncOperation::RenameLocal { file_id: 2, from: "Documents".to_string(), to: "Papers".to_string() },
+            SyncOperation::Upload { file_id: Some(3), path: "Papers/report.pdf".to_string() },
+            SyncOperation::Upload { file_id: None, path: "Papers/new.txt".to_string() },
+        ]);
+        assert_eq!(plan.directory_ids.get("Papers"), Some(&2));
+    }
+
+    #[test]
+    fn remote_move_wins_over_a_local_one() {
+        // Given a file moved to different places on both sides
+        let base = FileTree::new(synced_base());
+        let mut moved = synced_base();
+        moved[1].parent_id = ROOT_DIRECTORY_ID;
+        let remote = FileTree::new(moved);
+        let local = BTreeMap::from([
+            local("Documents", FileKind::Directory, 0),
+            local("Documents/renamed.pdf", FileKind::File, MODIFIED_AT),
+        ]);
+
+        // When planning
+        let plan = SyncPlanner::new(&base, &remote, &local).plan().unwrap();
+
+        // Then the local copy goes where kDrive has it
+        assert_eq!(plan.operations, [SyncOperation::MoveLocal {
+            file_id: 3,
+            from: "Documents/renamed.pdf".to_string(),
+            to: "report.pdf".to_string(),
+        }]);
+    }
+
+    #[test]
+    fn files_new_on_both_sides_with_the_same_content_are_adopted() {
+        let base = FileTree::new(Vec::new());
+        let mut files = synced_base();
+        files[1].hash = Some(content_hash(b"PDF 1"));
+        let remote = FileTree::new(files);
+        let local = BTreeMap::from([
+            local("Documents", FileKind::Directory, 0),
+            local("Documents/report.pdf", FileKind::File, MODIFIED_AT + 5),
+        ]);
+        let hashes = HashMap::from([("Documents/report.pdf".to_string(), content_hash(b"PDF 1"))]);
+
+        let plan = SyncPlanner::new(&base, &remote, &local).with_local_hashes(hashes).plan().unwrap();
+
+        assert!(plan.operations.is_empty());
+        assert_eq!(plan.adopted, [(2, "Documents".to_string()), (3, "Documents/report.pdf".to_string())]);
+    }
+
+    #[test]
+    fn files_new_on_both_sides_with_the_same_size_but_other_content_are_a_conflict() {
+        // Given a file of the same size at the same path on both sides, with different content
+        let base = FileTree::new(Vec::new());
+        let mut files = synced_base();
+        files[1].hash = Some(content_hash(b"PDF 1"));
+        let remote = FileTree::new(files);
+        let local = BTreeMap::from([
+            local("Documents", FileKind::Directory, 0),
+            local("Documents/report.pdf", FileKind::File, MODIFIED_AT),
+        ]);
+        let hashes = HashMap::from([("Documents/report.pdf".to_string(), content_hash(b"PDF 2"))]);
+
+        // When planning
+        let plan = SyncPlanner::new(&base, &remote, &local).with_local_hashes(hashes).plan().unwrap();
+
+        // Then only the directory is taken as in sync, the conflict policy decides about the file
+        assert_eq!(plan.adopted, [(2, "Documents".to_string())]);
+        assert_eq!(plan.operations, [SyncOperation::Conflict { file_id: 3, path: "Documents/report.pdf".to_string() }]);
+    }
+
+    #[test]
+    fn files_new_on_both_sides_without_a_remote_hash_are_a_conflict() {
+        let base = FileTree::new(Vec::new());
+        let remote = FileTree::new(synced_base());
+        let local = BTreeMap::from([
+            local("Documents", FileKind::Directory, 0),
+            local("Documents/report.pdf", FileKind::File, MODIFIED_AT),
+        ]);
+
+        let plan = SyncPlanner::new(&base, &remote, &local).plan().unwrap();
+
+        assert_eq!(plan.operations, [SyncOperation::Conflict { file_id: 3, path: "Documents/report.pdf".to_string() }]);
+    }
+
+    #[test]
+    fn a_renamed_empty_directory_the_watcher_saw_is_renamed() {
+        // Given an empty directory that was renamed in the sync root
+        let base = FileTree::new(vec![remote(2, ROOT_DIRECTORY_ID, "Documents", FileKind::Directory)]);
+        let remote = FileTree::new(vec![remote(2, ROOT_DIRECTORY_ID, "Documents", FileKind::Directory)]);
+        let local = BTreeMap::from([local("Papers", FileKind::Directory, 0)]);
+        let moves = [("Documents".to_string(), "Papers".to_string())];
+
+        // When planning with the rename
+        let plan = SyncPlanner::new(&base, &remote, &local).with_local_moves(&moves).plan().unwrap();
+
+        // Then it is renamed on kDrive instead of deleted and created again
+        assert_eq!(plan.operations, [SyncOperation::RenameRemote { file_id: 2, to: "Papers".to_string() }]);
+    }
+
+    #[test]
+    fn files_alike_in_size_and_time_follow_the_renames_the_watcher_saw() {
+        // Given two files of the same size and time that were renamed in the sync root
+        let files = vec![
+            remote(2, ROOT_DIRECTORY_ID, "a.txt", FileKind::File),
+            remote(3, ROOT_DIRECTORY_ID, "b.txt", FileKind::File),
+        ];
+        let base = FileTree::new(files.clone());
+        let remote = FileTree::new(files);
+        let local = BTreeMap::from([
+            local("c.txt", FileKind::File, MODIFIED_AT),
+            local("d.txt", FileKind::File, MODIFIED_AT),
+        ]);
+        let moves = [("a.txt".to_string(), "d.txt".to_string()), ("b.txt".to_string(), "c.txt".to_string())];
+
+        // When planning with the renames
+        let plan = SyncPlanner::new(&base, &remote, &local).with_local_moves(&moves).plan().unwrap();
+
+        // Then each is renamed on kDrive to where it went, not to the first lookalike
+        assert_eq!(plan.operations, [
+            SyncOperation::RenameRemote { file_id: 3, to: "c.txt".to_string() },
+            SyncOperation::RenameRemote { file_id: 2, to: "d.txt".to_string() },
+        ]);
+    }
+
+    #[test]
+    fn directories_moved_into_each_other_on_both_sides_follow_kdrive() {
+        // Given A moved into B in the sync root and B moved into A on kDrive
+        let files = vec![
+            remote(2, ROOT_DIRECTORY_ID, "A", FileKind::Directory),
+            remote(3, ROOT_DIRECTORY_ID, "B", FileKind::Directory),
+            remote(4, 2, "a.txt", FileKind::File),
+            remote(5, 3, "b.txt", FileKind::File),
+        ];
+        let base = FileTree::new(files.clone());
+        let mut moved = files;
+        moved[1].parent_id = 2;
+        let remote = FileTree::new(moved);
+        let local = BTreeMap::from([
+            local("B", FileKind::Directory, 0),
+            local("B/A", FileKind::Directory, 0),
+            local("B/A/a.txt", FileKind::File, MODIFIED_AT),
+            local("B/b.txt", FileKind::File, MODIFIED_AT),
+        ]);
+
+        // When planning
+        let plan = SyncPlanner::new(&base, &remote, &local).plan().unwrap();
+
+        // Then the local move gives way, both sides end up with B inside A
+        assert_eq!(plan.operations, [
+            SyncOperation::MoveLocal { file_id: 2, from: "B/A".to_string(), to: "A".to_string() },
+            SyncOperation::MoveLocal { file_id: 3, from: "B".to_string(), to: "A/B".to_string() },
+        ]);
+    }
+
+    #[test]
+    fn the_same_move_on_both_sides_needs_nothing() {
+        let base = FileTree::new(synced_base());
+        let mut moved = synced_base();
+        moved[1].parent_id = ROOT_DIRECTORY_ID;
+        let remote = FileTree::new(moved);
+        let local = BTreeMap::from([
+            local("Documents", FileKind::Directory, 0),
+            local("report.pdf", FileKind::File, MODIFIED_AT),
+        ]);
+
+        let plan = SyncPlanner::new(&base, &remote, &local).plan().unwrap();
+
+        assert!(plan.operations.is_empty());
+    }
+
+    #[test]
+    fn a_local_delete_gives_way_to_an_edit_on_kdrive() {
+        // Given a file deleted in the sync root and edited on kDrive
+        let base = FileTree::new(synced_base());
+        let mut edited = synced_base();
+        edited[1].size = Some(6);
+        let remote = FileTree::new(edited);
+        let local = BTreeMap::from([local("Documents", FileKind::Directory, 0)]);
+
+        // When planning
+        let plan = SyncPlanner::new(&base, &remote, &local).plan().unwrap();
+
+        // Then the edit is downloaded again
+        assert_eq!(plan.forgotten, [3]);
+        assert_eq!(plan.operations, [SyncOperation::Download { file_id: 3, path: "Documents/report.pdf".to_string() }]);
+    }
+
+    #[test]
+    fn a_delete_on_kdrive_gives_way_to_a_local_edit() {
+        // Given a file deleted on kDrive and edited in the sync root
+        let base = FileTree::new(synced_base());
+        let remote = FileTree::new(vec![remote(2, ROOT_DIRECTORY_ID, "Documents", FileKind::Directory)]);
+        let local = BTreeMap::from([
+            local("Documents", FileKind::Directory, 0),
+            local("Documents/report.pdf", FileKind::File, MODIFIED_AT + 60),
+        ]);
+
+        // When planning
+        let plan = SyncPlanner::new(&base, &remote, &local).plan().unwrap();
+
+        // Then the edit is uploaded as a new file
+        assert_eq!(plan.forgotten, [3]);
+        assert_eq!(plan.operations, [SyncOperation::Upload { file_id: None, path: "Documents/report.pdf".to_string() }]);
+    }
+
+    #[test]
+    fn nested_directories_renamed_on_different_sides_keep_both_renames() {
+        // Given Documents renamed on kDrive and Reports inside it renamed in the sync root
+        let files = vec![
+            remote(2, ROOT_DIRECTORY_ID, "Documents", FileKind::Directory),
+            remote(3, 2, "Reports", FileKind::Directory),
+            remote(4, 3, "q1.pdf", FileKind::File),
+        ];
+        let base = FileTree::new(files.clone());
+        let mut renamed = files;
+        renamed[0].name = "Papers".to_string();
+        let remote = FileTree::new(renamed);
+        let local = BTreeMap::from([
+            local("Documents", FileKind::Directory, 0),
+            local("Documents/Quarterly", FileKind::Directory, 0),
+            local("Documents/Quarterly/q1.pdf", FileKind::File, MODIFIED_AT),
+        ]);
+
+        // When planning
+        let plan = SyncPlanner::new(&base, &remote, &local).plan().unwrap();
+
+        // Then each side gets the rename of the other one
+        assert_eq!(plan.operations, [
+            SyncOperation::RenameLocal { file_id: 2, from: "Documents".to_string(), to: "Papers".to_string() },
+            SyncOperation::RenameRemote { file_id: 3, to: "Papers/Quarterly".to_string() },
+        ]);
+    }
+
+    #[test]
+    fn deleting_a_directory_is_one_operation() {
+        let base = FileTree::new(synced_base());
+        let remote = FileTree::new(synced_base());
+        let local = BTreeMap::new();
+
+        let plan = SyncPlanner::new(&base, &remote, &local).plan().unwrap();
+
+        assert_eq!(plan.operations, [SyncOperation::DeleteRemote { file_id: 2, path: "Documents".to_string() }]);
+    }
+}
@@ -0,0 +1,1066 @@
+use std::collections::{BTreeMap, HashMap};
+use common::application_error;
+use common::domain::errors::ApplicationError;
+use common::domain::sync_conflict::{ConflictResolution, FileVersion, SyncConflict};
+use common::domain::text_keys::TextKeys::{ApiNotFound, DirectoryNotFound, LocalFileError};
+use crate::domain::configuration::ConflictPolicy;
+use crate::domain::content_hash::read_hash;
+use crate::domain::default_values::configurator_defaults::{DEFAULT_UPLOAD_CHUNK_SIZE, DEFAULT_UPLOAD_PARALLELISM};
+use crate::domain::default_values::general_defaults::ROOT_DIRECTORY_ID;
+use crate::domain::local_file::LocalFile;
+use crate::domain::metadata::{FileMetadata, SyncState};
+use crate::domain::remote_file::{DriveId, FileId, RemoteFile};
+use crate::domain::sync::chunked_upload::ChunkedUpload;
+use crate::domain::sync::file_tree::FileTree;
+use crate::domain::sync::ignore_rules::IgnoreRules;
+use crate::domain::sync::resumable_download::{DownloadProgressListener, ResumableDownload};
+use crate::domain::sync::selection_filter::SelectionFilter;
+use crate::domain::sync::sync_operation::SyncOperation;
+use crate::domain::sync::sync_path::{conflicted_copy_path, file_name, is_below, moved_path, parent_path};
+use crate::domain::sync::sync_planner::SyncPlanner;
+use crate::domain::upload_session::UploadTarget;
+use crate::ports::driven::kdrive_api_driven_port::KDriveApiDrivenPort;
+use crate::ports::driven::local_fs_driven_port::LocalFsDrivenPort;
+use crate::ports::driven::metadata_driven_port::MetadataDrivenPort;
+
+/// What one sync did, an operation that failed is tried again by the next sync
+#[derive(Debug, Default, PartialEq)]
+pub struct SyncReport {
+    pub completed: Vec<SyncOperation>,
+    pub failed: Vec<(SyncOperation, ApplicationError)>,
+    /// Found by this sync, a conflict that was already waiting for the user is not repeated
+    pub conflicts: Vec<SyncConflict>,
+}
+
+/// Brings the sync root and the drive in line with each other, with the synced files of the metadata store as
+/// the state both sides had after the last sync. Every finished operation is stored right away, so an interrupted
+/// sync only repeats what did not finish.
+pub struct Synchronizer<'a, MetadataPort, ApiPort, LocalPort> {
+    metadata: &'a MetadataPort,
+    api: &'a ApiPort,
+    local: &'a LocalPort,
+    drive_id: DriveId,
+    conflict_policy: ConflictPolicy,
+    ignore_patterns: &'a [String],
+    local_moves: &'a [(String, String)],
+    now: i64,
+    upload_chunk_size: u64,
+    upload_parallelism: usize,
+    on_download_progress: Option<DownloadProgressListener<'a>>,
+}
+
+/// The drive and the sync root as the operations leave them, the sync root as it was planned with
+struct Execution {
+    files: HashMap<FileId, RemoteFile>,
+    directory_ids: HashMap<String, FileId>,
+    local: BTreeMap<String, LocalFile>,
+    conflicts: Vec<SyncConflict>,
+}
+
+impl Execution {
+    fn new(remote: &FileTree, directory_ids: HashMap<String, FileId>, local: BTreeMap<String, LocalFile>) -> Self {
+        Self {
+            files: remote.sorted().into_iter().map(|(_, file)| (file.id, file.clone())).collect(),
+            directory_ids,
+            local,
+            conflicts: Vec::new(),
+        }
+    }
+
+    fn local_moved(&mut self, from: &str, to: &str) {
+        self.local = std::mem::take(&mut self.local)
+            .into_values()
+            .map(|mut file| {
+                if let Some(path) = moved_path(&file.path, from, to) {
+                    file.path = path;
+                }
+                (file.path.clone(), file)
+            })
+            .collect();
+    }
+
+    fn local_removed(&mut self, path: &str) {
+        self.local.retain(|local, _| local != path && !is_below(local, path));
+    }
+
+    fn file(&self, file_id: FileId) -> Result<RemoteFile, ApplicationError> {
+        self.files.get(&file_id).cloned().ok_or(application_error!(ApiNotFound, file_id))
+    }
+
+    fn parent_id(&self, path: &str) -> Result<FileId, ApplicationError> {
+        let parent = parent_path(path);
+        if parent.is_empty() {
+            return Ok(ROOT_DIRECTORY_ID);
+        }
+        self.directory_ids.get(parent).copied().ok_or_else(|| application_error!(DirectoryNotFound, parent))
+    }
+}
+
+impl<'a, MetadataPort, ApiPort, LocalPort> Synchronizer<'a, MetadataPort, ApiPort, LocalPort>
+where
+    MetadataPort: MetadataDrivenPort + Sync,
+    ApiPort: KDriveApiDrivenPort + Sync,
+    LocalPort: LocalFsDrivenPort + Sync,
+{
+    /// Leaves conflicts for the user to resolve unless a policy says otherwise
+    pub fn new(metadata: &'a MetadataPort, api: &'a ApiPort, local: &'a LocalPort, drive_id: DriveId) -> Self {
+        Self {
+            metadata,
+            api,
+            local,
+            drive_id,
+            conflict_policy: ConflictPolicy::Ask,
+            ignore_patterns: &[],
+            local_moves: &[],
+            now: 0,
+            upload_chunk_size: DEFAULT_UPLOAD_CHUNK_SIZE,
+            upload_parallelism: DEFAULT_UPLOAD_PARALLELISM,
+            on_download_progress: None,
+        }
+    }
+
+    pub fn with_conflict_policy(mut self, conflict_policy: ConflictPolicy) -> Self {
+        self.conflict_policy = conflict_policy;
+        self
+    }
+
+    /// Ignored on top of what the `.kdriveignore` files in the sync root ignore
+    pub fn with_ignore_patterns(mut self, ignore_patterns: &'a [String]) -> Self {
+        self.ignore_patterns = ignore_patterns;
+        self
+    }
+
+    /// Renames seen in the sync root as (from, to) in the order they happened, the planner guesses the others
+    pub fn with_local_moves(mut self, local_moves: &'a [(String, String)]) -> Self {
+        self.local_moves = local_moves;
+        self
+    }
+
+    /// Files larger than `chunk_size` are uploaded in chunks of that size, `parallelism` of them at the same time
+    pub fn with_upload_chunks(mut self, chunk_size: u64, parallelism: usize) -> Self {
+        self.upload_chunk_size = chunk_size.max(1);
+        self.upload_parallelism = parallelism.max(1);
+        self
+    }
+
+    pub fn with_download_progress(mut self, on_download_progress: DownloadProgressListener<'a>) -> Self {
+        self.on_download_progress = Some(on_download_progress);
+        self
+    }
+
+    /// The time of the sync, it dates the conflicted copies
+    pub fn with_time(mut self, now: i64) -> Self {
+        self.now = now;
+        self
+    }
+
+    /// Expects the store to hold kDrive as the last poll of its changes found it, only the selected folders are synced.
+    /// Ignored files are left out on both sides, one that was synced before is forgotten but stays where it is.
+    pub async fn run(&self) -> Result<SyncReport, ApplicationError> {
+        let base = FileTree::new(self.metadata.synced_files()?);
+        let selection = self.metadata.sync_selection()?;
+        let (local, ignore_rules) = self.local_files().await?;
+        let remote = FileTree::new(self.metadata.all_files()?.into_iter().map(|file| file.remote));
+        let remote = remote.sorted()
+            .into_iter()
+            .filter(|(path, file)| !ignore_rules.is_ignored(path, file.is_directory()))
+            .map(|(_, file)| file.clone())
+            .collect();
+        let (remote, local) = SelectionFilter::new(&selection, &base).apply(remote, local);
+        let local_hashes = self.new_file_hashes(&base, &remote, &local).await;
+        let plan = SyncPlanner::new(&base, &remote, &local)
+            .with_local_hashes(local_hashes)
+            .with_local_moves(self.local_moves)
+            .plan()?;
+
+        for id in &plan.forgotten {
+            self.metadata.forget_synced_file(*id)?;
+        }
+        for (id, path) in &plan.adopted {
+            let file = remote.get(*id).cloned().ok_or(application_error!(ApiNotFound, id))?;
+            if !file.is_directory() {
+                self.local.set_modified_at(path, file.last_modified_at).await?;
+            }
+            self.save_synced(file)?;
+        }
+
+        let mut execution = Execution::new(&remote, plan.directory_ids, local);
+        let mut report = SyncReport::default();
+        for operation in plan.operations {
+            match self.execute(&operation, &mut execution).await {
+                Ok(()) => report.completed.push(operation),
+                Err(error) => report.failed.push((operation, error)),
+            }
+        }
+        report.conflicts = execution.conflicts;
+        Ok(report)
+    }
+
+    /// Settles a conflict the user was asked about, with the file at `path` in the sync root as the local side
+    pub async fn resolve_conflict(&self, file_id: FileId, path: &str, resolution: ConflictResolution) -> Result<(), ApplicationError> {
+        let remote = FileTree::new(self.metadata.all_files()?.into_iter().map(|file| file.remote));
+        let directory_ids = remote.sorted()
+            .into_iter()
+            .filter(|(_, file)| file.is_directory())
+            .map(|(path, file)| (path.to_string(), file.id))
+            .collect();
+        let mut execution = Execution::new(&remote, directory_ids, BTreeMap::new());
+        let file = execution.file(file_id)?;
+        let local = self.local.stat(path).await?.ok_or_else(|| application_error!(LocalFileError, path))?;
+        self.resolve(resolution, &file, &local, &mut execution).await
+    }
+
+    /// What the sync root has apart from the ignored files, with the rules of its ignore files
+    async fn local_files(&self) -> Result<(BTreeMap<String, LocalFile>, IgnoreRules), ApplicationError> {
+        let mut rules = IgnoreRules::new(self.ignore_patterns);
+        let mut files = BTreeMap::new();
+        let mut pending = vec![String::new()];
+        while let Some(directory) = pending.pop() {
+            rules.load_directory(self.local, &directory).await?;
+            for child in self.local.read_dir(&directory).await? {
+                if rules.is_ignored(&child.path, child.is_directory()) {
+                    continue;
+                }
+                if child.is_directory() {
+                    pending.push(child.path.clone());
+                }
+                files.insert(child.path.clone(), child);
+            }
+        }
+        Ok((files, rules))
+    }
+
+    /// Hashes the local files at the path of a file that is new on kDrive with the same size, the planner only takes
+    /// those as in sync when the content is the same. A file that cannot be read is left out, it becomes a conflict.
+    async fn new_file_hashes(&self, base: &FileTree, remote: &FileTree, local: &BTreeMap<String, LocalFile>) -> HashMap<String, String> {
+        let mut hashes = HashMap::new();
+        for (path, file) in remote.sorted() {
+            let candidate = local.get(path).is_some_and(|local| !local.is_directory() && local.size == file.size);
+            if !candidate || file.hash.is_none() || base.contains(file.id) {
+                continue;
+            }
+            if let Ok(hash) = self.local_hash(path).await {
+                hashes.insert(path.to_string(), hash);
+            }
+        }
+        hashes
+    }
+
+    async fn local_hash(&self, path: &str) -> Result<String, ApplicationError> {
+        let mut reader = self.local.open_file(path).await?;
+        read_hash(&mut reader).await.map_err(|e| application_error!(LocalFileError, "{}: {}", path, e))
+    }
+
+    fn save_synced(&self, file: RemoteFile) -> Result<(), ApplicationError> {
+        self.metadata.save_synced_file(&file)
+    }
+
+    async fn execute(&self, operation: &SyncOperation, execution: &mut Execution) -> Result<(), ApplicationError> {
+        match operation {
+            SyncOperation::CreateLocalDirectory { file_id, path } => {
+                self.local.create_directory(path).await?;
+                self.save_synced(execution.file(*file_id)?)
+            }
+            SyncOperation::CreateRemoteDirectory { path } => {
+                let parent_id = execution.parent_id(path)?;
+                let directory = self.api.create_directory(self.drive_id, parent_id, file_name(path)).await?;
+                execution.directory_ids.insert(path.clone(), directory.id);
+                execution.files.insert(directory.id, directory.clone());
+                self.save_synced(directory)
+            }
+            SyncOperation::RenameLocal { file_id, from, to } | SyncOperation::MoveLocal { file_id, from, to } => {
+                self.local.rename(from, to).await?;
+                execution.local_moved(from, to);
+                self.save_synced(execution.file(*file_id)?)
+            }
+            SyncOperation::RenameRemote { file_id, to } | SyncOperation::MoveRemote { file_id, to } => {
+                let mut file = execution.file(*file_id)?;
+                let parent_id = execution.parent_id(to)?;
+                if file.parent_id != parent_id {
+                    self.api.move_file(self.drive_id, file.id, parent_id).await?;
+                    file.parent_id = parent_id;
+                }
+                let name = file_name(to);
+                if file.name != name {
+                    self.api.rename_file(self.drive_id, file.id, name).await?;
+                    file.name = name.to_string();
+                }
+                execution.files.insert(file.id, file.clone());
+                self.save_synced(file)
+            }
+            SyncOperation::Download { file_id, path } => {
+                let file = execution.file(*file_id)?;
+                self.download(file, path, execution.local.get(path)).await
+            }
+            SyncOperation::Upload { file_id, path } => self.upload(*file_id, path, execution).await,
+            SyncOperation::DeleteLocal { file_id, path } => {
+                self.local.move_to_trash(path).await?;
+                execution.local_removed(path);
+                self.metadata.forget_synced_file(*file_id)
+            }
+            SyncOperation::DeleteRemote { file_id, .. } => {
+                self.api.delete_file(self.drive_id, *file_id).await?;
+                self.metadata.delete_file(*file_id)?;
+                self.metadata.forget_synced_file(*file_id)
+            }
+            SyncOperation::Conflict { file_id, path } => {
+                let remote = execution.file(*file_id)?;
+                let local = self.local.stat(path).await?.ok_or_else(|| application_error!(LocalFileError, path))?;
+                let resolution = self.conflict_policy.resolution();
+                let waiting = self.metadata.get_file(remote.id)?.is_some_and(|file| file.sync_state == SyncState::Conflict);
+                match resolution {
+                    Some(resolution) => self.resolve(resolution, &remote, &local, execution).await?,
+                    None => self.metadata.save_file(&FileMetadata::new(remote.clone(), SyncState::Conflict))?,
+                }
+                if resolution.is_some() || !waiting {
+                    execution.conflicts.push(sync_conflict(&remote, &local, resolution));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// `replacing` is what the sync root had at the path when the sync was planned
+    async fn download(&self, file: RemoteFile, path: &str, replacing: Option<&LocalFile>) -> Result<(), ApplicationError> {
+        ResumableDownload::new(self.metadata, self.api, self.local, self.drive_id)
+            .with_progress(self.on_download_progress)
+            .replacing(replacing)
+            .run(&file, path)
+            .await?;
+        self.local.set_modified_at(path, file.last_modified_at).await?;
+        self.save_synced(file)
+    }
+
+    async fn upload(&self, file_id: Option<FileId>, path: &str, execution: &mut Execution) -> Result<(), ApplicationError> {
+        let local = self.local.stat(path).await?.ok_or_else(|| application_error!(LocalFileError, path))?;
+        let file = match local.size {
+            Some(size) if size > self.upload_chunk_size => {
+                let target = match file_id {
+                    Some(file_id) => UploadTarget::NewVersion { file_id },
+                    None => UploadTarget::NewFile { directory_id: execution.parent_id(path)?, name: file_name(path).to_string() },
+                };
+                ChunkedUpload::new(self.metadata, self.api, self.local, self.drive_id)
+                    .with_chunks(self.upload_chunk_size, self.upload_parallelism)
+                    .run(&local, target)
+                    .await?
+            }
+            _ => {
+                let content = self.local.read_file(path).await?;
+                match file_id {
+                    Some(file_id) => self.api.update_file(self.drive_id, file_id, content).await?,
+                    None => {
+                        let parent_id = execution.parent_id(path)?;
+                        self.api.upload_file(self.drive_id, parent_id, file_name(path), content).await?
+                    }
+                }
+            }
+        };
+        // The same time on both sides is what tells the next sync nothing changed, a file written to during the upload
+        // keeps its own time so the next sync uploads it again
+        if self.local.stat(path).await?.as_ref() == Some(&local) {
+            self.local.set_modified_at(path, file.last_modified_at).await?;
+        }
+        execution.files.insert(file.id, file.clone());
+        self.save_synced(file)
+    }
+
+    /// A file and a directory at the same path can only both be kept
+    async fn resolve(&self, resolution: ConflictResolution, remote: &RemoteFile, local: &LocalFile, execution: &mut Execution) -> Result<(), ApplicationError> {
+        let path = local.path.as_str();
+        let both_files = !remote.is_directory() && !local.is_directory();
+        match resolution {
+            ConflictResolution::KeepLocal if both_files => self.upload(Some(remote.id), path, execution).await,
+            ConflictResolution::KeepRemote if both_files => {
+                self.local.move_to_trash(path).await?;
+                self.download(remote.clone(), path, None).await
+            }
+            _ => {
+                let copy = self.free_conflicted_copy_path(path).await?;
+                self.local.rename(path, &copy).await?;
+                if remote.is_directory() {
+                    self.metadata.set_sync_state(remote.id, SyncState::PendingDownload)?;
+                } else {
+                    self.download(remote.clone(), path, None).await?;
+                }
+                // Directories on either side are new to the next sync, which brings them over with their content
+                if !local.is_directory() {
+                    self.upload(None, &copy, execution).await?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    async fn free_conflicted_copy_path(&self, path: &str) -> Result<String, ApplicationError> {
+        let mut attempt = 1;
+        loop {
+            let copy = conflicted_copy_path(path, self.now, attempt);
+            if self.local.stat(&copy).await?.is_none() {
+                return Ok(copy);
+            }
+            attempt += 1;
+        }
+    }
+}
+
+fn sync_conflict(remote: &RemoteFile, local: &LocalFile, resolution: Option<ConflictResolution>) -> SyncConflict {
+    SyncConflict {
+        file_id: remote.id,
+        path: local.path.clone(),
+        local: FileVersion {
+            kind: local.kind.into(),
+            size: local.size,
+            modified_at: local.modified_at,
+            hash: None,
+        },
+        remote: FileVersion {
+            kind: remote.kind.into(),
+            size: remote.size,
+            modified_at: remote.last_modified_at,
+            hash: remote.hash.clone(),
+        },
+        resolution,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common::domain::sync_conflict::ConflictResolution;
+    use common::domain::sync_selection::SyncSelection;
+    use common::domain::text_keys::TextKeys::{DownloadCorrupted, LocalFileChanged};
+    use crate::domain::configuration::ConflictPolicy;
+    use crate::domain::content_hash::content_hash;
+    use crate::domain::default_values::general_defaults::ROOT_DIRECTORY_ID;
+    use crate::domain::metadata::SyncState;
+    use crate::domain::sync::remote_change_tracker::RemoteChangeTracker;
+    use crate::domain::sync::sync_operation::SyncOperation;
+    use crate::domain::sync::synchronizer::{SyncReport, Synchronizer};
+    use crate::domain::test_helpers::fake_clock::TEST_NOW;
+    use crate::domain::test_helpers::fake_kdrive_api::{FakeKDriveApi, TEST_DRIVE_ID, TEST_MODIFIED_AT};
+    use crate::domain::test_helpers::fake_local_fs::FakeLocalFs;
+    use crate::domain::test_helpers::fake_metadata_store::FakeMetadataStore;
+    use crate::ports::driven::kdrive_api_driven_port::KDriveApiDrivenPort;
+    use crate::ports::driven::metadata_driven_port::MetadataDrivenPort;
+
+    struct TestSync {
+        metadata: FakeMetadataStore,
+        api: FakeKDriveApi,
+        local: FakeLocalFs,
+    }
+
+    impl TestSync {
+        /// kDrive with Documents/report.pdf and notes.txt, synced once to an empty sync root
+        async fn synced() -> Self {
+            let api = FakeKDriveApi::new_default();
+            let documents = api.add_directory(ROOT_DIRECTORY_ID, "Documents");
+            api.add_file(documents, "report.pdf", b"report");
+            api.add_file(ROOT_DIRECTORY_ID, "notes.txt", b"notes");
+            let test = TestSync { metadata: FakeMetadataStore::new(), api, local: FakeLocalFs::new() };
+            test.sync().await;
+            test
+        }
+
+        async fn sync(&self) -> SyncReport {
+            self.sync_with(ConflictPolicy::Ask).await
+        }
+
+        async fn sync_with(&self, conflict_policy: ConflictPolicy) -> SyncReport {
+            RemoteChangeTracker::new(&self.metadata, &self.api, TEST_DRIVE_ID).poll().await.unwrap();
+            self.synchronizer().with_conflict_policy(conflict_policy).run().await.unwrap()
+        }
+
+        async fn sync_ignoring(&self, patterns: &[&str]) -> SyncReport {
+            let patterns: Vec<String> = patterns.iter().map(|pattern| pattern.to_string()).collect();
+            RemoteChangeTracker::new(&self.metadata, &self.api, TEST_DRIVE_ID).poll().await.unwrap();
+            self.synchronizer().with_ignore_patterns(&patterns).run().await.unwrap()
+        }
+
+        /// Two chunks at a time
+        async fn sync_in_chunks_of(&self, chunk_size: u64) -> SyncReport {
+            RemoteChangeTracker::new(&self.metadata, &self.api, TEST_DRIVE_ID).poll().await.unwrap();
+            self.synchronizer().with_upload_chunks(chunk_size, 2).run().await.unwrap()
+        }
+
+        fn synchronizer(&self) -> Synchronizer<'_, FakeMetadataStore, FakeKDriveApi, FakeLocalFs> {
+            Synchronizer::new(&self.metadata, &self.api, &self.local, TEST_DRIVE_ID).with_time(TEST_NOW)
+        }
+
+        /// notes.txt edited locally and on kDrive since the last sync
+        async fn with_conflict() -> (Self, u64) {
+            let test = TestSync::synced().await;
+            let notes = test.remote_id("notes.txt");
+            test.local.add_file("notes.txt", b"local notes");
+            test.api.change_file(notes, b"remote notes");
+            (test, notes)
+        }
+
+        fn remote_id(&self, path: &str) -> u64 {
+            path.split('/').fold(ROOT_DIRECTORY_ID, |parent, name| self.api.file_id(parent, name).unwrap())
+        }
+    }
+
+    #[tokio::test]
+    async fn first_sync_downloads_the_drive_and_uploads_local_files() {
+        // Given a drive with files and a sync root with a file of its own
+        let api = FakeKDriveApi::new_default();
+        let documents = api.add_directory(ROOT_DIRECTORY_ID, "Documents");
+        let report = api.add_file(documents, "report.pdf", b"report");
+        let test = TestSync {
+            metadata: FakeMetadataStore::new(),
+            api,
+            local: FakeLocalFs::new().with_file("Photos/holiday.jpg", b"holiday"),
+        };
+
+        // When the first sync runs
+        let report_of_sync = test.sync().await;
+
+        // Then both sides have everything and the store knows it is in sync
+        assert!(report_of_sync.failed.is_empty());
+        assert_eq!(test.local.paths(), ["Documents", "Documents/report.pdf", "Photos", "Photos/holiday.jpg"]);
+        assert_eq!(test.local.content("Documents/report.pdf").unwrap(), b"report");
+        assert_eq!(test.local.modified_at("Documents/report.pdf"), Some(TEST_MODIFIED_AT));
+        let holiday = test.remote_id("Photos/holiday.jpg");
+        assert_eq!(test.api.content(holiday).unwrap(), b"holiday");
+        assert_eq!(test.metadata.get_file(report).unwrap().unwrap().sync_state, SyncState::Synced);
+        assert_eq!(test.metadata.get_file(holiday).unwrap().unwrap().sync_state, SyncState::Synced);
+    }
+
+    #[tokio::test]
+    async fn sync_without_changes_does_nothing() {
+        let test = TestSync::synced().await;
+
+        let report = test.sync().await;
+
+        assert_eq!(report, SyncReport::default());
+    }
+
+    #[tokio::test]
+    async fn edits_go_to_the_side_that_did_not_make_them() {
+        // Given a synced drive, a file edited locally and another one on kDrive
+        let test = TestSync::synced().await;
+        let report = test.remote_id("Documents/report.pdf");
+        let notes = test.remote_id("notes.txt");
+        test.local.add_file("Documents/report.pdf", b"final report");
+        test.api.change_file(notes, b"more notes");
+
+        // When syncing
+        let sync_report = test.sync().await;
+
+        // Then the local edit is a new version of the same remote file and the remote edit is downloaded
+        assert_eq!(sync_report.completed, [
+            SyncOperation::Upload { file_id: Some(report), path: "Documents/report.pdf".to_string() },
+            SyncOperation::Download { file_id: notes, path: "notes.txt".to_string() },
+        ]);
+        assert_eq!(test.api.content(report).unwrap(), b"final report");
+        assert_eq!(test.local.content("notes.txt").unwrap(), b"more notes");
+        assert_eq!(test.sync().await, SyncReport::default());
+    }
+
+    #[tokio::test]
+    async fn a_local_edit_made_during_a_download_is_not_overwritten() {
+        // Given a file edited on kDrive that is also written to locally while it downloads
+        let test = TestSync::synced().await;
+        let notes = test.remote_id("notes.txt");
+        test.api.change_file(notes, b"more notes");
+        test.local.edit_during_next_transfer("notes.txt", b"local notes");
+
+        // When syncing
+        let report = test.sync().await;
+
+        // Then the local edit stays and the next sync sees both edits as a conflict
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].1.text_key, LocalFileChanged);
+        assert_eq!(test.local.content("notes.txt").unwrap(), b"local notes");
+        assert_eq!(test.sync().await.completed, [SyncOperation::Conflict { file_id: notes, path: "notes.txt".to_string() }]);
+    }
+
+    #[tokio::test]
+    async fn a_file_written_to_during_its_upload_is_uploaded_again() {
+        // Given a local edit that is written to again while it uploads
+        let test = TestSync::synced().await;
+        let notes = test.remote_id("notes.txt");
+        test.local.add_file("notes.txt", b"new notes");
+        test.local.edit_during_next_transfer("notes.txt", b"newer notes");
+
+        // When syncing
+        let report = test.sync().await;
+
+        // Then the file keeps its own time, so the next sync uploads the later write
+        assert_eq!(report.completed, [SyncOperation::Upload { file_id: Some(notes), path: "notes.txt".to_string() }]);
+        assert_eq!(test.api.content(notes).unwrap(), b"new notes");
+        test.sync().await;
+        assert_eq!(test.api.content(notes).unwrap(), b"newer notes");
+    }
+
+    #[tokio::test]
+    async fn local_renames_and_moves_are_applied_on_kdrive() {
+        // Given a synced drive where the user renamed a folder and moved a file into it
+        let test = TestSync::synced().await;
+        let documents = test.remote_id("Documents");
+        let report_pdf = test.remote_id("Documents/report.pdf");
+        let notes = test.remote_id("notes.txt");
+        test.local.move_path("Documents", "Papers");
+        test.local.move_path("notes.txt", "Papers/notes.txt");
+
+        // When syncing
+        let report = test.sync().await;
+
+        // Then kDrive follows without transferring any content
+        assert_eq!(report.completed, [
+            SyncOperation::RenameRemote { file_id: documents, to: "Papers".to_string() },
+            SyncOperation::MoveRemote { file_id: notes, to: "Papers/notes.txt".to_string() },
+        ]);
+        assert_eq!(test.remote_id("Papers/report.pdf"), report_pdf);
+        assert_eq!(test.api.file_id(documents, "notes.txt"), Some(notes));
+        assert_eq!(test.sync().await, SyncReport::default());
+    }
+
+    #[tokio::test]
+    async fn remote_moves_are_applied_locally() {
+        // Given a synced drive where a file was moved and renamed on kDrive
+        let test = TestSync::synced().await;
+        let notes = test.remote_id("notes.txt");
+        let documents = test.remote_id("Documents");
+        test.api.move_file(TEST_DRIVE_ID, notes, documents).await.unwrap();
+        test.api.rename_file(TEST_DRIVE_ID, notes, "todo.txt").await.unwrap();
+
+        // When syncing
+        let report = test.sync().await;
+
+        // Then the local copy moves along and keeps its content
+        assert_eq!(report.completed, [SyncOperation::MoveLocal {
+            file_id: notes,
+            from: "notes.txt".to_string(),
+            to: "Documents/todo.txt".to_string(),
+        }]);
+        assert_eq!(test.local.content("Documents/todo.txt").unwrap(), b"notes");
+        assert_eq!(test.local.content("notes.txt"), None);
+    }
+
+    #[tokio::test]
+    async fn deletes_go_to_the_other_side() {
+        // Given a synced drive where a folder was deleted locally and a file on kDrive
+        let test = TestSync::synced().await;
+        let documents = test.remote_id("Documents");
+        let notes = test.remote_id("notes.txt");
+        test.local.remove("Documents");
+        test.api.delete_file(TEST_DRIVE_ID, notes).await.unwrap();
+
+        // When syncing
+        let report = test.sync().await;
+
+        // Then the folder goes to the trash of kDrive and the file to the local trash
+        assert_eq!(report.completed, [
+            SyncOperation::DeleteRemote { file_id: documents, path: "Documents".to_string() },
+            SyncOperation::DeleteLocal { file_id: notes, path: "notes.txt".to_string() },
+        ]);
+        assert!(test.api.is_in_trash(documents));
+        assert!(test.local.paths().is_empty());
+        assert_eq!(test.local.trashed(), ["notes.txt"]);
+        assert_eq!(test.metadata.get_file(documents).unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn a_deleted_folder_with_new_content_on_the_other_side_is_kept() {
+        // Given a folder deleted locally while a file was added to it on kDrive
+        let test = TestSync::synced().await;
+        let documents = test.remote_id("Documents");
+        let report = test.remote_id("Documents/report.pdf");
+        test.local.remove("Documents");
+        let added = test.api.add_file(documents, "added.txt", b"added");
+
+        // When syncing
+        test.sync().await;
+
+        // Then the folder comes back with only the new file, the old one is deleted as the user asked
+        assert_eq!(test.local.paths(), ["Documents", "Documents/added.txt", "notes.txt"]);
+        assert!(test.api.is_in_trash(report));
+        assert!(!test.api.is_in_trash(added));
+    }
+
+    #[tokio::test]
+    async fn edits_on_both_sides_are_a_conflict_that_touches_neither() {
+        // Given a file edited locally and on kDrive
+        let (test, notes) = TestSync::with_conflict().await;
+
+        // When syncing while the user decides about conflicts
+        let report = test.sync().await;
+
+        // Then both versions are left as they are and the file is marked as a conflict
+        assert_eq!(report.completed, [SyncOperation::Conflict { file_id: notes, path: "notes.txt".to_string() }]);
+        assert_eq!(test.local.content("notes.txt").unwrap(), b"local notes");
+        assert_eq!(test.api.content(notes).unwrap(), b"remote notes");
+        assert_eq!(test.metadata.get_file(notes).unwrap().unwrap().sync_state, SyncState::Conflict);
+        let conflict = &report.conflicts[0];
+        assert_eq!((conflict.file_id, conflict.path.as_str(), conflict.resolution), (notes, "notes.txt", None));
+        assert_eq!(conflict.local.size, Some(11));
+        assert_eq!(conflict.remote.size, Some(12));
+    }
+
+    #[tokio::test]
+    async fn files_on_both_sides_before_the_first_sync_are_only_taken_as_synced_with_the_same_content() {
+        // Given notes.txt and draft.txt on both sides, the drafts have the same size but other content
+        let api = FakeKDriveApi::new_default();
+        let notes = api.add_file(ROOT_DIRECTORY_ID, "notes.txt", b"notes");
+        let draft = api.add_file(ROOT_DIRECTORY_ID, "draft.txt", b"draft");
+        let local = FakeLocalFs::new().with_file("notes.txt", b"notes").with_file("draft.txt", b"DRAFT");
+        let test = TestSync { metadata: FakeMetadataStore::new(), api, local };
+
+        // When syncing for the first time
+        let report = test.sync().await;
+
+        // Then the notes are in sync and the drafts wait for the user, neither version was overwritten
+        assert_eq!(report.completed, [SyncOperation::Conflict { file_id: draft, path: "draft.txt".to_string() }]);
+        assert_eq!(test.metadata.get_file(notes).unwrap().unwrap().sync_state, SyncState::Synced);
+        assert_eq!(test.local.content("draft.txt").unwrap(), b"DRAFT");
+        assert_eq!(test.api.content(draft).unwrap(), b"draft");
+    }
+
+    #[tokio::test]
+    async fn a_conflict_waiting_for_the_user_is_reported_once() {
+        let (test, _) = TestSync::with_conflict().await;
+        test.sync().await;
+
+        let report = test.sync().await;
+
+        assert_eq!(report.completed.len(), 1);
+        assert!(report.conflicts.is_empty());
+    }
+
+    #[tokio::test]
+    async fn keeping_both_puts_the_local_version_next_to_the_one_of_kdrive() {
+        // Given a file edited on both sides
+        let (test, notes) = TestSync::with_conflict().await;
+
+        // When syncing with the policy to keep both
+        let report = test.sync_with(ConflictPolicy::KeepBoth).await;
+
+        // Then the local edit lives on as a conflicted copy on both sides
+        let copy = "notes (conflicted copy 2023-11-14).txt";
+        assert_eq!(report.conflicts[0].resolution, Some(ConflictResolution::KeepBoth));
+        assert_eq!(test.local.content("notes.txt").unwrap(), b"remote notes");
+        assert_eq!(test.local.content(copy).unwrap(), b"local notes");
+        assert_eq!(test.api.content(test.remote_id(copy)).unwrap(), b"local notes");
+        assert_eq!(test.metadata.get_file(notes).unwrap().unwrap().sync_state, SyncState::Synced);
+        assert_eq!(test.sync().await, SyncReport::default());
+    }
+
+    #[tokio::test]
+    async fn a_second_conflicted_copy_on_the_same_day_gets_a_number() {
+        let (test, _) = TestSync::with_conflict().await;
+        test.local.add_file("notes (conflicted copy 2023-11-14).txt", b"older notes");
+
+        test.sync_with(ConflictPolicy::KeepBoth).await;
+
+        assert_eq!(test.local.content("notes (conflicted copy 2023-11-14).txt").unwrap(), b"older notes");
+        assert_eq!(test.local.content("notes (conflicted copy 2023-11-14 2).txt").unwrap(), b"local notes");
+    }
+
+    #[tokio::test]
+    async fn preferring_local_overwrites_kdrive() {
+        let (test, notes) = TestSync::with_conflict().await;
+
+        let report = test.sync_with(ConflictPolicy::PreferLocal).await;
+
+        assert_eq!(report.conflicts[0].resolution, Some(ConflictResolution::KeepLocal));
+        assert_eq!(test.api.content(notes).unwrap(), b"local notes");
+        assert_eq!(test.sync().await, SyncReport::default());
+    }
+
+    #[tokio::test]
+    async fn preferring_remote_moves_the_local_version_to_the_trash() {
+        let (test, _) = TestSync::with_conflict().await;
+
+        let report = test.sync_with(ConflictPolicy::PreferRemote).await;
+
+        assert_eq!(report.conflicts[0].resolution, Some(ConflictResolution::KeepRemote));
+        assert_eq!(test.local.content("notes.txt").unwrap(), b"remote notes");
+        assert_eq!(test.local.trashed(), ["notes.txt"]);
+        assert_eq!(test.sync().await, SyncReport::default());
+    }
+
+    #[tokio::test]
+    async fn a_conflict_the_user_resolved_is_synced() {
+        // Given a conflict that waits for the user
+        let (test, notes) = TestSync::with_conflict().await;
+        test.sync().await;
+
+        // When the user keeps the local version
+        test.synchronizer().resolve_conflict(notes, "notes.txt", ConflictResolution::KeepLocal).await.unwrap();
+
+        // Then kDrive has it and the next sync has nothing left to do
+        assert_eq!(test.api.content(notes).unwrap(), b"local notes");
+        assert_eq!(test.metadata.get_file(notes).unwrap().unwrap().sync_state, SyncState::Synced);
+        assert_eq!(test.sync().await, SyncReport::default());
+    }
+
+    #[tokio::test]
+    async fn failed_operations_are_reported_and_the_rest_continues() {
+        // Given a local file edited while the same path became a folder on kDrive
+        let test = TestSync::synced().await;
+        let notes = test.remote_id("notes.txt");
+        test.api.delete_file(TEST_DRIVE_ID, notes).await.unwrap();
+        test.local.add_file("new.txt", b"new");
+        test.local.add_file("Documents/report.pdf", b"final report");
+        test.api.add_directory(ROOT_DIRECTORY_ID, "new.txt");
+
+        // When syncing
+        let report = test.sync().await;
+
+        // Then the clash is a conflict and the other changes still go through
+        assert!(report.failed.is_empty());
+        assert!(report.completed.iter().any(|operation| matches!(operation, SyncOperation::Conflict { path, .. } if path == "new.txt")));
+        assert_eq!(test.api.content(test.remote_id("Documents/report.pdf")).unwrap(), b"final report");
+        assert_eq!(test.local.content("notes.txt"), None);
+    }
+
+    #[tokio::test]
+    async fn a_folder_that_is_no_longer_selected_leaves_the_sync_root() {
+        // Given a synced folder the user no longer wants on this computer
+        let test = TestSync::synced().await;
+        let documents = test.remote_id("Documents");
+        test.metadata.save_sync_selection(&SyncSelection::default().with_rule(documents, false)).unwrap();
+
+        // When syncing
+        test.sync().await;
+
+        // Then it goes to the local trash and stays on kDrive
+        assert_eq!(test.local.paths(), ["notes.txt"]);
+        assert_eq!(test.local.trashed(), ["Documents"]);
+        assert!(test.api.file_id(documents, "report.pdf").is_some());
+        assert_eq!(test.sync().await, SyncReport::default());
+    }
+
+    #[tokio::test]
+    async fn a_folder_that_is_selected_again_is_downloaded() {
+        let test = TestSync::synced().await;
+        let documents = test.remote_id("Documents");
+        test.metadata.save_sync_selection(&SyncSelection::default().with_rule(documents, false)).unwrap();
+        test.sync().await;
+
+        test.metadata.save_sync_selection(&SyncSelection::default()).unwrap();
+        test.sync().await;
+
+        assert_eq!(test.local.content("Documents/report.pdf").unwrap(), b"report");
+    }
+
+    #[tokio::test]
+    async fn a_folder_with_unsynced_changes_leaves_once_they_are_uploaded() {
+        // Given a folder that is no longer selected but holds a local edit
+        let test = TestSync::synced().await;
+        let documents = test.remote_id("Documents");
+        test.local.add_file("Documents/report.pdf", b"final report");
+        test.metadata.save_sync_selection(&SyncSelection::default().with_rule(documents, false)).unwrap();
+
+        // When syncing
+        test.sync().await;
+
+        // Then the edit is uploaded first and the folder only leaves with the next sync
+        assert_eq!(test.api.content(test.remote_id("Documents/report.pdf")).unwrap(), b"final report");
+        assert!(test.local.content("Documents/report.pdf").is_some());
+        test.sync().await;
+        assert_eq!(test.local.paths(), ["notes.txt"]);
+    }
+
+    #[tokio::test]
+    async fn nothing_is_uploaded_from_the_path_of_a_folder_that_was_never_selected() {
+        let api = FakeKDriveApi::new_default();
+        let documents = api.add_directory(ROOT_DIRECTORY_ID, "Documents");
+        let test = TestSync { metadata: FakeMetadataStore::new(), api, local: FakeLocalFs::new().with_file("Documents/draft.txt", b"draft") };
+        test.metadata.save_sync_selection(&SyncSelection::default().with_rule(documents, false)).unwrap();
+
+        test.sync().await;
+
+        assert!(test.api.file_id(documents, "draft.txt").is_none());
+        assert_eq!(test.local.content("Documents/draft.txt").unwrap(), b"draft");
+    }
+
+    #[tokio::test]
+    async fn ignored_local_files_are_not_uploaded() {
+        // Given a global pattern for temporary files and an ignore file for the build output
+        let test = TestSync::synced().await;
+        test.local.add_file("cache.tmp", b"cache");
+        test.local.add_file(".kdriveignore", b"# build output\nbuild/\n");
+        test.local.add_file("build/app.bin", b"binary");
+
+        // When syncing
+        test.sync_ignoring(&["*.tmp"]).await;
+
+        // Then only the ignore file itself goes to kDrive
+        assert!(test.api.file_id(ROOT_DIRECTORY_ID, ".kdriveignore").is_some());
+        assert!(test.api.file_id(ROOT_DIRECTORY_ID, "cache.tmp").is_none());
+        assert!(test.api.file_id(ROOT_DIRECTORY_ID, "build").is_none());
+    }
+
+    #[tokio::test]
+    async fn ignored_files_on_kdrive_are_not_downloaded() {
+        // Given a file on kDrive an ignore file in its directory leaves out
+        let test = TestSync::synced().await;
+        let documents = test.remote_id("Documents");
+        test.api.add_file(documents, "Thumbs.db", b"thumbnails");
+        test.local.add_file("Documents/.kdriveignore", b"Thumbs.db\n");
+
+        // When syncing
+        test.sync_ignoring(&[]).await;
+
+        // Then it stays on kDrive only
+        assert!(test.local.content("Documents/Thumbs.db").is_none());
+        assert!(test.api.file_id(documents, ".kdriveignore").is_some());
+    }
+
+    #[tokio::test]
+    async fn a_synced_file_that_becomes_ignored_stays_on_both_sides() {
+        // Given notes.txt is ignored after it was synced
+        let test = TestSync::synced().await;
+        let notes = test.remote_id("notes.txt");
+        test.local.add_file(".kdriveignore", b"notes.txt\n");
+        test.sync_ignoring(&[]).await;
+
+        // When it changes on kDrive
+        test.api.change_file(notes, b"remote notes");
+        test.sync_ignoring(&[]).await;
+
+        // Then neither side loses it and the change is not downloaded
+        assert_eq!(test.local.content("notes.txt").unwrap(), b"notes");
+        assert_eq!(test.api.content(notes).unwrap(), b"remote notes");
+        assert!(test.local.trashed().is_empty());
+    }
+
+    #[tokio::test]
+    async fn large_files_are_uploaded_in_chunks() {
+        // Given a local file of three chunks
+        let test = TestSync::synced().await;
+        test.local.add_file("video.mp4", b"0123456789");
+
+        // When syncing
+        let report = test.sync_in_chunks_of(4).await;
+
+        // Then kDrive puts the file together and no session is left behind
+        assert!(report.failed.is_empty());
+        let video = test.remote_id("video.mp4");
+        assert_eq!(test.api.content(video).unwrap(), b"0123456789");
+        assert_eq!(test.api.chunks_received(), 3);
+        assert_eq!(test.api.upload_session_count(), 0);
+        assert_eq!(test.metadata.upload_session("video.mp4").unwrap(), None);
+        assert_eq!(test.sync_in_chunks_of(4).await, SyncReport::default());
+    }
+
+    #[tokio::test]
+    async fn an_interrupted_upload_only_sends_the_missing_chunks() {
+        // Given an upload that lost its connection on the third chunk
+        let test = TestSync::synced().await;
+        test.local.add_file("video.mp4", b"0123456789");
+        test.api.fail_next_chunk(3);
+        let interrupted = test.sync_in_chunks_of(4).await;
+        assert_eq!(interrupted.failed.len(), 1);
+        assert_eq!(test.metadata.upload_session("video.mp4").unwrap().unwrap().uploaded_chunks, [1, 2].into());
+
+        // When the next sync runs
+        let report = test.sync_in_chunks_of(4).await;
+
+        // Then it finishes the same session with the last chunk
+        assert!(report.failed.is_empty());
+        assert_eq!(test.api.chunks_received(), 3);
+        assert_eq!(test.api.content(test.remote_id("video.mp4")).unwrap(), b"0123456789");
+    }
+
+    #[tokio::test]
+    async fn a_file_that_changed_after_an_interrupted_upload_starts_over() {
+        // Given an interrupted upload of a file that was edited afterwards
+        let test = TestSync::synced().await;
+        test.local.add_file("video.mp4", b"0123456789");
+        test.api.fail_next_chunk(3);
+        test.sync_in_chunks_of(4).await;
+        test.local.add_file("video.mp4", b"abcdefghijkl");
+
+        // When syncing
+        let report = test.sync_in_chunks_of(4).await;
+
+        // Then the old session is cancelled and the new content is uploaded
+        assert!(report.failed.is_empty());
+        assert_eq!(test.api.content(test.remote_id("video.mp4")).unwrap(), b"abcdefghijkl");
+        assert_eq!(test.api.upload_session_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn an_expired_upload_session_is_started_again() {
+        // Given an interrupted upload whose session kDrive dropped
+        let test = TestSync::synced().await;
+        let notes = test.remote_id("notes.txt");
+        test.local.add_file("notes.txt", b"notes that grew");
+        test.api.fail_next_chunk(3);
+        test.sync_in_chunks_of(4).await;
+        test.api.expire_upload_sessions();
+
+        // When syncing twice
+        let expired = test.sync_in_chunks_of(4).await;
+        let report = test.sync_in_chunks_of(4).await;
+
+        // Then the first sync forgets the session and the second uploads the new version
+        assert_eq!(expired.failed.len(), 1);
+        assert!(report.failed.is_empty());
+        assert_eq!(test.api.content(notes).unwrap(), b"notes that grew");
+        assert_eq!(test.metadata.upload_session("notes.txt").unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn an_interrupted_download_resumes_where_it_stopped() {
+        // Given a download of a changed file that lost its connection after five bytes
+        let test = TestSync::synced().await;
+        let notes = test.remote_id("notes.txt");
+        test.api.change_file(notes, b"notes that grew");
+        test.api.truncate_next_download(5);
+        let interrupted = test.sync().await;
+        assert_eq!(interrupted.failed.len(), 1);
+        assert_eq!(test.local.content("notes.txt").unwrap(), b"notes");
+        assert_eq!(test.local.partial("notes.txt").unwrap(), b"notes");
+
+        // When the next sync runs
+        let report = test.sync().await;
+
+        // Then only the rest is requested and the complete file replaces the old one
+        assert!(report.failed.is_empty());
+        assert_eq!(test.api.download_offsets().last(), Some(&5));
+        assert_eq!(test.local.content("notes.txt").unwrap(), b"notes that grew");
+        assert_eq!(test.local.partial("notes.txt"), None);
+    }
+
+    #[tokio::test]
+    async fn a_download_that_does_not_match_the_hash_starts_over() {
+        // Given a partial file of the version on kDrive that has other content than that version
+        let test = TestSync::synced().await;
+        let notes = test.remote_id("notes.txt");
+        test.api.change_file(notes, b"new notes");
+        test.local.add_partial("notes.txt", b"old n");
+        test.metadata.save_partial_download("notes.txt", &content_hash(b"new notes")).unwrap();
+
+        // When syncing twice
+        let corrupted = test.sync().await;
+        let report = test.sync().await;
+
+        // Then the first download is refused without touching the old file and the second one starts from scratch
+        assert_eq!(corrupted.failed[0].1.text_key, DownloadCorrupted);
+        assert!(report.failed.is_empty());
+        assert_eq!(test.api.download_offsets().last(), Some(&0));
+        assert_eq!(test.local.content("notes.txt").unwrap(), b"new notes");
+    }
+    #[tokio::test]
+    async fn a_partial_file_of_a_version_kdrive_replaced_is_not_resumed() {
+        // Given a download that stopped after five bytes and a file that changed on kDrive since
+        let test = TestSync::synced().await;
+        let notes = test.remote_id("notes.txt");
+        test.api.change_file(notes, b"notes that grew");
+        test.api.truncate_next_download(5);
+        test.sync().await;
+        test.api.change_file(notes, b"other notes again");
+
+        // When the next sync runs
+        let report = test.sync().await;
+
+        // Then the new version is downloaded from the start instead of being appended to the old one
+        assert!(report.failed.is_empty());
+        assert_eq!(test.api.download_offsets().last(), Some(&0));
+        assert_eq!(test.local.content("notes.txt").unwrap(), b"other notes again");
+        assert_eq!(test.metadata.partial_download("notes.txt").unwrap(), None);
+    }
+}
@@ -4,25 +4,29 @@ use oauth2::{AuthUrl, ClientId, RedirectUrl, TokenUrl};
 use url::Url;
 use common::application_error;
 use common::domain::errors::ApplicationError;
-use common::domain::text_keys::TextKeys::{FlowNotStarted, InvalidRedirectUrl};
+use common::domain::text_keys::TextKeys::{FlowNotStarted, InvalidRedirectUrl, TokenRequestFailed, TokenRevocationFailed};
 use crate::domain::test_helpers::fake_configurator_adapter::FakeConfiguratorPort;
 use crate::domain::test_helpers::fake_token_store_adapter::*;
 use crate::domain::tokens::Tokens;
-use crate::ports::driven::authenticator_driven_port::AuthenticatorDrivenPort;
+use crate::ports::driven::authenticator_driven_port::{AuthenticatorDrivenPort, PendingAuthorizationCode, PendingTokenRefresh};
 use crate::ports::driven::configurator_driven_port::ConfiguratorPort;
 
+pub const TEST_AUTHORIZATION_CODE: &str = "test-authorization-code";
+
 #[allow(dead_code)]
 pub struct FakeAuthenticatorDrivenAdapter {
     auth_url: AuthUrl,
     token_url: TokenUrl,
     client_id: ClientId,
     redirect_url: RedirectUrl,
-    should_fail: bool
+    should_fail: bool,
+    callback_arrives: bool,
+    token_endpoint_answers: bool,
 }
 
 impl FakeAuthenticatorDrivenAdapter {
     pub fn new(auth_url: AuthUrl, token_url: TokenUrl, client_id: ClientId, redirect_url: RedirectUrl) -> Self {
-        FakeAuthenticatorDrivenAdapter { auth_url, token_url, client_id, redirect_url, should_fail: false }
+        FakeAuthenticatorDrivenAdapter { auth_url, token_url, client_id, redirect_url, should_fail: false, callback_arrives: true, token_endpoint_answers: true }
     }
     pub fn new_default() -> Self {
         let port = FakeConfiguratorPort::with_client_id("test-client-id");
@@ -48,6 +52,20 @@ impl FakeAuthenticatorDrivenAdapter {
         adapter.should_fail = true;
         adapter
     }
+
+    /// The user never finishes logging in, so the authorization code never arrives
+    pub fn new_default_waiting() -> Self {
+        let mut adapter = FakeAuthenticatorDrivenAdapter::new_default();
+        adapter.callback_arrives = false;
+        adapter
+    }
+
+    /// The token endpoint never answers a refresh
+    pub fn new_default_unresponsive() -> Self {
+        let mut adapter = FakeAuthenticatorDrivenAdapter::new_default();
+        adapter.token_endpoint_answers = false;
+        adapter
+    }
 }
 
 #[async_trait]
@@ -61,13 +79,22 @@ impl AuthenticatorDrivenPort for FakeAuthenticatorDrivenAdapter {
         Ok(url.to_string())
     }
 
-    async fn continue_initial_auth_flow(&mut self) -> Result<(), ApplicationError> {
+    fn pending_authorization_code(&mut self) -> Result<PendingAuthorizationCode, ApplicationError> {
+        if !self.callback_arrives {
+            return Ok(Box::pin(std::future::pending()));
+        }
+        Ok(Box::pin(std::future::ready(Ok(TEST_AUTHORIZATION_CODE.to_string()))))
+    }
+
+    async fn exchange_authorization_code(&mut self, _code: &str) -> Result<(), ApplicationError> {
         if self.should_fail {
             return Err(application_error!(FlowNotStarted));
         }
         Ok(())
     }
 
+    async fn cancel_auth_flow(&mut self) {}
+
     async fn get_tokens(&self) -> Result<Tokens, ApplicationError> {
         if self.should_fail {
             return Err(application_error!(FlowNotStarted));
@@ -78,4 +105,25 @@ impl AuthenticatorDrivenPort for FakeAuthenticatorDrivenAdapter {
             expires_at: TEST_RING_EXPIRES_AT,
         })
     }
+
+    fn pending_token_refresh(&self, refresh_token: &str) -> PendingTokenRefresh {
+        if !self.token_endpoint_answers {
+            return Box::pin(std::future::pending());
+        }
+        if self.should_fail {
+            return Box::pin(std::future::ready(Err(application_error!(TokenRequestFailed, refresh_token))));
+        }
+        Box::pin(std::future::ready(Ok(Tokens {
+            access_token: TEST_REFRESHED_ACCESS_TOKEN.to_string(),
+            refresh_token: TEST_REFRESHED_REFRESH_TOKEN.to_string(),
+            expires_at: TEST_REFRESHED_EXPIRES_AT,
+        })))
+    }
+
+    async fn revoke_tokens(&mut self, refresh_token: &str) -> Result<(), ApplicationError> {
+        if self.should_fail {
+            return Err(application_error!(TokenRevocationFailed, refresh_token));
+        }
+        Ok(())
+    }
 }
\ No newline at end of file
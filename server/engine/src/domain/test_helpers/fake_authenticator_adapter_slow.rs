@@ -2,7 +2,7 @@ use async_trait::async_trait;
 use common::domain::errors::ApplicationError;
 use crate::domain::test_helpers::fake_token_store_adapter::{TEST_RING_ACCESS_TOKEN, TEST_RING_EXPIRES_AT, TEST_RING_REFRESH_TOKEN};
 use crate::domain::tokens::Tokens;
-use crate::ports::driven::authenticator_driven_port::AuthenticatorDrivenPort;
+use crate::ports::driven::authenticator_driven_port::{AuthenticatorDrivenPort, PendingAuthorizationCode, PendingTokenRefresh};
 
 pub struct SlowAuthenticatorAdapter;
 
@@ -12,11 +12,20 @@ impl AuthenticatorDrivenPort for SlowAuthenticatorAdapter {
         Ok("http://example.com".to_string())
     }
 
-    async fn continue_initial_auth_flow(&mut self) -> Result<(), ApplicationError> {
+    fn pending_authorization_code(&mut self) -> Result<PendingAuthorizationCode, ApplicationError> {
+        Ok(Box::pin(async {
+            tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+            Ok("slow-authorization-code".to_string())
+        }))
+    }
+
+    async fn exchange_authorization_code(&mut self, _code: &str) -> Result<(), ApplicationError> {
         tokio::time::sleep(std::time::Duration::from_secs(2)).await;
         Ok(())
     }
 
+    async fn cancel_auth_flow(&mut self) {}
+
     async fn get_tokens(&self) -> Result<Tokens, ApplicationError> {
         tokio::time::sleep(std::time::Duration::from_secs(2)).await;
         Ok(Tokens {
@@ -25,4 +34,20 @@ impl AuthenticatorDrivenPort for SlowAuthenticatorAdapter {
             expires_at: TEST_RING_EXPIRES_AT,
         })
     }
+
+    fn pending_token_refresh(&self, _refresh_token: &str) -> PendingTokenRefresh {
+        Box::pin(async {
+            tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+            Ok(Tokens {
+                access_token: TEST_RING_ACCESS_TOKEN.parse().unwrap(),
+                refresh_token: TEST_RING_REFRESH_TOKEN.parse().unwrap(),
+                expires_at: TEST_RING_EXPIRES_AT,
+            })
+        })
+    }
+
+    async fn revoke_tokens(&mut self, _refresh_token: &str) -> Result<(), ApplicationError> {
+        tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+        Ok(())
+    }
 }
\ No newline at end of file
@@ -0,0 +1,30 @@
+use crate::ports::driven::clock_driven_port::ClockDrivenPort;
+
+pub const TEST_NOW: i64 = 1_700_000_000;
+
+#[derive(Clone, Debug)]
+pub struct FakeClock {
+    now: i64,
+}
+
+impl FakeClock {
+    pub fn new() -> Self {
+        FakeClock::at(TEST_NOW)
+    }
+
+    pub fn at(now: i64) -> Self {
+        FakeClock { now }
+    }
+}
+
+impl Default for FakeClock {
+    fn default() -> Self {
+        FakeClock::new()
+    }
+}
+
+impl ClockDrivenPort for FakeClock {
+    fn now(&self) -> i64 {
+        self.now
+    }
+}
@@ -1,7 +1,12 @@
-use oauth2::{AuthUrl, ClientId, RedirectUrl, TokenUrl};
+use std::path::PathBuf;
+use std::time::Duration;
+use oauth2::{AuthUrl, ClientId, RedirectUrl, RevocationUrl, TokenUrl};
+use oauth2::url::Url;
 use common::domain::errors::ApplicationError;
+use common::domain::defaults::SESSION_SECRET_FILE_NAME;
 use crate::domain::configuration::Configuration;
 use crate::domain::default_values::configurator_defaults::*;
+use crate::domain::default_values::general_defaults::APPLICATION_NAME;
 use crate::ports::driven::configurator_driven_port::ConfiguratorPort;
 
 pub struct FakeConfiguratorPort {
@@ -29,6 +34,22 @@ impl ConfiguratorPort for FakeConfiguratorPort {
             token_url: TokenUrl::new(DEFAULT_TOKEN_URL.to_string())?,
             client_id: ClientId::new(self.client_id.clone()),
             redirect_url: RedirectUrl::new(DEFAULT_REDIRECT_URL.to_string())?,
+            fallback_redirect_ports: DEFAULT_REDIRECT_FALLBACK_PORTS.to_vec(),
+            revocation_url: RevocationUrl::new(DEFAULT_REVOCATION_URL.to_string())?,
+            api_url: Url::parse(DEFAULT_API_URL)?,
+            api_connect_timeout: Duration::from_secs(DEFAULT_API_CONNECT_TIMEOUT_SECONDS),
+            api_read_timeout: Duration::from_secs(DEFAULT_API_READ_TIMEOUT_SECONDS),
+            auth_flow_timeout: Duration::from_secs(DEFAULT_AUTH_FLOW_TIMEOUT_SECONDS),
+            grpc_listen_addr: DEFAULT_GRPC_LISTEN_ADDRESS.parse().unwrap(),
+            grpc_socket_path: None,
+            session_secret_path: PathBuf::from(APPLICATION_NAME).join(SESSION_SECRET_FILE_NAME),
+            sync_root: PathBuf::from(DEFAULT_SYNC_ROOT_FOLDER_NAME),
+            data_dir: PathBuf::from(APPLICATION_NAME),
+            log_level: DEFAULT_LOG_LEVEL,
+            conflict_policy: DEFAULT_CONFLICT_POLICY,
+            ignore_patterns: DEFAULT_IGNORE_PATTERNS.iter().map(|pattern| pattern.to_string()).collect(),
+            upload_chunk_size: DEFAULT_UPLOAD_CHUNK_SIZE,
+            upload_parallelism: DEFAULT_UPLOAD_PARALLELISM,
         })
     }
 }
\ No newline at end of file
@@ -0,0 +1,473 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{Cursor, ErrorKind};
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::task::{Context, Poll};
+use async_trait::async_trait;
+use tokio::io::{AsyncRead, AsyncReadExt, ReadBuf};
+use common::application_error;
+use common::domain::errors::ApplicationError;
+use common::domain::text_keys::TextKeys::{ApiConflict, ApiNotFound, ApiRequestFailed};
+use crate::domain::content_hash::content_hash;
+use crate::domain::default_values::general_defaults::ROOT_DIRECTORY_ID;
+use crate::domain::remote_file::{ChangePage, Drive, DriveId, FileId, FileKind, FilePage, RemoteChange, RemoteFile};
+use crate::domain::upload_session::{chunk_hash, UploadTarget};
+use crate::ports::driven::kdrive_api_driven_port::{KDriveApiDrivenPort, RemoteFileReader};
+
+pub const TEST_DRIVE_ID: DriveId = 100;
+pub const TEST_DRIVE_NAME: &str = "test drive";
+pub const TEST_MODIFIED_AT: i64 = 1_700_000_000;
+
+/// An in-memory kDrive with a single drive, files keep their content so downloads return what was uploaded
+pub struct FakeKDriveApi {
+    state: Mutex<FakeDriveState>,
+    page_size: usize,
+    failure: Option<ApplicationError>,
+}
+
+struct FakeDriveState {
+    drive_id: DriveId,
+    files: BTreeMap<FileId, (RemoteFile, Vec<u8>)>,
+    trash: HashSet<FileId>,
+    changes: Vec<RemoteChange>,
+    next_id: FileId,
+    uploads: HashMap<String, FakeUpload>,
+    next_upload: u64,
+    failing_chunks: HashSet<u64>,
+    chunks_received: usize,
+    truncated_download: Option<u64>,
+    stalled_download: bool,
+    stalled_listing: bool,
+    download_offsets: Vec<u64>,
+}
+
+/// A chunked upload that was started and not finished yet
+struct FakeUpload {
+    target: UploadTarget,
+    total_size: u64,
+    total_chunks: u64,
+    chunks: BTreeMap<u64, Vec<u8>>,
+}
+
+impl FakeKDriveApi {
+    pub fn new_default() -> Self {
+        let root = RemoteFile {
+            id: ROOT_DIRECTORY_ID,
+            parent_id: ROOT_DIRECTORY_ID,
+            name: String::new(),
+            kind: FileKind::Directory,
+            size: None,
+            last_modified_at: TEST_MODIFIED_AT,
+            hash: None,
+        };
+
+        Self {
+            state: Mutex::new(FakeDriveState {
+                drive_id: TEST_DRIVE_ID,
+                files: BTreeMap::from([(ROOT_DIRECTORY_ID, (root, Vec::new()))]),
+                trash: HashSet::new(),
+                changes: Vec::new(),
+                next_id: ROOT_DIRECTORY_ID + 1,
+                uploads: HashMap::new(),
+                next_upload: 1,
+                failing_chunks: HashSet::new(),
+                chunks_received: 0,
+                truncated_download: None,
+                stalled_download: false,
+                stalled_listing: false,
+                download_offsets: Vec::new(),
+            }),
+            page_size: usize::MAX,
+            failure: None,
+        }
+    }
+
+    pub fn with_directory(self, parent_id: FileId, name: &str) -> Self {
+        self.add_directory(parent_id, name);
+        self
+    }
+
+    pub fn with_file(self, parent_id: FileId, name: &str, content: &[u8]) -> Self {
+        self.add_file(parent_id, name, content);
+        self
+    }
+
+    /// Changes the drive behind the back of the client, like another device would
+    pub fn add_directory(&self, parent_id: FileId, name: &str) -> FileId {
+        self.add(parent_id, name, FileKind::Directory, Vec::new())
+    }
+
+    pub fn add_file(&self, parent_id: FileId, name: &str, content: &[u8]) -> FileId {
+        self.add(parent_id, name, FileKind::File, content.to_vec())
+    }
+
+    /// Edits a file like another device would, the new content gets a later modification time
+    pub fn change_file(&self, file_id: FileId, content: &[u8]) {
+        self.state.lock().unwrap().replace_content(file_id, content.to_vec()).unwrap();
+    }
+
+    pub fn content(&self, file_id: FileId) -> Option<Vec<u8>> {
+        self.state.lock().unwrap().files.get(&file_id).map(|(_, content)| content.clone())
+    }
+
+    pub fn with_page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size;
+        self
+    }
+
+    /// Every call fails with this error, like an unreachable or refusing kDrive
+    pub fn failing_with(mut self, error: ApplicationError) -> Self {
+        self.failure = Some(error);
+        self
+    }
+
+    pub fn file_id(&self, parent_id: FileId, name: &str) -> Option<FileId> {
+        let state = self.state.lock().unwrap();
+        state.find(parent_id, name)
+    }
+
+    pub fn is_in_trash(&self, file_id: FileId) -> bool {
+        self.state.lock().unwrap().trash.contains(&file_id)
+    }
+
+    /// The next upload of the chunk fails like a dropped connection would
+    pub fn fail_next_chunk(&self, chunk_number: u64) {
+        self.state.lock().unwrap().failing_chunks.insert(chunk_number);
+    }
+
+    /// The next download drops its connection after this many bytes
+    pub fn truncate_next_download(&self, after: u64) {
+        self.state.lock().unwrap().truncated_download = Some(after);
+    }
+
+    /// The next download never answers, like kDrive on a connection that hangs
+    pub fn stall_next_download(&self) {
+        self.state.lock().unwrap().stalled_download = true;
+    }
+
+    /// The next listing of a directory never answers
+    pub fn stall_next_listing(&self) {
+        self.state.lock().unwrap().stalled_listing = true;
+    }
+
+    /// Where every download started, 0 unless it resumed a partial file
+    pub fn download_offsets(&self) -> Vec<u64> {
+        self.state.lock().unwrap().download_offsets.clone()
+    }
+
+    /// The chunks kDrive accepted over all upload sessions
+    pub fn chunks_received(&self) -> usize {
+        self.state.lock().unwrap().chunks_received
+    }
+
+    pub fn upload_session_count(&self) -> usize {
+        self.state.lock().unwrap().uploads.len()
+    }
+
+    /// The account now has access to another drive with nothing but the root, like after logging in to another account
+    pub fn replace_drive(&self, drive_id: DriveId) {
+        let mut state = self.state.lock().unwrap();
+        state.drive_id = drive_id;
+        state.files.retain(|id, _| *id == ROOT_DIRECTORY_ID);
+        state.trash.clear();
+        state.changes.clear();
+        state.uploads.clear();
+    }
+
+    /// Drops the upload sessions that did not finish, like kDrive does when they expire
+    pub fn expire_upload_sessions(&self) {
+        self.state.lock().unwrap().uploads.clear();
+    }
+
+    fn add(&self, parent_id: FileId, name: &str, kind: FileKind, content: Vec<u8>) -> FileId {
+        let mut state = self.state.lock().unwrap();
+        let id = state.next_id;
+        state.next_id += 1;
+
+        let file = RemoteFile {
+            id,
+            parent_id,
+            name: name.to_string(),
+            kind,
+            size: (kind == FileKind::File).then_some(content.len() as u64),
+            last_modified_at: TEST_MODIFIED_AT,
+            hash: (kind == FileKind::File).then(|| content_hash(&content)),
+        };
+        state.changes.push(RemoteChange::Created(file.clone()));
+        state.files.insert(id, (file, content));
+        id
+    }
+
+    fn check(&self, drive_id: DriveId) -> Result<(), ApplicationError> {
+        if let Some(failure) = &self.failure {
+            return Err(failure.clone());
+        }
+        if drive_id != self.state.lock().unwrap().drive_id {
+            return Err(application_error!(ApiNotFound, drive_id));
+        }
+        Ok(())
+    }
+}
+
+/// The end of a download whose connection dropped
+struct DroppedConnection;
+
+impl AsyncRead for DroppedConnection {
+    fn poll_read(self: Pin<&mut Self>, _: &mut Context<'_>, _: &mut ReadBuf<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Err(std::io::Error::new(ErrorKind::ConnectionReset, "connection reset")))
+    }
+}
+
+impl FakeDriveState {
+    fn find(&self, parent_id: FileId, name: &str) -> Option<FileId> {
+        self.files
+            .values()
+            .filter(|(file, _)| file.id != ROOT_DIRECTORY_ID && !self.trash.contains(&file.id))
+            .find(|(file, _)| file.parent_id == parent_id && file.name == name)
+            .map(|(file, _)| file.id)
+    }
+
+    fn replace_content(&mut self, file_id: FileId, content: Vec<u8>) -> Result<RemoteFile, ApplicationError> {
+        let (file, current) = self.existing(file_id)?;
+        file.size = Some(content.len() as u64);
+        file.hash = Some(content_hash(&content));
+        file.last_modified_at += 1;
+        *current = content;
+        let file = file.clone();
+        self.changes.push(RemoteChange::Updated(file.clone()));
+        Ok(file)
+    }
+
+    fn moved(&mut self, file_id: FileId) -> Result<(), ApplicationError> {
+        let file = self.existing(file_id)?.0.clone();
+        self.changes.push(RemoteChange::Moved(file));
+        Ok(())
+    }
+
+    fn existing(&mut self, file_id: FileId) -> Result<&mut (RemoteFile, Vec<u8>), ApplicationError> {
+        if self.trash.contains(&file_id) {
+            return Err(application_error!(ApiNotFound, file_id));
+        }
+        self.files
+            .get_mut(&file_id)
+            .ok_or(application_error!(ApiNotFound, file_id))
+    }
+}
+
+#[async_trait]
+impl KDriveApiDrivenPort for FakeKDriveApi {
+    async fn list_drives(&self) -> Result<Vec<Drive>, ApplicationError> {
+        let drive_id = self.state.lock().unwrap().drive_id;
+        self.check(drive_id)?;
+        Ok(vec![Drive { id: drive_id, name: TEST_DRIVE_NAME.to_string() }])
+    }
+
+    async fn list_files(&self, drive_id: DriveId, directory_id: FileId, cursor: Option<&str>) -> Result<FilePage, ApplicationError> {
+        self.check(drive_id)?;
+        let stalled = std::mem::take(&mut self.state.lock().unwrap().stalled_listing);
+        if stalled {
+            std::future::pending::<()>().await;
+        }
+        let mut state = self.state.lock().unwrap();
+        state.existing(directory_id)?;
+
+        let children: Vec<RemoteFile> = state.files
+            .values()
+            .filter(|(file, _)| file.parent_id == directory_id && file.id != ROOT_DIRECTORY_ID)
+            .filter(|(file, _)| !state.trash.contains(&file.id))
+            .map(|(file, _)| file.clone())
+            .collect();
+
+        let start = cursor.and_then(|cursor| cursor.parse::<usize>().ok()).unwrap_or(0);
+        let end = start.saturating_add(self.page_size).min(children.len());
+        let has_more = end < children.len();
+
+        Ok(FilePage {
+            files: children[start.min(end)..end].to_vec(),
+            cursor: has_more.then(|| end.to_string()),
+            has_more,
+        })
+    }
+
+    async fn list_changes(&self, drive_id: DriveId, cursor: Option<&str>) -> Result<ChangePage, ApplicationError> {
+        self.check(drive_id)?;
+        let state = self.state.lock().unwrap();
+        let total = state.changes.len();
+        let start = match cursor {
+            None => total,
+            Some(cursor) => cursor.parse::<usize>().ok().filter(|start| *start <= total).ok_or(application_error!(ApiNotFound, cursor))?,
+        };
+        let end = start.saturating_add(self.page_size).min(total);
+
+        Ok(ChangePage {
+            changes: state.changes[start..end].to_vec(),
+            cursor: end.to_string(),
+            has_more: end < total,
+        })
+    }
+
+    async fn get_file(&self, drive_id: DriveId, file_id: FileId) -> Result<RemoteFile, ApplicationError> {
+        self.check(drive_id)?;
+        let mut state = self.state.lock().unwrap();
+        Ok(state.existing(file_id)?.0.clone())
+    }
+
+    async fn download_file_from(&self, drive_id: DriveId, file_id: FileId, offset: u64) -> Result<RemoteFileReader, ApplicationError> {
+        self.check(drive_id)?;
+        let stalled = std::mem::take(&mut self.state.lock().unwrap().stalled_download);
+        if stalled {
+            std::future::pending::<()>().await;
+        }
+        let mut state = self.state.lock().unwrap();
+        let content = state.existing(file_id)?.1.clone();
+        let rest = content.get(offset as usize..).unwrap_or_default().to_vec();
+        state.download_offsets.push(offset);
+        match state.truncated_download.take() {
+            Some(after) => Ok(Box::new(Cursor::new(rest).take(after).chain(DroppedConnection))),
+            None => Ok(Box::new(Cursor::new(rest))),
+        }
+    }
+
+    async fn upload_file(&self, drive_id: DriveId, directory_id: FileId, name: &str, content: Vec<u8>) -> Result<RemoteFile, ApplicationError> {
+        self.check(drive_id)?;
+        {
+            let mut state = self.state.lock().unwrap();
+            state.existing(directory_id)?;
+            if state.find(directory_id, name).is_some() {
+                return Err(application_error!(ApiConflict, name));
+            }
+        }
+
+        let id = self.add(directory_id, name, FileKind::File, content);
+        self.get_file(drive_id, id).await
+    }
+
+    async fn update_file(&self, drive_id: DriveId, file_id: FileId, content: Vec<u8>) -> Result<RemoteFile, ApplicationError> {
+        self.check(drive_id)?;
+        self.state.lock().unwrap().replace_content(file_id, content)
+    }
+
+    async fn start_upload_session(&self, drive_id: DriveId, target: &UploadTarget, total_size: u64, total_chunks: u64) -> Result<String, ApplicationError> {
+        self.check(drive_id)?;
+        let mut state = self.state.lock().unwrap();
+        match target {
+            UploadTarget::NewFile { directory_id, name } => {
+                state.existing(*directory_id)?;
+                if state.find(*directory_id, name).is_some() {
+                    return Err(application_error!(ApiConflict, name));
+                }
+            }
+            UploadTarget::NewVersion { file_id } => {
+                state.existing(*file_id)?;
+            }
+        }
+
+        let token = format!("upload-{}", state.next_upload);
+        state.next_upload += 1;
+        state.uploads.insert(token.clone(), FakeUpload { target: target.clone(), total_size, total_chunks, chunks: BTreeMap::new() });
+        Ok(token)
+    }
+
+    async fn upload_chunk(&self, drive_id: DriveId, token: &str, chunk_number: u64, content: Vec<u8>, hash: &str) -> Result<(), ApplicationError> {
+        self.check(drive_id)?;
+        let mut state = self.state.lock().unwrap();
+        if state.failing_chunks.remove(&chunk_number) {
+            return Err(application_error!(ApiRequestFailed, "connection reset"));
+        }
+        let upload = state.uploads.get_mut(token).ok_or(application_error!(ApiNotFound, token))?;
+        if chunk_number == 0 || chunk_number > upload.total_chunks {
+            return Err(application_error!(ApiRequestFailed, "chunk {} of {}", chunk_number, upload.total_chunks));
+        }
+        if chunk_hash(&content) != hash {
+            return Err(application_error!(ApiRequestFailed, "chunk {} does not match its hash", chunk_number));
+        }
+        upload.chunks.insert(chunk_number, content);
+        state.chunks_received += 1;
+        Ok(())
+    }
+
+    async fn finish_upload_session(&self, drive_id: DriveId, token: &str) -> Result<RemoteFile, ApplicationError> {
+        self.check(drive_id)?;
+        let (target, content) = {
+            let mut state = self.state.lock().unwrap();
+            let upload = state.uploads.get(token).ok_or(application_error!(ApiNotFound, token))?;
+            let content: Vec<u8> = upload.chunks.values().flatten().copied().collect();
+            if upload.chunks.len() as u64 != upload.total_chunks || content.len() as u64 != upload.total_size {
+                return Err(application_error!(ApiRequestFailed, "upload {} is not complete", token));
+            }
+            let target = upload.target.clone();
+            state.uploads.remove(token);
+            (target, content)
+        };
+
+        match target {
+            UploadTarget::NewFile { directory_id, name } => self.upload_file(drive_id, directory_id, &name, content).await,
+            UploadTarget::NewVersion { file_id } => self.update_file(drive_id, file_id, content).await,
+        }
+    }
+
+    async fn cancel_upload_session(&self, drive_id: DriveId, token: &str) -> Result<(), ApplicationError> {
+        self.check(drive_id)?;
+        match self.state.lock().unwrap().uploads.remove(token) {
+            Some(_) => Ok(()),
+            None => Err(application_error!(ApiNotFound, token)),
+        }
+    }
+
+    async fn create_directory(&self, drive_id: DriveId, parent_id: FileId, name: &str) -> Result<RemoteFile, ApplicationError> {
+        self.check(drive_id)?;
+        {
+            let mut state = self.state.lock().unwrap();
+            state.existing(parent_id)?;
+            if state.find(parent_id, name).is_some() {
+                return Err(application_error!(ApiConflict, name));
+            }
+        }
+
+        let id = self.add_directory(parent_id, name);
+        self.get_file(drive_id, id).await
+    }
+
+    async fn move_file(&self, drive_id: DriveId, file_id: FileId, destination_directory_id: FileId) -> Result<(), ApplicationError> {
+        self.check(drive_id)?;
+        let mut state = self.state.lock().unwrap();
+        state.existing(destination_directory_id)?;
+        let name = state.existing(file_id)?.0.name.clone();
+        if state.find(destination_directory_id, &name).is_some() {
+            return Err(application_error!(ApiConflict, name));
+        }
+        state.existing(file_id)?.0.parent_id = destination_directory_id;
+        state.moved(file_id)
+    }
+
+    async fn rename_file(&self, drive_id: DriveId, file_id: FileId, name: &str) -> Result<(), ApplicationError> {
+        self.check(drive_id)?;
+        let mut state = self.state.lock().unwrap();
+        let parent_id = state.existing(file_id)?.0.parent_id;
+        if state.find(parent_id, name).is_some() {
+            return Err(application_error!(ApiConflict, name));
+        }
+        state.existing(file_id)?.0.name = name.to_string();
+        state.moved(file_id)
+    }
+
+    async fn delete_file(&self, drive_id: DriveId, file_id: FileId) -> Result<(), ApplicationError> {
+        self.check(drive_id)?;
+        let mut state = self.state.lock().unwrap();
+        state.existing(file_id)?;
+        state.trash.insert(file_id);
+        state.changes.push(RemoteChange::Deleted(file_id));
+        Ok(())
+    }
+
+    async fn restore_file(&self, drive_id: DriveId, file_id: FileId) -> Result<(), ApplicationError> {
+        self.check(drive_id)?;
+        let mut state = self.state.lock().unwrap();
+        if !state.trash.remove(&file_id) {
+            return Err(application_error!(ApiNotFound, file_id));
+        }
+        let file = state.existing(file_id)?.0.clone();
+        state.changes.push(RemoteChange::Created(file));
+        Ok(())
+    }
+}
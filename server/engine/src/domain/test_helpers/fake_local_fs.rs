@@ -0,0 +1,300 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use async_trait::async_trait;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use common::application_error;
+use common::domain::errors::ApplicationError;
+use common::domain::text_keys::TextKeys::{LocalFileError, LocalFileExists, LocalFileNotFound, NotADirectory};
+use crate::domain::local_file::LocalFile;
+use crate::domain::remote_file::FileKind;
+use crate::domain::sync::sync_path::{is_below, moved_path, parent_path};
+use crate::ports::driven::local_fs_driven_port::{LocalFileReader, LocalFsDrivenPort};
+
+pub const TEST_LOCAL_MODIFIED_AT: i64 = 1_750_000_000;
+
+/// An in-memory sync root, every write moves its clock on so edits always get a later modification time
+pub struct FakeLocalFs {
+    state: Mutex<FakeLocalState>,
+}
+
+struct FakeLocalState {
+    entries: BTreeMap<String, FakeLocalEntry>,
+    partials: BTreeMap<String, Vec<u8>>,
+    trashed: Vec<String>,
+    edit_during_transfer: Option<(String, Vec<u8>)>,
+    now: i64,
+}
+
+#[derive(Clone)]
+struct FakeLocalEntry {
+    kind: FileKind,
+    content: Vec<u8>,
+    modified_at: i64,
+}
+
+impl FakeLocalFs {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(FakeLocalState {
+                entries: BTreeMap::new(),
+                partials: BTreeMap::new(),
+                trashed: Vec::new(),
+                edit_during_transfer: None,
+                now: TEST_LOCAL_MODIFIED_AT,
+            }),
+        }
+    }
+
+    pub fn with_directory(self, path: &str) -> Self {
+        self.add_directory(path);
+        self
+    }
+
+    pub fn with_file(self, path: &str, content: &[u8]) -> Self {
+        self.add_file(path, content);
+        self
+    }
+
+    /// Changes the sync root like the user would, missing parent directories are created
+    pub fn add_directory(&self, path: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.create_parents(path);
+        state.insert(path, FileKind::Directory, Vec::new());
+    }
+
+    pub fn add_file(&self, path: &str, content: &[u8]) {
+        let mut state = self.state.lock().unwrap();
+        state.create_parents(path);
+        state.insert(path, FileKind::File, content.to_vec());
+    }
+
+    pub fn remove(&self, path: &str) {
+        self.state.lock().unwrap().entries.retain(|entry, _| entry != path && !is_below(entry, path));
+    }
+
+    pub fn move_path(&self, from: &str, to: &str) {
+        self.state.lock().unwrap().move_entries(from, to);
+    }
+
+    pub fn content(&self, path: &str) -> Option<Vec<u8>> {
+        self.state.lock().unwrap().entries.get(path).map(|entry| entry.content.clone())
+    }
+
+    pub fn modified_at(&self, path: &str) -> Option<i64> {
+        self.state.lock().unwrap().entries.get(path).map(|entry| entry.modified_at)
+    }
+
+    /// Like a download that was interrupted after `content`
+    pub fn add_partial(&self, path: &str, content: &[u8]) {
+        self.state.lock().unwrap().partials.insert(path.to_string(), content.to_vec());
+    }
+
+    /// The user writes `content` to the file while the next download to it or upload from it is going on
+    pub fn edit_during_next_transfer(&self, path: &str, content: &[u8]) {
+        self.state.lock().unwrap().edit_during_transfer = Some((path.to_string(), content.to_vec()));
+    }
+
+    pub fn partial(&self, path: &str) -> Option<Vec<u8>> {
+        self.state.lock().unwrap().partials.get(path).cloned()
+    }
+
+    /// The paths moved to the trash, in the order they went there
+    pub fn trashed(&self) -> Vec<String> {
+        self.state.lock().unwrap().trashed.clone()
+    }
+
+    /// Every path in the sync root, sorted
+    pub fn paths(&self) -> Vec<String> {
+        self.state.lock().unwrap().entries.keys().cloned().collect()
+    }
+}
+
+impl Default for FakeLocalFs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FakeLocalState {
+    fn insert(&mut self, path: &str, kind: FileKind, content: Vec<u8>) {
+        self.now += 1;
+        let modified_at = self.now;
+        self.entries.insert(path.to_string(), FakeLocalEntry { kind, content, modified_at });
+    }
+
+    fn create_parents(&mut self, path: &str) {
+        let parent = parent_path(path);
+        if !parent.is_empty() && !self.entries.contains_key(parent) {
+            self.create_parents(parent);
+            self.insert(parent, FileKind::Directory, Vec::new());
+        }
+    }
+
+    fn move_entries(&mut self, from: &str, to: &str) {
+        let moved: Vec<_> = self.entries.keys()
+            .filter_map(|path| moved_path(path, from, to).map(|new_path| (path.clone(), new_path)))
+            .collect();
+        for (path, new_path) in moved {
+            let entry = self.entries.remove(&path).unwrap();
+            self.entries.insert(new_path, entry);
+        }
+    }
+
+    fn edit_after_transfer(&mut self, path: &str) {
+        if self.edit_during_transfer.as_ref().is_some_and(|(edited, _)| edited == path) {
+            let (_, content) = self.edit_during_transfer.take().unwrap();
+            self.insert(path, FileKind::File, content);
+        }
+    }
+
+    fn existing(&self, path: &str) -> Result<&FakeLocalEntry, ApplicationError> {
+        self.entries.get(path).ok_or_else(|| application_error!(LocalFileNotFound, path))
+    }
+
+    fn check_directory(&self, path: &str) -> Result<(), ApplicationError> {
+        if path.is_empty() {
+            return Ok(());
+        }
+        match self.existing(path)?.kind {
+            FileKind::Directory => Ok(()),
+            FileKind::File => Err(application_error!(NotADirectory, path)),
+        }
+    }
+
+    fn check_free(&self, path: &str) -> Result<(), ApplicationError> {
+        if self.entries.contains_key(path) {
+            return Err(application_error!(LocalFileExists, path));
+        }
+        Ok(())
+    }
+
+    fn local_file(path: &str, entry: &FakeLocalEntry) -> LocalFile {
+        LocalFile {
+            path: path.to_string(),
+            kind: entry.kind,
+            size: (entry.kind == FileKind::File).then_some(entry.content.len() as u64),
+            modified_at: entry.modified_at,
+        }
+    }
+}
+
+#[async_trait]
+impl LocalFsDrivenPort for FakeLocalFs {
+    async fn read_dir(&self, path: &str) -> Result<Vec<LocalFile>, ApplicationError> {
+        let state = self.state.lock().unwrap();
+        state.check_directory(path)?;
+        Ok(state.entries.iter()
+            .filter(|(entry, _)| is_below(entry, path) && parent_path(entry) == path)
+            .map(|(entry_path, entry)| FakeLocalState::local_file(entry_path, entry))
+            .collect())
+    }
+
+    async fn stat(&self, path: &str) -> Result<Option<LocalFile>, ApplicationError> {
+        let state = self.state.lock().unwrap();
+        Ok(state.entries.get(path).map(|entry| FakeLocalState::local_file(path, entry)))
+    }
+
+    async fn open_file(&self, path: &str) -> Result<LocalFileReader, ApplicationError> {
+        let mut state = self.state.lock().unwrap();
+        let entry = state.existing(path)?;
+        if entry.kind == FileKind::Directory {
+            return Err(application_error!(LocalFileExists, path));
+        }
+        let content = entry.content.clone();
+        state.edit_after_transfer(path);
+        Ok(Box::new(std::io::Cursor::new(content)))
+    }
+
+    async fn write_file_from(&self, path: &str, content: &mut (dyn AsyncRead + Send + Unpin)) -> Result<u64, ApplicationError> {
+        let mut buffer = Vec::new();
+        content.read_to_end(&mut buffer).await.map_err(|e| application_error!(LocalFileError, "{}: {}", path, e))?;
+
+        let mut state = self.state.lock().unwrap();
+        state.check_directory(parent_path(path))?;
+        if state.entries.get(path).is_some_and(|entry| entry.kind == FileKind::Directory) {
+            return Err(application_error!(LocalFileExists, path));
+        }
+        let size = buffer.len() as u64;
+        state.insert(path, FileKind::File, buffer);
+        Ok(size)
+    }
+
+    async fn partial_size(&self, path: &str) -> Result<u64, ApplicationError> {
+        Ok(self.state.lock().unwrap().partials.get(path).map_or(0, |content| content.len() as u64))
+    }
+
+    async fn append_partial(&self, path: &str, content: &mut (dyn AsyncRead + Send + Unpin)) -> Result<u64, ApplicationError> {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.check_directory(parent_path(path))?;
+            state.partials.entry(path.to_string()).or_default();
+        }
+        let mut appended = 0;
+        let mut buffer = [0; 4096];
+        loop {
+            let read = content.read(&mut buffer).await.map_err(|e| application_error!(LocalFileError, "{}: {}", path, e))?;
+            if read == 0 {
+                self.state.lock().unwrap().edit_after_transfer(path);
+                return Ok(appended);
+            }
+            self.state.lock().unwrap().partials.entry(path.to_string()).or_default().extend_from_slice(&buffer[..read]);
+            appended += read as u64;
+        }
+    }
+
+    async fn open_partial(&self, path: &str) -> Result<LocalFileReader, ApplicationError> {
+        let state = self.state.lock().unwrap();
+        let content = state.partials.get(path).ok_or_else(|| application_error!(LocalFileNotFound, path))?;
+        Ok(Box::new(std::io::Cursor::new(content.clone())))
+    }
+
+    async fn finish_partial(&self, path: &str) -> Result<(), ApplicationError> {
+        let mut state = self.state.lock().unwrap();
+        state.check_directory(parent_path(path))?;
+        if state.entries.get(path).is_some_and(|entry| entry.kind == FileKind::Directory) {
+            return Err(application_error!(LocalFileExists, path));
+        }
+        let content = state.partials.remove(path).ok_or_else(|| application_error!(LocalFileNotFound, path))?;
+        state.insert(path, FileKind::File, content);
+        Ok(())
+    }
+
+    async fn discard_partial(&self, path: &str) -> Result<(), ApplicationError> {
+        self.state.lock().unwrap().partials.remove(path);
+        Ok(())
+    }
+
+    async fn create_directory(&self, path: &str) -> Result<(), ApplicationError> {
+        let mut state = self.state.lock().unwrap();
+        state.check_directory(parent_path(path))?;
+        state.check_free(path)?;
+        state.insert(path, FileKind::Directory, Vec::new());
+        Ok(())
+    }
+
+    async fn rename(&self, from: &str, to: &str) -> Result<(), ApplicationError> {
+        let mut state = self.state.lock().unwrap();
+        state.existing(from)?;
+        state.check_directory(parent_path(to))?;
+        state.check_free(to)?;
+        state.move_entries(from, to);
+        Ok(())
+    }
+
+    async fn move_to_trash(&self, path: &str) -> Result<(), ApplicationError> {
+        let mut state = self.state.lock().unwrap();
+        state.existing(path)?;
+        state.entries.retain(|entry, _| entry != path && !is_below(entry, path));
+        state.trashed.push(path.to_string());
+        Ok(())
+    }
+
+    async fn set_modified_at(&self, path: &str, modified_at: i64) -> Result<(), ApplicationError> {
+        let mut state = self.state.lock().unwrap();
+        state.existing(path)?;
+        if let Some(entry) = state.entries.get_mut(path) {
+            entry.modified_at = modified_at;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,37 @@
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use common::application_error;
+use common::domain::errors::ApplicationError;
+use common::domain::text_keys::TextKeys::LocalWatcherFailed;
+use crate::domain::local_change::LocalFsEvent;
+use crate::ports::driven::local_watcher_driven_port::LocalWatcherDrivenPort;
+
+/// Hands out the events a test sends through `events`, the sync root can only be watched once.
+/// The stream ends once every sender taken before `watch` is dropped.
+pub struct FakeLocalWatcher {
+    sender: Option<UnboundedSender<LocalFsEvent>>,
+    receiver: Option<UnboundedReceiver<LocalFsEvent>>,
+}
+
+impl FakeLocalWatcher {
+    pub fn new() -> Self {
+        let (sender, receiver) = unbounded_channel();
+        Self { sender: Some(sender), receiver: Some(receiver) }
+    }
+
+    pub fn events(&self) -> UnboundedSender<LocalFsEvent> {
+        self.sender.clone().expect("events are taken before watching")
+    }
+}
+
+impl Default for FakeLocalWatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LocalWatcherDrivenPort for FakeLocalWatcher {
+    fn watch(&mut self) -> Result<UnboundedReceiver<LocalFsEvent>, ApplicationError> {
+        self.sender = None;
+        self.receiver.take().ok_or(application_error!(LocalWatcherFailed, "already watching"))
+    }
+}
@@ -1,35 +1,284 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Mutex;
+use common::domain::errors::ApplicationError;
+use common::domain::file_search::FileSearch;
+use common::domain::sync_selection::SyncSelection;
+use crate::domain::metadata::{CrawlProgress, FileMetadata, SyncState};
+use crate::domain::remote_file::{DriveId, FileId, RemoteFile};
+use crate::domain::upload_session::UploadSession;
 use crate::ports::driven::metadata_driven_port::MetadataDrivenPort;
 
 pub struct FakeMetadataStore {
+    state: Mutex<FakeMetadataState>,
+}
+
+struct FakeMetadataState {
+    files: BTreeMap<FileId, FileMetadata>,
     has_metadata: bool,
     has_index: bool,
+    crawl_queue: VecDeque<FileId>,
+    synced: BTreeMap<FileId, RemoteFile>,
+    remote_cursor: Option<String>,
+    sync_selection: SyncSelection,
+    upload_sessions: BTreeMap<String, UploadSession>,
+    partial_downloads: BTreeMap<String, String>,
+    sync_root_drive: Option<DriveId>,
 }
 
 impl FakeMetadataStore {
     pub fn new() -> Self {
         Self {
-            has_metadata: true,
-            has_index: true
-
+            state: Mutex::new(FakeMetadataState {
+                files: BTreeMap::new(),
+                has_metadata: true,
+                has_index: true,
+                crawl_queue: VecDeque::new(),
+                synced: BTreeMap::new(),
+                remote_cursor: None,
+                sync_selection: SyncSelection::default(),
+                upload_sessions: BTreeMap::new(),
+                partial_downloads: BTreeMap::new(),
+                sync_root_drive: None,
+            }),
         }
     }
-    pub fn without_metadata(mut self) -> Self {
-        self.has_metadata = false;
+
+    pub fn without_metadata(self) -> Self {
+        self.state.lock().unwrap().has_metadata = false;
+        self
+    }
+
+    pub fn without_index(self) -> Self {
+        self.state.lock().unwrap().has_index = false;
         self
     }
 
-    pub fn without_index(mut self) -> Self {
-        self.has_index = false;
+    pub fn with_files(self, files: &[FileMetadata]) -> Self {
+        self.save_files(files).unwrap();
         self
     }
+
+    pub fn file_count(&self) -> usize {
+        self.state.lock().unwrap().files.len()
+    }
+}
+
+/// Removes the entry and everything whose parent chain leads to it
+fn remove_subtree<T>(entries: &mut BTreeMap<FileId, T>, id: FileId, parent_id: impl Fn(&T) -> FileId) {
+    let mut pending = vec![id];
+    while let Some(id) = pending.pop() {
+        entries.remove(&id);
+        pending.extend(entries.iter()
+            .filter(|(child_id, entry)| parent_id(entry) == id && **child_id != id)
+            .map(|(child_id, _)| *child_id));
+    }
+}
+
+fn matches_search(file: &RemoteFile, search: &FileSearch) -> bool {
+    let name = file.name.to_lowercase();
+    search.name.as_ref().is_none_or(|part| name.contains(&part.to_lowercase()))
+        && search.extension.as_ref().is_none_or(|extension| {
+            !file.is_directory() && name.ends_with(&format!(".{}", extension.to_lowercase()))
+        })
+        && search.min_size.is_none_or(|min_size| file.size.is_some_and(|size| size >= min_size))
+        && search.max_size.is_none_or(|max_size| file.size.is_some_and(|size| size <= max_size))
+        && search.modified_after.is_none_or(|after| file.last_modified_at >= after)
+        && search.modified_before.is_none_or(|before| file.last_modified_at <= before)
 }
 
 impl MetadataDrivenPort for FakeMetadataStore {
-    fn has_metadata(&self) -> bool {
-        self.has_metadata
+    fn has_metadata(&self) -> Result<bool, ApplicationError> {
+        Ok(self.state.lock().unwrap().has_metadata)
+    }
+
+    fn has_index(&self) -> Result<bool, ApplicationError> {
+        Ok(self.state.lock().unwrap().has_index)
+    }
+
+    fn mark_indexed(&self) -> Result<(), ApplicationError> {
+        self.state.lock().unwrap().has_index = true;
+        Ok(())
+    }
+
+    fn save_files(&self, files: &[FileMetadata]) -> Result<(), ApplicationError> {
+        let mut state = self.state.lock().unwrap();
+        for file in files {
+            state.files.insert(file.remote.id, file.clone());
+        }
+        state.has_metadata = true;
+        Ok(())
+    }
+
+    fn all_files(&self) -> Result<Vec<FileMetadata>, ApplicationError> {
+        Ok(self.state.lock().unwrap().files.values().cloned().collect())
+    }
+
+    fn get_file(&self, id: FileId) -> Result<Option<FileMetadata>, ApplicationError> {
+        Ok(self.state.lock().unwrap().files.get(&id).cloned())
+    }
+
+    fn get_children(&self, parent_id: FileId) -> Result<Vec<FileMetadata>, ApplicationError> {
+        let state = self.state.lock().unwrap();
+        let mut children: Vec<_> = state.files.values()
+            .filter(|file| file.remote.parent_id == parent_id && file.remote.id != parent_id)
+            .cloned()
+            .collect();
+        children.sort_by(|a, b| a.remote.name.cmp(&b.remote.name));
+        Ok(children)
+    }
+
+    fn get_children_page(&self, parent_id: FileId, offset: usize, limit: usize) -> Result<Vec<FileMetadata>, ApplicationError> {
+        Ok(self.get_children(parent_id)?.into_iter().skip(offset).take(limit).collect())
+    }
+
+    fn search_files(&self, search: &FileSearch) -> Result<Vec<FileMetadata>, ApplicationError> {
+        let state = self.state.lock().unwrap();
+        let mut matches: Vec<_> = state.files.values()
+            .filter(|file| file.remote.id != file.remote.parent_id)
+            .filter(|file| matches_search(&file.remote, search))
+            .cloned()
+            .collect();
+        matches.sort_by(|a, b| a.remote.name.cmp(&b.remote.name));
+        matches.truncate(search.limit);
+        Ok(matches)
+    }
+
+    fn set_sync_state(&self, id: FileId, sync_state: SyncState) -> Result<(), ApplicationError> {
+        if let Some(file) = self.state.lock().unwrap().files.get_mut(&id) {
+            file.sync_state = sync_state;
+        }
+        Ok(())
+    }
+
+    fn delete_file(&self, id: FileId) -> Result<(), ApplicationError> {
+        remove_subtree(&mut self.state.lock().unwrap().files, id, |file| file.remote.parent_id);
+        Ok(())
+    }
+
+    fn synced_files(&self) -> Result<Vec<RemoteFile>, ApplicationError> {
+        Ok(self.state.lock().unwrap().synced.values().cloned().collect())
+    }
+
+    fn save_synced_file(&self, file: &RemoteFile) -> Result<(), ApplicationError> {
+        self.save_file(&FileMetadata::new(file.clone(), SyncState::Synced))?;
+        self.state.lock().unwrap().synced.insert(file.id, file.clone());
+        Ok(())
     }
 
-    fn has_index(&self) -> bool {
-        self.has_index
+    fn forget_synced_file(&self, id: FileId) -> Result<(), ApplicationError> {
+        remove_subtree(&mut self.state.lock().unwrap().synced, id, |file| file.parent_id);
+        Ok(())
     }
-}
\ No newline at end of file
+
+    fn remote_cursor(&self) -> Result<Option<String>, ApplicationError> {
+        Ok(self.state.lock().unwrap().remote_cursor.clone())
+    }
+
+    fn apply_remote_changes(&self, saved: &[FileMetadata], deleted: &[FileId], cursor: &str) -> Result<(), ApplicationError> {
+        for id in deleted {
+            self.delete_file(*id)?;
+        }
+        self.save_files(saved)?;
+        self.state.lock().unwrap().remote_cursor = Some(cursor.to_string());
+        Ok(())
+    }
+
+    fn sync_selection(&self) -> Result<SyncSelection, ApplicationError> {
+        Ok(self.state.lock().unwrap().sync_selection.clone())
+    }
+
+    fn save_sync_selection(&self, selection: &SyncSelection) -> Result<(), ApplicationError> {
+        self.state.lock().unwrap().sync_selection = selection.clone();
+        Ok(())
+    }
+
+    fn upload_session(&self, path: &str) -> Result<Option<UploadSession>, ApplicationError> {
+        Ok(self.state.lock().unwrap().upload_sessions.get(path).cloned())
+    }
+
+    fn save_upload_session(&self, session: &UploadSession) -> Result<(), ApplicationError> {
+        self.state.lock().unwrap().upload_sessions.insert(session.path.clone(), session.clone());
+        Ok(())
+    }
+
+    fn save_uploaded_chunk(&self, path: &str, chunk_number: u64) -> Result<(), ApplicationError> {
+        if let Some(session) = self.state.lock().unwrap().upload_sessions.get_mut(path) {
+            session.uploaded_chunks.insert(chunk_number);
+        }
+        Ok(())
+    }
+
+    fn delete_upload_session(&self, path: &str) -> Result<(), ApplicationError> {
+        self.state.lock().unwrap().upload_sessions.remove(path);
+        Ok(())
+    }
+
+    fn partial_download(&self, path: &str) -> Result<Option<String>, ApplicationError> {
+        Ok(self.state.lock().unwrap().partial_downloads.get(path).cloned())
+    }
+
+    fn save_partial_download(&self, path: &str, version: &str) -> Result<(), ApplicationError> {
+        self.state.lock().unwrap().partial_downloads.insert(path.to_string(), version.to_string());
+        Ok(())
+    }
+
+    fn delete_partial_download(&self, path: &str) -> Result<(), ApplicationError> {
+        self.state.lock().unwrap().partial_downloads.remove(path);
+        Ok(())
+    }
+
+    fn sync_root_drive(&self) -> Result<Option<DriveId>, ApplicationError> {
+        Ok(self.state.lock().unwrap().sync_root_drive)
+    }
+
+    fn save_sync_root_drive(&self, drive_id: DriveId) -> Result<(), ApplicationError> {
+        self.state.lock().unwrap().sync_root_drive = Some(drive_id);
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<(), ApplicationError> {
+        let mut state = self.state.lock().unwrap();
+        state.upload_sessions.clear();
+        state.partial_downloads.clear();
+        state.files.clear();
+        state.synced.clear();
+        state.remote_cursor = None;
+        state.sync_selection = SyncSelection::default();
+        state.has_metadata = false;
+        state.has_index = false;
+        state.crawl_queue.clear();
+        Ok(())
+    }
+
+    fn start_crawl(&self, root: &FileMetadata) -> Result<(), ApplicationError> {
+        self.save_file(root)?;
+        self.state.lock().unwrap().crawl_queue.push_back(root.remote.id);
+        Ok(())
+    }
+
+    fn next_crawl_directory(&self) -> Result<Option<FileId>, ApplicationError> {
+        Ok(self.state.lock().unwrap().crawl_queue.front().copied())
+    }
+
+    fn save_crawled_directory(&self, directory_id: FileId, children: &[FileMetadata]) -> Result<(), ApplicationError> {
+        self.save_files(children)?;
+        let mut state = self.state.lock().unwrap();
+        state.crawl_queue.retain(|id| *id != directory_id);
+        state.crawl_queue.extend(children.iter()
+            .filter(|child| child.remote.is_directory() && child.remote.id != directory_id)
+            .map(|child| child.remote.id));
+        Ok(())
+    }
+
+    fn crawl_progress(&self) -> Result<CrawlProgress, ApplicationError> {
+        let state = self.state.lock().unwrap();
+        let (directories, files): (Vec<_>, Vec<_>) = state.files.values().partition(|file| file.remote.is_directory());
+        let directories_pending = state.crawl_queue.len() as u64;
+        Ok(CrawlProgress {
+            directories_visited: (directories.len() as u64).saturating_sub(directories_pending),
+            directories_pending,
+            files_found: files.len() as u64,
+            bytes_total: files.iter().filter_map(|file| file.remote.size).sum(),
+        })
+    }
+}
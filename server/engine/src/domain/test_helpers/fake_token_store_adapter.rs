@@ -40,6 +40,10 @@ impl TokenStoreDrivenPort for FakeTokenStoreFileAdapter {
         _ = tokens;
         Ok(())
     }
+
+    fn clear(&self) -> Result<(), ApplicationError> {
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
@@ -50,6 +54,9 @@ pub struct FakeTokenStoreRingAdapter {
 pub const TEST_RING_ACCESS_TOKEN: &str = "test ring access token";
 pub const TEST_RING_REFRESH_TOKEN: &str = "test ring refresh token";
 pub const TEST_RING_EXPIRES_AT: i64 = i64::MAX;
+pub const TEST_REFRESHED_ACCESS_TOKEN: &str = "test refreshed access token";
+pub const TEST_REFRESHED_REFRESH_TOKEN: &str = "test refreshed refresh token";
+pub const TEST_REFRESHED_EXPIRES_AT: i64 = i64::MAX;
 
 impl FakeTokenStoreRingAdapter {
     pub fn with_tokens() -> Self {
@@ -61,6 +68,15 @@ impl FakeTokenStoreRingAdapter {
             }),
         }
     }
+    pub fn with_tokens_expiring_at(expires_at: i64) -> Self {
+        FakeTokenStoreRingAdapter {
+            tokens: Some(Tokens {
+                access_token: TEST_RING_ACCESS_TOKEN.to_string(),
+                refresh_token: TEST_RING_REFRESH_TOKEN.to_string(),
+                expires_at,
+            }),
+        }
+    }
     pub fn empty() -> Self {
         FakeTokenStoreRingAdapter { tokens: None }
     }
@@ -78,5 +94,9 @@ impl TokenStoreDrivenPort for FakeTokenStoreRingAdapter {
         _ = tokens;
         Ok(())
     }
+
+    fn clear(&self) -> Result<(), ApplicationError> {
+        Ok(())
+    }
 }
 
@@ -6,4 +6,8 @@ pub mod fake_event_bus;
 pub mod fake_i18n;
 pub mod fake_authenticator_adapter_slow;
 pub mod fake_metadata_store;
-pub(crate) mod test_engine_builder;
\ No newline at end of file
+pub mod fake_clock;
+pub(crate) mod test_engine_builder;
+pub mod fake_kdrive_api;
+pub mod fake_local_fs;
+pub mod fake_local_watcher;
@@ -1,6 +1,9 @@
 use crate::domain::engine::Engine;
 use crate::domain::test_helpers::fake_authenticator_adapter::FakeAuthenticatorDrivenAdapter;
+use crate::domain::test_helpers::fake_clock::FakeClock;
 use crate::domain::test_helpers::fake_event_bus::FakeEventBus;
+use crate::domain::test_helpers::fake_kdrive_api::FakeKDriveApi;
+use crate::domain::test_helpers::fake_local_fs::FakeLocalFs;
 use crate::domain::test_helpers::fake_metadata_store::FakeMetadataStore;
 use crate::domain::test_helpers::fake_token_store::FakeTokenStore;
 use crate::domain::test_helpers::fake_token_store_adapter::FakeTokenStoreRingAdapter;
@@ -11,7 +14,10 @@ pub struct TestEngineBuilder {
     auth: FakeAuthenticatorDrivenAdapter,
     token_store: FakeTokenStore,
     event_bus: FakeEventBus,
-    metadata_store: FakeMetadataStore
+    metadata_store: FakeMetadataStore,
+    clock: FakeClock,
+    api: FakeKDriveApi,
+    local_fs: FakeLocalFs,
 }
 
 #[allow(dead_code)]
@@ -24,8 +30,10 @@ impl TestEngineBuilder {
                 None
             ).unwrap(),
             event_bus: FakeEventBus::new(),
-            metadata_store: FakeMetadataStore::new()
-
+            metadata_store: FakeMetadataStore::new(),
+            clock: FakeClock::new(),
+            api: FakeKDriveApi::new_default(),
+            local_fs: FakeLocalFs::new(),
         }
     }
 
@@ -42,6 +50,28 @@ impl TestEngineBuilder {
         self
     }
 
+    pub fn with_expiring_tokens(self) -> Self {
+        self.with_tokens_expiring_at(0)
+    }
+
+    pub fn with_tokens_expiring_at(mut self, expires_at: i64) -> Self {
+        self.token_store = TokenStore::load(
+            Some(FakeTokenStoreRingAdapter::with_tokens_expiring_at(expires_at)),
+            None
+        ).unwrap();
+        self
+    }
+
+    pub fn with_clock(mut self, clock: FakeClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn with_event_bus(mut self, event_bus: FakeEventBus) -> Self {
+        self.event_bus = event_bus;
+        self
+    }
+
     pub fn without_metadata(mut self) -> Self {
         self.metadata_store = FakeMetadataStore::new().without_metadata();
         self
@@ -52,13 +82,31 @@ impl TestEngineBuilder {
         self
     }
 
-    pub fn build(self) -> Engine<FakeAuthenticatorDrivenAdapter, FakeTokenStore, FakeEventBus, FakeMetadataStore>
+    pub fn with_metadata_store(mut self, metadata_store: FakeMetadataStore) -> Self {
+        self.metadata_store = metadata_store;
+        self
+    }
+
+    pub fn with_api(mut self, api: FakeKDriveApi) -> Self {
+        self.api = api;
+        self
+    }
+
+    pub fn with_local_fs(mut self, local_fs: FakeLocalFs) -> Self {
+        self.local_fs = local_fs;
+        self
+    }
+
+    pub fn build(self) -> Engine<FakeAuthenticatorDrivenAdapter, FakeTokenStore, FakeEventBus, FakeMetadataStore, FakeClock, FakeKDriveApi, FakeLocalFs>
     {
         Engine::new(
             self.auth,
             self.token_store,
             self.event_bus,
-            self.metadata_store
+            self.metadata_store,
+            self.clock,
+            self.api,
+            self.local_fs,
         )
     }
 }
\ No newline at end of file
@@ -0,0 +1,152 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use crate::domain::default_values::general_defaults::{TOKEN_REFRESH_CHECK_INTERVAL_SECONDS, TOKEN_REFRESH_RETRY_SECONDS};
+use crate::ports::driving::authenticator_driving_port::AuthenticatorDrivingPort;
+
+/// Keeps the stored tokens usable by refreshing them shortly before they expire.
+pub struct TokenRefreshScheduler {
+    check_interval: Duration,
+    retry_interval: Duration,
+}
+
+impl TokenRefreshScheduler {
+    pub fn new() -> Self {
+        Self::with_intervals(
+            Duration::from_secs(TOKEN_REFRESH_CHECK_INTERVAL_SECONDS),
+            Duration::from_secs(TOKEN_REFRESH_RETRY_SECONDS),
+        )
+    }
+
+    pub fn with_intervals(check_interval: Duration, retry_interval: Duration) -> Self {
+        Self { check_interval, retry_interval }
+    }
+
+    pub fn start<E>(self, engine: Arc<Mutex<E>>) -> JoinHandle<()>
+    where
+        E: AuthenticatorDrivingPort + Send + 'static,
+    {
+        tokio::spawn(async move {
+            loop {
+                // Wake up at least every check interval, tokens can appear after an auth flow
+                let delay = engine.lock().await
+                    .token_refresh_delay()
+                    .map_or(self.check_interval, |delay| delay.min(self.check_interval));
+                tokio::time::sleep(delay).await;
+
+                let pending = {
+                    let engine = engine.lock().await;
+                    if engine.token_refresh_delay() != Some(Duration::ZERO) {
+                        continue;
+                    }
+                    engine.pending_token_refresh()
+                };
+
+                // The lock is released here, other requests are served while the token endpoint answers
+                let refreshed = match pending {
+                    Ok(pending) => Ok(pending.await),
+                    Err(error) => Err(error),
+                };
+
+                if engine.lock().await.complete_token_refresh(refreshed).is_err() {
+                    tokio::time::sleep(self.retry_interval).await;
+                }
+            }
+        })
+    }
+}
+
+impl Default for TokenRefreshScheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::Mutex;
+    use crate::domain::events::EngineEvent;
+    use crate::domain::test_helpers::fake_authenticator_adapter::FakeAuthenticatorDrivenAdapter;
+    use crate::domain::test_helpers::fake_event_bus::FakeEventBus;
+    use crate::domain::test_helpers::test_engine_builder::TestEngineBuilder;
+    use crate::domain::token_refresh_scheduler::TokenRefreshScheduler;
+    use crate::ports::driving::authenticator_driving_port::AuthenticatorDrivingPort;
+
+    fn fast_scheduler() -> TokenRefreshScheduler {
+        TokenRefreshScheduler::with_intervals(Duration::from_millis(10), Duration::from_millis(10))
+    }
+
+    #[tokio::test]
+    async fn scheduler_refreshes_tokens_that_are_about_to_expire() {
+        // Given an engine with tokens that expire now
+        let event_bus = FakeEventBus::new();
+        let engine = TestEngineBuilder::new()
+            .with_expiring_tokens()
+            .with_event_bus(event_bus.clone())
+            .build();
+
+        // When the scheduler is running
+        let handle = fast_scheduler().start(Arc::new(Mutex::new(engine)));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        handle.abort();
+
+        // Then the tokens are refreshed
+        assert!(event_bus.get_events().contains(&EngineEvent::TokensRefreshed));
+    }
+
+    #[tokio::test]
+    async fn scheduler_does_not_refresh_tokens_that_are_still_valid() {
+        // Given an engine with tokens that are valid for a long time
+        let event_bus = FakeEventBus::new();
+        let engine = TestEngineBuilder::new()
+            .with_event_bus(event_bus.clone())
+            .build();
+
+        // When the scheduler is running
+        let handle = fast_scheduler().start(Arc::new(Mutex::new(engine)));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        handle.abort();
+
+        // Then no refresh is attempted
+        assert!(event_bus.get_events().is_empty());
+    }
+
+    #[tokio::test]
+    async fn scheduler_does_not_refresh_without_tokens() {
+        // Given an engine without tokens
+        let event_bus = FakeEventBus::new();
+        let engine = TestEngineBuilder::new()
+            .with_empty_token_store()
+            .with_event_bus(event_bus.clone())
+            .build();
+
+        // When the scheduler is running
+        let handle = fast_scheduler().start(Arc::new(Mutex::new(engine)));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        handle.abort();
+
+        // Then no refresh is attempted
+        assert!(event_bus.get_events().is_empty());
+    }
+
+    #[tokio::test]
+    async fn scheduler_does_not_lock_the_engine_while_refreshing() {
+        // Given a scheduler refreshing tokens with a token endpoint that does not answer
+        let engine = Arc::new(Mutex::new(TestEngineBuilder::new()
+            .with_auth(FakeAuthenticatorDrivenAdapter::new_default_unresponsive())
+            .with_expiring_tokens()
+            .build()));
+        let handle = fast_scheduler().start(engine.clone());
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // When another request needs the engine
+        let result = tokio::time::timeout(Duration::from_millis(100), engine.lock()).await;
+
+        // Then the engine is available
+        assert!(result.expect("Engine stayed locked during the refresh").has_tokens());
+        handle.abort();
+    }
+}
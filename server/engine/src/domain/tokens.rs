@@ -12,12 +12,32 @@ pub struct Tokens {
     pub expires_at: i64,
 }
 
+/// The token store in use, the key ring when the system has one and the file otherwise
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
-enum ActivePort<TRP, TFP> {
+pub enum ActivePort<TRP, TFP> {
     KeyRing(TRP),
     File(TFP)
 }
 
+impl<TRP: TokenStoreDrivenPort, TFP: TokenStoreDrivenPort> ActivePort<TRP, TFP> {
+    pub fn choose(key_ring_store: Option<TRP>,   file_store: Option<TFP>) 
+        -> Result<Self, ApplicationError>
+    {
+        if let Some(key_ring_store) = key_ring_store {
+            if key_ring_store.is_available() {
+                return Ok(ActivePort::KeyRing(key_ring_store))
+            }
+        }
+
+        if let Some(file_store) = file_store {
+            if file_store.is_available() {
+                return Ok(ActivePort::File(file_store))
+            }
+        }
+        Err(application_error!(MissingStorePort))
+    }
+}
+
 impl<TRP: TokenStoreDrivenPort, TFP: TokenStoreDrivenPort> TokenStoreDrivenPort for ActivePort<TRP, TFP> {
     fn is_available(&self) -> bool {
         match self {
@@ -39,6 +59,13 @@ impl<TRP: TokenStoreDrivenPort, TFP: TokenStoreDrivenPort> TokenStoreDrivenPort
             ActivePort::File(p) => p.save(tokens),
         }
     }
+
+    fn clear(&self) -> Result<(), ApplicationError> {
+        match self {
+            ActivePort::KeyRing(p) => p.clear(),
+            ActivePort::File(p) => p.clear(),
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
@@ -69,18 +96,7 @@ impl <TRP: TokenStoreDrivenPort, TFP: TokenStoreDrivenPort>TokenStore<TRP, TFP>
     fn choose_port(key_ring_store: Option<TRP>,   file_store: Option<TFP>) 
         -> Result<ActivePort<TRP, TFP>, ApplicationError>
     {
-        if let Some(key_ring_store) = key_ring_store {
-            if key_ring_store.is_available() {
-                return Ok(ActivePort::KeyRing(key_ring_store))
-            }
-        }
-
-        if let Some(file_store) = file_store {
-            if file_store.is_available() {
-                return Ok(ActivePort::File(file_store))
-            }
-        }
-        Err(application_error!(MissingStorePort))
+        ActivePort::choose(key_ring_store, file_store)
     }
 }
 
@@ -113,15 +129,29 @@ for TokenStore<TRP, TFP>
         }
     }
 
+    fn is_expired(&self, now: i64) -> bool {
+        match self.expires_at() {
+            Some(expires_at) => expires_at <= now,
+            None => true,
+        }
+    }
+
     fn save_tokens(&mut self, tokens: &Tokens) -> Result<(), ApplicationError> {
         self.port.save(tokens)?;
         self.tokens = Some(tokens.clone());
         Ok(())
     }
+
+    fn clear_tokens(&mut self) -> Result<(), ApplicationError> {
+        self.port.clear()?;
+        self.tokens = None;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::domain::test_helpers::fake_clock::TEST_NOW;
     use crate::domain::test_helpers::fake_token_store_adapter::*;
     use crate::domain::test_helpers::fake_token_store::FakeTokenStore;
     use crate::ports::driven::token_store_driven_port::TokenStoreDrivenPort;
@@ -239,4 +269,42 @@ mod tests {
         // Then it returns false
         assert_eq!(result, false);
     }
+
+    #[test]
+    fn token_store_has_no_tokens_after_clearing_them() {
+        // Given a token store with tokens
+        let mut store = both_store();
+
+        // When the tokens are cleared
+        let result = store.clear_tokens();
+
+        // Then the store has no tokens anymore
+        assert!(result.is_ok());
+        assert!(!store.has_tokens());
+    }
+
+    // is_expired
+    #[test]
+    fn tokens_that_expire_in_the_future_are_not_expired() {
+        let ring_adapter = FakeTokenStoreRingAdapter::with_tokens_expiring_at(TEST_NOW + 60);
+        let store = FakeTokenStore::load(Some(ring_adapter), None).unwrap();
+
+        assert!(!store.is_expired(TEST_NOW));
+    }
+
+    #[test]
+    fn tokens_that_expire_now_are_expired() {
+        let ring_adapter = FakeTokenStoreRingAdapter::with_tokens_expiring_at(TEST_NOW);
+        let store = FakeTokenStore::load(Some(ring_adapter), None).unwrap();
+
+        assert!(store.is_expired(TEST_NOW));
+    }
+
+    #[test]
+    fn a_store_without_tokens_is_expired() {
+        let ring_adapter = FakeTokenStoreRingAdapter::empty();
+        let store = FakeTokenStore::load(Some(ring_adapter), None).unwrap();
+
+        assert!(store.is_expired(TEST_NOW));
+    }
 }
\ No newline at end of file
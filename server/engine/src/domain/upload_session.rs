@@ -0,0 +1,104 @@
+use std::collections::BTreeSet;
+use serde::{Deserialize, Serialize};
+use crate::domain::content_hash::content_hash;
+use crate::domain::local_file::LocalFile;
+use crate::domain::remote_file::FileId;
+
+/// Where a chunked upload ends up on kDrive
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UploadTarget {
+    NewFile { directory_id: FileId, name: String },
+    /// kDrive keeps the previous content as a version
+    NewVersion { file_id: FileId },
+}
+
+/// A chunked upload kDrive has started for a file in the sync root. It is stored with the chunks kDrive confirmed,
+/// so an upload that was interrupted only sends the chunks it is missing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UploadSession {
+    /// The file in the sync root that is uploaded
+    pub path: String,
+    pub token: String,
+    pub target: UploadTarget,
+    pub total_size: u64,
+    pub chunk_size: u64,
+    /// Seconds since the Unix epoch, the time the local file had when the session started
+    pub modified_at: i64,
+    /// Numbered from 1 like kDrive does
+    pub uploaded_chunks: BTreeSet<u64>,
+}
+
+impl UploadSession {
+    pub fn chunk_count(&self) -> u64 {
+        chunk_count(self.total_size, self.chunk_size)
+    }
+
+    /// The number of bytes in a chunk, only the last one can be shorter
+    pub fn chunk_length(&self, chunk_number: u64) -> u64 {
+        let start = (chunk_number - 1) * self.chunk_size;
+        self.chunk_size.min(self.total_size.saturating_sub(start))
+    }
+
+    /// Whether the session still uploads the file as it is now, a file that changed since needs a new one
+    pub fn matches(&self, local: &LocalFile, target: &UploadTarget, chunk_size: u64) -> bool {
+        self.target == *target
+            && self.chunk_size == chunk_size
+            && local.size == Some(self.total_size)
+            && local.modified_at == self.modified_at
+    }
+}
+
+/// An empty file is still sent as a single empty chunk
+pub fn chunk_count(total_size: u64, chunk_size: u64) -> u64 {
+    total_size.div_ceil(chunk_size).max(1)
+}
+
+/// The checksum kDrive verifies every chunk against
+pub fn chunk_hash(content: &[u8]) -> String {
+    content_hash(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::remote_file::FileKind;
+    use super::*;
+
+    fn session(total_size: u64, chunk_size: u64) -> UploadSession {
+        UploadSession {
+            path: "video.mp4".to_string(),
+            token: "token".to_string(),
+            target: UploadTarget::NewVersion { file_id: 4 },
+            total_size,
+            chunk_size,
+            modified_at: 1_700_000_000,
+            uploaded_chunks: BTreeSet::new(),
+        }
+    }
+
+    #[test]
+    fn the_last_chunk_holds_what_is_left() {
+        let session = session(10, 4);
+
+        assert_eq!(session.chunk_count(), 3);
+        assert_eq!(session.chunk_length(1), 4);
+        assert_eq!(session.chunk_length(3), 2);
+        assert_eq!(chunk_count(0, 4), 1);
+    }
+
+    #[test]
+    fn a_changed_file_no_longer_matches_its_session() {
+        let session = session(10, 4);
+        let local = LocalFile { path: "video.mp4".to_string(), kind: FileKind::File, size: Some(10), modified_at: 1_700_000_000 };
+        let target = UploadTarget::NewVersion { file_id: 4 };
+
+        assert!(session.matches(&local, &target, 4));
+        assert!(!session.matches(&local, &target, 8));
+        assert!(!session.matches(&LocalFile { modified_at: 1_700_000_001, ..local.clone() }, &target, 4));
+        assert!(!session.matches(&local, &UploadTarget::NewVersion { file_id: 5 }, 4));
+    }
+
+    #[test]
+    fn chunk_hash_is_the_xxh3_of_the_content() {
+        assert_eq!(chunk_hash(b"abc"), "xxh3:78af5f94892f3950");
+    }
+}
@@ -1,10 +1,22 @@
+use std::future::Future;
+use std::pin::Pin;
 use async_trait::async_trait;
 use common::domain::errors::ApplicationError;
 use crate::domain::tokens::Tokens;
 
+/// Resolves with the authorization code once the user finished logging in with the browser.
+pub type PendingAuthorizationCode = Pin<Box<dyn Future<Output = Result<String, ApplicationError>> + Send>>;
+
+/// Resolves with new tokens once the token endpoint answered a refresh.
+pub type PendingTokenRefresh = Pin<Box<dyn Future<Output = Result<Tokens, ApplicationError>> + Send>>;
+
 #[async_trait]
 pub trait AuthenticatorDrivenPort {
     async fn start_initial_auth_flow(&mut self) -> Result<String, ApplicationError>;
-    async fn continue_initial_auth_flow(&mut self) -> Result<(), ApplicationError>;
+    fn pending_authorization_code(&mut self) -> Result<PendingAuthorizationCode, ApplicationError>;
+    async fn exchange_authorization_code(&mut self, code: &str) -> Result<(), ApplicationError>;
+    async fn cancel_auth_flow(&mut self);
     async fn get_tokens(&self) -> Result<Tokens, ApplicationError>;
-}
\ No newline at end of file
+    fn pending_token_refresh(&self, refresh_token: &str) -> PendingTokenRefresh;
+    async fn revoke_tokens(&mut self, refresh_token: &str) -> Result<(), ApplicationError>;
+}
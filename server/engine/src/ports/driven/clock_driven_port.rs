@@ -0,0 +1,4 @@
+pub trait ClockDrivenPort {
+    /// Current wall-clock time as a Unix timestamp in seconds
+    fn now(&self) -> i64;
+}
@@ -0,0 +1,69 @@
+use async_trait::async_trait;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use common::application_error;
+use common::domain::errors::ApplicationError;
+use common::domain::text_keys::TextKeys::ApiRequestFailed;
+use crate::domain::remote_file::{ChangePage, Drive, DriveId, FileId, FilePage, RemoteFile};
+use crate::domain::upload_session::UploadTarget;
+
+pub type RemoteFileReader = Box<dyn AsyncRead + Send + Unpin>;
+
+/// The kDrive REST API, the adapter takes care of the access token of the user
+#[async_trait]
+pub trait KDriveApiDrivenPort {
+    async fn list_drives(&self) -> Result<Vec<Drive>, ApplicationError>;
+    async fn list_files(&self, drive_id: DriveId, directory_id: FileId, cursor: Option<&str>) -> Result<FilePage, ApplicationError>;
+
+    /// Every file in a directory, reading page after page
+    async fn list_all_files(&self, drive_id: DriveId, directory_id: FileId) -> Result<Vec<RemoteFile>, ApplicationError> {
+        let mut files = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = self.list_files(drive_id, directory_id, cursor.as_deref()).await?;
+            files.extend(page.files);
+            cursor = page.cursor;
+            if !page.has_more || cursor.is_none() {
+                return Ok(files);
+            }
+        }
+    }
+
+    /// What changed on the drive since the cursor. Without a cursor there are no changes yet, only the cursor
+    /// to start from.
+    async fn list_changes(&self, drive_id: DriveId, cursor: Option<&str>) -> Result<ChangePage, ApplicationError>;
+
+    async fn get_file(&self, drive_id: DriveId, file_id: FileId) -> Result<RemoteFile, ApplicationError>;
+
+    /// The content of a file from byte `offset` on, reading fails when the connection drops before the end
+    async fn download_file_from(&self, drive_id: DriveId, file_id: FileId, offset: u64) -> Result<RemoteFileReader, ApplicationError>;
+
+    async fn download_file(&self, drive_id: DriveId, file_id: FileId) -> Result<Vec<u8>, ApplicationError> {
+        let mut content = Vec::new();
+        self.download_file_from(drive_id, file_id, 0)
+            .await?
+            .read_to_end(&mut content)
+            .await
+            .map_err(|e| application_error!(ApiRequestFailed, e.to_string()))?;
+        Ok(content)
+    }
+
+    async fn upload_file(&self, drive_id: DriveId, directory_id: FileId, name: &str, content: Vec<u8>) -> Result<RemoteFile, ApplicationError>;
+    /// Replaces the content of an existing file, kDrive keeps the previous content as a version
+    async fn update_file(&self, drive_id: DriveId, file_id: FileId, content: Vec<u8>) -> Result<RemoteFile, ApplicationError>;
+
+    /// Starts a chunked upload of `total_size` bytes in `total_chunks` chunks, returns the token of the session
+    async fn start_upload_session(&self, drive_id: DriveId, target: &UploadTarget, total_size: u64, total_chunks: u64) -> Result<String, ApplicationError>;
+    /// Sends one chunk, numbered from 1. kDrive refuses a chunk whose content does not match its hash.
+    async fn upload_chunk(&self, drive_id: DriveId, token: &str, chunk_number: u64, content: Vec<u8>, hash: &str) -> Result<(), ApplicationError>;
+    /// Puts the file together once every chunk of the session is uploaded
+    async fn finish_upload_session(&self, drive_id: DriveId, token: &str) -> Result<RemoteFile, ApplicationError>;
+    async fn cancel_upload_session(&self, drive_id: DriveId, token: &str) -> Result<(), ApplicationError>;
+
+    async fn create_directory(&self, drive_id: DriveId, parent_id: FileId, name: &str) -> Result<RemoteFile, ApplicationError>;
+    async fn move_file(&self, drive_id: DriveId, file_id: FileId, destination_directory_id: FileId) -> Result<(), ApplicationError>;
+    async fn rename_file(&self, drive_id: DriveId, file_id: FileId, name: &str) -> Result<(), ApplicationError>;
+    /// Moves the file to the trash of the drive
+    async fn delete_file(&self, drive_id: DriveId, file_id: FileId) -> Result<(), ApplicationError>;
+    /// Restores a file from the trash to where it was deleted from
+    async fn restore_file(&self, drive_id: DriveId, file_id: FileId) -> Result<(), ApplicationError>;
+}
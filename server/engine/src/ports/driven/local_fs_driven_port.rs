@@ -0,0 +1,64 @@
+use async_trait::async_trait;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use common::application_error;
+use common::domain::errors::ApplicationError;
+use common::domain::text_keys::TextKeys::LocalFileError;
+use crate::domain::local_file::LocalFile;
+
+pub type LocalFileReader = Box<dyn AsyncRead + Send + Unpin>;
+
+/// The local sync root, paths are relative to it and use `/` between the names
+#[async_trait]
+pub trait LocalFsDrivenPort {
+    /// The files and directories directly inside a directory, sorted by path
+    async fn read_dir(&self, path: &str) -> Result<Vec<LocalFile>, ApplicationError>;
+    async fn stat(&self, path: &str) -> Result<Option<LocalFile>, ApplicationError>;
+
+    async fn open_file(&self, path: &str) -> Result<LocalFileReader, ApplicationError>;
+
+    /// Creates the file or replaces it with everything the reader gives, returns the number of bytes.
+    /// The content only shows up at `path` once it is complete, the directory it is in has to exist.
+    async fn write_file_from(&self, path: &str, content: &mut (dyn AsyncRead + Send + Unpin)) -> Result<u64, ApplicationError>;
+
+    async fn read_file(&self, path: &str) -> Result<Vec<u8>, ApplicationError> {
+        let mut content = Vec::new();
+        self.open_file(path)
+            .await?
+            .read_to_end(&mut content)
+            .await
+            .map_err(|e| application_error!(LocalFileError, "{}: {}", path, e))?;
+        Ok(content)
+    }
+
+    async fn write_file(&self, path: &str, content: &[u8]) -> Result<(), ApplicationError> {
+        let mut reader = content;
+        self.write_file_from(path, &mut reader).await?;
+        Ok(())
+    }
+
+    /// The number of bytes a download to `path` left in its partial file, 0 when there is none.
+    /// Partial files are hidden from the sync root and stay there until they are finished or discarded.
+    async fn partial_size(&self, path: &str) -> Result<u64, ApplicationError>;
+
+    /// Adds everything the reader gives to the partial file of `path` and returns the number of bytes.
+    /// What arrived before the reader failed stays in the partial file.
+    async fn append_partial(&self, path: &str, content: &mut (dyn AsyncRead + Send + Unpin)) -> Result<u64, ApplicationError>;
+
+    async fn open_partial(&self, path: &str) -> Result<LocalFileReader, ApplicationError>;
+
+    /// Replaces the file at `path` with its partial file in one step
+    async fn finish_partial(&self, path: &str) -> Result<(), ApplicationError>;
+
+    async fn discard_partial(&self, path: &str) -> Result<(), ApplicationError>;
+
+    async fn create_directory(&self, path: &str) -> Result<(), ApplicationError>;
+
+    /// Moves or renames a file or a directory with everything below it in one step, never over an existing path
+    async fn rename(&self, from: &str, to: &str) -> Result<(), ApplicationError>;
+
+    /// Moves the file, or the directory with everything below it, out of the sync root into its trash
+    async fn move_to_trash(&self, path: &str) -> Result<(), ApplicationError>;
+
+    /// Seconds since the Unix epoch
+    async fn set_modified_at(&self, path: &str, modified_at: i64) -> Result<(), ApplicationError>;
+}
@@ -0,0 +1,8 @@
+use tokio::sync::mpsc::UnboundedReceiver;
+use common::domain::errors::ApplicationError;
+use crate::domain::local_change::LocalFsEvent;
+
+pub trait LocalWatcherDrivenPort {
+    /// Starts watching the whole sync root, events arrive as they happen for as long as the port lives
+    fn watch(&mut self) -> Result<UnboundedReceiver<LocalFsEvent>, ApplicationError>;
+}
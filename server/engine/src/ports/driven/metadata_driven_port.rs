@@ -1,4 +1,101 @@
+use common::domain::errors::ApplicationError;
+use common::domain::file_search::FileSearch;
+use common::domain::sync_selection::SyncSelection;
+use crate::domain::metadata::{CrawlProgress, FileMetadata, SyncState};
+use crate::domain::remote_file::{DriveId, FileId, RemoteFile};
+use crate::domain::upload_session::UploadSession;
+
 pub trait MetadataDrivenPort {
-    fn has_metadata(&self) -> bool;
-    fn has_index(&self) -> bool;
-}
\ No newline at end of file
+    fn has_metadata(&self) -> Result<bool, ApplicationError>;
+    fn has_index(&self) -> Result<bool, ApplicationError>;
+
+    /// Records that the whole drive has been crawled into the store
+    fn mark_indexed(&self) -> Result<(), ApplicationError>;
+
+    /// Inserts the files or replaces the ones with the same id, all or nothing
+    fn save_files(&self, files: &[FileMetadata]) -> Result<(), ApplicationError>;
+
+    fn save_file(&self, file: &FileMetadata) -> Result<(), ApplicationError> {
+        self.save_files(std::slice::from_ref(file))
+    }
+
+    /// Everything in the store, in no particular order
+    fn all_files(&self) -> Result<Vec<FileMetadata>, ApplicationError>;
+
+    fn get_file(&self, id: FileId) -> Result<Option<FileMetadata>, ApplicationError>;
+
+    /// The direct children of a directory, sorted by name
+    fn get_children(&self, parent_id: FileId) -> Result<Vec<FileMetadata>, ApplicationError>;
+
+    /// At most `limit` of the direct children of a directory, sorted by name, after skipping the first `offset`
+    fn get_children_page(&self, parent_id: FileId, offset: usize, limit: usize) -> Result<Vec<FileMetadata>, ApplicationError>;
+
+    /// The files and directories matching every filter of the search, sorted by name and at most `search.limit`
+    fn search_files(&self, search: &FileSearch) -> Result<Vec<FileMetadata>, ApplicationError>;
+
+    fn set_sync_state(&self, id: FileId, sync_state: SyncState) -> Result<(), ApplicationError>;
+
+    /// Removes the file, or the directory with everything below it
+    fn delete_file(&self, id: FileId) -> Result<(), ApplicationError>;
+
+    /// The files as both sides had them after the last sync they took part in
+    fn synced_files(&self) -> Result<Vec<RemoteFile>, ApplicationError>;
+
+    /// Stores the file as it is on kDrive and as the state both sides agree on
+    fn save_synced_file(&self, file: &RemoteFile) -> Result<(), ApplicationError>;
+
+    /// Removes the file, or the directory with everything below it, from the state both sides agreed on
+    fn forget_synced_file(&self, id: FileId) -> Result<(), ApplicationError>;
+
+    /// Where the list of changes on kDrive continues, none before the first poll
+    fn remote_cursor(&self) -> Result<Option<String>, ApplicationError>;
+
+    /// Saves what changed on kDrive together with the cursor after those changes, all or nothing.
+    /// The deletes go first and take everything below a directory along.
+    fn apply_remote_changes(&self, saved: &[FileMetadata], deleted: &[FileId], cursor: &str) -> Result<(), ApplicationError>;
+
+    /// Which folders are synced, everything until the user chose otherwise
+    fn sync_selection(&self) -> Result<SyncSelection, ApplicationError>;
+
+    /// Replaces every rule of the selection
+    fn save_sync_selection(&self, selection: &SyncSelection) -> Result<(), ApplicationError>;
+
+    /// The chunked upload of the file at `path` in the sync root that did not finish
+    fn upload_session(&self, path: &str) -> Result<Option<UploadSession>, ApplicationError>;
+
+    /// Stores a session with the chunks it has so far, replacing the one for the same path
+    fn save_upload_session(&self, session: &UploadSession) -> Result<(), ApplicationError>;
+
+    /// Records that kDrive confirmed a chunk of the session for the file at `path`
+    fn save_uploaded_chunk(&self, path: &str, chunk_number: u64) -> Result<(), ApplicationError>;
+
+    fn delete_upload_session(&self, path: &str) -> Result<(), ApplicationError>;
+
+    /// The version of the file on kDrive the partial file of a download to `path` was started from
+    fn partial_download(&self, path: &str) -> Result<Option<String>, ApplicationError>;
+
+    /// Replaces the version the partial file of `path` is from
+    fn save_partial_download(&self, path: &str, version: &str) -> Result<(), ApplicationError>;
+
+    fn delete_partial_download(&self, path: &str) -> Result<(), ApplicationError>;
+
+    /// The drive whose files the sync root holds, none before the first sync
+    fn sync_root_drive(&self) -> Result<Option<DriveId>, ApplicationError>;
+
+    fn save_sync_root_drive(&self, drive_id: DriveId) -> Result<(), ApplicationError>;
+
+    /// Forgets every file, the index, the crawl queue, the selection, the uploads and the downloads, as if nothing
+    /// was ever crawled. The drive of the sync root stays, its files are still there
+    fn clear(&self) -> Result<(), ApplicationError>;
+
+    /// Saves the root of the drive and queues it as the first directory to crawl
+    fn start_crawl(&self, root: &FileMetadata) -> Result<(), ApplicationError>;
+
+    /// The directory that was queued first and has not been crawled yet
+    fn next_crawl_directory(&self) -> Result<Option<FileId>, ApplicationError>;
+
+    /// Saves the children of a crawled directory, dequeues it and queues its subdirectories, all or nothing
+    fn save_crawled_directory(&self, directory_id: FileId, children: &[FileMetadata]) -> Result<(), ApplicationError>;
+
+    fn crawl_progress(&self) -> Result<CrawlProgress, ApplicationError>;
+}
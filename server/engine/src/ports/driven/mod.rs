@@ -2,4 +2,8 @@ pub mod configurator_driven_port;
 pub mod authenticator_driven_port;
 pub mod token_store_driven_port;
 pub mod event_bus_driven_port;
-pub mod metadata_driven_port;
\ No newline at end of file
+pub mod metadata_driven_port;
+pub mod clock_driven_port;
+pub mod kdrive_api_driven_port;
+pub mod local_fs_driven_port;
+pub mod local_watcher_driven_port;
@@ -5,4 +5,5 @@ pub trait TokenStoreDrivenPort {
     fn is_available(&self) -> bool;
     fn load(&self) -> Result<Option<Tokens>, ApplicationError>;
     fn save(&self, tokens: &Tokens) -> Result<(), ApplicationError>;
+    fn clear(&self) -> Result<(), ApplicationError>;
 }
\ No newline at end of file
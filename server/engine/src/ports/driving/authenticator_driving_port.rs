@@ -1,8 +1,41 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
 use common::domain::errors::ApplicationError;
+use crate::domain::tokens::Tokens;
+use crate::ports::driven::authenticator_driven_port::PendingAuthorizationCode;
+
+/// The end of a logout that waits for the running sync to stop, awaited without borrowing the engine
+pub type PendingLogout = Pin<Box<dyn Future<Output = Result<(), ApplicationError>> + Send>>;
+
+/// What the token endpoint answered to a refresh, with the refresh token the refresh was started with
+pub struct RefreshedTokens {
+    pub started_with: String,
+    pub tokens: Result<Tokens, ApplicationError>,
+}
+
+/// A refresh that asks the token endpoint without borrowing the engine
+pub type PendingRefresh = Pin<Box<dyn Future<Output = RefreshedTokens> + Send>>;
 
 #[async_trait::async_trait]
 pub trait AuthenticatorDrivingPort {
+    fn has_tokens(&self) -> bool;
     fn is_authenticated(&self) -> bool;
     async fn start_initial_auth_flow(&mut self) -> Result<String, ApplicationError>;
-    async fn continue_initial_auth_flow(&mut self);
-}
\ No newline at end of file
+    fn pending_authorization_code(&mut self) -> Result<PendingAuthorizationCode, ApplicationError>;
+    async fn complete_initial_auth_flow(&mut self, code: Result<String, ApplicationError>);
+    async fn cancel_initial_auth_flow(&mut self);
+    fn token_refresh_delay(&self) -> Option<Duration>;
+    /// Starts a refresh with the stored refresh token, the token endpoint is asked without borrowing the engine
+    fn pending_token_refresh(&self) -> Result<PendingRefresh, ApplicationError>;
+    /// Stores the tokens of a refresh, unless the stored refresh token is no longer the one the refresh was
+    /// started with, because the user logged out or logged in again while the token endpoint answered
+    fn complete_token_refresh(&mut self, refreshed: Result<RefreshedTokens, ApplicationError>) -> Result<(), ApplicationError>;
+    async fn refresh_tokens(&mut self) -> Result<(), ApplicationError>;
+    /// Removes the tokens and stops the running sync, the store is cleared once the sync let go of it
+    async fn start_logout(&mut self) -> Result<PendingLogout, ApplicationError>;
+
+    async fn logout(&mut self) -> Result<(), ApplicationError> {
+        self.start_logout().await?.await
+    }
+}
@@ -1,6 +1,49 @@
-use common::domain::directory_listing::DirectoryListing;
+use std::future::Future;
+use std::pin::Pin;
+use common::domain::directory_listing::{DirectoryListing, ListingPage};
 use common::domain::errors::ApplicationError;
+use common::domain::file_search::{FileSearch, SearchResult};
+use common::domain::sync_conflict::ConflictResolution;
+use common::domain::sync_selection::SyncSelection;
+use crate::domain::remote_file::FileId;
+use crate::domain::sync::synchronizer::SyncReport;
 
+/// A poll, sync, conflict resolution or query that does not borrow the engine, so the engine can be unlocked while it
+/// transfers files or waits for kDrive. Polls, syncs and conflict resolutions run one at a time, in the order they
+/// are awaited.
+pub type PendingSync<T> = Pin<Box<dyn Future<Output = Result<T, ApplicationError>> + Send>>;
+
+#[async_trait::async_trait]
 pub trait DataDrivingPort {
-    fn get_directory_listing(&self, path: String, depth: u32) -> Result<DirectoryListing, ApplicationError>;
-}
\ No newline at end of file
+    /// Lists one page of the directory at `path`, `depth` 1 returns its entries, 2 also their children and so on.
+    /// The page only limits the top level entries.
+    fn get_directory_listing(&self, path: String, depth: u32, page: ListingPage) -> PendingSync<DirectoryListing>;
+
+    /// Searches the stored metadata, also while the crawl is still running, so it works without a connection
+    fn search_files(&self, search: FileSearch) -> PendingSync<Vec<SearchResult>>;
+
+    /// Queues the root of the drive when the store has no metadata yet, true while there is something to crawl
+    async fn start_crawl(&self) -> Result<bool, ApplicationError>;
+
+    /// Stores the contents of the next queued directory, false once the whole drive is indexed
+    async fn crawl_next_directory(&self) -> Result<bool, ApplicationError>;
+
+    /// Stores what changed on kDrive since the last poll, only once the drive is indexed, and returns for how many
+    /// files the store changed
+    fn poll_remote_changes(&self) -> PendingSync<usize>;
+
+    /// Brings the sync root and the drive in line with each other, only once the drive is indexed
+    fn synchronize(&self) -> PendingSync<SyncReport>;
+
+    /// Like `synchronize`, with the renames the watcher saw in the sync root as (from, to) in the order they
+    /// happened, so they are taken as moves instead of being guessed
+    fn synchronize_with_moves(&self, local_moves: Vec<(String, String)>) -> PendingSync<SyncReport>;
+
+    /// Settles a conflict that waits for the user, `path` is where the sync root has the local version
+    fn resolve_conflict(&self, file_id: FileId, path: String, resolution: ConflictResolution) -> PendingSync<()>;
+
+    fn get_sync_selection(&self) -> PendingSync<SyncSelection>;
+
+    /// Replaces the selection when every rule is about a stored folder, the next sync follows it
+    async fn set_sync_selection(&self, selection: SyncSelection) -> Result<(), ApplicationError>;
+}
@@ -6,5 +6,7 @@ pub trait TokenStoreDrivingPort {
     fn access_token(&self) -> Option<&str>;
     fn refresh_token(&self) -> Option<&str>;
     fn expires_at(&self) -> Option<i64>;
+    fn is_expired(&self, now: i64) -> bool;
     fn save_tokens(&mut self, tokens: &Tokens) -> Result<(), ApplicationError>;
+    fn clear_tokens(&mut self) -> Result<(), ApplicationError>;
 }
\ No newline at end of file
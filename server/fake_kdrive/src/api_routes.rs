@@ -0,0 +1,336 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use axum::body::{Body, Bytes};
+use axum::extract::{Path, Query, State};
+use axum::http::header::{CONTENT_LENGTH, CONTENT_RANGE, RANGE};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use futures_util::{stream, StreamExt};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::io::AsyncReadExt;
+use common::domain::errors::ApplicationError;
+use common::domain::text_keys::TextKeys::{ApiConflict, ApiNotFound, ApiRequestFailed};
+use engine::domain::remote_file::{DriveId, FileId, FileKind, RemoteChange, RemoteFile};
+use engine::domain::upload_session::UploadTarget;
+use engine::ports::driven::kdrive_api_driven_port::KDriveApiDrivenPort;
+use crate::{api_error, AppState};
+
+pub fn api_router(state: AppState) -> Router {
+    Router::new()
+        .route("/2/drive", get(list_drives))
+        .route("/3/drive/{drive_id}/files/{file_id}/files", get(list_files))
+        .route("/3/drive/{drive_id}/files/activities", get(list_activities))
+        .route("/3/drive/{drive_id}/files/{file_id}", get(get_file))
+        .route("/2/drive/{drive_id}/files/{file_id}/download", get(download_file))
+        .route("/3/drive/{drive_id}/upload", post(upload_file))
+        .route("/3/drive/{drive_id}/upload/session/start", post(start_upload_session))
+        .route("/3/drive/{drive_id}/upload/session/{token}/chunk", post(upload_chunk))
+        .route("/3/drive/{drive_id}/upload/session/{token}/finish", post(finish_upload_session))
+        .route("/2/drive/{drive_id}/upload/session/{token}", axum::routing::delete(cancel_upload_session))
+        .route("/3/drive/{drive_id}/files/{file_id}/directory", post(create_directory))
+        .route("/3/drive/{drive_id}/files/{file_id}/move/{destination_id}", post(move_file))
+        .route("/2/drive/{drive_id}/files/{file_id}/rename", post(rename_file))
+        .route("/2/drive/{drive_id}/files/{file_id}", axum::routing::delete(delete_file))
+        .route("/2/drive/{drive_id}/trash/{file_id}/restore", post(restore_file))
+        .with_state(state)
+}
+
+type ApiResult = Result<Response, Response>;
+
+fn authorize(state: &AppState, headers: &HeaderMap) -> Result<(), Response> {
+    let token = headers
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    match token {
+        Some(token) if state.auth.lock().unwrap().is_valid_access_token(token) => Ok(()),
+        _ => Err(api_error(StatusCode::UNAUTHORIZED, "not_authorized", "The access token is missing or invalid")),
+    }
+}
+
+fn to_response(error: ApplicationError) -> Response {
+    let description = error.detail.unwrap_or_default();
+    match error.text_key {
+        ApiNotFound => api_error(StatusCode::NOT_FOUND, "object_not_found", &description),
+        ApiConflict => api_error(StatusCode::CONFLICT, "destination_already_exists", &description),
+        ApiRequestFailed => api_error(StatusCode::BAD_REQUEST, "validation_failed", &description),
+        _ => api_error(StatusCode::INTERNAL_SERVER_ERROR, "internal_error", &description),
+    }
+}
+
+fn success(data: Value) -> Response {
+    Json(json!({ "result": "success", "data": data })).into_response()
+}
+
+fn file_json(file: &RemoteFile) -> Value {
+    json!({
+        "id": file.id,
+        "parent_id": file.parent_id,
+        "name": file.name,
+        "type": match file.kind {
+            FileKind::Directory => "dir",
+            FileKind::File => "file",
+        },
+        "size": file.size,
+        "last_modified_at": file.last_modified_at,
+        "hash": file.hash,
+    })
+}
+
+async fn list_drives(State(state): State<AppState>, headers: HeaderMap) -> ApiResult {
+    authorize(&state, &headers)?;
+    let drives = state.api.list_drives().await.map_err(to_response)?;
+
+    Ok(success(drives.iter().map(|drive| json!({ "id": drive.id, "name": drive.name })).collect()))
+}
+
+#[derive(Deserialize)]
+struct ListQuery {
+    cursor: Option<String>,
+}
+
+async fn list_files(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path((drive_id, file_id)): Path<(DriveId, FileId)>,
+    Query(query): Query<ListQuery>,
+) -> ApiResult {
+    authorize(&state, &headers)?;
+    let page = state.api
+        .list_files(drive_id, file_id, query.cursor.as_deref())
+        .await
+        .map_err(to_response)?;
+
+    Ok(Json(json!({
+        "result": "success",
+        "data": page.files.iter().map(file_json).collect::<Vec<_>>(),
+        "cursor": page.cursor,
+        "has_more": page.has_more,
+    })).into_response())
+}
+
+fn activity_json(change: &RemoteChange) -> Value {
+    match change {
+        RemoteChange::Created(file) => json!({ "action": "file_create", "file_id": file.id, "file": file_json(file) }),
+        RemoteChange::Updated(file) => json!({ "action": "file_update", "file_id": file.id, "file": file_json(file) }),
+        RemoteChange::Moved(file) => json!({ "action": "file_move", "file_id": file.id, "file": file_json(file) }),
+        RemoteChange::Deleted(file_id) => json!({ "action": "file_trash", "file_id": file_id }),
+    }
+}
+
+async fn list_activities(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(drive_id): Path<DriveId>,
+    Query(query): Query<ListQuery>,
+) -> ApiResult {
+    authorize(&state, &headers)?;
+    let page = state.api
+        .list_changes(drive_id, query.cursor.as_deref())
+        .await
+        .map_err(to_response)?;
+
+    Ok(Json(json!({
+        "result": "success",
+        "data": page.changes.iter().map(activity_json).collect::<Vec<_>>(),
+        "cursor": page.cursor,
+        "has_more": page.has_more,
+    })).into_response())
+}
+
+async fn get_file(State(state): State<AppState>, headers: HeaderMap, Path((drive_id, file_id)): Path<(DriveId, FileId)>) -> ApiResult {
+    authorize(&state, &headers)?;
+    let file = state.api.get_file(drive_id, file_id).await.map_err(to_response)?;
+
+    Ok(success(file_json(&file)))
+}
+
+/// Only open ranges like `bytes=100-` are supported, that is all a resumed download asks for
+fn range_start(headers: &HeaderMap) -> Option<u64> {
+    headers.get(RANGE)?.to_str().ok()?.strip_prefix("bytes=")?.strip_suffix('-')?.parse().ok()
+}
+
+async fn download_file(State(state): State<AppState>, headers: HeaderMap, Path((drive_id, file_id)): Path<(DriveId, FileId)>) -> ApiResult {
+    authorize(&state, &headers)?;
+    let size = state.api.get_file(drive_id, file_id).await.map_err(to_response)?.size.unwrap_or_default();
+    let offset = range_start(&headers).unwrap_or_default().min(size);
+    let mut reader = state.api.download_file_from(drive_id, file_id, offset).await.map_err(to_response)?;
+
+    // A truncated download still announces all of its length, sends what it has and then drops the connection
+    let mut content = Vec::new();
+    let body = match reader.read_to_end(&mut content).await {
+        Ok(_) => Body::from(content),
+        Err(e) => {
+            let dropped = async move {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                Err(e)
+            };
+            Body::from_stream(stream::once(async { Ok(Bytes::from(content)) }).chain(stream::once(dropped)))
+        }
+    };
+    let length = (CONTENT_LENGTH, (size - offset).to_string());
+    if offset == 0 {
+        return Ok(([length], body).into_response());
+    }
+    let range = (CONTENT_RANGE, format!("bytes {}-{}/{}", offset, size.saturating_sub(1), size));
+    Ok((StatusCode::PARTIAL_CONTENT, [length, range], body).into_response())
+}
+
+async fn upload_file(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(drive_id): Path<DriveId>,
+    Query(query): Query<HashMap<String, String>>,
+    body: Bytes,
+) -> ApiResult {
+    authorize(&state, &headers)?;
+    // With a file id the upload is a new version of that file
+    if let Some(file_id) = query.get("file_id").and_then(|id| id.parse::<FileId>().ok()) {
+        let file = state.api.update_file(drive_id, file_id, body.to_vec()).await.map_err(to_response)?;
+        return Ok(success(file_json(&file)));
+    }
+    let directory_id = query
+        .get("directory_id")
+        .and_then(|id| id.parse::<FileId>().ok())
+        .ok_or_else(|| api_error(StatusCode::BAD_REQUEST, "validation_failed", "directory_id is required"))?;
+    let name = query
+        .get("file_name")
+        .ok_or_else(|| api_error(StatusCode::BAD_REQUEST, "validation_failed", "file_name is required"))?;
+
+    let file = state.api
+        .upload_file(drive_id, directory_id, name, body.to_vec())
+        .await
+        .map_err(to_response)?;
+
+    Ok(success(file_json(&file)))
+}
+
+#[derive(Deserialize)]
+struct StartUploadBody {
+    file_id: Option<FileId>,
+    directory_id: Option<FileId>,
+    file_name: Option<String>,
+    total_size: u64,
+    total_chunks: u64,
+}
+
+async fn start_upload_session(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(drive_id): Path<DriveId>,
+    body: Bytes,
+) -> ApiResult {
+    authorize(&state, &headers)?;
+    let body: StartUploadBody = serde_json::from_slice(&body)
+        .map_err(|e| api_error(StatusCode::BAD_REQUEST, "validation_failed", &e.to_string()))?;
+    let target = match (body.file_id, body.directory_id, body.file_name) {
+        (Some(file_id), _, _) => UploadTarget::NewVersion { file_id },
+        (None, Some(directory_id), Some(name)) => UploadTarget::NewFile { directory_id, name },
+        _ => return Err(api_error(StatusCode::BAD_REQUEST, "validation_failed", "file_id or directory_id and file_name are required")),
+    };
+    let token = state.api
+        .start_upload_session(drive_id, &target, body.total_size, body.total_chunks)
+        .await
+        .map_err(to_response)?;
+
+    Ok(success(json!({ "token": token })))
+}
+
+#[derive(Deserialize)]
+struct ChunkQuery {
+    chunk_number: u64,
+    chunk_hash: String,
+}
+
+async fn upload_chunk(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path((drive_id, token)): Path<(DriveId, String)>,
+    Query(query): Query<ChunkQuery>,
+    body: Bytes,
+) -> ApiResult {
+    authorize(&state, &headers)?;
+    let received_bytes = body.len();
+    state.api
+        .upload_chunk(drive_id, &token, query.chunk_number, body.to_vec(), &query.chunk_hash)
+        .await
+        .map_err(to_response)?;
+
+    Ok(success(json!({ "received_bytes": received_bytes })))
+}
+
+async fn finish_upload_session(State(state): State<AppState>, headers: HeaderMap, Path((drive_id, token)): Path<(DriveId, String)>) -> ApiResult {
+    authorize(&state, &headers)?;
+    let file = state.api.finish_upload_session(drive_id, &token).await.map_err(to_response)?;
+
+    Ok(success(json!({ "token": token, "file": file_json(&file) })))
+}
+
+async fn cancel_upload_session(State(state): State<AppState>, headers: HeaderMap, Path((drive_id, token)): Path<(DriveId, String)>) -> ApiResult {
+    authorize(&state, &headers)?;
+    state.api.cancel_upload_session(drive_id, &token).await.map_err(to_response)?;
+
+    Ok(success(json!(true)))
+}
+
+async fn move_file(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path((drive_id, file_id, destination_id)): Path<(DriveId, FileId, FileId)>,
+) -> ApiResult {
+    authorize(&state, &headers)?;
+    state.api.move_file(drive_id, file_id, destination_id).await.map_err(to_response)?;
+
+    Ok(success(json!(true)))
+}
+
+#[derive(Deserialize)]
+struct NameBody {
+    name: String,
+}
+
+async fn create_directory(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path((drive_id, parent_id)): Path<(DriveId, FileId)>,
+    body: Bytes,
+) -> ApiResult {
+    authorize(&state, &headers)?;
+    let body: NameBody = serde_json::from_slice(&body)
+        .map_err(|e| api_error(StatusCode::BAD_REQUEST, "validation_failed", &e.to_string()))?;
+    let directory = state.api.create_directory(drive_id, parent_id, &body.name).await.map_err(to_response)?;
+
+    Ok(success(file_json(&directory)))
+}
+
+async fn rename_file(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path((drive_id, file_id)): Path<(DriveId, FileId)>,
+    body: Bytes,
+) -> ApiResult {
+    authorize(&state, &headers)?;
+    let body: NameBody = serde_json::from_slice(&body)
+        .map_err(|e| api_error(StatusCode::BAD_REQUEST, "validation_failed", &e.to_string()))?;
+    state.api.rename_file(drive_id, file_id, &body.name).await.map_err(to_response)?;
+
+    Ok(success(json!(true)))
+}
+
+async fn delete_file(State(state): State<AppState>, headers: HeaderMap, Path((drive_id, file_id)): Path<(DriveId, FileId)>) -> ApiResult {
+    authorize(&state, &headers)?;
+    state.api.delete_file(drive_id, file_id).await.map_err(to_response)?;
+
+    Ok(success(json!(true)))
+}
+
+async fn restore_file(State(state): State<AppState>, headers: HeaderMap, Path((drive_id, file_id)): Path<(DriveId, FileId)>) -> ApiResult {
+    authorize(&state, &headers)?;
+    state.api.restore_file(drive_id, file_id).await.map_err(to_response)?;
+
+    Ok(success(json!(true)))
+}
@@ -0,0 +1,157 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use axum::extract::{Request, State};
+use axum::http::header::{RANGE, RETRY_AFTER};
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use crate::api_error;
+
+/// Misbehaviour of the fake server, the tests change it while the server runs
+#[derive(Debug, Default)]
+pub struct Faults {
+    latency: Duration,
+    injected_errors: VecDeque<InjectedError>,
+    rate_limit: Option<RateLimit>,
+    ignore_next_range: bool,
+    request_count: usize,
+}
+
+/// The next request whose path starts with `path_prefix` fails with this status and kDrive error code
+#[derive(Debug, Clone, PartialEq)]
+pub struct InjectedError {
+    pub path_prefix: String,
+    pub status: StatusCode,
+    pub code: String,
+}
+
+#[derive(Debug)]
+struct RateLimit {
+    max_requests: usize,
+    window: Duration,
+    window_started_at: Instant,
+    requests_in_window: usize,
+}
+
+pub type SharedFaults = Arc<Mutex<Faults>>;
+
+impl Faults {
+    pub fn set_latency(&mut self, latency: Duration) {
+        self.latency = latency;
+    }
+
+    pub fn inject_error(&mut self, error: InjectedError) {
+        self.injected_errors.push_back(error);
+    }
+
+    /// Allows `max_requests` per `window`, the requests above it get a 429 with a Retry-After header
+    pub fn set_rate_limit(&mut self, max_requests: usize, window: Duration) {
+        self.rate_limit = Some(RateLimit {
+            max_requests,
+            window,
+            window_started_at: Instant::now(),
+            requests_in_window: 0,
+        });
+    }
+
+    /// The next request with a Range header gets all of the content, like a server without range support
+    pub fn ignore_next_range(&mut self) {
+        self.ignore_next_range = true;
+    }
+
+    pub fn clear(&mut self) {
+        let request_count = self.request_count;
+        *self = Faults { request_count, ..Faults::default() };
+    }
+
+    pub fn request_count(&self) -> usize {
+        self.request_count
+    }
+
+    fn take_injected_error(&mut self, path: &str) -> Option<InjectedError> {
+        let position = self.injected_errors
+            .iter()
+            .position(|error| path.starts_with(&error.path_prefix))?;
+        self.injected_errors.remove(position)
+    }
+
+    /// Seconds until the window ends, when the request is over the limit
+    fn rate_limited(&mut self, now: Instant) -> Option<u64> {
+        let limit = self.rate_limit.as_mut()?;
+        if now.duration_since(limit.window_started_at) >= limit.window {
+            limit.window_started_at = now;
+            limit.requests_in_window = 0;
+        }
+
+        limit.requests_in_window += 1;
+        if limit.requests_in_window <= limit.max_requests {
+            return None;
+        }
+
+        let left = limit.window.saturating_sub(now.duration_since(limit.window_started_at));
+        Some((left.as_secs_f64().ceil() as u64).max(1))
+    }
+}
+
+pub async fn apply_faults(State(faults): State<SharedFaults>, mut request: Request, next: Next) -> Response {
+    let (latency, injected_error, retry_after) = {
+        let mut faults = faults.lock().unwrap();
+        faults.request_count += 1;
+        if faults.ignore_next_range && request.headers_mut().remove(RANGE).is_some() {
+            faults.ignore_next_range = false;
+        }
+        (
+            faults.latency,
+            faults.take_injected_error(request.uri().path()),
+            faults.rate_limited(Instant::now()),
+        )
+    };
+
+    if !latency.is_zero() {
+        tokio::time::sleep(latency).await;
+    }
+
+    if let Some(retry_after) = retry_after {
+        let mut response = api_error(StatusCode::TOO_MANY_REQUESTS, "too_many_requests", "Rate limit exceeded");
+        response.headers_mut().insert(RETRY_AFTER, retry_after.into());
+        return response;
+    }
+
+    if let Some(error) = injected_error {
+        return api_error(error.status, &error.code, "Injected error").into_response();
+    }
+
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn injected_error_only_hits_a_matching_path_once() {
+        let mut faults = Faults::default();
+        faults.inject_error(InjectedError {
+            path_prefix: "/2/drive".to_string(),
+            status: StatusCode::BAD_GATEWAY,
+            code: "bad_gateway".to_string(),
+        });
+
+        assert_eq!(faults.take_injected_error("/token"), None);
+        assert!(faults.take_injected_error("/2/drive/100/files/1").is_some());
+        assert_eq!(faults.take_injected_error("/2/drive/100/files/1"), None);
+    }
+
+    #[test]
+    fn requests_above_the_rate_limit_are_refused_until_the_window_ends() {
+        let mut faults = Faults::default();
+        faults.set_rate_limit(2, Duration::from_secs(10));
+        let start = Instant::now();
+
+        assert_eq!(faults.rate_limited(start), None);
+        assert_eq!(faults.rate_limited(start), None);
+        assert_eq!(faults.rate_limited(start + Duration::from_secs(4)), Some(6));
+        assert_eq!(faults.rate_limited(start + Duration::from_secs(11)), None);
+    }
+}
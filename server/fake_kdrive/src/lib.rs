@@ -0,0 +1,114 @@
+//! A stand-in for the kDrive API and the Infomaniak OAuth endpoints, so the adapters and the whole
+//! service can be tested over real HTTP without a network connection.
+
+pub mod api_routes;
+pub mod faults;
+pub mod oauth_routes;
+
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, MutexGuard};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::{middleware, Json, Router};
+use serde_json::json;
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+use engine::domain::test_helpers::fake_kdrive_api::FakeKDriveApi;
+use crate::api_routes::api_router;
+use crate::faults::{apply_faults, Faults, SharedFaults};
+use crate::oauth_routes::{oauth_router, AuthState};
+
+#[derive(Clone)]
+pub struct AppState {
+    pub api: Arc<FakeKDriveApi>,
+    pub auth: Arc<Mutex<AuthState>>,
+}
+
+/// The error body kDrive sends with a failed request
+pub fn api_error(status: StatusCode, code: &str, description: &str) -> Response {
+    let body = json!({
+        "result": "error",
+        "error": { "code": code, "description": description },
+    });
+    (status, Json(body)).into_response()
+}
+
+pub fn router(state: AppState, faults: SharedFaults) -> Router {
+    api_router(state.clone())
+        .merge(oauth_router(state))
+        .layer(middleware::from_fn_with_state(faults, apply_faults))
+}
+
+/// A running fake server, it stops when dropped
+pub struct FakeKDriveServer {
+    addr: SocketAddr,
+    state: AppState,
+    faults: SharedFaults,
+    handle: JoinHandle<()>,
+}
+
+impl FakeKDriveServer {
+    /// Serves the drive on a free port of the loopback interface
+    pub async fn start(api: FakeKDriveApi) -> io::Result<Self> {
+        Self::start_on(SocketAddr::from(([127, 0, 0, 1], 0)), api).await
+    }
+
+    pub async fn start_on(addr: SocketAddr, api: FakeKDriveApi) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr).await?;
+        let addr = listener.local_addr()?;
+
+        let state = AppState {
+            api: Arc::new(api),
+            auth: Arc::default(),
+        };
+        let faults = SharedFaults::default();
+        let app = router(state.clone(), faults.clone());
+
+        let handle = tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+
+        Ok(Self { addr, state, faults, handle })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// The base URL of the API, use it as `api_url` in the configuration
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    pub fn auth_url(&self) -> String {
+        format!("{}/authorize", self.url())
+    }
+
+    pub fn token_url(&self) -> String {
+        format!("{}/token", self.url())
+    }
+
+    pub fn revocation_url(&self) -> String {
+        format!("{}/revoke", self.url())
+    }
+
+    /// The drive behind the server, changes show up in the next response
+    pub fn api(&self) -> &FakeKDriveApi {
+        &self.state.api
+    }
+
+    pub fn auth(&self) -> MutexGuard<'_, AuthState> {
+        self.state.auth.lock().unwrap()
+    }
+
+    pub fn faults(&self) -> MutexGuard<'_, Faults> {
+        self.faults.lock().unwrap()
+    }
+}
+
+impl Drop for FakeKDriveServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
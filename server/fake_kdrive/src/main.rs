@@ -0,0 +1,46 @@
+use std::net::SocketAddr;
+use engine::domain::default_values::general_defaults::ROOT_DIRECTORY_ID;
+use engine::domain::test_helpers::fake_kdrive_api::FakeKDriveApi;
+use fake_kdrive::FakeKDriveServer;
+
+const DEFAULT_PORT: u16 = 13900;
+const USAGE: &str = "\
+Usage: fake_kdrive [--port <PORT>] [--access-token <TOKEN>]
+
+Serves a fake kDrive API and OAuth endpoints on 127.0.0.1 until stopped with Ctrl+C.";
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut port = DEFAULT_PORT;
+    let mut access_tokens = Vec::new();
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--port" => port = args.next().ok_or(USAGE)?.parse()?,
+            "--access-token" => access_tokens.push(args.next().ok_or(USAGE)?),
+            _ => {
+                eprintln!("{USAGE}");
+                return Err(format!("unknown argument {arg}").into());
+            }
+        }
+    }
+
+    let api = FakeKDriveApi::new_default()
+        .with_directory(ROOT_DIRECTORY_ID, "Documents")
+        .with_file(ROOT_DIRECTORY_ID, "welcome.txt", b"Welcome to the fake kDrive\n");
+    let server = FakeKDriveServer::start_on(SocketAddr::from(([127, 0, 0, 1], port)), api).await?;
+    for token in &access_tokens {
+        server.auth().accept_access_token(token);
+    }
+
+    println!("Fake kDrive listening on {}", server.url());
+    println!("Point the service at it with:");
+    println!("  KDRIVE_API_URL={}", server.url());
+    println!("  KDRIVE_AUTH_URL={}", server.auth_url());
+    println!("  KDRIVE_TOKEN_URL={}", server.token_url());
+    println!("  KDRIVE_REVOCATION_URL={}", server.revocation_url());
+
+    tokio::signal::ctrl_c().await?;
+    Ok(())
+}
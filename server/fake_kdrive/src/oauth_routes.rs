@@ -0,0 +1,145 @@
+use std::collections::HashSet;
+use axum::extract::{Query, State};
+use axum::http::header::LOCATION;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Form, Json, Router};
+use serde::Deserialize;
+use serde_json::json;
+use crate::AppState;
+
+pub const DEFAULT_TOKEN_LIFETIME_SECONDS: i64 = 3600;
+
+/// The tokens this server handed out, only these open the API
+#[derive(Debug)]
+pub struct AuthState {
+    authorization_codes: HashSet<String>,
+    access_tokens: HashSet<String>,
+    refresh_tokens: HashSet<String>,
+    token_lifetime_seconds: i64,
+    issued: u64,
+}
+
+impl Default for AuthState {
+    fn default() -> Self {
+        Self {
+            authorization_codes: HashSet::new(),
+            access_tokens: HashSet::new(),
+            refresh_tokens: HashSet::new(),
+            token_lifetime_seconds: DEFAULT_TOKEN_LIFETIME_SECONDS,
+            issued: 0,
+        }
+    }
+}
+
+impl AuthState {
+    pub fn is_valid_access_token(&self, token: &str) -> bool {
+        self.access_tokens.contains(token)
+    }
+
+    pub fn accept_access_token(&mut self, token: &str) {
+        self.access_tokens.insert(token.to_string());
+    }
+
+    pub fn accept_refresh_token(&mut self, token: &str) {
+        self.refresh_tokens.insert(token.to_string());
+    }
+
+    /// Makes every access token invalid, like an expired session
+    pub fn expire_access_tokens(&mut self) {
+        self.access_tokens.clear();
+    }
+
+    pub fn set_token_lifetime_seconds(&mut self, seconds: i64) {
+        self.token_lifetime_seconds = seconds;
+    }
+
+    fn next(&mut self, kind: &str) -> String {
+        self.issued += 1;
+        format!("fake-{kind}-{}", self.issued)
+    }
+
+    fn issue_tokens(&mut self) -> (String, String) {
+        let access_token = self.next("access-token");
+        let refresh_token = self.next("refresh-token");
+        self.access_tokens.insert(access_token.clone());
+        self.refresh_tokens.insert(refresh_token.clone());
+        (access_token, refresh_token)
+    }
+}
+
+pub fn oauth_router(state: AppState) -> Router {
+    Router::new()
+        .route("/authorize", get(authorize))
+        .route("/token", post(token))
+        .route("/revoke", post(revoke))
+        .with_state(state)
+}
+
+#[derive(Deserialize)]
+struct AuthorizeQuery {
+    redirect_uri: String,
+    state: Option<String>,
+}
+
+/// Logs the user in without asking and sends the browser back to the redirect URL with a code
+async fn authorize(State(state): State<AppState>, Query(query): Query<AuthorizeQuery>) -> Response {
+    let code = {
+        let mut auth = state.auth.lock().unwrap();
+        let code = auth.next("authorization-code");
+        auth.authorization_codes.insert(code.clone());
+        code
+    };
+
+    let separator = if query.redirect_uri.contains('?') { '&' } else { '?' };
+    let mut location = format!("{}{separator}code={code}", query.redirect_uri);
+    if let Some(csrf_state) = query.state {
+        location.push_str(&format!("&state={csrf_state}"));
+    }
+
+    (StatusCode::FOUND, [(LOCATION, location)]).into_response()
+}
+
+#[derive(Deserialize)]
+struct TokenForm {
+    grant_type: String,
+    code: Option<String>,
+    refresh_token: Option<String>,
+}
+
+async fn token(State(state): State<AppState>, Form(form): Form<TokenForm>) -> Response {
+    let mut auth = state.auth.lock().unwrap();
+
+    let granted = match form.grant_type.as_str() {
+        "authorization_code" => form.code.is_some_and(|code| auth.authorization_codes.remove(&code)),
+        "refresh_token" => form.refresh_token.is_some_and(|token| auth.refresh_tokens.remove(&token)),
+        _ => false,
+    };
+    if !granted {
+        return (StatusCode::BAD_REQUEST, Json(json!({
+            "error": "invalid_grant",
+            "error_description": "The code or refresh token is invalid",
+        }))).into_response();
+    }
+
+    let (access_token, refresh_token) = auth.issue_tokens();
+    Json(json!({
+        "access_token": access_token,
+        "refresh_token": refresh_token,
+        "token_type": "bearer",
+        "expires_in": auth.token_lifetime_seconds,
+    })).into_response()
+}
+
+#[derive(Deserialize)]
+struct RevokeForm {
+    token: String,
+}
+
+async fn revoke(State(state): State<AppState>, Form(form): Form<RevokeForm>) -> StatusCode {
+    let mut auth = state.auth.lock().unwrap();
+    auth.refresh_tokens.remove(&form.token);
+    auth.access_tokens.remove(&form.token);
+    StatusCode::OK
+}
@@ -0,0 +1,103 @@
+use std::time::{Duration, Instant};
+use reqwest::StatusCode;
+use serde_json::Value;
+use engine::domain::default_values::general_defaults::ROOT_DIRECTORY_ID;
+use engine::domain::test_helpers::fake_kdrive_api::{FakeKDriveApi, TEST_DRIVE_ID};
+use fake_kdrive::FakeKDriveServer;
+
+const ACCESS_TOKEN: &str = "test-access-token";
+
+async fn start_server() -> FakeKDriveServer {
+    let api = FakeKDriveApi::new_default().with_file(ROOT_DIRECTORY_ID, "notes.txt", b"some notes");
+    let server = FakeKDriveServer::start(api).await.unwrap();
+    server.auth().accept_access_token(ACCESS_TOKEN);
+    server
+}
+
+async fn get(server: &FakeKDriveServer, path: &str, token: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .get(format!("{}{path}", server.url()))
+        .bearer_auth(token)
+        .send()
+        .await
+        .unwrap()
+}
+
+async fn request_tokens(server: &FakeKDriveServer, form: &[(&str, &str)]) -> (StatusCode, Value) {
+    let response = reqwest::Client::new()
+        .post(server.token_url())
+        .form(form)
+        .send()
+        .await
+        .unwrap();
+    let status = response.status();
+    (status, json_body(response).await)
+}
+
+async fn json_body(response: reqwest::Response) -> Value {
+    serde_json::from_slice(&response.bytes().await.unwrap()).unwrap_or_default()
+}
+
+#[tokio::test]
+async fn api_answers_like_kdrive() {
+    let server = start_server().await;
+
+    let response = get(&server, &format!("/3/drive/{TEST_DRIVE_ID}/files/{ROOT_DIRECTORY_ID}/files"), ACCESS_TOKEN).await;
+    let body = json_body(response).await;
+
+    assert_eq!(body["result"], "success");
+    assert_eq!(body["data"][0]["name"], "notes.txt");
+    assert_eq!(body["data"][0]["type"], "file");
+}
+
+#[tokio::test]
+async fn unknown_file_returns_a_kdrive_error_body() {
+    let server = start_server().await;
+
+    let response = get(&server, &format!("/3/drive/{TEST_DRIVE_ID}/files/999"), ACCESS_TOKEN).await;
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let body = json_body(response).await;
+    assert_eq!(body["result"], "error");
+    assert_eq!(body["error"]["code"], "object_not_found");
+}
+
+#[tokio::test]
+async fn refresh_token_can_be_used_only_once() {
+    // Given a refresh token the server handed out
+    let server = start_server().await;
+    server.auth().accept_refresh_token("refresh-1");
+
+    // When it is used twice
+    let (first_status, first) = request_tokens(&server, &[("grant_type", "refresh_token"), ("refresh_token", "refresh-1")]).await;
+    let (second_status, second) = request_tokens(&server, &[("grant_type", "refresh_token"), ("refresh_token", "refresh-1")]).await;
+
+    // Then the first use rotates the tokens and the second is refused
+    assert_eq!(first_status, StatusCode::OK);
+    let access_token = first["access_token"].as_str().unwrap();
+    assert!(server.auth().is_valid_access_token(access_token));
+    assert_eq!(second_status, StatusCode::BAD_REQUEST);
+    assert_eq!(second["error"], "invalid_grant");
+}
+
+#[tokio::test]
+async fn latency_delays_every_response() {
+    let server = start_server().await;
+    server.faults().set_latency(Duration::from_millis(200));
+
+    let start = Instant::now();
+    get(&server, "/2/drive", ACCESS_TOKEN).await;
+
+    assert!(start.elapsed() >= Duration::from_millis(200));
+}
+
+#[tokio::test]
+async fn rate_limited_response_tells_when_to_retry() {
+    let server = start_server().await;
+    server.faults().set_rate_limit(0, Duration::from_secs(30));
+
+    let response = get(&server, "/2/drive", ACCESS_TOKEN).await;
+
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response.headers()["retry-after"], "30");
+}
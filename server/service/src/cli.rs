@@ -0,0 +1,254 @@
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use common::application_error;
+use common::domain::defaults::{default_socket_path, session_secret_path_next_to};
+use common::domain::errors::ApplicationError;
+use common::domain::text_keys::TextKeys::{InvalidListenAddress, MissingCommandLineValue, UnknownCommandLineArgument};
+use engine::domain::configuration::{Configuration, LogLevel};
+
+pub const USAGE: &str = "\
+Usage: kdrive_service [OPTIONS]
+
+Options:
+  -l, --listen <ADDRESS>    Address the gRPC service listens on, e.g. 127.0.0.1:50051
+  -s, --socket              Listen on the Unix domain socket in the user's runtime folder
+      --socket-path <FILE>  Listen on this Unix domain socket instead of a TCP address
+      --session-secret-path <FILE>
+                            Write the session secret to this file, next to the socket by default
+  -c, --config <FILE>       Configuration file to use instead of the default one
+  -d, --data-dir <DIR>      Folder for tokens, metadata and the log file
+  -f, --foreground          Log to the terminal instead of the log file
+      --log-level <LEVEL>   error, warn, info, debug or trace
+  -v, --verbose             Log more, repeat for even more
+  -q, --quiet               Only log errors
+  -h, --help                Show this help";
+
+#[derive(Debug, PartialEq)]
+pub enum CliCommand {
+    Run(CliOptions),
+    Help,
+}
+
+/// Command line options win over the configuration file and environment variables
+#[derive(Debug, Default, PartialEq)]
+pub struct CliOptions {
+    pub listen_addr: Option<SocketAddr>,
+    pub socket_path: Option<PathBuf>,
+    pub session_secret_path: Option<PathBuf>,
+    pub config_path: Option<PathBuf>,
+    pub data_dir: Option<PathBuf>,
+    pub foreground: bool,
+    pub log_level: Option<LogLevel>,
+}
+
+impl CliOptions {
+    pub fn parse<I>(args: I) -> Result<CliCommand, ApplicationError>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut options = CliOptions::default();
+        let mut verbosity = 0;
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            // Both "--listen value" and "--listen=value" are accepted
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value.to_string())),
+                _ => (arg, None),
+            };
+            let mut value = || inline_value
+                .clone()
+                .or_else(|| args.next())
+                .ok_or(application_error!(MissingCommandLineValue, &flag));
+
+            match flag.as_str() {
+                "-h" | "--help" => return Ok(CliCommand::Help),
+                "-l" | "--listen" => {
+                    let address = value()?;
+                    options.listen_addr = Some(address
+                        .parse()
+                        .map_err(|_| application_error!(InvalidListenAddress, address))?);
+                }
+                "-s" | "--socket" => options.socket_path = Some(default_socket_path()),
+                "--socket-path" => options.socket_path = Some(PathBuf::from(value()?)),
+                "--session-secret-path" => options.session_secret_path = Some(PathBuf::from(value()?)),
+                "-c" | "--config" => options.config_path = Some(PathBuf::from(value()?)),
+                "-d" | "--data-dir" => options.data_dir = Some(PathBuf::from(value()?)),
+                "-f" | "--foreground" => options.foreground = true,
+                "--log-level" => options.log_level = Some(value()?.parse()?),
+                "-v" | "--verbose" => verbosity += 1,
+                "-vv" => verbosity += 2,
+                "-q" | "--quiet" => options.log_level = Some(LogLevel::Error),
+                _ => return Err(application_error!(UnknownCommandLineArgument, flag)),
+            }
+        }
+
+        match verbosity {
+            0 => {}
+            1 => options.log_level = Some(LogLevel::Debug),
+            _ => options.log_level = Some(LogLevel::Trace),
+        }
+
+        Ok(CliCommand::Run(options))
+    }
+
+    pub fn apply_to(&self, mut config: Configuration) -> Configuration {
+        // An explicit TCP address turns off a socket from the configuration, an explicit socket wins over both
+        if let Some(listen_addr) = self.listen_addr {
+            config.grpc_listen_addr = listen_addr;
+            config.grpc_socket_path = None;
+        }
+        if let Some(socket_path) = &self.socket_path {
+            config.grpc_socket_path = Some(socket_path.clone());
+        }
+        // A service on a socket of its own keeps its secret next to it, unless told otherwise
+        if let Some(session_secret_path) = self.session_secret_path.clone()
+            .or_else(|| self.socket_path.as_deref().map(session_secret_path_next_to))
+        {
+            config.session_secret_path = session_secret_path;
+        }
+        if let Some(data_dir) = &self.data_dir {
+            config.data_dir = data_dir.clone();
+        }
+        if let Some(log_level) = self.log_level {
+            config.log_level = log_level;
+        }
+        config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common::domain::text_keys::TextKeys::InvalidLogLevel;
+    use engine::domain::test_helpers::fake_configurator_adapter::FakeConfiguratorPort;
+    use engine::ports::driven::configurator_driven_port::ConfiguratorPort;
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<CliCommand, ApplicationError> {
+        CliOptions::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn parse_options(args: &[&str]) -> CliOptions {
+        match parse(args).unwrap() {
+            CliCommand::Run(options) => options,
+            CliCommand::Help => panic!("Expected options, got help"),
+        }
+    }
+
+    #[test]
+    fn no_arguments_keep_the_configuration() {
+        assert_eq!(parse_options(&[]), CliOptions::default());
+    }
+
+    #[test]
+    fn all_options_are_parsed() {
+        let options = parse_options(&[
+            "--listen", "127.0.0.1:50100",
+            "--socket-path", "/tmp/kdrive.sock",
+            "--session-secret-path", "/tmp/kdrive-secret",
+            "--config", "/tmp/kdrive.toml",
+            "--data-dir=/tmp/kdrive-data",
+            "--foreground",
+            "--log-level", "warn",
+        ]);
+
+        assert_eq!(options, CliOptions {
+            listen_addr: Some("127.0.0.1:50100".parse().unwrap()),
+            socket_path: Some(PathBuf::from("/tmp/kdrive.sock")),
+            session_secret_path: Some(PathBuf::from("/tmp/kdrive-secret")),
+            config_path: Some(PathBuf::from("/tmp/kdrive.toml")),
+            data_dir: Some(PathBuf::from("/tmp/kdrive-data")),
+            foreground: true,
+            log_level: Some(LogLevel::Warn),
+        });
+    }
+
+    #[test]
+    fn verbose_flags_raise_the_log_level() {
+        assert_eq!(parse_options(&["-v"]).log_level, Some(LogLevel::Debug));
+        assert_eq!(parse_options(&["-v", "-v"]).log_level, Some(LogLevel::Trace));
+        assert_eq!(parse_options(&["-q"]).log_level, Some(LogLevel::Error));
+    }
+
+    #[test]
+    fn socket_flag_uses_the_default_socket_path() {
+        assert_eq!(parse_options(&["--socket"]).socket_path, Some(default_socket_path()));
+    }
+
+    #[test]
+    fn help_flag_asks_for_help() {
+        assert_eq!(parse(&["--listen", "127.0.0.1:1", "-h"]).unwrap(), CliCommand::Help);
+    }
+
+    #[test]
+    fn unknown_argument_returns_an_error() {
+        let err = parse(&["--port", "1"]).expect_err("Expected UnknownCommandLineArgument error");
+
+        assert_eq!(err.text_key, UnknownCommandLineArgument);
+    }
+
+    #[test]
+    fn missing_value_returns_an_error() {
+        let err = parse(&["--listen"]).expect_err("Expected MissingCommandLineValue error");
+
+        assert_eq!(err.text_key, MissingCommandLineValue);
+    }
+
+    #[test]
+    fn invalid_values_return_an_error() {
+        assert_eq!(parse(&["--listen", "nowhere"]).unwrap_err().text_key, InvalidListenAddress);
+        assert_eq!(parse(&["--log-level", "loud"]).unwrap_err().text_key, InvalidLogLevel);
+    }
+
+    #[test]
+    fn options_override_the_configuration() {
+        let config = FakeConfiguratorPort::with_client_id("client-id").load().unwrap();
+        let options = parse_options(&["--listen", "[::1]:50100", "--data-dir", "/tmp/kdrive-data", "-q"]);
+
+        let config = options.apply_to(config);
+
+        assert_eq!(config.grpc_listen_addr, "[::1]:50100".parse().unwrap());
+        assert_eq!(config.data_dir, PathBuf::from("/tmp/kdrive-data"));
+        assert_eq!(config.log_level, LogLevel::Error);
+    }
+
+    #[test]
+    fn listen_address_replaces_a_configured_socket() {
+        let mut config = FakeConfiguratorPort::with_client_id("client-id").load().unwrap();
+        config.grpc_socket_path = Some(PathBuf::from("/tmp/kdrive.sock"));
+
+        let config = parse_options(&["--listen", "127.0.0.1:50100"]).apply_to(config);
+
+        assert_eq!(config.grpc_socket_path, None);
+    }
+
+    #[test]
+    fn socket_path_overrides_the_configuration() {
+        let config = FakeConfiguratorPort::with_client_id("client-id").load().unwrap();
+
+        let config = parse_options(&["--socket-path", "/tmp/kdrive.sock"]).apply_to(config);
+
+        assert_eq!(config.grpc_socket_path, Some(PathBuf::from("/tmp/kdrive.sock")));
+        assert_eq!(config.session_secret_path, PathBuf::from("/tmp/session_secret"));
+    }
+
+    #[test]
+    fn session_secret_path_overrides_the_configuration_and_the_socket() {
+        let config = FakeConfiguratorPort::with_client_id("client-id").load().unwrap();
+        let options = parse_options(&["--socket-path", "/tmp/kdrive.sock", "--session-secret-path", "/tmp/kdrive-secret"]);
+
+        let config = options.apply_to(config);
+
+        assert_eq!(config.session_secret_path, PathBuf::from("/tmp/kdrive-secret"));
+    }
+
+    #[test]
+    fn session_secret_path_is_kept_without_a_socket() {
+        let config = FakeConfiguratorPort::with_client_id("client-id").load().unwrap();
+        let configured = config.session_secret_path.clone();
+
+        let config = parse_options(&["--listen", "127.0.0.1:50100"]).apply_to(config);
+
+        assert_eq!(config.session_secret_path, configured);
+    }
+}
@@ -1,62 +1,169 @@
 use std::pin::Pin;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
 use tonic::{Request, Response, Status};
 use tonic::codegen::tokio_stream::Stream;
 use adapters::driven::event_bus_adapter::EventBusAdapter;
+use engine::domain::auth_flow_runner::AuthFlowRunner;
+use engine::domain::default_values::configurator_defaults::DEFAULT_AUTH_FLOW_TIMEOUT_SECONDS;
 use engine::domain::engine::Engine;
 use engine::ports::driven::authenticator_driven_port::AuthenticatorDrivenPort;
 use engine::ports::driven::event_bus_driven_port::EventBusDrivenPort;
 use engine::ports::driving::authenticator_driving_port::AuthenticatorDrivingPort;
 use engine::ports::driving::token_store_driving_port::TokenStoreDrivingPort;
 use engine::domain::events::EngineEvent;
+use engine::domain::token_refresh_scheduler::TokenRefreshScheduler;
+use engine::domain::metadata::CrawlProgress;
+use engine::domain::metadata_crawler::MetadataCrawler;
+use engine::domain::local_change_watcher::LocalChangeWatcher;
+use engine::domain::sync::resumable_download::DownloadProgress;
+use engine::domain::remote_poller::RemotePoller;
 use tokio_stream::wrappers::BroadcastStream;
 use futures_util::StreamExt;
 use common::kdrive::kdrive_service_server::KdriveService;
-use common::kdrive::{AuthFlowCompleted, AuthStatus, AuthUrlResponse, Empty, ServerEvent};
+use common::kdrive::{AuthFlowCompleted, AuthStatus, AuthUrlResponse, Empty, ListDirectoryRequest, ListDirectoryResponse, LoggedOut, SearchFilesRequest, RemoteChangesDetected, ResolveConflictRequest, SearchFilesResponse, ServerEvent, SyncCompleted, SyncSelection, TokensRefreshed};
 use common::kdrive::server_event::Event as ServerEventKind;
+use common::domain::directory_listing::ListingPage;
+use common::domain::sync_conflict::ConflictResolution;
 use engine::ports::driven::metadata_driven_port::MetadataDrivenPort;
+use engine::ports::driven::clock_driven_port::ClockDrivenPort;
+use engine::ports::driven::kdrive_api_driven_port::KDriveApiDrivenPort;
+use engine::ports::driven::local_fs_driven_port::LocalFsDrivenPort;
+use engine::ports::driven::local_watcher_driven_port::LocalWatcherDrivenPort;
+use engine::ports::driving::data_driving_port::DataDrivingPort;
+
+fn crawl_progress_event(progress: CrawlProgress, completed: bool) -> ServerEvent {
+    ServerEvent {
+        event: Some(ServerEventKind::CrawlProgress(common::kdrive::CrawlProgress {
+            directories_visited: progress.directories_visited,
+            directories_pending: progress.directories_pending,
+            files_found: progress.files_found,
+            bytes_total: progress.bytes_total,
+            completed,
+        })),
+    }
+}
+
+fn download_progress_event(progress: DownloadProgress) -> ServerEvent {
+    ServerEvent {
+        event: Some(ServerEventKind::DownloadProgress(common::kdrive::DownloadProgress {
+            file_id: progress.file_id,
+            path: progress.path,
+            bytes_downloaded: progress.bytes_downloaded,
+            bytes_total: progress.bytes_total,
+        })),
+    }
+}
 
 type EventStream = Pin<Box<dyn Stream<Item = Result<ServerEvent, Status>> + Send>>;
+type SharedEngine<AuthPort, TokenPort, EventPort, MetadataPort, ClockPort, ApiPort, LocalPort> =
+    Arc<Mutex<Engine<AuthPort, TokenPort, EventPort, MetadataPort, ClockPort, ApiPort, LocalPort>>>;
 
-pub struct KdriveServiceHandler<AuthPort, TokenPort, EventPort, MetadataPort>
+pub struct KdriveServiceHandler<AuthPort, TokenPort, EventPort, MetadataPort, ClockPort, ApiPort, LocalPort>
 where
     AuthPort: AuthenticatorDrivenPort,
     TokenPort: TokenStoreDrivingPort,
     EventPort: EventBusDrivenPort,
-    MetadataPort: MetadataDrivenPort
+    MetadataPort: MetadataDrivenPort,
+    ClockPort: ClockDrivenPort,
+    ApiPort: KDriveApiDrivenPort,
+    LocalPort: LocalFsDrivenPort,
 {
-    engine: Arc<Mutex<Engine<AuthPort, TokenPort, EventPort, MetadataPort>>>,
+    engine: SharedEngine<AuthPort, TokenPort, EventPort, MetadataPort, ClockPort, ApiPort, LocalPort>,
     event_bus: EventBusAdapter,
+    auth_flow: std::sync::Mutex<Option<JoinHandle<()>>>,
+    auth_flow_timeout: Duration,
 }
 
-impl<AuthPort, TokenPort, EventPort, MetadataPort> KdriveServiceHandler<AuthPort, TokenPort, EventPort, MetadataPort>
+impl<AuthPort, TokenPort, EventPort, MetadataPort, ClockPort, ApiPort, LocalPort> KdriveServiceHandler<AuthPort, TokenPort, EventPort, MetadataPort, ClockPort, ApiPort, LocalPort>
 where
     AuthPort: AuthenticatorDrivenPort,
     TokenPort: TokenStoreDrivingPort,
     EventPort: EventBusDrivenPort,
-    MetadataPort: MetadataDrivenPort
+    MetadataPort: MetadataDrivenPort,
+    ClockPort: ClockDrivenPort,
+    ApiPort: KDriveApiDrivenPort,
+    LocalPort: LocalFsDrivenPort,
 {
     pub fn new(
-        engine: Engine<AuthPort, TokenPort, EventPort, MetadataPort>,
+        engine: Engine<AuthPort, TokenPort, EventPort, MetadataPort, ClockPort, ApiPort, LocalPort>,
         event_bus: EventBusAdapter)
         -> Self
     {
         KdriveServiceHandler {
             engine: Arc::new(Mutex::new(engine)),
             event_bus,
+            auth_flow: std::sync::Mutex::new(None),
+            auth_flow_timeout: Duration::from_secs(DEFAULT_AUTH_FLOW_TIMEOUT_SECONDS),
+        }
+    }
+
+    pub fn with_auth_flow_timeout(mut self, timeout: Duration) -> Self {
+        self.auth_flow_timeout = timeout;
+        self
+    }
+
+    fn stop_auth_flow(&self) {
+        if let Some(auth_flow) = self.auth_flow.lock().unwrap().take() {
+            auth_flow.abort();
         }
     }
 }
 
+impl<AuthPort, TokenPort, EventPort, MetadataPort, ClockPort, ApiPort, LocalPort> KdriveServiceHandler<AuthPort, TokenPort, EventPort, MetadataPort, ClockPort, ApiPort, LocalPort>
+where
+    AuthPort: AuthenticatorDrivenPort + Send + 'static,
+    TokenPort: TokenStoreDrivingPort + Send + 'static,
+    EventPort: EventBusDrivenPort + Send + 'static,
+    MetadataPort: MetadataDrivenPort + Send + 'static,
+    ClockPort: ClockDrivenPort + Send + 'static,
+    ApiPort: KDriveApiDrivenPort + Send + 'static,
+    LocalPort: LocalFsDrivenPort + Send + 'static,
+{
+    pub fn start_token_refresh(&self) -> JoinHandle<()> {
+        TokenRefreshScheduler::new().start(self.engine.clone())
+    }
+}
+
+impl<AuthPort, TokenPort, EventPort, MetadataPort, ClockPort, ApiPort, LocalPort> KdriveServiceHandler<AuthPort, TokenPort, EventPort, MetadataPort, ClockPort, ApiPort, LocalPort>
+where
+    AuthPort: AuthenticatorDrivenPort + Send + Sync + 'static,
+    TokenPort: TokenStoreDrivingPort + Send + Sync + 'static,
+    EventPort: EventBusDrivenPort + Send + Sync + 'static,
+    MetadataPort: MetadataDrivenPort + Send + Sync + 'static,
+    ClockPort: ClockDrivenPort + Send + Sync + 'static,
+    ApiPort: KDriveApiDrivenPort + Send + Sync + 'static,
+    LocalPort: LocalFsDrivenPort + Send + Sync + 'static,
+{
+    pub fn start_metadata_crawl(&self) -> JoinHandle<()> {
+        MetadataCrawler::new().start(self.engine.clone())
+    }
+
+    pub fn start_local_watch<W>(&self, watcher: W) -> JoinHandle<()>
+    where
+        W: LocalWatcherDrivenPort + Send + 'static,
+    {
+        LocalChangeWatcher::new().start(self.engine.clone(), watcher)
+    }
+
+    pub fn start_remote_polling(&self) -> JoinHandle<()> {
+        RemotePoller::new().start(self.engine.clone())
+    }
+}
+
 #[tonic::async_trait]
-impl<AuthPort, TokenPort, EventPort, MetadataPort>
-    KdriveService for KdriveServiceHandler<AuthPort, TokenPort, EventPort, MetadataPort>
+impl<AuthPort, TokenPort, EventPort, MetadataPort, ClockPort, ApiPort, LocalPort>
+    KdriveService for KdriveServiceHandler<AuthPort, TokenPort, EventPort, MetadataPort, ClockPort, ApiPort, LocalPort>
 where
     AuthPort: AuthenticatorDrivenPort + Send + Sync + 'static,
     TokenPort: TokenStoreDrivingPort + Send + Sync + 'static,
     EventPort: EventBusDrivenPort + Send + Sync + 'static,
     MetadataPort: MetadataDrivenPort + Send + Sync + 'static,
+    ClockPort: ClockDrivenPort + Send + Sync + 'static,
+    ApiPort: KDriveApiDrivenPort + Send + Sync + 'static,
+    LocalPort: LocalFsDrivenPort + Send + Sync + 'static,
 {
     async fn is_authenticated(&self, _request: Request<Empty>)
         -> Result<Response<AuthStatus>, Status>
@@ -64,11 +171,13 @@ where
         let engine = self.engine.lock().await;
         Ok(Response::new(AuthStatus {
             is_authenticated: engine.is_authenticated(),
+            has_tokens: engine.has_tokens(),
         }))
     }
     async fn start_initial_auth_flow(&self, _request: Request<Empty>)
         -> Result<Response<AuthUrlResponse>, Status>
     {
+        self.stop_auth_flow();
         let mut engine = self.engine.lock().await;
 
         engine
@@ -81,17 +190,38 @@ where
     async fn continue_initial_auth_flow(&self, _request: Request<Empty>)
         -> Result<Response<Empty>, Status>
     {
-       let engine = self.engine.clone();
+        let mut auth_flow = self.auth_flow.lock().unwrap();
 
-        // Fire-and-forget
-        tokio::spawn(async move {
-            let mut engine = engine.lock().await;
-            let _ = engine.continue_initial_auth_flow().await;
-         });
+        // Fire-and-forget, a flow that is already waiting keeps waiting
+        if auth_flow.as_ref().is_none_or(JoinHandle::is_finished) {
+            let runner = AuthFlowRunner::with_timeout(self.auth_flow_timeout);
+            *auth_flow = Some(runner.start(self.engine.clone()));
+        }
 
         Ok(Response::new(Empty {}))
     }
 
+    async fn cancel_auth_flow(&self, _request: Request<Empty>)
+        -> Result<Response<Empty>, Status>
+    {
+        self.stop_auth_flow();
+        self.engine.lock().await.cancel_initial_auth_flow().await;
+
+        Ok(Response::new(Empty {}))
+    }
+
+    async fn logout(&self, _request: Request<Empty>)
+        -> Result<Response<Empty>, Status>
+    {
+        // The store is cleared once the running sync stopped, the engine is not locked while it does
+        let logout = self.engine.lock().await.start_logout().await.map_err(Status::from)?;
+
+        logout
+            .await
+            .map(|()| Response::new(Empty {}))
+            .map_err(Status::from)
+    }
+
     type SubscribeEventsStream = EventStream;
 
     async fn subscribe_events(&self,_request: Request<Empty>)
@@ -114,12 +244,152 @@ where
                         Some(Ok(reason.into()))
                     }
 
+                    Ok(EngineEvent::TokensRefreshed) => {
+                        Some(Ok(ServerEvent {
+                            event: Some(ServerEventKind::TokensRefreshed(
+                                TokensRefreshed {},
+                            )),
+                        }))
+                    }
+
+                    Ok(EngineEvent::TokenRefreshFailed { reason }) => {
+                        Some(Ok(reason.into()))
+                    }
+
+                    Ok(EngineEvent::LoggedOut) => {
+                        Some(Ok(ServerEvent {
+                            event: Some(ServerEventKind::LoggedOut(
+                                LoggedOut {},
+                            )),
+                        }))
+                    }
+
+                    Ok(EngineEvent::CrawlProgressed { progress }) => {
+                        Some(Ok(crawl_progress_event(progress, false)))
+                    }
+
+                    Ok(EngineEvent::CrawlCompleted { progress }) => {
+                        Some(Ok(crawl_progress_event(progress, true)))
+                    }
+
+                    Ok(EngineEvent::CrawlFailed { reason }) => {
+                        Some(Ok(reason.into()))
+                    }
+
+                    Ok(EngineEvent::SyncCompleted { operations_completed, operations_failed }) => {
+                        Some(Ok(ServerEvent {
+                            event: Some(ServerEventKind::SyncCompleted(SyncCompleted {
+                                operations_completed: operations_completed as u64,
+                                operations_failed: operations_failed as u64,
+                            })),
+                        }))
+                    }
+
+                    Ok(EngineEvent::SyncFailed { reason }) => {
+                        Some(Ok(reason.into()))
+                    }
+
+                    Ok(EngineEvent::RemoteChangesDetected { changes }) => {
+                        Some(Ok(ServerEvent {
+                            event: Some(ServerEventKind::RemoteChangesDetected(RemoteChangesDetected {
+                                changes: changes as u64,
+                            })),
+                        }))
+                    }
+
+                    Ok(EngineEvent::ConflictDetected { conflict }) => {
+                        Some(Ok(ServerEvent {
+                            event: Some(ServerEventKind::ConflictDetected(conflict.into())),
+                        }))
+                    }
+
+                    Ok(EngineEvent::DownloadProgressed { progress }) => {
+                        Some(Ok(download_progress_event(progress)))
+                    }
+
                     Err(_) => None, // receiver lagged, drop event
                 }
             });
 
         Ok(Response::new(Box::pin(stream)))
     }
+
+    async fn list_directory(&self, request: Request<ListDirectoryRequest>)
+        -> Result<Response<ListDirectoryResponse>, Status>
+    {
+        let request = request.into_inner();
+        let page = ListingPage::parse(&request.page_token, request.page_size).map_err(Status::from)?;
+        // Awaited without the lock, a listing that asks kDrive does not hold up the other requests
+        let listing = self.engine.lock().await.get_directory_listing(request.path, request.depth, page);
+
+        listing
+            .await
+            .map(|listing| Response::new(listing.into()))
+            .map_err(Status::from)
+    }
+
+    async fn search_files(&self, request: Request<SearchFilesRequest>)
+        -> Result<Response<SearchFilesResponse>, Status>
+    {
+        let search = self.engine.lock().await.search_files(request.into_inner().into());
+
+        search
+            .await
+            .map(|results| Response::new(SearchFilesResponse {
+                results: results.into_iter().map(Into::into).collect(),
+            }))
+            .map_err(Status::from)
+    }
+
+    async fn resolve_conflict(&self, request: Request<ResolveConflictRequest>)
+        -> Result<Response<Empty>, Status>
+    {
+        let request = request.into_inner();
+        let resolution = ConflictResolution::try_from(request.resolution()).map_err(Status::from)?;
+        // Awaited without the lock, the other requests are answered while the file is transferred
+        let resolve = self.engine.lock().await.resolve_conflict(request.file_id, request.path, resolution);
+
+        resolve
+            .await
+            .map(|_| Response::new(Empty {}))
+            .map_err(Status::from)
+    }
+
+    async fn get_sync_selection(&self, _request: Request<Empty>)
+        -> Result<Response<SyncSelection>, Status>
+    {
+        let selection = self.engine.lock().await.get_sync_selection();
+
+        selection
+            .await
+            .map(|selection| Response::new(selection.into()))
+            .map_err(Status::from)
+    }
+
+    async fn set_sync_selection(&self, request: Request<SyncSelection>)
+        -> Result<Response<Empty>, Status>
+    {
+        self.engine
+            .lock()
+            .await
+            .set_sync_selection(request.into_inner().into())
+            .await
+            .map_err(Status::from)?;
+
+        // Fire-and-forget, removing and downloading folders can take a while and reports through the events
+        let engine = self.engine.clone();
+        tokio::spawn(async move {
+            let sync = {
+                let engine = engine.lock().await;
+                engine.has_tokens().then(|| engine.synchronize())
+            };
+            if let Some(sync) = sync {
+                let _ = sync.await;
+            }
+        });
+
+        Ok(Response::new(Empty {}))
+    }
 }
 
 #[cfg(test)]
@@ -131,13 +401,37 @@ mod tests {
     use engine::domain::engine::Engine;
     use engine::domain::test_helpers::fake_authenticator_adapter::FakeAuthenticatorDrivenAdapter;
     use engine::domain::test_helpers::fake_authenticator_adapter_slow::SlowAuthenticatorAdapter;
+    use engine::domain::test_helpers::fake_clock::FakeClock;
     use engine::domain::test_helpers::fake_event_bus::FakeEventBus;
+    use engine::domain::test_helpers::fake_kdrive_api::FakeKDriveApi;
+    use engine::domain::test_helpers::fake_local_fs::FakeLocalFs;
     use engine::domain::test_helpers::fake_metadata_store::FakeMetadataStore;
     use engine::domain::test_helpers::fake_token_store_adapter::{
         FakeTokenStoreRingAdapter, FakeTokenStoreFileAdapter
     };
     use engine::domain::test_helpers::fake_token_store::FakeTokenStore;
     use crate::grpc_handler::KdriveServiceHandler;
+    use std::time::Duration;
+    use common::application_error;
+    use common::domain::text_keys::TextKeys::{AuthFlowCancelled, AuthFlowTimedOut};
+    use engine::domain::events::EngineEvent;
+
+    fn waiting_handler(events: FakeEventBus) -> KdriveServiceHandler<FakeAuthenticatorDrivenAdapter, FakeTokenStore, FakeEventBus, FakeMetadataStore, FakeClock, FakeKDriveApi, FakeLocalFs> {
+        let token_store: FakeTokenStore = FakeTokenStore::load(
+            Some(FakeTokenStoreRingAdapter::empty()),
+            None
+        ).unwrap();
+        let engine = Engine::new(
+            FakeAuthenticatorDrivenAdapter::new_default_waiting(),
+            token_store,
+            events,
+            FakeMetadataStore::new(),
+            FakeClock::new(),
+            FakeKDriveApi::new_default(),
+            FakeLocalFs::new(),
+        );
+        KdriveServiceHandler::new(engine, EventBusAdapter::new())
+    }
 
     #[tokio::test]
     async fn check_authentication_get_engine_status() {
@@ -151,7 +445,7 @@ mod tests {
         ).unwrap();
         let fake_events = FakeEventBus::new();
         let fake_metadata_store = FakeMetadataStore::new();
-        let engine = Engine::new(fake_engine, token_store, fake_events, fake_metadata_store);
+        let engine = Engine::new(fake_engine, token_store, fake_events, fake_metadata_store, FakeClock::new(), FakeKDriveApi::new_default(), FakeLocalFs::new());
         let event_bus = EventBusAdapter::new();
         let handler = KdriveServiceHandler::new(engine, event_bus);
 
@@ -175,7 +469,7 @@ mod tests {
         ).unwrap();
         let fake_events = FakeEventBus::new();
         let fake_metadata_store = FakeMetadataStore::new();
-        let engine = Engine::new(fake_engine, token_store, fake_events, fake_metadata_store);
+        let engine = Engine::new(fake_engine, token_store, fake_events, fake_metadata_store, FakeClock::new(), FakeKDriveApi::new_default(), FakeLocalFs::new());
         let event_bus = EventBusAdapter::new();
         let handler = KdriveServiceHandler::new(engine, event_bus);
 
@@ -199,7 +493,7 @@ mod tests {
         ).unwrap();
         let fake_events = FakeEventBus::new();
         let fake_metadata_store = FakeMetadataStore::new();
-        let engine = Engine::new(fake_engine, token_store, fake_events, fake_metadata_store);
+        let engine = Engine::new(fake_engine, token_store, fake_events, fake_metadata_store, FakeClock::new(), FakeKDriveApi::new_default(), FakeLocalFs::new());
         let event_bus = EventBusAdapter::new();
         let handler = KdriveServiceHandler::new(engine, event_bus);
 
@@ -227,7 +521,7 @@ mod tests {
         ).unwrap();
         let fake_events = FakeEventBus::new();
         let fake_metadata_store = FakeMetadataStore::new();
-        let engine = Engine::new(slow_adapter, token_store, fake_events, fake_metadata_store);
+        let engine = Engine::new(slow_adapter, token_store, fake_events, fake_metadata_store, FakeClock::new(), FakeKDriveApi::new_default(), FakeLocalFs::new());
         let event_bus = EventBusAdapter::new();
         let handler = KdriveServiceHandler::new(engine, event_bus);
         // ... setup met slow_adapter ...
@@ -240,4 +534,80 @@ mod tests {
         // Then it returns immediately, NOT after 2+ seconds
         assert!(elapsed < std::time::Duration::from_millis(100));
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn logout_removes_the_tokens_of_the_engine() {
+        // Given a handler with an authenticated engine
+        let fake_engine = FakeAuthenticatorDrivenAdapter::new_default();
+        let fake_ring_tokens = FakeTokenStoreRingAdapter::with_tokens();
+        let token_store: FakeTokenStore = FakeTokenStore::load(
+            Some(fake_ring_tokens),
+            None
+        ).unwrap();
+        let fake_events = FakeEventBus::new();
+        let fake_metadata_store = FakeMetadataStore::new();
+        let engine = Engine::new(fake_engine, token_store, fake_events, fake_metadata_store, FakeClock::new(), FakeKDriveApi::new_default(), FakeLocalFs::new());
+        let event_bus = EventBusAdapter::new();
+        let handler = KdriveServiceHandler::new(engine, event_bus);
+
+        // When we call logout
+        handler.logout(Request::new(Empty {})).await.unwrap();
+
+        // Then the engine no longer has tokens
+        let response = handler.is_authenticated(Request::new(Empty {})).await.unwrap();
+        assert!(!response.into_inner().has_tokens);
+    }
+
+    #[tokio::test]
+    async fn is_authenticated_responds_while_the_auth_flow_is_waiting() {
+        // Given a handler waiting for a user who has not logged in yet
+        let handler = waiting_handler(FakeEventBus::new());
+        handler.start_initial_auth_flow(Request::new(Empty {})).await.unwrap();
+        handler.continue_initial_auth_flow(Request::new(Empty {})).await.unwrap();
+
+        // When we ask whether the user is authenticated
+        let response = tokio::time::timeout(
+            Duration::from_millis(500),
+            handler.is_authenticated(Request::new(Empty {})),
+        ).await;
+
+        // Then the handler answers without waiting for the auth flow
+        assert!(!response.expect("IsAuthenticated was blocked by the auth flow").unwrap().into_inner().is_authenticated);
+    }
+
+    #[tokio::test]
+    async fn auth_flow_fails_when_the_timeout_passes() {
+        // Given a handler with a short auth flow timeout
+        let events = FakeEventBus::new();
+        let handler = waiting_handler(events.clone())
+            .with_auth_flow_timeout(Duration::from_millis(10));
+        handler.start_initial_auth_flow(Request::new(Empty {})).await.unwrap();
+
+        // When the user does not log in in time
+        handler.continue_initial_auth_flow(Request::new(Empty {})).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // Then the auth flow failed with the timeout as reason
+        assert!(events.get_events().contains(
+            &EngineEvent::AuthFlowFailed { reason: application_error!(AuthFlowTimedOut) }
+        ));
+    }
+
+    #[tokio::test]
+    async fn cancel_auth_flow_stops_the_waiting_auth_flow() {
+        // Given a handler waiting for a user who has not logged in yet
+        let events = FakeEventBus::new();
+        let handler = waiting_handler(events.clone());
+        handler.start_initial_auth_flow(Request::new(Empty {})).await.unwrap();
+        handler.continue_initial_auth_flow(Request::new(Empty {})).await.unwrap();
+
+        // When the auth flow is cancelled
+        handler.cancel_auth_flow(Request::new(Empty {})).await.unwrap();
+
+        // Then the flow failed with the cancellation as reason and can be started again
+        assert!(events.get_events().contains(
+            &EngineEvent::AuthFlowFailed { reason: application_error!(AuthFlowCancelled) }
+        ));
+        assert!(handler.start_initial_auth_flow(Request::new(Empty {})).await.is_ok());
+    }
+}
@@ -1,32 +1,61 @@
 pub mod grpc_handler;
 pub mod error;
+pub mod cli;
+pub mod session_secret;
+#[cfg(unix)]
+pub mod unix_socket;
+#[cfg(unix)]
+pub mod private_folder;
 
 pub use common as default_values;
 
+use std::fs::OpenOptions;
+use std::path::Path;
+use std::sync::Mutex;
 use tonic::transport::Server;
+use tonic::transport::server::Router;
+#[cfg(unix)]
+use tokio_stream::wrappers::UnixListenerStream;
+use crate::cli::CliOptions;
+use crate::session_secret::{generate_session_secret, write_session_secret, RequireSessionSecret};
 use crate::grpc_handler::KdriveServiceHandler;
-use std::net::SocketAddr;
-use adapters::driven::build_time_env_var_configurator_adapter::BuildTimeEnvVarConfiguratorPort;
+use adapters::driven::toml_file_configurator_adapter::TomlFileConfiguratorAdapter;
 use adapters::driven::event_bus_adapter::EventBusAdapter;
 use adapters::driven::metadata_sqlite_adapter::MetadataSqliteAdapter;
+use adapters::driven::system_clock_adapter::SystemClockAdapter;
 use engine::domain::engine::Engine;
+use adapters::driven::kdrive_api_adapter::KDriveApiAdapter;
+use adapters::driven::local_fs_adapter::LocalFsAdapter;
+use adapters::driven::local_watcher_adapter::LocalWatcherAdapter;
 use adapters::driven::kdrive_authenticator_adapter::KDriveAuthenticator;
 use adapters::driven::token_store_file_adapter::TokenStoreFileAdapter;
 use adapters::driven::token_store_key_ring_adapter::TokenStoreKeyRingAdapter;
-use engine::domain::tokens::TokenStore;
+use engine::domain::tokens::{ActivePort, TokenStore};
 use engine::ports::driven::configurator_driven_port::ConfiguratorPort;
+use engine::domain::configuration::LogLevel;
+use engine::domain::default_values::general_defaults::LOG_FILE_NAME;
 use common::domain::errors::ApplicationError;
 use common::kdrive::kdrive_service_server::KdriveServiceServer;
 
-pub async fn start_server(addr: SocketAddr) -> Result<(), ApplicationError> {
-    println!("kdrive_service starting on {:?}", addr);
+pub async fn start_server(options: CliOptions) -> Result<(), ApplicationError> {
+    let configurator = match &options.config_path {
+        Some(path) => TomlFileConfiguratorAdapter::new(path.clone()),
+        None => TomlFileConfiguratorAdapter::from_default_location()?,
+    };
+    let config = options.apply_to(configurator.load()?);
+    init_logging(config.log_level, options.foreground, &config.data_dir);
 
-    let config = BuildTimeEnvVarConfiguratorPort.load()?;
     let authenticator = KDriveAuthenticator::new_from_config(&config);
     let token_store =
-        TokenStore::load(Some(TokenStoreKeyRingAdapter), Some(TokenStoreFileAdapter))?;
-    let metadata_store = MetadataSqliteAdapter::new();
+        TokenStore::load(Some(TokenStoreKeyRingAdapter), Some(TokenStoreFileAdapter::new(config.data_dir.clone())))?;
+    let metadata_store = MetadataSqliteAdapter::new_from_config(&config)?;
+    // The API reads the tokens the engine saves, so refreshed tokens are used right away
+    let api = KDriveApiAdapter::new_from_config(
+        &config,
+        ActivePort::choose(Some(TokenStoreKeyRingAdapter), Some(TokenStoreFileAdapter::new(config.data_dir.clone())))?,
+    );
 
+    let local_fs = LocalFsAdapter::new_from_config(&config)?;
     let event_bus = EventBusAdapter::new();
 
     let engine = Engine::new(
@@ -34,17 +63,79 @@ pub async fn start_server(addr: SocketAddr) -> Result<(), ApplicationError> {
         token_store,
         event_bus.clone(),
         metadata_store,
-    );
+        SystemClockAdapter,
+        api,
+        local_fs,
+    )
+    .with_conflict_policy(config.conflict_policy)
+    .with_ignore_patterns(config.ignore_patterns.clone())
+    .with_upload_chunks(config.upload_chunk_size, config.upload_parallelism);
 
     let handler = KdriveServiceHandler::new(
         engine,
         event_bus,
-    );
+    ).with_auth_flow_timeout(config.auth_flow_timeout);
+    handler.start_token_refresh();
+    handler.start_metadata_crawl();
+    handler.start_local_watch(LocalWatcherAdapter::new_from_config(&config));
+    handler.start_remote_polling();
+
+    let session_secret = generate_session_secret();
+    write_session_secret(&config.session_secret_path, &session_secret)?;
+    tracing::info!("session secret written to {:?}", config.session_secret_path);
 
-    Server::builder()
-        .add_service(KdriveServiceServer::new(handler))
-        .serve(addr)
-        .await?;
+    let router = Server::builder().add_service(KdriveServiceServer::with_interceptor(
+        handler,
+        RequireSessionSecret::new(&session_secret),
+    ));
+    match &config.grpc_socket_path {
+        Some(path) => {
+            tracing::info!("kdrive_service starting on {:?}", path);
+            serve_on_unix_socket(router, path).await
+        }
+        None => {
+            tracing::info!("kdrive_service starting on {:?}", config.grpc_listen_addr);
+            router.serve(config.grpc_listen_addr).await?;
+            Ok(())
+        }
+    }
+}
 
+#[cfg(unix)]
+async fn serve_on_unix_socket(router: Router, path: &Path) -> Result<(), ApplicationError> {
+    let listener = unix_socket::bind_unix_socket(path)?;
+    router.serve_with_incoming(UnixListenerStream::new(listener)).await?;
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(not(unix))]
+async fn serve_on_unix_socket(_router: Router, _path: &Path) -> Result<(), ApplicationError> {
+    Err(common::application_error!(common::domain::text_keys::TextKeys::UnixSocketsNotSupported))
+}
+
+fn init_logging(log_level: LogLevel, foreground: bool, data_dir: &Path) {
+    let level = match log_level {
+        LogLevel::Error => tracing::Level::ERROR,
+        LogLevel::Warn => tracing::Level::WARN,
+        LogLevel::Info => tracing::Level::INFO,
+        LogLevel::Debug => tracing::Level::DEBUG,
+        LogLevel::Trace => tracing::Level::TRACE,
+    };
+
+    let log_file = std::fs::create_dir_all(data_dir)
+        .and_then(|()| OpenOptions::new().create(true).append(true).open(data_dir.join(LOG_FILE_NAME)));
+
+    // Logging may already be set up by the process embedding the service
+    match log_file {
+        Ok(file) if !foreground => {
+            let _ = tracing_subscriber::fmt()
+                .with_max_level(level)
+                .with_ansi(false)
+                .with_writer(Mutex::new(file))
+                .try_init();
+        }
+        _ => {
+            let _ = tracing_subscriber::fmt().with_max_level(level).try_init();
+        }
+    }
+}
@@ -1,9 +1,18 @@
-use common::domain::defaults::default_server_addr;
 use common::domain::errors::ApplicationError;
+use kdrive_service::cli::{CliCommand, CliOptions, USAGE};
 use kdrive_service::start_server;
 
 #[tokio::main]
 async fn main() -> Result<(), ApplicationError> {
-    let addr = default_server_addr();
-    start_server(addr).await
-}
\ No newline at end of file
+    match CliOptions::parse(std::env::args().skip(1)) {
+        Ok(CliCommand::Run(options)) => start_server(options).await,
+        Ok(CliCommand::Help) => {
+            println!("{USAGE}");
+            Ok(())
+        }
+        Err(error) => {
+            eprintln!("{USAGE}");
+            Err(error)
+        }
+    }
+}
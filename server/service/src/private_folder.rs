@@ -0,0 +1,99 @@
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+use std::path::Path;
+
+const PRIVATE_FOLDER_MODE: u32 = 0o700;
+
+/// Creates the folder of the socket or session secret so only the user can enter it. A folder that is already
+/// there is used only when it is a folder of the user that nobody else can enter, otherwise someone else could
+/// swap the socket or read the secret.
+pub fn ensure_private_folder(folder: &Path) -> Result<(), Error> {
+    if let Err(e) = fs::symlink_metadata(folder)
+        && e.kind() == ErrorKind::NotFound
+    {
+        fs::DirBuilder::new()
+            .recursive(true)
+            .mode(PRIVATE_FOLDER_MODE)
+            .create(folder)?;
+    }
+
+    // Checked also right after creating it, another user may have created it first
+    let metadata = fs::symlink_metadata(folder)?;
+    let refused = |reason: &str| Err(Error::new(ErrorKind::PermissionDenied, format!("{} {reason}", folder.display())));
+    if !metadata.is_dir() {
+        return refused("is not a folder");
+    }
+    // SAFETY: geteuid has no preconditions and cannot fail
+    if metadata.uid() != unsafe { libc::geteuid() } {
+        return refused("belongs to another user");
+    }
+    if metadata.permissions().mode() & 0o777 != PRIVATE_FOLDER_MODE {
+        return refused("can be entered by other users");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use super::*;
+
+    struct TempFolder(PathBuf);
+
+    impl TempFolder {
+        fn new(name: &str) -> Self {
+            TempFolder(std::env::temp_dir().join(format!("kdrive_rs_{}_{}", name, std::process::id())))
+        }
+    }
+
+    impl Drop for TempFolder {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn missing_folder_is_created_private() {
+        let folder = TempFolder::new("private_folder_created");
+
+        ensure_private_folder(&folder.0.join("runtime")).unwrap();
+
+        let mode = fs::metadata(folder.0.join("runtime")).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode, PRIVATE_FOLDER_MODE);
+    }
+
+    #[test]
+    fn folder_other_users_can_enter_is_refused() {
+        // Given a folder someone else created with the default permissions
+        let folder = TempFolder::new("private_folder_open");
+        fs::create_dir_all(&folder.0).unwrap();
+        fs::set_permissions(&folder.0, fs::Permissions::from_mode(0o777)).unwrap();
+
+        // When it is used for the socket
+        let result = ensure_private_folder(&folder.0);
+
+        // Then it is refused and left alone
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::PermissionDenied);
+        assert_eq!(fs::metadata(&folder.0).unwrap().permissions().mode() & 0o777, 0o777);
+    }
+
+    #[test]
+    fn link_to_a_folder_is_refused() {
+        let folder = TempFolder::new("private_folder_link");
+        fs::DirBuilder::new().recursive(true).mode(PRIVATE_FOLDER_MODE).create(folder.0.join("target")).unwrap();
+        std::os::unix::fs::symlink(folder.0.join("target"), folder.0.join("link")).unwrap();
+
+        let result = ensure_private_folder(&folder.0.join("link"));
+
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::PermissionDenied);
+    }
+
+    #[test]
+    fn private_folder_of_the_user_is_used() {
+        let folder = TempFolder::new("private_folder_existing");
+        fs::DirBuilder::new().recursive(true).mode(PRIVATE_FOLDER_MODE).create(&folder.0).unwrap();
+
+        assert!(ensure_private_folder(&folder.0).is_ok());
+    }
+}
@@ -0,0 +1,183 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use rand::RngCore;
+use rand::rngs::OsRng;
+use tonic::service::Interceptor;
+use tonic::{Request, Status};
+use common::application_error;
+use common::domain::defaults::{SESSION_SECRET_METADATA_KEY, SESSION_SECRET_SCHEME};
+use common::domain::errors::ApplicationError;
+use common::domain::text_keys::TextKeys::{CouldNotWriteSessionSecret, UnauthenticatedClient};
+
+const SESSION_SECRET_BYTES: usize = 32;
+
+/// A fresh secret for every start of the service, so a secret of an earlier session is worthless
+pub fn generate_session_secret() -> String {
+    let mut bytes = [0u8; SESSION_SECRET_BYTES];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Writes the secret to a file only the user can read, in a folder only the user can enter, replacing the secret
+/// of an earlier session
+pub fn write_session_secret(path: &Path, secret: &str) -> Result<(), ApplicationError> {
+    let write_error = |e: std::io::Error| application_error!(CouldNotWriteSessionSecret, format!("{}: {}", path.display(), e));
+
+    if let Some(folder) = path.parent().filter(|folder| !folder.as_os_str().is_empty()) {
+        #[cfg(unix)]
+        crate::private_folder::ensure_private_folder(folder).map_err(write_error)?;
+        #[cfg(not(unix))]
+        fs::create_dir_all(folder).map_err(write_error)?;
+    }
+
+    // Removing first makes sure a file with wider permissions is not reused
+    if let Err(e) = fs::remove_file(path)
+        && e.kind() != std::io::ErrorKind::NotFound
+    {
+        return Err(write_error(e));
+    }
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    options
+        .open(path)
+        .and_then(|mut file| file.write_all(secret.as_bytes()))
+        .map_err(write_error)
+}
+
+/// Refuses every request that does not carry the session secret of this service
+#[derive(Clone)]
+pub struct RequireSessionSecret {
+    expected: String,
+}
+
+impl RequireSessionSecret {
+    pub fn new(secret: &str) -> Self {
+        Self { expected: format!("{SESSION_SECRET_SCHEME}{secret}") }
+    }
+}
+
+impl Interceptor for RequireSessionSecret {
+    fn call(&mut self, request: Request<()>) -> Result<Request<()>, Status> {
+        let presented = request
+            .metadata()
+            .get(SESSION_SECRET_METADATA_KEY)
+            .map(|value| value.as_bytes())
+            .unwrap_or_default();
+
+        if constant_time_eq(presented, self.expected.as_bytes()) {
+            Ok(request)
+        } else {
+            Err(application_error!(UnauthenticatedClient).into())
+        }
+    }
+}
+
+/// Compares without returning early, so the time taken tells nothing about the secret
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use tonic::Code;
+    use super::*;
+
+    struct TempSecretFolder(PathBuf);
+
+    impl TempSecretFolder {
+        fn new(name: &str) -> Self {
+            TempSecretFolder(std::env::temp_dir().join(format!("kdrive_rs_{}_{}", name, std::process::id())))
+        }
+
+        fn secret_path(&self) -> PathBuf {
+            self.0.join("session_secret")
+        }
+    }
+
+    impl Drop for TempSecretFolder {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn request_with(authorization: Option<&str>) -> Request<()> {
+        let mut request = Request::new(());
+        if let Some(authorization) = authorization {
+            request
+                .metadata_mut()
+                .insert(SESSION_SECRET_METADATA_KEY, authorization.parse().unwrap());
+        }
+        request
+    }
+
+    #[test]
+    fn every_session_gets_another_secret() {
+        let secret = generate_session_secret();
+
+        assert_eq!(secret.len(), SESSION_SECRET_BYTES * 2);
+        assert_ne!(secret, generate_session_secret());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn secret_file_is_only_readable_by_the_owner() {
+        use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+        // Given a secret left behind by an earlier session with wide permissions
+        let folder = TempSecretFolder::new("secret_permissions");
+        fs::DirBuilder::new().mode(0o700).create(&folder.0).unwrap();
+        fs::write(folder.secret_path(), "old").unwrap();
+        fs::set_permissions(folder.secret_path(), fs::Permissions::from_mode(0o644)).unwrap();
+
+        // When the secret of the new session is written
+        write_session_secret(&folder.secret_path(), "new").unwrap();
+
+        // Then it replaces the old one and only the user can read it
+        assert_eq!(fs::read_to_string(folder.secret_path()).unwrap(), "new");
+        let mode = fs::metadata(folder.secret_path()).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode, 0o600);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn secret_is_not_written_to_a_folder_other_users_can_enter() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // Given a secret folder anyone can read
+        let folder = TempSecretFolder::new("open_secret_folder");
+        fs::create_dir_all(&folder.0).unwrap();
+        fs::set_permissions(&folder.0, fs::Permissions::from_mode(0o755)).unwrap();
+
+        // When the secret of the new session is written
+        let result = write_session_secret(&folder.secret_path(), "new");
+
+        // Then it is refused and no secret is left behind
+        assert_eq!(result.unwrap_err().text_key, CouldNotWriteSessionSecret);
+        assert!(!folder.secret_path().exists());
+    }
+
+    #[test]
+    fn request_with_the_secret_is_accepted() {
+        let mut interceptor = RequireSessionSecret::new("secret");
+
+        assert!(interceptor.call(request_with(Some("Bearer secret"))).is_ok());
+    }
+
+    #[test]
+    fn request_without_or_with_a_wrong_secret_is_refused() {
+        let mut interceptor = RequireSessionSecret::new("secret");
+
+        for authorization in [None, Some("Bearer wrong"), Some("secret"), Some("Bearer secret2")] {
+            let status = interceptor.call(request_with(authorization)).expect_err("Expected Unauthenticated status");
+
+            assert_eq!(status.code(), Code::Unauthenticated);
+            assert_eq!(ApplicationError::from(status).text_key, UnauthenticatedClient);
+        }
+    }
+}
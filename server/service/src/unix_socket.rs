@@ -0,0 +1,136 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener as StdUnixListener, UnixStream};
+use std::path::Path;
+use tokio::net::UnixListener;
+use common::application_error;
+use common::domain::errors::ApplicationError;
+use common::domain::text_keys::TextKeys::{CouldNotBindSocket, SocketInUse};
+use crate::private_folder::ensure_private_folder;
+
+const SOCKET_FILE_MODE: u32 = 0o600;
+
+/// Binds the gRPC socket so only the owning user can connect, in a folder only that user can enter.
+/// The socket is bound under a temporary name and renamed once its permissions are set,
+/// so other users never see it with the default permissions.
+pub fn bind_unix_socket(path: &Path) -> Result<UnixListener, ApplicationError> {
+    let bind_error = |e: std::io::Error| application_error!(CouldNotBindSocket, format!("{}: {}", path.display(), e));
+
+    if let Some(folder) = path.parent().filter(|folder| !folder.as_os_str().is_empty()) {
+        ensure_private_folder(folder).map_err(bind_error)?;
+    }
+
+    remove_stale_socket(path)?;
+
+    let mut temporary_name = path.as_os_str().to_owned();
+    temporary_name.push(format!(".{}", std::process::id()));
+    let temporary_path = Path::new(&temporary_name);
+    let _ = fs::remove_file(temporary_path);
+
+    let listener = StdUnixListener::bind(temporary_path).map_err(bind_error)?;
+    let result = fs::set_permissions(temporary_path, fs::Permissions::from_mode(SOCKET_FILE_MODE))
+        .and_then(|()| fs::rename(temporary_path, path))
+        .and_then(|()| listener.set_nonblocking(true));
+    if let Err(e) = result {
+        let _ = fs::remove_file(temporary_path);
+        return Err(bind_error(e));
+    }
+
+    UnixListener::from_std(listener).map_err(bind_error)
+}
+
+/// A socket left behind by a service that stopped is removed, one that still answers is not
+fn remove_stale_socket(path: &Path) -> Result<(), ApplicationError> {
+    match fs::symlink_metadata(path) {
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(application_error!(CouldNotBindSocket, e.to_string())),
+        Ok(_) if UnixStream::connect(path).is_ok() => {
+            Err(application_error!(SocketInUse, path.display().to_string()))
+        }
+        Ok(_) => fs::remove_file(path)
+            .map_err(|e| application_error!(CouldNotBindSocket, e.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use super::*;
+
+    struct TempSocketFolder(PathBuf);
+
+    impl TempSocketFolder {
+        fn new(name: &str) -> Self {
+            TempSocketFolder(std::env::temp_dir().join(format!("kdrive_rs_{}_{}", name, std::process::id())))
+        }
+
+        fn socket_path(&self) -> PathBuf {
+            self.0.join("kdrive.sock")
+        }
+    }
+
+    impl Drop for TempSocketFolder {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn mode(path: &Path) -> u32 {
+        fs::metadata(path).unwrap().permissions().mode() & 0o777
+    }
+
+    #[tokio::test]
+    async fn socket_is_only_accessible_by_the_owner() {
+        // Given a socket folder that does not exist yet
+        let folder = TempSocketFolder::new("socket_permissions");
+
+        // When the socket is bound
+        let _listener = bind_unix_socket(&folder.socket_path()).unwrap();
+
+        // Then the folder and socket are private to the user
+        assert_eq!(mode(&folder.0), 0o700);
+        assert_eq!(mode(&folder.socket_path()), SOCKET_FILE_MODE);
+    }
+
+    #[tokio::test]
+    async fn stale_socket_is_replaced() {
+        // Given a socket left behind by a service that stopped
+        let folder = TempSocketFolder::new("stale_socket");
+        drop(bind_unix_socket(&folder.socket_path()).unwrap());
+
+        // When the socket is bound again
+        let result = bind_unix_socket(&folder.socket_path());
+
+        // Then the stale socket is replaced
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn socket_in_use_returns_an_error() {
+        // Given a service listening on the socket
+        let folder = TempSocketFolder::new("socket_in_use");
+        let _listener = bind_unix_socket(&folder.socket_path()).unwrap();
+
+        // When a second service binds the same socket
+        let err = bind_unix_socket(&folder.socket_path()).expect_err("Expected SocketInUse error");
+
+        // Then it is refused
+        assert_eq!(err.text_key, SocketInUse);
+    }
+
+    #[tokio::test]
+    async fn socket_folder_other_users_can_enter_is_refused() {
+        // Given a socket folder anyone can write to
+        let folder = TempSocketFolder::new("open_socket_folder");
+        fs::create_dir_all(&folder.0).unwrap();
+        fs::set_permissions(&folder.0, fs::Permissions::from_mode(0o777)).unwrap();
+
+        // When the socket is bound in it
+        let err = bind_unix_socket(&folder.socket_path()).expect_err("Expected CouldNotBindSocket error");
+
+        // Then it is refused and no socket is created
+        assert_eq!(err.text_key, CouldNotBindSocket);
+        assert!(!folder.socket_path().exists());
+    }
+}
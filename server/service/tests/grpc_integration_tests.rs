@@ -1,30 +1,39 @@
 
 use engine::domain::engine::Engine;
 use engine::domain::test_helpers::fake_authenticator_adapter::FakeAuthenticatorDrivenAdapter;
+use engine::domain::test_helpers::fake_clock::FakeClock;
 use engine::domain::test_helpers::fake_event_bus::FakeEventBus;
 use engine::domain::test_helpers::fake_token_store_adapter::{
     FakeTokenStoreRingAdapter, FakeTokenStoreFileAdapter
 };
 use engine::domain::test_helpers::fake_token_store::FakeTokenStore;
 use kdrive_service::grpc_handler::KdriveServiceHandler;
+use kdrive_service::session_secret::RequireSessionSecret;
+use common::adapters::session_secret::SessionSecretInterceptor;
+use common::domain::text_keys::TextKeys::UnauthenticatedClient;
+use tonic::Code;
+use tonic::service::interceptor::InterceptedService;
 use tonic::transport::Server;
 use std::net::SocketAddr;
+use std::time::Duration;
 use tonic::Request;
 use tokio::net::TcpListener;
 use tonic::codegen::tokio_stream::wrappers::TcpListenerStream;
 use adapters::driven::event_bus_adapter::EventBusAdapter;
 use common::domain::errors::ApplicationError;
-use common::kdrive::Empty;
+use common::kdrive::{Empty, ListDirectoryRequest};
+use adapters::driven::kdrive_api_adapter::KDriveApiAdapter;
+use engine::domain::default_values::general_defaults::ROOT_DIRECTORY_ID;
+use engine::domain::test_helpers::fake_token_store_adapter::TEST_FILE_ACCESS_TOKEN;
+use fake_kdrive::FakeKDriveServer;
+use oauth2::url::Url;
 use common::kdrive::kdrive_service_client::KdriveServiceClient;
-use common::kdrive::kdrive_service_server::KdriveServiceServer;
+use common::kdrive::kdrive_service_server::{KdriveService, KdriveServiceServer};
+use engine::domain::test_helpers::fake_kdrive_api::FakeKDriveApi;
+use engine::domain::test_helpers::fake_local_fs::FakeLocalFs;
 use engine::domain::test_helpers::fake_metadata_store::FakeMetadataStore;
 
-async fn start_test_server() -> Result<(SocketAddr, tokio::task::JoinHandle<()>), ApplicationError> {
-    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
-    let addr = listener.local_addr().unwrap();
-
-    println!("test server: starting gRPC server on {:?}", addr);
-
+fn test_service() -> Result<KdriveServiceServer<impl KdriveService>, ApplicationError> {
     let fake_engine = FakeAuthenticatorDrivenAdapter::new_default();
     let fake_ring_tokens = FakeTokenStoreRingAdapter::empty();
     let fake_file_tokens = FakeTokenStoreFileAdapter::empty();
@@ -37,17 +46,23 @@ async fn start_test_server() -> Result<(SocketAddr, tokio::task::JoinHandle<()>)
     let fake_events = FakeEventBus::new();
     let fake_metadata = FakeMetadataStore::new();
 
-    let engine = Engine::new(fake_engine, token_store, fake_events, fake_metadata);
+    let engine = Engine::new(fake_engine, token_store, fake_events, fake_metadata, FakeClock::new(), FakeKDriveApi::new_default(), FakeLocalFs::new());
     let event_bus = EventBusAdapter::new();
     let handler = KdriveServiceHandler::new(engine, event_bus);
 
+    Ok(KdriveServiceServer::new(handler))
+}
+
+async fn start_test_server() -> Result<(SocketAddr, tokio::task::JoinHandle<()>), ApplicationError> {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    println!("test server: starting gRPC server on {:?}", addr);
+
+    let service = test_service()?;
     let handle = tokio::spawn(async move {
         Server::builder()
-            .add_service(
-                KdriveServiceServer::new(
-                    handler,
-                ),
-            )
+            .add_service(service)
             .serve_with_incoming(TcpListenerStream::new(listener))
             .await
             .expect("gRPC server failed");
@@ -144,4 +159,204 @@ async fn grpc_client_can_complete_auth_flow() {
     assert!(response.is_ok());
 
     server_handle.abort();
-}
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn grpc_client_is_not_authenticated_after_logout() {
+    let (addr, server_handle) = start_test_server().await.unwrap();
+
+    let channel = connect_to_server(addr).await;
+    let mut client = KdriveServiceClient::new(channel);
+
+    client
+        .start_initial_auth_flow(Request::new(Empty {}))
+        .await
+        .unwrap();
+
+    client
+        .continue_initial_auth_flow(Request::new(Empty {}))
+        .await
+        .unwrap();
+
+    client
+        .logout(Request::new(Empty {}))
+        .await
+        .unwrap();
+
+    let response = client
+        .is_authenticated(Request::new(Empty {}))
+        .await
+        .unwrap();
+
+    assert!(!response.into_inner().is_authenticated);
+
+    server_handle.abort();
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn grpc_client_connects_through_the_unix_socket() {
+    use std::os::unix::fs::PermissionsExt;
+    use common::adapters::grpc_channel::connect_channel;
+    use kdrive_service::unix_socket::bind_unix_socket;
+    use tonic::codegen::tokio_stream::wrappers::UnixListenerStream;
+
+    // Given a service listening on a Unix domain socket
+    let folder = std::env::temp_dir().join(format!("kdrive_rs_grpc_socket_{}", std::process::id()));
+    let socket_path = folder.join("kdrive.sock");
+    let listener = bind_unix_socket(&socket_path).unwrap();
+    let service = test_service().unwrap();
+    let server_handle = tokio::spawn(async move {
+        Server::builder()
+            .add_service(service)
+            .serve_with_incoming(UnixListenerStream::new(listener))
+            .await
+            .expect("gRPC server failed");
+    });
+
+    // When a client connects through the socket
+    let channel = connect_channel(&format!("unix://{}", socket_path.display()), Duration::from_secs(2))
+        .await
+        .unwrap();
+    let mut client = KdriveServiceClient::new(channel);
+    let response = client
+        .is_authenticated(Request::new(Empty {}))
+        .await
+        .unwrap();
+
+    // Then the service answers and only the owner can use the socket
+    assert!(!response.into_inner().is_authenticated);
+    let mode = std::fs::metadata(&socket_path).unwrap().permissions().mode() & 0o777;
+    assert_eq!(mode, 0o600);
+
+    server_handle.abort();
+    let _ = std::fs::remove_dir_all(folder);
+}
+
+async fn start_test_server_with_session_secret(secret: &str) -> (SocketAddr, tokio::task::JoinHandle<()>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let service = test_service().unwrap();
+    let interceptor = RequireSessionSecret::new(secret);
+    let handle = tokio::spawn(async move {
+        Server::builder()
+            .add_service(InterceptedService::new(service, interceptor))
+            .serve_with_incoming(TcpListenerStream::new(listener))
+            .await
+            .expect("gRPC server failed");
+    });
+
+    (addr, handle)
+}
+
+#[tokio::test]
+async fn grpc_client_without_the_session_secret_is_refused() {
+    // Given a service that requires a session secret
+    let (addr, server_handle) = start_test_server_with_session_secret("session-secret").await;
+
+    // When a client calls it without the secret
+    let mut client = KdriveServiceClient::new(connect_to_server(addr).await);
+    let status = client
+        .start_initial_auth_flow(Request::new(Empty {}))
+        .await
+        .expect_err("Expected Unauthenticated status");
+
+    // Then the request is refused as unauthenticated
+    assert_eq!(status.code(), Code::Unauthenticated);
+    assert_eq!(ApplicationError::from(status).text_key, UnauthenticatedClient);
+
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn grpc_client_with_a_wrong_session_secret_is_refused() {
+    // Given a service that requires a session secret
+    let (addr, server_handle) = start_test_server_with_session_secret("session-secret").await;
+
+    // When a client presents the secret of another session
+    let interceptor = SessionSecretInterceptor::new("old-session-secret").unwrap();
+    let mut client = KdriveServiceClient::with_interceptor(connect_to_server(addr).await, interceptor);
+    let status = client
+        .is_authenticated(Request::new(Empty {}))
+        .await
+        .expect_err("Expected Unauthenticated status");
+
+    // Then the request is refused as unauthenticated
+    assert_eq!(status.code(), Code::Unauthenticated);
+
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn grpc_client_with_the_session_secret_is_served() {
+    // Given a service that requires a session secret
+    let (addr, server_handle) = start_test_server_with_session_secret("session-secret").await;
+
+    // When a client presents the secret
+    let interceptor = SessionSecretInterceptor::new("session-secret").unwrap();
+    let mut client = KdriveServiceClient::with_interceptor(connect_to_server(addr).await, interceptor);
+    let response = client
+        .is_authenticated(Request::new(Empty {}))
+        .await
+        .unwrap();
+
+    // Then the service answers
+    assert!(!response.into_inner().is_authenticated);
+
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn grpc_client_lists_the_drive_of_the_fake_kdrive_page_by_page() {
+    // Given a service that talks to a fake kDrive and has no metadata yet
+    let api = FakeKDriveApi::new_default();
+    let documents = api.add_directory(ROOT_DIRECTORY_ID, "Documents");
+    api.add_file(documents, "report.pdf", b"report");
+    for name in ["a.txt", "b.txt"] {
+        api.add_file(ROOT_DIRECTORY_ID, name, b"text");
+    }
+    let kdrive = FakeKDriveServer::start(api).await.unwrap();
+    kdrive.auth().accept_access_token(TEST_FILE_ACCESS_TOKEN);
+
+    let token_store = FakeTokenStore::load(Some(FakeTokenStoreRingAdapter::with_tokens()), None).unwrap();
+    let engine = Engine::new(
+        FakeAuthenticatorDrivenAdapter::new_default(),
+        token_store,
+        FakeEventBus::new(),
+        FakeMetadataStore::new().without_metadata(),
+        FakeClock::new(),
+        KDriveApiAdapter::new(Url::parse(&kdrive.url()).unwrap(), FakeTokenStoreFileAdapter::with_tokens()),
+        FakeLocalFs::new(),
+    );
+    let service = KdriveServiceServer::new(KdriveServiceHandler::new(engine, EventBusAdapter::new()));
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server_handle = tokio::spawn(async move {
+        Server::builder()
+            .add_service(service)
+            .serve_with_incoming(TcpListenerStream::new(listener))
+            .await
+            .expect("gRPC server failed");
+    });
+    let mut client = KdriveServiceClient::new(connect_to_server(addr).await);
+
+    // When the root is listed two entries at a time with their children
+    let request = |page_token: String| Request::new(ListDirectoryRequest {
+        path: "/".to_string(),
+        depth: 2,
+        page_size: 2,
+        page_token,
+    });
+    let first = client.list_directory(request(String::new())).await.unwrap().into_inner();
+    let second = client.list_directory(request(first.next_page_token.clone())).await.unwrap().into_inner();
+
+    // Then both pages together hold the whole root, including the file in Documents
+    let names: Vec<_> = first.entries.iter().chain(&second.entries).map(|entry| entry.name.as_str()).collect();
+    assert_eq!(names, ["Documents", "a.txt", "b.txt"]);
+    assert_eq!(first.entries[0].children[0].name, "report.pdf");
+    assert!(second.next_page_token.is_empty());
+
+    server_handle.abort();
+}